@@ -0,0 +1,44 @@
+//! Drop tables of the reactors, a table is a json file named by the id of the reactor
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DropItem {
+    pub item: u32,
+    #[serde(default = "one")]
+    pub max_quantity: usize,
+    /// Chance between 0.0 and 1.0 that the item is dropped
+    pub chance: f32,
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DropTable {
+    #[serde(default)]
+    pub items: Vec<DropItem>,
+    /// Maximum mesos, no mesos are dropped If it's zero
+    #[serde(default)]
+    pub money: u32,
+    /// The dropped mesos are between `money - money_variance` and `money`
+    #[serde(default)]
+    pub money_variance: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DropTable;
+
+    #[test]
+    fn load_drop_table() {
+        let table: DropTable = serde_json::from_str(
+            r#"{ "items": [{ "item": 4001007, "chance": 0.5 }], "money": 100 }"#,
+        )
+        .unwrap();
+        assert_eq!(table.items[0].max_quantity, 1);
+        assert_eq!(table.money, 100);
+        assert_eq!(table.money_variance, 0);
+    }
+}
@@ -46,6 +46,26 @@ pub struct Mob {
     pub exp: u32,
     #[serde(default)]
     pub boss: bool,
+    /// Damage of the mob touching a character
+    #[serde(rename = "PADamage", default, deserialize_with = "deserialize_num")]
+    pub pa_damage: u32,
+    #[serde(rename = "MADamage", default, deserialize_with = "deserialize_num")]
+    pub ma_damage: u32,
+    #[serde(rename = "acc", default, deserialize_with = "deserialize_num")]
+    pub acc: u32,
+    /// Attacks of the mob, ordered by their attack index
+    #[serde(rename = "attack", default)]
+    pub attacks: Vec<MobAttack>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MobAttack {
+    #[serde(rename = "PADamage", default, deserialize_with = "deserialize_num")]
+    pub pa_damage: u32,
+    #[serde(rename = "MADamage", default, deserialize_with = "deserialize_num")]
+    pub ma_damage: u32,
+    #[serde(default)]
+    pub magic: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -97,6 +117,11 @@ pub struct Item {
     pub price: u32,
     #[serde(rename = "unitPrice")]
     pub unit_price: Option<f32>,
+    /// Recovery while sitting on the item, used by portable chairs
+    #[serde(rename = "recoveryHP", default, deserialize_with = "deserialize_num")]
+    pub recovery_hp: u32,
+    #[serde(rename = "recoveryMP", default, deserialize_with = "deserialize_num")]
+    pub recovery_mp: u32,
 
     #[serde(rename = "incEVA", default, deserialize_with = "deserialize_num")]
     pub inc_eva: u32,
@@ -158,7 +183,81 @@ pub struct Item {
     pub big_size: bool,
 }
 
+fn both_genders() -> u32 {
+    2
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Commodity {
+    #[serde(rename = "SN", deserialize_with = "deserialize_num")]
+    pub sn: u32,
+    #[serde(rename = "ItemId", deserialize_with = "deserialize_num")]
+    pub item_id: u32,
+    #[serde(rename = "Count", default, deserialize_with = "deserialize_num")]
+    pub count: u32,
+    #[serde(rename = "Price", default, deserialize_with = "deserialize_num")]
+    pub price: u32,
+    #[serde(rename = "Period", default, deserialize_with = "deserialize_num")]
+    pub period: u32,
+    #[serde(rename = "Priority", default, deserialize_with = "deserialize_num")]
+    pub priority: u32,
+    /// 0 = male, 1 = female, 2 = both
+    #[serde(rename = "Gender", default = "both_genders", deserialize_with = "deserialize_num")]
+    pub gender: u32,
+    #[serde(rename = "OnSale", default, deserialize_with = "deserialize_num")]
+    pub on_sale: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReactorEvent {
+    /// 0-5 = hit, 6-9 = touch, 100 = item, 101 = timeout
+    #[serde(rename = "type", deserialize_with = "deserialize_num")]
+    pub ty: u32,
+    #[serde(rename = "state", deserialize_with = "deserialize_num")]
+    pub state: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReactorEvents {
+    /// Time in ms after which the timeout event is triggered
+    #[serde(rename = "timeOut", default, deserialize_with = "deserialize_num")]
+    pub time_out: u32,
+    #[serde(flatten)]
+    pub events: BTreeMap<String, ReactorEvent>,
+}
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReactorState {
+    pub event: Option<ReactorEvents>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReactorInfo {
+    /// Delay in ms before the drops and the script of the final state are triggered
+    #[serde(rename = "delay", default, deserialize_with = "deserialize_num")]
+    pub delay: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Reactor {
+    /// Script which is run once the reactor reaches the final state
+    pub action: Option<String>,
+    pub info: Option<ReactorInfo>,
+    /// The states are keyed by their index
+    #[serde(flatten)]
+    pub states: BTreeMap<String, ReactorState>,
+}
+
+
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FieldInfo {
+    /// Rate of the natural hp and mp recovery on the field
+    pub recovery: Option<f32>,
+    /// Damage of the obstacles on the field
+    #[serde(default)]
+    pub obstacle_damage: Option<u32>,
+}
 
 pub fn load_all<T: DeserializeOwned>(
     base_path: impl AsRef<Path>,
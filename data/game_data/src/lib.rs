@@ -1,6 +1,8 @@
 pub mod schema;
 pub mod ha_xml;
 pub mod gen;
+pub mod pq;
+pub mod drops;
 
 pub use crate::gen::map;
 pub use crate::gen::mob;
@@ -0,0 +1,79 @@
+//! Definitions of the party quests, a definition is a json file named by the id of the quest
+
+use serde::{Deserialize, Serialize};
+
+/// Condition, which clears a stage
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClearCondition {
+    /// The items are handed in at a npc on the stage
+    CollectItem { item: u32, count: u32 },
+    /// All mobs on the stage are killed
+    KillMobs,
+    /// The named reactor reached the state
+    ReactorState { reactor: String, state: u8 },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Stage {
+    pub map: u32,
+    pub clear: ClearCondition,
+    /// Npc on the stage, which checks the clear condition when it's talked to
+    #[serde(default)]
+    pub npc: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Reward {
+    pub item: u32,
+    #[serde(default = "one")]
+    pub quantity: u16,
+}
+
+fn one() -> u16 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PartyQuest {
+    pub name: String,
+    /// Npc, which lets the party enter the quest
+    pub npc: u32,
+    pub min_party: usize,
+    pub max_party: usize,
+    pub min_level: u32,
+    pub max_level: u32,
+    /// Time limit of the whole quest in seconds
+    pub time_limit: u32,
+    /// Map the party returns to after the quest
+    pub exit_map: u32,
+    pub stages: Vec<Stage>,
+    /// Map, which can be entered after the last stage
+    pub bonus_map: Option<u32>,
+    /// Rewards the characters can choose from after the last stage
+    #[serde(default)]
+    pub rewards: Vec<Reward>,
+}
+
+impl PartyQuest {
+    pub fn maps(&self) -> impl Iterator<Item = u32> + '_ {
+        self.stages
+            .iter()
+            .map(|stage| stage.map)
+            .chain(self.bonus_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClearCondition, PartyQuest};
+
+    #[test]
+    fn load_kpq() {
+        let pq: PartyQuest = serde_json::from_str(include_str!("../pq/1.json")).unwrap();
+        assert_eq!(pq.npc, 9020000);
+        assert_eq!(pq.stages.last().unwrap().clear, ClearCondition::KillMobs);
+        assert_eq!(pq.maps().count(), pq.stages.len() + 1);
+        assert_eq!(pq.stages[0].npc, Some(9020001));
+    }
+}
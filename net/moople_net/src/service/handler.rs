@@ -45,6 +45,16 @@ pub trait MapleSessionHandler: Sized {
 pub trait MapleServerSessionHandler: MapleSessionHandler {
     fn get_ping_interval() -> Duration;
     fn get_ping_packet(&mut self) -> Result<MaplePacket, Self::Error>;
+
+    /// Interval in which `handle_tick` is called
+    fn get_tick_interval() -> Duration {
+        Duration::from_secs(1)
+    }
+
+    /// Periodic work of the session, like timed events which are not triggered by a packet
+    async fn handle_tick(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 #[async_trait]
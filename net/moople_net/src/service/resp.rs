@@ -54,6 +54,30 @@ impl<Resp: Response + Send> Response for Vec<Resp> {
     }
 }
 
+#[async_trait]
+impl<A: Response + Send, B: Response + Send> Response for (A, B) {
+    async fn send<Trans: SessionTransport + Send + Unpin>(
+        self,
+        session: &mut MapleSession<Trans>,
+    ) -> NetResult<SessionHandleResult> {
+        self.0.send(session).await?;
+        self.1.send(session).await
+    }
+}
+
+#[async_trait]
+impl<L: Response + Send, R: Response + Send> Response for either::Either<L, R> {
+    async fn send<Trans: SessionTransport + Send + Unpin>(
+        self,
+        session: &mut MapleSession<Trans>,
+    ) -> NetResult<SessionHandleResult> {
+        match self {
+            either::Either::Left(resp) => resp.send(session).await,
+            either::Either::Right(resp) => resp.send(session).await,
+        }
+    }
+}
+
 pub struct ResponsePacket<Op, T> {
     pub op: Op,
     pub data: T,
@@ -160,5 +184,6 @@ mod tests {
         check_is_into_response::<Option<()>>();
         check_is_into_response::<ResponsePacket<u16, ()>>();
         check_is_into_response::<Vec<ResponsePacket<u16, ()>>>();
+        check_is_into_response::<(Vec<ResponsePacket<u16, ()>>, Option<()>)>();
     }
 }
@@ -26,6 +26,17 @@ impl<Key: Eq + Ord> SessionSet<Key> {
         self.0.write().expect("Session remove").remove(&key);
     }
 
+    pub fn keys(&self) -> Vec<Key>
+    where
+        Key: Clone,
+    {
+        self.0.read().expect("Session keys").keys().cloned().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.read().expect("Session is empty").is_empty()
+    }
+
     pub fn send_packet_to(&self, rx_key: Key, pkt: MaplePacket) -> anyhow::Result<()> {
         self.0
             .read()
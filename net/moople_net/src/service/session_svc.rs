@@ -31,14 +31,17 @@ impl SharedSessionHandle {
 
 impl SharedSessionHandle {
     pub fn new() -> (Self, FramedPipeReceiver) {
+        Self::with_token(CancellationToken::new())
+    }
+
+    /// Handle, which is also cancelled when the parent token gets cancelled
+    pub fn new_child(parent: &CancellationToken) -> (Self, FramedPipeReceiver) {
+        Self::with_token(parent.child_token())
+    }
+
+    fn with_token(ct: CancellationToken) -> (Self, FramedPipeReceiver) {
         let (tx, rx) = framed_pipe(8 * 1024, 128);
-        (
-            Self {
-                ct: CancellationToken::new(),
-                tx,
-            },
-            rx,
-        )
+        (Self { ct, tx }, rx)
     }
 }
 
@@ -62,6 +65,36 @@ where
         session_handle: SharedSessionHandle,
         mut session_rx: FramedPipeReceiver,
     ) -> Result<(), H::Error>
+    where
+        H: MapleServerSessionHandler,
+        H::Transport: Unpin,
+    {
+        let res =
+            Self::exec_session_loop(&mut session, &mut handler, &session_handle, &mut session_rx)
+                .await;
+
+        // The handler is finished in any case, so the session data is written to the db
+        let is_migrating = matches!(res, Ok(true));
+        handler.finish(is_migrating).await?;
+        if is_migrating {
+            log::info!("Session migrated");
+            // Socket has to be kept open cause the client doesn't support
+            // reading a packet when the socket is closed
+            // TODO: make this configurable
+            tokio::time::sleep(Duration::from_millis(7500)).await;
+        }
+
+        session.close().await?;
+        res.map(|_| ())
+    }
+
+    /// Runs the session until it's closed, returns true If the session migrates
+    async fn exec_session_loop(
+        session: &mut MapleSession<H::Transport>,
+        handler: &mut H,
+        session_handle: &SharedSessionHandle,
+        session_rx: &mut FramedPipeReceiver,
+    ) -> Result<bool, H::Error>
     where
         H: MapleServerSessionHandler,
         H::Transport: Unpin,
@@ -69,6 +102,8 @@ where
         let mut ping_interval = tokio::time::interval(H::get_ping_interval());
         ping_interval.tick().await;
         let mut pending_ping = false;
+        let mut tick_interval = tokio::time::interval(H::get_tick_interval());
+        tick_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
         loop {
             //TODO might need some micro-optimization to ensure no future gets stalled
@@ -77,17 +112,11 @@ where
                 // Handle next incoming packet
                 p = session.read_packet() => {
                     let p = p?;
-                    let res = handler.handle_packet(p, &mut session).await?;
+                    let res = handler.handle_packet(p, session).await?;
                     // Handle special results here
                     match res {
                         SessionHandleResult::Migrate => {
-                            log::info!("Session migrated");
-                            handler.finish(true).await?;
-                            // Socket has to be kept open cause the client doesn't support
-                            // reading a packet when the socket is closed
-                            // TODO: make this configurable
-                            tokio::time::sleep(Duration::from_millis(7500)).await;
-                            break;
+                            return Ok(true);
                         },
                         SessionHandleResult::Pong => {
                             // TODO handle this here
@@ -107,6 +136,9 @@ where
                     let ping_packet = handler.get_ping_packet()?;
                     session.send_raw_packet(&ping_packet.data).await?;
                 },
+                _ = tick_interval.tick() => {
+                    handler.handle_tick().await?;
+                },
                 //Handle external Session packets
                 p = session_rx.next() => {
                     // note tx is never dropped, so there'll be always a packet here
@@ -124,17 +156,15 @@ where
             };
         }
 
-        session.close().await?;
-
         // Normal cancellation by timeout or cancellation
-        // TODO: handle panic and gracefully shutdown the session(for example write data to db and other stuff)
-        Ok(())
+        Ok(false)
     }
 
     pub fn spawn_server_session<M>(
         io: M::Transport,
         mut mk: M,
         handshake: Handshake,
+        shutdown: &CancellationToken,
     ) -> Result<Self, M::Error>
     where
         M: MakeServerSessionHandler<Handler = H, Transport = H::Transport, Error = H::Error>
@@ -144,11 +174,12 @@ where
         H::Transport: Unpin + Send + 'static,
         H::Error: Send + 'static,
     {
+        let shutdown = shutdown.clone();
         let handle = tokio::spawn(async move {
             let res = async move {
                 let mut session = MapleSession::initialize_server_session(io, handshake).await?;
 
-                let (sess_handle, sess_rx) = SharedSessionHandle::new();
+                let (sess_handle, sess_rx) = SharedSessionHandle::new_child(&shutdown);
                 let handler = mk
                     .make_handler(&mut session, sess_handle.clone())
                     .await?;
@@ -184,6 +215,8 @@ where
     handshake_gen: H,
     make_handler: MH,
     handles: Vec<MapleSessionHandle<MH::Handler>>,
+    shutdown: CancellationToken,
+    stop_accept: CancellationToken,
 }
 
 impl<MH, H> MapleServer<MH, H>
@@ -197,6 +230,30 @@ where
             handshake_gen,
             make_handler,
             handles: Vec::new(),
+            shutdown: CancellationToken::new(),
+            stop_accept: CancellationToken::new(),
+        }
+    }
+
+    /// Cancelling the token stops accepting connections and closes all sessions
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Cancelling the token stops accepting connections, the open sessions are kept
+    /// until the shutdown token is cancelled
+    pub fn with_stop_accept(mut self, stop_accept: CancellationToken) -> Self {
+        self.stop_accept = stop_accept;
+        self
+    }
+
+    /// Waits until all sessions are finished
+    pub async fn join_sessions(&mut self) {
+        for handle in self.handles.drain(..) {
+            if let Err(err) = handle.handle.await {
+                log::error!("Session task failed: {:?}", err);
+            }
         }
     }
 
@@ -212,8 +269,12 @@ where
         MH::Transport: Send + Unpin + 'static,
     {
         let handshake = self.handshake_gen.generate_handshake();
-        let handle =
-            MapleSessionHandle::spawn_server_session(io, self.make_handler.clone(), handshake)?;
+        let handle = MapleSessionHandle::spawn_server_session(
+            io,
+            self.make_handler.clone(),
+            handshake,
+            &self.shutdown,
+        )?;
         // TODO: there should be an upper limit for active connections
         // cleaning closed connection should operate on Vec<Option<Handle>> probably
         // so a new conneciton just has to find a gap
@@ -257,8 +318,20 @@ where
         let listener = TcpListener::bind(addr).await.map_err(NetError::IO)?;
 
         loop {
-            let (io, _) = listener.accept().await.map_err(NetError::IO)?;
-            self.handle_incoming(io)?;
+            tokio::select! {
+                res = listener.accept() => {
+                    let (io, _) = res.map_err(NetError::IO)?;
+                    self.handle_incoming(io)?;
+                },
+                _ = self.shutdown.cancelled() => break,
+                _ = self.stop_accept.cancelled() => break,
+            }
         }
+        // Refuse new connections until the sessions are closed
+        drop(listener);
+
+        // The sessions are cancelled with the shutdown token
+        self.join_sessions().await;
+        Ok(())
     }
 }
@@ -46,7 +46,15 @@ where
     }
 }
 
-pub struct OptionTail<T>(Option<T>);
+/// Optional value at the end of a packet, which is only present If there's data left
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionTail<T>(pub Option<T>);
+
+impl<T> From<Option<T>> for OptionTail<T> {
+    fn from(value: Option<T>) -> Self {
+        Self(value)
+    }
+}
 
 impl<T> EncodePacket for OptionTail<T>
 where
@@ -72,7 +80,13 @@ where
 {
     fn decode_packet(pr: &mut MaplePacketReader<'de>) -> NetResult<Self> {
         let mut sub_reader = pr.sub_reader();
-        Ok(Self(T::decode_packet(&mut sub_reader).ok()))
+        Ok(Self(match T::decode_packet(&mut sub_reader) {
+            Ok(val) => {
+                pr.commit_sub_reader(sub_reader)?;
+                Some(val)
+            }
+            Err(_) => None,
+        }))
     }
 }
 
@@ -254,7 +268,12 @@ impl<D: EncodePacket> EncodePacket for Option<D> {
 
 #[cfg(test)]
 mod tests {
-    use crate::proto::tests::enc_dec_test_all;
+    use crate::{
+        proto::tests::{enc_dec_test, enc_dec_test_all},
+        DecodePacket, MaplePacketReader,
+    };
+
+    use super::OptionTail;
 
     #[test]
     fn test_name() {
@@ -272,4 +291,18 @@ mod tests {
 
         test_num!(u8,i8,u16,i16,u32,i32,u64,i64,u128,i128,f32,f64,);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn option_tail() {
+        enc_dec_test((1u8, OptionTail(Some(2u32))));
+        enc_dec_test((1u8, OptionTail::<u32>(None)));
+
+        // An incomplete tail is skipped without consuming the data
+        let data = [1u8, 2, 3];
+        let mut pr = MaplePacketReader::new(&data);
+        let (a, tail) = <(u8, OptionTail<u32>)>::decode_packet(&mut pr).unwrap();
+        assert_eq!(a, 1);
+        assert_eq!(tail, OptionTail(None));
+        assert_eq!(pr.remaining_slice(), &[2, 3]);
+    }
+}
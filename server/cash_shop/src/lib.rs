@@ -0,0 +1,578 @@
+use std::{net::IpAddr, time::Duration};
+
+use async_trait::async_trait;
+use data::{
+    entities::sea_orm_active_enums::GenderTy,
+    services::{
+        data::cash_shop::{BuyItemResult, CashItemId, LOCKER_SLOTS},
+        helper::intentory::inv::{InventoryExt, InventoryType},
+        session::{session_data::OwnedMoopleSession, ClientKey, MoopleMigrationKey},
+        SharedServices,
+    },
+};
+use game_data::wz2::Commodity;
+use moople_net::{
+    maple_router_handler,
+    service::{
+        handler::{
+            MakeServerSessionHandler, MapleServerSessionHandler, MapleSessionHandler,
+            SessionHandleResult,
+        },
+        resp::{MigrateResponse, PacketOpcodeExt, PongResponse, ResponsePacket},
+        session_svc::SharedSessionHandle,
+    },
+    MapleSession,
+};
+use moople_packet::{
+    proto::MapleList32, DecodePacket, EncodePacket, HasOpcode, MaplePacket, MaplePacketReader,
+    MaplePacketWriter,
+};
+use proto95::{
+    cash_shop::{
+        CashItemInfo, CashShopBestItem, CashShopBuyReq, CashShopCashItemReq, CashShopCashItemResp,
+        CashShopFailReason, CashShopGiftDone, CashShopGiftInfo, CashShopGiftReq,
+        CashShopLockerData, CashShopMoveLToSDone, CashShopMoveLToSReq, CashShopMoveSToLReq,
+        CashShopQueryCashReq, CashShopQueryCashResp, CashShopSetWishReq, CashShopTransferFieldReq,
+        CashShopWishList, CashType, SetCashShopResp, BEST_ITEMS, GIFT_MSG_LEN,
+    },
+    game::{MigrateCommandResp, MigrateInGameReq},
+    id::ItemId,
+    login::world::WorldId,
+    recv_opcodes::RecvOpcodes,
+    send_opcodes::SendOpcodes,
+    shared::{
+        char::{CharDataAll, CharDataFlagsAll, CharDataHeader},
+        PongReq,
+    },
+};
+use tokio::net::TcpStream;
+
+/// Pets bought in the cash shop start with this name
+const DEFAULT_PET_NAME: &str = "Pet";
+
+/// Cuts the gift message at a char boundary, so It fits into the gift info
+fn truncate_gift_msg(msg: &str) -> &str {
+    let mut len = msg.len().min(GIFT_MSG_LEN - 1);
+    while !msg.is_char_boundary(len) {
+        len -= 1;
+    }
+    &msg[..len]
+}
+
+#[derive(Debug, Clone)]
+pub struct MakeCashShopHandler {
+    services: SharedServices,
+    world_id: WorldId,
+}
+
+impl MakeCashShopHandler {
+    pub fn new(services: SharedServices, world_id: WorldId) -> Self {
+        Self { services, world_id }
+    }
+}
+
+#[async_trait::async_trait]
+impl MakeServerSessionHandler for MakeCashShopHandler {
+    type Transport = TcpStream;
+
+    type Error = anyhow::Error;
+
+    type Handler = CashShopHandler;
+
+    async fn make_handler(
+        &mut self,
+        sess: &mut MapleSession<Self::Transport>,
+        sess_handle: SharedSessionHandle,
+    ) -> Result<Self::Handler, Self::Error> {
+        let mut handler =
+            CashShopHandler::from_session(sess, self.services.clone(), self.world_id, sess_handle)
+                .await?;
+        sess.send_packet(handler.set_cash_shop().await?).await?;
+        handler.init_cash_shop().await?;
+
+        Ok(handler)
+    }
+}
+
+pub struct CashShopHandler {
+    session: OwnedMoopleSession,
+    world_id: WorldId,
+    services: SharedServices,
+    addr: IpAddr,
+    client_key: ClientKey,
+    sess_handle: SharedSessionHandle,
+}
+
+impl CashShopHandler {
+    pub async fn from_session(
+        net_session: &mut MapleSession<TcpStream>,
+        services: SharedServices,
+        world_id: WorldId,
+        sess_handle: SharedSessionHandle,
+    ) -> anyhow::Result<Self> {
+        let pkt = net_session.read_packet().await?;
+        let mut pr = pkt.into_reader();
+
+        let op = pr.read_opcode::<RecvOpcodes>()?;
+        if op != MigrateInGameReq::OPCODE {
+            anyhow::bail!("Wrong client hello packet: {op:?}")
+        }
+
+        let req = MigrateInGameReq::decode_packet(&mut pr)?;
+        let addr = net_session.peer_addr()?.ip();
+
+        let session = services
+            .session_manager
+            .claim_migration_session(MoopleMigrationKey::new(req.client_key, addr))
+            .await?;
+
+        log::info!(
+            "Cash shop session for acc: {} - char: {}",
+            session.acc.username,
+            session.char.model.name
+        );
+
+        Ok(Self {
+            session,
+            world_id,
+            services,
+            addr,
+            client_key: req.client_key,
+            sess_handle,
+        })
+    }
+
+    fn send_pkt<T: EncodePacket + HasOpcode>(&mut self, pkt: T) -> anyhow::Result<()> {
+        let mut pw = MaplePacketWriter::default();
+        pw.write_opcode(T::OPCODE);
+        pkt.encode_packet(&mut pw)?;
+        self.sess_handle.tx.try_send(&pw.into_packet().data)?;
+        Ok(())
+    }
+
+    async fn set_cash_shop(&mut self) -> anyhow::Result<SetCashShopResp> {
+        let highest_level = self
+            .services
+            .data
+            .char
+            .get_world_characters_for_account(self.session.acc.id, self.world_id)
+            .await?
+            .iter()
+            .map(|char| char.level as u32)
+            .max()
+            .unwrap_or(0);
+
+        Ok(SetCashShopResp {
+            char_data_flags: CharDataFlagsAll,
+            char_data_hdr: CharDataHeader {
+                combat_orders: 0,
+                extra_data: None.into(),
+            },
+            char_data: CharDataAll::from(&*self.session),
+            authorized: true,
+            nexon_club_id: self.session.acc.username.clone(),
+            not_sale: MapleList32::default(),
+            modified_commodities: 0,
+            discount_rates: 0,
+            best_items: [CashShopBestItem::default(); BEST_ITEMS],
+            stock: 0,
+            limit_goods: 0,
+            zero_goods: 0,
+            event_on: false,
+            highest_level,
+        })
+    }
+
+    async fn init_cash_shop(&mut self) -> anyhow::Result<()> {
+        let locker = self.locker_data().await?;
+        self.send_pkt(CashShopCashItemResp::LoadLockerDone(locker))?;
+
+        let gifts = self
+            .services
+            .data
+            .cash_shop
+            .take_gifts(self.session.acc.id)
+            .await?;
+        if !gifts.is_empty() {
+            let gifts = gifts
+                .iter()
+                .map(|gift| {
+                    let msg = truncate_gift_msg(gift.gift_msg.as_deref().unwrap_or_default());
+                    Ok(CashShopGiftInfo {
+                        sn: gift.id as u64,
+                        item_id: ItemId(gift.item_id as u32),
+                        sender: gift.buyer.as_str().try_into().map_err(|_| {
+                            anyhow::format_err!("Invalid gift sender: {}", gift.buyer)
+                        })?,
+                        msg: msg.try_into().expect("Truncated gift message"),
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            self.send_pkt(CashShopCashItemResp::LoadGiftDone(gifts.into()))?;
+        }
+
+        let wish_list = self
+            .services
+            .data
+            .cash_shop
+            .load_wish_list(self.session.char.model.id)
+            .await?;
+        self.send_pkt(CashShopCashItemResp::LoadWishDone(CashShopWishList {
+            wish_list,
+        }))?;
+
+        let cash = self.cash();
+        self.send_pkt(cash)
+    }
+
+    async fn locker_data(&self) -> anyhow::Result<CashShopLockerData> {
+        let acc = &self.session.acc;
+        let items = self.services.data.cash_shop.load_locker(acc.id).await?;
+        let char_count = self
+            .services
+            .data
+            .char
+            .get_world_characters_for_account(acc.id, self.world_id)
+            .await?
+            .len();
+
+        Ok(CashShopLockerData {
+            items: items
+                .iter()
+                .map(CashItemInfo::try_from)
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .into(),
+            trunk_slots: LOCKER_SLOTS,
+            char_slots: acc.character_slots as u16,
+            buy_char_count: 0,
+            char_count: char_count as u16,
+        })
+    }
+
+    fn cash(&self) -> CashShopQueryCashResp {
+        let acc = &self.session.acc;
+        CashShopQueryCashResp {
+            nx_credit: acc.nx_credit as u32,
+            maple_points: acc.maple_points as u32,
+            nx_prepaid: acc.nx_prepaid as u32,
+        }
+    }
+
+    /// Looks up the commodity and checks whether a character with the gender may own it
+    fn get_commodity(
+        &self,
+        sn: u32,
+        gender: &GenderTy,
+    ) -> Result<&'static Commodity, CashShopFailReason> {
+        let commodity = self
+            .services
+            .meta
+            .get_commodity(sn)
+            .filter(|commodity| commodity.on_sale != 0)
+            .ok_or(CashShopFailReason::NotAvailable)?;
+
+        let gender = match gender {
+            GenderTy::Male => 0,
+            GenderTy::Female => 1,
+        };
+        if commodity.gender != 2 && commodity.gender != gender {
+            return Err(CashShopFailReason::GenderRestriction);
+        }
+
+        Ok(commodity)
+    }
+
+    async fn handle_pong(&mut self, _req: PongReq) -> anyhow::Result<PongResponse> {
+        Ok(PongResponse)
+    }
+
+    async fn handle_query_cash(&mut self, _req: CashShopQueryCashReq) -> anyhow::Result<()> {
+        let cash = self.cash();
+        self.send_pkt(cash)
+    }
+
+    async fn handle_cash_item(&mut self, req: CashShopCashItemReq) -> anyhow::Result<()> {
+        match req {
+            CashShopCashItemReq::Buy(req) => self.handle_buy(req).await,
+            CashShopCashItemReq::Gift(req) => self.handle_gift(req).await,
+            CashShopCashItemReq::SetWish(req) => self.handle_set_wish(req).await,
+            CashShopCashItemReq::MoveLToS(req) => self.handle_move_l_to_s(req).await,
+            CashShopCashItemReq::MoveSToL(req) => self.handle_move_s_to_l(req).await,
+        }
+    }
+
+    async fn handle_buy(&mut self, req: CashShopBuyReq) -> anyhow::Result<()> {
+        let commodity = match self.get_commodity(req.sn, &self.session.char.model.gender) {
+            Ok(commodity) => commodity,
+            Err(reason) => return self.send_pkt(CashShopCashItemResp::BuyFailed(reason)),
+        };
+
+        let acc_id = self.session.acc.id;
+        let fail = |reason| CashShopCashItemResp::BuyFailed(reason);
+        let (acc, item) = match self
+            .services
+            .data
+            .cash_shop
+            .buy_item(
+                acc_id,
+                req.cash_type,
+                acc_id,
+                commodity,
+                &self.session.char.model.name,
+                None,
+            )
+            .await?
+        {
+            BuyItemResult::Bought(acc, item) => (acc, item),
+            BuyItemResult::NotEnoughCash => {
+                return self.send_pkt(fail(CashShopFailReason::NotEnoughCash))
+            }
+            BuyItemResult::LockerFull => {
+                return self.send_pkt(fail(CashShopFailReason::TooManyCashItems))
+            }
+        };
+        self.session.acc = acc;
+        self.send_pkt(CashShopCashItemResp::BuyDone((&item).try_into()?))?;
+
+        let cash = self.cash();
+        self.send_pkt(cash)
+    }
+
+    async fn handle_gift(&mut self, req: CashShopGiftReq) -> anyhow::Result<()> {
+        let fail = |reason| CashShopCashItemResp::GiftFailed(reason);
+        let Some(receiver) = self.services.data.char.get_by_name(&req.receiver).await? else {
+            return self.send_pkt(fail(CashShopFailReason::InvalidReceiver));
+        };
+        if receiver.acc_id == self.session.acc.id {
+            return self.send_pkt(fail(CashShopFailReason::CantGiftOwnAccount));
+        }
+
+        let commodity = match self.get_commodity(req.sn, &receiver.gender) {
+            Ok(commodity) => commodity,
+            Err(reason) => return self.send_pkt(fail(reason)),
+        };
+
+        // Gifts can only be paid with NX credit
+        let msg = truncate_gift_msg(&req.msg);
+        let acc = match self
+            .services
+            .data
+            .cash_shop
+            .buy_item(
+                self.session.acc.id,
+                CashType::NxCredit,
+                receiver.acc_id,
+                commodity,
+                &self.session.char.model.name,
+                Some(msg),
+            )
+            .await?
+        {
+            BuyItemResult::Bought(acc, _) => acc,
+            BuyItemResult::NotEnoughCash => {
+                return self.send_pkt(fail(CashShopFailReason::NotEnoughCash))
+            }
+            BuyItemResult::LockerFull => {
+                return self.send_pkt(fail(CashShopFailReason::ReceiverInventoryFull))
+            }
+        };
+        self.session.acc = acc;
+        self.send_pkt(CashShopCashItemResp::GiftDone(CashShopGiftDone {
+            receiver: receiver.name,
+            item_id: ItemId(commodity.item_id),
+            quantity: commodity.count as u16,
+            price: commodity.price,
+        }))?;
+
+        let cash = self.cash();
+        self.send_pkt(cash)
+    }
+
+    async fn handle_set_wish(&mut self, req: CashShopSetWishReq) -> anyhow::Result<()> {
+        self.services
+            .data
+            .cash_shop
+            .save_wish_list(self.session.char.model.id, &req.wish_list)
+            .await?;
+        self.send_pkt(CashShopCashItemResp::SetWishDone(CashShopWishList {
+            wish_list: req.wish_list,
+        }))
+    }
+
+    async fn handle_move_l_to_s(&mut self, req: CashShopMoveLToSReq) -> anyhow::Result<()> {
+        let fail = CashShopCashItemResp::MoveLToSFailed(CashShopFailReason::Unknown);
+        let acc_id = self.session.acc.id;
+        let cash_shop = &self.services.data.cash_shop;
+        let Some(cash_item) = cash_shop
+            .get_locker_item(acc_id, req.sn as CashItemId)
+            .await?
+        else {
+            return self.send_pkt(fail);
+        };
+
+        if !self
+            .session
+            .inv()
+            .has_free_slot(ItemId(cash_item.item_id as u32))
+        {
+            return self.send_pkt(fail);
+        }
+
+        // The locker and the inventory are written together, so the item can't get lost
+        let char_id = self.session.char.model.id;
+        let mut inv = self.session.inv().clone();
+        let Some((slot, item)) = cash_shop
+            .take_item(acc_id, cash_item.id, DEFAULT_PET_NAME, char_id, &mut inv)
+            .await?
+        else {
+            return self.send_pkt(fail);
+        };
+        *self.session.inv_mut() = inv;
+
+        self.send_pkt(CashShopCashItemResp::MoveLToSDone(CashShopMoveLToSDone {
+            pos: slot as u16 + 1,
+            item: item.to_proto(),
+        }))
+    }
+
+    async fn handle_move_s_to_l(&mut self, req: CashShopMoveSToLReq) -> anyhow::Result<()> {
+        let fail = |reason| CashShopCashItemResp::MoveSToLFailed(reason);
+        let inv = self.session.inv();
+        let item = match InventoryType::try_from(req.inv_type)? {
+            InventoryType::Equip => inv
+                .equip
+                .iter()
+                .find(|(_, item)| item.item.cash_id == Some(req.sn))
+                .map(|(slot, _)| (InventoryType::Equip, slot, 1)),
+            InventoryType::Cash => inv
+                .cash
+                .iter()
+                .find(|(_, item)| item.item.cash_id == Some(req.sn))
+                .map(|(slot, item)| (InventoryType::Cash, slot, item.item.quantity)),
+            _ => None,
+        };
+        let Some((inv_type, slot, quantity)) = item else {
+            return self.send_pkt(fail(CashShopFailReason::NotAvailable));
+        };
+
+        // The locker and the inventory are written together, so the item can't be duplicated
+        let mut inv = self.session.inv().clone();
+        match inv_type {
+            InventoryType::Equip => {
+                inv.equip.remove(slot);
+            }
+            _ => {
+                inv.cash.remove(slot);
+            }
+        }
+        let Some(cash_item) = self
+            .services
+            .data
+            .cash_shop
+            .return_item(
+                self.session.acc.id,
+                req.sn as CashItemId,
+                quantity,
+                self.session.char.model.id,
+                &mut inv,
+            )
+            .await?
+        else {
+            return self.send_pkt(fail(CashShopFailReason::Unknown));
+        };
+        *self.session.inv_mut() = inv;
+        self.send_pkt(CashShopCashItemResp::MoveSToLDone((&cash_item).try_into()?))
+    }
+
+    /// Leaving the cash shop returns the character to the channel it came from
+    async fn handle_transfer_field(
+        &mut self,
+        _req: CashShopTransferFieldReq,
+    ) -> anyhow::Result<MigrateResponse<ResponsePacket<SendOpcodes, MigrateCommandResp>>> {
+        let addr = self
+            .services
+            .server_info
+            .get_channel_addr(self.world_id, self.session.channel_id)?;
+
+        let pkt: ResponsePacket<_, _> = MigrateCommandResp {
+            unknown: true,
+            addr: addr.try_into()?,
+        }
+        .into_response(MigrateCommandResp::OPCODE);
+
+        Ok(MigrateResponse(pkt))
+    }
+
+    pub async fn handle_default(
+        &mut self,
+        _op: RecvOpcodes,
+        pr: MaplePacketReader<'_>,
+    ) -> anyhow::Result<SessionHandleResult> {
+        log::info!("Unhandled cash shop packet: {:?}", pr.into_inner());
+        Ok(SessionHandleResult::Ok)
+    }
+}
+
+#[async_trait]
+impl MapleSessionHandler for CashShopHandler {
+    type Transport = TcpStream;
+    type Error = anyhow::Error;
+
+    async fn handle_packet(
+        &mut self,
+        packet: MaplePacket,
+        session: &mut MapleSession<Self::Transport>,
+    ) -> Result<SessionHandleResult, Self::Error> {
+        maple_router_handler!(
+            handler,
+            CashShopHandler,
+            MapleSession<TcpStream>,
+            anyhow::Error,
+            CashShopHandler::handle_default,
+            PongReq => CashShopHandler::handle_pong,
+            CashShopQueryCashReq => CashShopHandler::handle_query_cash,
+            CashShopCashItemReq => CashShopHandler::handle_cash_item,
+            CashShopTransferFieldReq => CashShopHandler::handle_transfer_field,
+        );
+
+        Ok(handler(self, session, packet.into_reader()).await?)
+    }
+
+    async fn finish(mut self, is_migrating: bool) -> Result<(), Self::Error> {
+        log::info!("Finishing cash shop session...");
+        if is_migrating {
+            // Persist the state before the session is handed to the next server
+            if let Err(err) = self
+                .services
+                .session_manager
+                .save_session(&mut self.session)
+                .await
+            {
+                log::error!("Unable to save the session: {err:?}");
+            }
+            self.services.session_manager.migrate_session(
+                MoopleMigrationKey::new(self.client_key, self.addr),
+                self.session,
+            )?;
+        } else {
+            self.services
+                .session_manager
+                .close_session(self.session)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl MapleServerSessionHandler for CashShopHandler {
+    fn get_ping_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn get_ping_packet(&mut self) -> Result<MaplePacket, Self::Error> {
+        let mut pw = MaplePacketWriter::default();
+        pw.write_opcode(SendOpcodes::AliveReq);
+        Ok(pw.into_packet())
+    }
+}
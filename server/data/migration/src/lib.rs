@@ -4,12 +4,32 @@ pub mod moople;
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20261019_000001_entrusted_shop;
+mod m20261019_000002_mini_game_record;
+mod m20261019_000003_cash_shop;
+mod m20261019_000004_fame_log;
+mod m20261019_000005_key_map;
+mod m20261019_000006_gm_log;
+mod m20261019_000007_ban;
+mod m20261019_000008_char_deletion;
+mod m20261019_000009_char_world;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::<m20220101_000001_create_table::Migration>::default()]
+        vec![
+            Box::<m20220101_000001_create_table::Migration>::default(),
+            Box::<m20261019_000001_entrusted_shop::Migration>::default(),
+            Box::<m20261019_000002_mini_game_record::Migration>::default(),
+            Box::<m20261019_000003_cash_shop::Migration>::default(),
+            Box::<m20261019_000004_fame_log::Migration>::default(),
+            Box::<m20261019_000005_key_map::Migration>::default(),
+            Box::<m20261019_000006_gm_log::Migration>::default(),
+            Box::<m20261019_000007_ban::Migration>::default(),
+            Box::<m20261019_000008_char_deletion::Migration>::default(),
+            Box::<m20261019_000009_char_world::Migration>::default(),
+        ]
     }
 }
@@ -288,10 +288,17 @@ impl Migration {
     }
 }
 
+fn is_postgres(manager: &SchemaManager) -> bool {
+    manager.get_database_backend() == sea_orm::DbBackend::Postgres
+}
+
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        manager.create_type(moople_gender_ty()).await?;
+        // Only Postgres has enum types, other backends store the gender as string
+        if is_postgres(manager) {
+            manager.create_type(moople_gender_ty()).await?;
+        }
 
         for tbl in self.table_iter() {
             tbl.create_table(manager).await?;
@@ -306,9 +313,11 @@ impl MigrationTrait for Migration {
             tbl.drop_table(manager).await?;
         }
 
-        manager
-            .drop_type(Type::drop().name(Gender::GenderTy).to_owned())
-            .await?;
+        if is_postgres(manager) {
+            manager
+                .drop_type(Type::drop().name(Gender::GenderTy).to_owned())
+                .await?;
+        }
 
         Ok(())
     }
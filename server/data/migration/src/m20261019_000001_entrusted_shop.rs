@@ -0,0 +1,114 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum ItemStack {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum EquipItem {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum EntrustedShop {
+    Table,
+    Id,
+    CharId,
+    MapId,
+    PosX,
+    PosY,
+    Fh,
+    PermitId,
+    Title,
+    Mesos,
+    Open,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum EntrustedShopItem {
+    Table,
+    Id,
+    ShopId,
+    EquipItemId,
+    StackItemId,
+    Bundles,
+    BundleSize,
+    Price,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    entrusted_shop_table: MoopleTbl,
+    entrusted_shop_item_table: MoopleTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        // Tables of the initial migration, only used as reference
+        let char_table = MoopleTbl::new(Character::Table, Character::Id, [], []);
+        let item_stack_table = MoopleTbl::new(ItemStack::Table, ItemStack::Id, [], []);
+        let item_equip_table = MoopleTbl::new(EquipItem::Table, EquipItem::Id, [], []);
+
+        let entrusted_shop_table = MoopleTbl::new(
+            EntrustedShop::Table,
+            EntrustedShop::Id,
+            [
+                moople_id(EntrustedShop::MapId),
+                moople_int(EntrustedShop::PosX),
+                moople_int(EntrustedShop::PosY),
+                moople_int(EntrustedShop::Fh),
+                moople_id(EntrustedShop::PermitId),
+                moople_str(EntrustedShop::Title).not_null().to_owned(),
+                moople_size(EntrustedShop::Mesos),
+                moople_bool(EntrustedShop::Open),
+                created_at(EntrustedShop::CreatedAt),
+            ],
+            [Ref::ownership(EntrustedShop::CharId, &char_table)],
+        );
+
+        let entrusted_shop_item_table = MoopleTbl::new(
+            EntrustedShopItem::Table,
+            EntrustedShopItem::Id,
+            [
+                moople_size(EntrustedShopItem::Bundles),
+                moople_size(EntrustedShopItem::BundleSize),
+                moople_size(EntrustedShopItem::Price),
+            ],
+            [
+                Ref::ownership(EntrustedShopItem::ShopId, &entrusted_shop_table),
+                Ref::opt(EntrustedShopItem::EquipItemId, &item_equip_table),
+                Ref::opt(EntrustedShopItem::StackItemId, &item_stack_table),
+            ],
+        );
+
+        Self {
+            entrusted_shop_table,
+            entrusted_shop_item_table,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.entrusted_shop_table.create_table(manager).await?;
+        self.entrusted_shop_item_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.entrusted_shop_item_table.drop_table(manager).await?;
+        self.entrusted_shop_table.drop_table(manager).await
+    }
+}
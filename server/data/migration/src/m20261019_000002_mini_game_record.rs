@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum MiniGameRecord {
+    Table,
+    Id,
+    CharId,
+    GameId,
+    Win,
+    Draw,
+    Lose,
+    Score,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    mini_game_record_table: MoopleTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        let char_table = MoopleTbl::new(Character::Table, Character::Id, [], []);
+
+        let mini_game_record_table = MoopleTbl::new(
+            MiniGameRecord::Table,
+            MiniGameRecord::Id,
+            [
+                moople_int(MiniGameRecord::GameId),
+                moople_int(MiniGameRecord::Win),
+                moople_int(MiniGameRecord::Draw),
+                moople_int(MiniGameRecord::Lose),
+                moople_int(MiniGameRecord::Score),
+            ],
+            [Ref::ownership(MiniGameRecord::CharId, &char_table)],
+        );
+
+        Self {
+            mini_game_record_table,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.mini_game_record_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.mini_game_record_table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,104 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Account {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum PetItem {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum CashItem {
+    Table,
+    Id,
+    AccId,
+    ItemId,
+    CommoditySn,
+    Quantity,
+    Buyer,
+    GiftMsg,
+    InInventory,
+    ExpiresAt,
+    CreatedAt,
+    PetItemId,
+}
+
+#[derive(Iden)]
+enum WishList {
+    Table,
+    Id,
+    CharId,
+    CommoditySn,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    cash_item_table: MoopleTbl,
+    wish_list_table: MoopleTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        let acc_table = MoopleTbl::new(Account::Table, Account::Id, [], []);
+        let char_table = MoopleTbl::new(Character::Table, Character::Id, [], []);
+        let pet_table = MoopleTbl::new(PetItem::Table, PetItem::Id, [], []);
+
+        let cash_item_table = MoopleTbl::new(
+            CashItem::Table,
+            CashItem::Id,
+            [
+                moople_id(CashItem::ItemId),
+                moople_id(CashItem::CommoditySn),
+                moople_size(CashItem::Quantity),
+                moople_name(CashItem::Buyer),
+                moople_str(CashItem::GiftMsg),
+                moople_bool(CashItem::InInventory),
+                date_time(CashItem::ExpiresAt),
+                created_at(CashItem::CreatedAt),
+            ],
+            // Pets keep their item while they are in the locker
+            [
+                Ref::ownership(CashItem::AccId, &acc_table),
+                Ref::opt(CashItem::PetItemId, &pet_table),
+            ],
+        );
+
+        let wish_list_table = MoopleTbl::new(
+            WishList::Table,
+            WishList::Id,
+            [moople_id(WishList::CommoditySn)],
+            [Ref::ownership(WishList::CharId, &char_table)],
+        );
+
+        Self {
+            cash_item_table,
+            wish_list_table,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.cash_item_table.create_table(manager).await?;
+        self.wish_list_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.wish_list_table.drop_table(manager).await?;
+        self.cash_item_table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum FameLog {
+    Table,
+    Id,
+    CharId,
+    TargetId,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    fame_log_table: MoopleTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        let char_table = MoopleTbl::new(Character::Table, Character::Id, [], []);
+
+        // Only the giver is a foreign key, both columns would reference the character table
+        let fame_log_table = MoopleTbl::new(
+            FameLog::Table,
+            FameLog::Id,
+            [moople_id(FameLog::TargetId), created_at(FameLog::CreatedAt)],
+            [Ref::ownership(FameLog::CharId, &char_table)],
+        );
+
+        Self { fame_log_table }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.fame_log_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.fame_log_table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,142 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum KeyBinding {
+    Table,
+    Id,
+    CharId,
+    Key,
+    Ty,
+    ActionId,
+}
+
+#[derive(Iden)]
+enum QuickSlot {
+    Table,
+    Id,
+    CharId,
+    Slot,
+    Key,
+}
+
+#[derive(Iden)]
+enum SkillMacro {
+    Table,
+    Id,
+    CharId,
+    Ix,
+    Name,
+    Mute,
+    Skill1,
+    Skill2,
+    Skill3,
+}
+
+#[derive(Iden)]
+enum PetConsumeItem {
+    Table,
+    Id,
+    CharId,
+    HpItemId,
+    MpItemId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    key_binding_table: MoopleTbl,
+    quick_slot_table: MoopleTbl,
+    skill_macro_table: MoopleTbl,
+    pet_consume_item_table: MoopleTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        let char_table = MoopleTbl::new(Character::Table, Character::Id, [], []);
+
+        let key_binding_table = MoopleTbl::new(
+            KeyBinding::Table,
+            KeyBinding::Id,
+            [
+                moople_int(KeyBinding::Key),
+                moople_int(KeyBinding::Ty),
+                moople_int(KeyBinding::ActionId),
+            ],
+            [Ref::ownership(KeyBinding::CharId, &char_table)],
+        );
+
+        let quick_slot_table = MoopleTbl::new(
+            QuickSlot::Table,
+            QuickSlot::Id,
+            [moople_int(QuickSlot::Slot), moople_int(QuickSlot::Key)],
+            [Ref::ownership(QuickSlot::CharId, &char_table)],
+        );
+
+        let skill_macro_table = MoopleTbl::new(
+            SkillMacro::Table,
+            SkillMacro::Id,
+            [
+                moople_int(SkillMacro::Ix),
+                moople_name(SkillMacro::Name),
+                moople_bool(SkillMacro::Mute),
+                moople_id(SkillMacro::Skill1),
+                moople_id(SkillMacro::Skill2),
+                moople_id(SkillMacro::Skill3),
+            ],
+            [Ref::ownership(SkillMacro::CharId, &char_table)],
+        );
+
+        let pet_consume_item_table = MoopleTbl::new(
+            PetConsumeItem::Table,
+            PetConsumeItem::Id,
+            [
+                moople_int(PetConsumeItem::HpItemId),
+                moople_int(PetConsumeItem::MpItemId),
+            ],
+            [Ref::ownership(PetConsumeItem::CharId, &char_table)],
+        );
+
+        Self {
+            key_binding_table,
+            quick_slot_table,
+            skill_macro_table,
+            pet_consume_item_table,
+        }
+    }
+}
+
+impl Migration {
+    fn table_iter(&self) -> impl DoubleEndedIterator<Item = &MoopleTbl> {
+        [
+            &self.key_binding_table,
+            &self.quick_slot_table,
+            &self.skill_macro_table,
+            &self.pet_consume_item_table,
+        ]
+        .into_iter()
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for tbl in self.table_iter() {
+            tbl.create_table(manager).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for tbl in self.table_iter().rev() {
+            tbl.drop_table(manager).await?;
+        }
+        Ok(())
+    }
+}
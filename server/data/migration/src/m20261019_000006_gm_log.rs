@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum GmLog {
+    Table,
+    Id,
+    AccId,
+    CharId,
+    CharName,
+    MapId,
+    Command,
+    Allowed,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration {
+    gm_log_table: MoopleTbl,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        // The log is kept for audits, so deleted characters must not take their entries along
+        let gm_log_table = MoopleTbl::new(
+            GmLog::Table,
+            GmLog::Id,
+            [
+                moople_id(GmLog::AccId),
+                moople_id(GmLog::CharId),
+                moople_name(GmLog::CharName),
+                moople_id(GmLog::MapId),
+                moople_str(GmLog::Command).not_null().to_owned(),
+                moople_bool(GmLog::Allowed),
+                created_at(GmLog::CreatedAt),
+            ],
+            [],
+        );
+
+        Self { gm_log_table }
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.gm_log_table.create_table(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        self.gm_log_table.drop_table(manager).await
+    }
+}
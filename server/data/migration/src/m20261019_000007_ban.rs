@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Account {
+    Table,
+    LastMachineId,
+}
+
+#[derive(Iden)]
+enum Ban {
+    Table,
+    ReasonCode,
+    Ip,
+    MachineId,
+    CreatedAt,
+}
+
+#[derive(DeriveMigrationName, Default)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Sqlite can't add a column with a non-constant default,
+        // so the creation time of the existing bans is filled in afterwards
+        add_columns(
+            manager,
+            Ban::Table,
+            [
+                moople_int(Ban::ReasonCode),
+                moople_str(Ban::Ip),
+                moople_str(Ban::MachineId),
+                date_time(Ban::CreatedAt),
+            ],
+        )
+        .await?;
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Ban::Table)
+                    .value(Ban::CreatedAt, Expr::current_timestamp())
+                    .and_where(Expr::col(Ban::CreatedAt).is_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        add_columns(
+            manager,
+            Account::Table,
+            [moople_str(Account::LastMachineId)],
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_columns(manager, Account::Table, [Account::LastMachineId]).await?;
+        drop_columns(
+            manager,
+            Ban::Table,
+            [Ban::ReasonCode, Ban::Ip, Ban::MachineId, Ban::CreatedAt],
+        )
+        .await
+    }
+}
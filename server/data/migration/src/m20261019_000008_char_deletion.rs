@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Account {
+    Table,
+    Birthday,
+}
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    DeleteAt,
+}
+
+#[derive(DeriveMigrationName, Default)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        add_columns(
+            manager,
+            Account::Table,
+            [ColumnDef::new(Account::Birthday).date().to_owned()],
+        )
+        .await?;
+        add_columns(manager, Character::Table, [date_time(Character::DeleteAt)]).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_columns(manager, Character::Table, [Character::DeleteAt]).await?;
+        drop_columns(manager, Account::Table, [Account::Birthday]).await
+    }
+}
@@ -0,0 +1,24 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helper::*;
+
+#[derive(Iden)]
+enum Character {
+    Table,
+    WorldId,
+}
+
+#[derive(DeriveMigrationName, Default)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing characters are moved to the first world by the default
+        add_columns(manager, Character::Table, [moople_int(Character::WorldId)]).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_columns(manager, Character::Table, [Character::WorldId]).await
+    }
+}
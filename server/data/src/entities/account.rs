@@ -25,12 +25,16 @@ pub struct Model {
     pub nx_prepaid: i32,
     pub maple_points: i32,
     pub tester: bool,
+    pub birthday: Option<Date>,
+    pub last_machine_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::ban::Entity")]
     Ban,
+    #[sea_orm(has_many = "super::cash_item::Entity")]
+    CashItem,
     #[sea_orm(has_many = "super::character::Entity")]
     Character,
 }
@@ -41,6 +45,12 @@ impl Related<super::ban::Entity> for Entity {
     }
 }
 
+impl Related<super::cash_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CashItem.def()
+    }
+}
+
 impl Related<super::character::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Character.def()
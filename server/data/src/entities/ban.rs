@@ -9,6 +9,10 @@ pub struct Model {
     pub id: i32,
     pub ban_reason: Option<String>,
     pub ban_time: Option<DateTime>,
+    pub reason_code: i32,
+    pub ip: Option<String>,
+    pub machine_id: Option<String>,
+    pub created_at: Option<DateTime>,
     pub acc_id: i32,
 }
 
@@ -42,6 +42,8 @@ pub struct Model {
     pub skin: i32,
     pub hair: i32,
     pub spawn_point: i32,
+    pub world_id: i32,
+    pub delete_at: Option<DateTime>,
     pub acc_id: i32,
 }
 
@@ -55,10 +57,26 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Account,
+    #[sea_orm(has_many = "super::entrusted_shop::Entity")]
+    EntrustedShop,
+    #[sea_orm(has_many = "super::fame_log::Entity")]
+    FameLog,
     #[sea_orm(has_many = "super::inventory_slot::Entity")]
     InventorySlot,
+    #[sea_orm(has_many = "super::key_binding::Entity")]
+    KeyBinding,
+    #[sea_orm(has_many = "super::mini_game_record::Entity")]
+    MiniGameRecord,
+    #[sea_orm(has_many = "super::pet_consume_item::Entity")]
+    PetConsumeItem,
+    #[sea_orm(has_many = "super::quick_slot::Entity")]
+    QuickSlot,
     #[sea_orm(has_many = "super::skill::Entity")]
     Skill,
+    #[sea_orm(has_many = "super::skill_macro::Entity")]
+    SkillMacro,
+    #[sea_orm(has_many = "super::wish_list::Entity")]
+    WishList,
 }
 
 impl Related<super::account::Entity> for Entity {
@@ -67,16 +85,64 @@ impl Related<super::account::Entity> for Entity {
     }
 }
 
+impl Related<super::entrusted_shop::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EntrustedShop.def()
+    }
+}
+
+impl Related<super::fame_log::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FameLog.def()
+    }
+}
+
 impl Related<super::inventory_slot::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::InventorySlot.def()
     }
 }
 
+impl Related<super::key_binding::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeyBinding.def()
+    }
+}
+
+impl Related<super::mini_game_record::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MiniGameRecord.def()
+    }
+}
+
+impl Related<super::pet_consume_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PetConsumeItem.def()
+    }
+}
+
+impl Related<super::quick_slot::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::QuickSlot.def()
+    }
+}
+
 impl Related<super::skill::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Skill.def()
     }
 }
 
+impl Related<super::skill_macro::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SkillMacro.def()
+    }
+}
+
+impl Related<super::wish_list::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WishList.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -16,10 +16,18 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::entrusted_shop_item::Entity")]
+    EntrustedShopItem,
     #[sea_orm(has_many = "super::inventory_slot::Entity")]
     InventorySlot,
 }
 
+impl Related<super::entrusted_shop_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EntrustedShopItem.def()
+    }
+}
+
 impl Related<super::inventory_slot::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::InventorySlot.def()
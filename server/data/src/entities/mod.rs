@@ -4,10 +4,21 @@ pub mod prelude;
 
 pub mod account;
 pub mod ban;
+pub mod cash_item;
 pub mod character;
+pub mod entrusted_shop;
+pub mod entrusted_shop_item;
 pub mod equip_item;
+pub mod fame_log;
+pub mod gm_log;
 pub mod inventory_slot;
 pub mod item_stack;
+pub mod key_binding;
+pub mod mini_game_record;
+pub mod pet_consume_item;
 pub mod pet_item;
+pub mod quick_slot;
 pub mod sea_orm_active_enums;
 pub mod skill;
+pub mod skill_macro;
+pub mod wish_list;
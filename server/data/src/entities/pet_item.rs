@@ -22,10 +22,18 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::cash_item::Entity")]
+    CashItem,
     #[sea_orm(has_many = "super::inventory_slot::Entity")]
     InventorySlot,
 }
 
+impl Related<super::cash_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CashItem.def()
+    }
+}
+
 impl Related<super::inventory_slot::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::InventorySlot.def()
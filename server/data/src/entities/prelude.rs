@@ -2,9 +2,20 @@
 
 pub use super::account::Entity as Account;
 pub use super::ban::Entity as Ban;
+pub use super::cash_item::Entity as CashItem;
 pub use super::character::Entity as Character;
+pub use super::entrusted_shop::Entity as EntrustedShop;
+pub use super::entrusted_shop_item::Entity as EntrustedShopItem;
 pub use super::equip_item::Entity as EquipItem;
+pub use super::fame_log::Entity as FameLog;
+pub use super::gm_log::Entity as GmLog;
 pub use super::inventory_slot::Entity as InventorySlot;
 pub use super::item_stack::Entity as ItemStack;
+pub use super::key_binding::Entity as KeyBinding;
+pub use super::mini_game_record::Entity as MiniGameRecord;
+pub use super::pet_consume_item::Entity as PetConsumeItem;
 pub use super::pet_item::Entity as PetItem;
+pub use super::quick_slot::Entity as QuickSlot;
 pub use super::skill::Entity as Skill;
+pub use super::skill_macro::Entity as SkillMacro;
+pub use super::wish_list::Entity as WishList;
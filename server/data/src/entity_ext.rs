@@ -1,3 +1,5 @@
+use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, Iterable, ModelTrait};
+
 use crate::entities::character;
 
 impl character::Model {
@@ -8,4 +10,26 @@ impl character::Model {
     pub fn get_skill_pages_mut(&mut self) -> &mut [u8; 10] {
         self.skill_points.as_mut_slice().try_into().unwrap()
     }
-}
\ No newline at end of file
+}
+
+/// Active model with only the columns set, which differ from the saved model,
+/// `None` If nothing changed
+pub fn changed_active_model<A>(
+    saved: &<A::Entity as EntityTrait>::Model,
+    model: &<A::Entity as EntityTrait>::Model,
+) -> Option<A>
+where
+    A: ActiveModelTrait,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A> + Clone,
+{
+    let mut active_model = saved.clone().into_active_model();
+    let mut changed = false;
+    for col in <A::Entity as EntityTrait>::Column::iter() {
+        let value = model.get(col);
+        if saved.get(col) != value {
+            active_model.set(col, value);
+            changed = true;
+        }
+    }
+    changed.then_some(active_model)
+}
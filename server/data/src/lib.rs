@@ -5,12 +5,9 @@ pub mod services;
 pub mod util;
 
 use chrono::{NaiveDateTime, Utc};
-use entities::{account, ban, character, equip_item, inventory_slot, item_stack, pet_item, skill};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ActiveValue, ConnectOptions, Database, DatabaseConnection, DbErr};
 
-use sea_orm::{
-    ActiveValue, ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend, DbErr,
-    Schema,
-};
 pub const SQL_OPT_MEMORY: &str = "sqlite::memory:";
 pub const SQL_OPT_TEST_FILE: &str = "sqlite://test.db?mode=rwc";
 
@@ -21,67 +18,47 @@ pub fn created_at(db: &DatabaseConnection) -> ActiveValue<NaiveDateTime> {
     }
 }
 
-pub async fn gen_sqlite(opt: &str) -> Result<DatabaseConnection, DbErr> {
-    let mut opt = ConnectOptions::new(opt.to_owned());
+/// Connects to the database and applies the pending migrations,
+/// supports sqlite and postgres urls
+pub async fn connect_db(url: &str) -> Result<DatabaseConnection, DbErr> {
+    let mut opt = ConnectOptions::new(url.to_owned());
     opt.sqlx_logging(true);
     let db = Database::connect(opt).await?;
-
-    let schema = Schema::new(DbBackend::Sqlite);
-
-    db.execute(
-        db.get_database_backend()
-            .build(&schema.create_table_from_entity(account::Entity)),
-    )
-    .await?;
-    db.execute(
-        db.get_database_backend()
-            .build(&schema.create_table_from_entity(character::Entity)),
-    )
-    .await?;
-    db.execute(
-        db.get_database_backend()
-            .build(&schema.create_table_from_entity(skill::Entity)),
-    )
-    .await?;
-    db.execute(
-        db.get_database_backend()
-            .build(&schema.create_table_from_entity(ban::Entity)),
-    )
-    .await?;
-
-    db.execute(
-        db.get_database_backend()
-            .build(&schema.create_table_from_entity(equip_item::Entity)),
-    )
-    .await?;
-
-    db.execute(
-        db.get_database_backend()
-            .build(&schema.create_table_from_entity(pet_item::Entity)),
-    )
-    .await?;
-
-    db.execute(
-        db.get_database_backend()
-            .build(&schema.create_table_from_entity(item_stack::Entity)),
-    )
-    .await?;
-
-    db.execute(
-        db.get_database_backend()
-            .build(&schema.create_table_from_entity(inventory_slot::Entity)),
-    )
-    .await?;
-
+    Migrator::up(&db, None).await?;
     Ok(db)
 }
 
+/// Sqlite database with the same schema as the migrations
+pub async fn gen_sqlite(opt: &str) -> Result<DatabaseConnection, DbErr> {
+    connect_db(opt).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::services::data::{account::Region, AccountService};
+
     #[tokio::test]
     async fn sqlite_build() {
         gen_sqlite(SQL_OPT_MEMORY).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn sqlite_file_reconnect() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("remember-{}.db", uuid::Uuid::new_v4()));
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+
+        let acc_id = AccountService::new(connect_db(&url).await?)
+            .create("file", "hunter3", Region::Europe, true, None)
+            .await?;
+
+        // Migrations are only applied once and the data is kept
+        let accs = AccountService::new(connect_db(&url).await?);
+        let acc = accs.get_by_username("file").await?;
+        assert_eq!(acc.map(|acc| acc.id), Some(acc_id));
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
 }
@@ -1,10 +1,25 @@
 use either::Either;
+use moople_packet::proto::{
+    list::{MapleIndexListZ, MapleIndexListZ16, MapleIndexListZ8},
+    time::MapleExpiration,
+    MapleList16,
+};
 use proto95::{
+    game::mini_room::MiniRoomType,
     id::{job_id::JobId, FaceId, HairId, MapId, Skin},
-    shared::char::{CharStat, Pets, SkillPointPage},
+    shared::{
+        char::{
+            CharDataAll, CharDataEquipped, CharDataStat, CharStat, Pets, SkillInfo,
+            SkillPointPage, TeleportRockInfo,
+        },
+        item::Item,
+    },
 };
 
-use crate::entities::character;
+use crate::{
+    entities::character,
+    services::{mini_room::game::MiniGameRecord, session::session_data::MoopleSessionData},
+};
 
 impl From<&character::Model> for CharStat {
     fn from(char: &character::Model) -> Self {
@@ -49,3 +64,90 @@ impl From<&character::Model> for CharStat {
         }
     }
 }
+
+impl From<&MoopleSessionData> for CharDataAll {
+    fn from(session: &MoopleSessionData) -> Self {
+        let char = &session.char;
+
+        let equipped: MapleIndexListZ16<Item> = session
+            .inv()
+            .equipped
+            .iter()
+            .map(|(slot, item)| (slot as u16, Item::Equip(item.item.as_ref().into())))
+            .collect();
+
+        let etc: MapleIndexListZ8<Item> = session
+            .inv()
+            .etc
+            .iter()
+            .map(|(slot, item)| (slot as u8 + 1, Item::Stack(item.item.as_ref().into())))
+            .collect();
+
+        let cash: MapleIndexListZ8<Item> = session
+            .inv()
+            .cash
+            .iter()
+            .map(|(slot, item)| (slot as u8 + 1, item.item.as_ref().into()))
+            .collect();
+
+        let invsize = [
+            char.model.equip_slots as u8,
+            char.model.use_slots as u8,
+            char.model.setup_slots as u8,
+            char.model.etc_slots as u8,
+            char.model.cash_slots as u8,
+        ];
+
+        let char_equipped = CharDataEquipped {
+            equipped,
+            ..Default::default()
+        };
+
+        let skill_records: MapleList16<SkillInfo> = session
+            .skills
+            .iter()
+            .map(|(id, skill)| SkillInfo {
+                id: *id,
+                level: skill.skill_level as u32,
+                expiration: skill.expires_at.into(),
+                master_level: skill.master_level as u32,
+            })
+            .collect();
+
+        let char_stat: &character::Model = &char.model.clone().into();
+
+        CharDataAll {
+            stat: CharDataStat {
+                stat: char_stat.into(),
+                friend_max: 30,
+                linked_character: None.into(),
+            },
+            money: char.model.mesos as u32,
+            invsize,
+            equipextslotexpiration: MapleExpiration::never(),
+            equipped: char_equipped,
+            useinv: MapleIndexListZ::default(),
+            setupinv: MapleIndexListZ::default(),
+            etcinv: etc,
+            cashinv: cash,
+            skillrecords: skill_records,
+            skllcooltime: MapleList16::default(),
+            quests: MapleList16::default(),
+            questscompleted: MapleList16::default(),
+            minigamerecords: session
+                .mini_game_records
+                .iter()
+                .filter_map(|record| {
+                    let ty = MiniRoomType::try_from(record.game_id as u8).ok()?;
+                    Some(MiniGameRecord::from(record).to_proto(ty))
+                })
+                .collect(),
+            socialrecords: MapleList16::default(),
+            teleportrockinfo: TeleportRockInfo::default(),
+            newyearcards: MapleList16::default(),
+            questrecordsexpired: MapleList16::default(),
+            questcompleteold: MapleList16::default(),
+            visitorquestloginfo: MapleList16::default(),
+        }
+    }
+}
@@ -1,8 +1,8 @@
 pub mod char;
 use moople_packet::proto::time::MapleTime;
-use proto95::{login::account::AccountInfo, shared::Gender};
+use proto95::{cash_shop::CashItemInfo, id::ItemId, login::account::AccountInfo, shared::Gender};
 
-use crate::entities::{account, sea_orm_active_enums::GenderTy};
+use crate::entities::{account, cash_item, sea_orm_active_enums::GenderTy};
 
 impl From<&GenderTy> for Gender {
     fn from(value: &GenderTy) -> Self {
@@ -41,3 +41,26 @@ impl From<&account::Model> for AccountInfo {
         }
     }
 }
+
+impl TryFrom<&cash_item::Model> for CashItemInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(model: &cash_item::Model) -> Result<Self, Self::Error> {
+        Ok(CashItemInfo {
+            sn: model.id as u64,
+            acc_id: model.acc_id as u32,
+            char_id: 0,
+            item_id: ItemId(model.item_id as u32),
+            commodity_sn: model.commodity_sn as u32,
+            quantity: model.quantity as u16,
+            buyer: model
+                .buyer
+                .as_str()
+                .try_into()
+                .map_err(|_| anyhow::format_err!("Invalid cash item buyer: {}", model.buyer))?,
+            expiration: model.expires_at.into(),
+            payback_rate: 0,
+            discount_rate: 0,
+        })
+    }
+}
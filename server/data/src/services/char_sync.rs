@@ -0,0 +1,43 @@
+use dashmap::DashSet;
+
+use super::data::character::CharacterID;
+
+/// Characters, whose stored stats were changed by other characters. The session
+/// of the character reloads the changed stats from the DB. The marks don't
+/// expire, a character which is offline or migrating takes It over once It's back
+#[derive(Debug, Default)]
+pub struct CharSyncService {
+    fame: DashSet<CharacterID>,
+}
+
+impl CharSyncService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the fame of the character as changed, the new fame must be stored already
+    pub fn mark_fame(&self, char_id: CharacterID) {
+        self.fame.insert(char_id);
+    }
+
+    /// Takes the mark, returns true If the fame must be reloaded
+    pub fn take_fame(&self, char_id: CharacterID) -> bool {
+        self.fame.remove(&char_id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CharSyncService;
+
+    #[test]
+    fn fame() {
+        let sync = CharSyncService::new();
+        sync.mark_fame(1);
+        sync.mark_fame(1);
+
+        assert!(sync.take_fame(1));
+        assert!(!sync.take_fame(1));
+        assert!(!sync.take_fame(2));
+    }
+}
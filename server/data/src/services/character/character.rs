@@ -1,7 +1,37 @@
-use std::ops::{Add, Div};
+use std::ops::Add;
 
 use crate::entities;
 
+pub const MAX_LEVEL: i32 = 200;
+/// Hp of a character after the respawn
+pub const REVIVE_HP: i32 = 50;
+
+/// Exp required for the levels 1-50, the later levels require 5.48% more exp than the previous level
+const EXP_TABLE: [u32; 50] = [
+    15, 34, 57, 92, 135, 372, 560, 840, 1242, 1716, 2360, 3216, 4200, 5460, 7050, 8840, 11040,
+    13716, 16680, 20216, 24402, 28980, 34320, 40512, 47216, 54900, 63666, 73080, 83720, 95700,
+    108480, 122760, 138666, 155540, 174216, 194832, 216600, 240500, 266682, 294216, 324240,
+    356916, 391160, 428280, 468450, 510420, 555680, 604416, 655200, 709716,
+];
+
+/// Exp which is required to reach the next level, 0 for the max level
+pub fn next_level_exp(level: i32) -> u32 {
+    if !(1..MAX_LEVEL).contains(&level) {
+        return 0;
+    }
+
+    let level = level as usize;
+    if level <= EXP_TABLE.len() {
+        return EXP_TABLE[level - 1];
+    }
+
+    let mut exp = EXP_TABLE[EXP_TABLE.len() - 1] as u64;
+    for _ in EXP_TABLE.len()..level {
+        exp = exp * 10548 / 10000;
+    }
+    exp as u32
+}
+
 #[derive(Debug, Clone)]
 pub struct Character {
     pub model: entities::character::Model,
@@ -14,22 +44,42 @@ impl From<entities::character::Model> for Character {
 }
 
 impl Character {
-    pub fn decrease_exp(&mut self, town: bool) {
-        if self.model.exp <= 0 || self.model.exp >= 200 {
-            return;
+    pub fn is_beginner(&self) -> bool {
+        // Beginner, Noblesse and Legend
+        self.model.job % 1000 == 0
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.model.hp <= 0
+    }
+
+    /// Exp the character loses on death, beginners don't lose any exp
+    pub fn death_exp_loss(&self, town: bool) -> i32 {
+        if self.model.exp <= 0 || self.model.level >= MAX_LEVEL || self.is_beginner() {
+            return 0;
         }
 
-        let reduction_rate = match town {
-            true => 0.01,
-            false => {
-                let temp_rate = if self.model.job.eq(&3) { 0.08 } else { 0.2 };
-                temp_rate.div((self.model.luk as f64).add(0.05))
-            }
+        let reduction_rate = if town {
+            0.01
+        } else {
+            // Thieves lose less exp
+            let temp_rate = if self.model.job / 100 % 10 == 4 { 0.08 } else { 0.2 };
+            temp_rate / (self.model.luk.max(1) as f64) + 0.05
         };
 
-        // set exp to the max of 0 or the current exp minus the next level xp times reduction rate
-        // TODO: get next level xp
-        self.model.exp = 0.max(self.model.exp - (self.model.exp as f64 * reduction_rate) as i32);
+        let loss = (next_level_exp(self.model.level) as f64 * reduction_rate) as i32;
+        loss.min(self.model.exp)
+    }
+
+    pub fn decrease_exp(&mut self, town: bool) {
+        self.model.exp -= self.death_exp_loss(town);
+    }
+
+    /// Revives a dead character on the given map and spawn point
+    pub fn revive(&mut self, map_id: i32, spawn_point: i32) {
+        self.model.hp = REVIVE_HP.min(self.model.max_hp);
+        self.model.map_id = map_id;
+        self.model.spawn_point = spawn_point;
     }
 
     pub fn update_hp(&mut self, hp: i32) {
@@ -40,3 +90,103 @@ impl Character {
         self.model.mp = 0.max(self.model.mp.add(mp)).min(self.model.max_mp);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use crate::entities::{character::Model, sea_orm_active_enums::GenderTy};
+
+    use super::{next_level_exp, Character, MAX_LEVEL, REVIVE_HP};
+
+    fn test_char(level: i32, exp: i32, job: i32) -> Character {
+        Model {
+            id: 1,
+            name: "Test".to_string(),
+            created_at: NaiveDateTime::default(),
+            last_login_at: None,
+            gender: GenderTy::Male,
+            skill_points: vec![0; 10],
+            play_time: 0,
+            world_id: 0,
+            delete_at: None,
+            level,
+            exp,
+            gacha_exp: 0,
+            str: 4,
+            dex: 4,
+            luk: 4,
+            int: 4,
+            hp: 100,
+            max_hp: 100,
+            mp: 10,
+            max_mp: 10,
+            mesos: 0,
+            map_id: 100000000,
+            buddy_capacity: 20,
+            fame: 0,
+            ap: 0,
+            sp: 0,
+            job,
+            equip_slots: 24,
+            use_slots: 24,
+            setup_slots: 24,
+            etc_slots: 24,
+            cash_slots: 24,
+            storage_slots: 4,
+            face: 20000,
+            skin: 0,
+            hair: 30000,
+            spawn_point: 0,
+            acc_id: 1,
+        }
+        .into()
+    }
+
+    #[test]
+    fn exp_table() {
+        assert_eq!(next_level_exp(0), 0);
+        assert_eq!(next_level_exp(1), 15);
+        assert_eq!(next_level_exp(50), 709716);
+        assert_eq!(next_level_exp(51), 748608);
+        assert_eq!(next_level_exp(53), 832902);
+        assert_eq!(next_level_exp(MAX_LEVEL), 0);
+        assert!(next_level_exp(MAX_LEVEL - 1) > next_level_exp(MAX_LEVEL - 2));
+    }
+
+    #[test]
+    fn death_exp_loss() {
+        // Beginners don't lose exp
+        let mut char = test_char(10, 1000, 0);
+        char.decrease_exp(false);
+        assert_eq!(char.model.exp, 1000);
+
+        // 1% of the next level exp in towns
+        let mut char = test_char(50, 100_000, 100);
+        char.decrease_exp(true);
+        assert_eq!(char.model.exp, 100_000 - 7097);
+
+        // 0.2 / luk + 5% outside of towns
+        let mut char = test_char(50, 100_000, 100);
+        char.decrease_exp(false);
+        assert_eq!(char.model.exp, 100_000 - (709716. * (0.2 / 4. + 0.05)) as i32);
+
+        // Exp never drops below 0
+        let mut char = test_char(50, 10, 100);
+        char.decrease_exp(false);
+        assert_eq!(char.model.exp, 0);
+    }
+
+    #[test]
+    fn revive() {
+        let mut char = test_char(10, 0, 100);
+        char.update_hp(-1000);
+        assert!(char.is_dead());
+        assert_eq!(char.model.hp, 0);
+
+        char.revive(100000001, 3);
+        assert!(!char.is_dead());
+        assert_eq!(char.model.hp, REVIVE_HP);
+        assert_eq!((char.model.map_id, char.model.spawn_point), (100000001, 3));
+    }
+}
@@ -0,0 +1,283 @@
+use game_data::wz2;
+use rand::Rng;
+
+/// Allowed deviation above the base damage of a hit, covers the damage range and level difference
+const MAX_DAMAGE_RATE: f64 = 1.5;
+/// Allowed deviation below the base damage of a hit
+const MIN_DAMAGE_RATE: f64 = 0.5;
+/// Hits are never bounded below this damage
+const MIN_DAMAGE_BOUND: u32 = 1;
+/// Lowest rate of the defense, which is subtracted from the damage of a hit
+const DEF_REDUCTION_RATE: f64 = 0.5;
+/// Highest rate of the defense, which is subtracted from the damage of a hit
+const MAX_DEF_REDUCTION_RATE: f64 = 0.6;
+const MIN_AVOID_RATE: f64 = 0.02;
+const MAX_AVOID_RATE: f64 = 0.8;
+/// Reported misses are only checked after this many hits
+const MIN_TRACKED_HITS: u32 = 20;
+/// Hits after which the tracked misses are reset, so only recent hits count
+const MAX_TRACKED_HITS: u32 = 200;
+/// Misses above the expected misses, which are still accepted as bad luck
+const MISS_TOLERANCE: f64 = 5.;
+const MISS_TOLERANCE_RATE: f64 = 1.5;
+
+/// Source of a hit the character received
+#[derive(Debug, Clone, Copy)]
+pub enum HitSource {
+    MobTouch(&'static wz2::Mob),
+    MobAttack(&'static wz2::Mob, &'static wz2::MobAttack),
+    /// Obstacle of the field with its damage
+    Obstacle(u32),
+}
+
+/// Defensive stats of the character receiving the hit
+#[derive(Debug, Clone, Copy)]
+pub struct HitDefense {
+    pub level: u32,
+    pub avoid: u32,
+    pub wdef: u32,
+    pub mdef: u32,
+    pub has_shield: bool,
+}
+
+impl HitDefense {
+    /// Chance to avoid a mob hit, magic attacks can't be avoided
+    pub fn avoid_rate(&self, src: &HitSource) -> f64 {
+        let mob = match src {
+            HitSource::MobTouch(mob) => mob,
+            HitSource::MobAttack(mob, attack) if !attack.magic => mob,
+            _ => return 0.,
+        };
+
+        let acc = mob.acc.max(1) as f64;
+        let level_penalty = mob.level.saturating_sub(self.level) as f64 * 0.01;
+        (self.avoid as f64 / (4.5 * acc) - level_penalty).clamp(MIN_AVOID_RATE, MAX_AVOID_RATE)
+    }
+}
+
+impl HitSource {
+    /// Base damage of the source and the defense against it, None for obstacles
+    fn base_damage(&self, def: &HitDefense) -> Option<(u32, u32)> {
+        Some(match self {
+            Self::MobTouch(mob) => (mob.pa_damage, def.wdef),
+            Self::MobAttack(_, attack) if attack.magic => (attack.ma_damage, def.mdef),
+            Self::MobAttack(mob, attack) => {
+                // Attacks without their own damage use the damage of the mob
+                let base = if attack.pa_damage > 0 {
+                    attack.pa_damage
+                } else {
+                    mob.pa_damage
+                };
+                (base, def.wdef)
+            }
+            Self::Obstacle(_) => return None,
+        })
+    }
+
+    /// Upper bound of the damage of this source
+    pub fn max_damage(&self, def: &HitDefense) -> u32 {
+        let Some((base, def)) = self.base_damage(def) else {
+            // Obstacles deal a fixed damage
+            return self.obstacle_damage();
+        };
+        let dmg = base as f64 * MAX_DAMAGE_RATE - def as f64 * DEF_REDUCTION_RATE;
+        (dmg.max(0.) as u32).max(MIN_DAMAGE_BOUND)
+    }
+
+    /// Lower bound of the damage of this source, a hit always deals at least this damage
+    pub fn min_damage(&self, def: &HitDefense) -> u32 {
+        let Some((base, def)) = self.base_damage(def) else {
+            return self.obstacle_damage();
+        };
+        let dmg = base as f64 * MIN_DAMAGE_RATE - def as f64 * MAX_DEF_REDUCTION_RATE;
+        (dmg.max(0.) as u32).max(MIN_DAMAGE_BOUND)
+    }
+
+    fn obstacle_damage(&self) -> u32 {
+        match self {
+            Self::Obstacle(dmg) => (*dmg).max(MIN_DAMAGE_BOUND),
+            _ => MIN_DAMAGE_BOUND,
+        }
+    }
+}
+
+/// Tracks the misses a client reported,
+/// so a client can't report more misses than the avoid of the character allows
+#[derive(Debug, Default, Clone)]
+pub struct AvoidTracker {
+    hits: u32,
+    misses: u32,
+    expected_misses: f64,
+}
+
+impl AvoidTracker {
+    /// Records a hit, returns false If the misses exceed the expected misses by the tolerance
+    pub fn record(&mut self, avoid_rate: f64, missed: bool) -> bool {
+        if self.hits >= MAX_TRACKED_HITS {
+            *self = Self::default();
+        }
+
+        self.hits += 1;
+        self.expected_misses += avoid_rate;
+        if missed {
+            self.misses += 1;
+        }
+
+        self.hits < MIN_TRACKED_HITS
+            || self.misses as f64 <= self.expected_misses * MISS_TOLERANCE_RATE + MISS_TOLERANCE
+    }
+}
+
+/// Damage of a verified hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifiedHit {
+    pub dmg: u32,
+    /// The client reported a miss, which was rejected as the client misses too often
+    pub rejected_miss: bool,
+}
+
+/// Computes the damage of a hit from the damage the client reported,
+/// the damage is bounded from both sides and reported misses are accepted unless they
+/// are too frequent. Guards count as misses, so they can't be claimed on every hit
+pub fn verify_hit_damage<R: Rng>(
+    src: HitSource,
+    def: &HitDefense,
+    dmg: u32,
+    guard: bool,
+    tracker: &mut AvoidTracker,
+    rng: &mut R,
+) -> VerifiedHit {
+    let max_dmg = src.max_damage(def);
+    let min_dmg = src.min_damage(def).min(max_dmg);
+    let avoid_rate = def.avoid_rate(&src);
+    if dmg > 0 {
+        tracker.record(avoid_rate, false);
+        return VerifiedHit {
+            dmg: dmg.clamp(min_dmg, max_dmg),
+            rejected_miss: false,
+        };
+    }
+
+    // Guarding requires a shield
+    let valid_guard = !guard || def.has_shield;
+    if valid_guard && tracker.record(avoid_rate, true) {
+        return VerifiedHit {
+            dmg: 0,
+            rejected_miss: false,
+        };
+    }
+
+    VerifiedHit {
+        dmg: rng.gen_range(min_dmg..=max_dmg),
+        rejected_miss: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_data::wz2;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{
+        verify_hit_damage, AvoidTracker, HitDefense, HitSource, MAX_AVOID_RATE, MIN_TRACKED_HITS,
+    };
+
+    fn mob(level: u32, pa_damage: u32, acc: u32) -> &'static wz2::Mob {
+        Box::leak(Box::new(wz2::Mob {
+            level,
+            max_hp: 100,
+            max_mp: 0,
+            exp: 0,
+            boss: false,
+            pa_damage,
+            ma_damage: 0,
+            acc,
+            attacks: Vec::new(),
+        }))
+    }
+
+    fn def(avoid: u32, wdef: u32, has_shield: bool) -> HitDefense {
+        HitDefense {
+            level: 10,
+            avoid,
+            wdef,
+            mdef: 0,
+            has_shield,
+        }
+    }
+
+    fn dmg(src: HitSource, def: HitDefense, dmg: u32, guard: bool) -> u32 {
+        let mut rng = StdRng::seed_from_u64(0);
+        verify_hit_damage(
+            src,
+            &def,
+            dmg,
+            guard,
+            &mut AvoidTracker::default(),
+            &mut rng,
+        )
+        .dmg
+    }
+
+    #[test]
+    fn bound_damage() {
+        let src = HitSource::MobTouch(mob(10, 100, 10));
+        assert_eq!(dmg(src, def(0, 0, false), 90, false), 90);
+        assert_eq!(dmg(src, def(0, 0, false), 10_000, false), 150);
+        assert_eq!(dmg(src, def(0, 100, false), 10_000, false), 100);
+        assert_eq!(dmg(src, def(0, 10_000, false), 10_000, false), 1);
+        assert_eq!(
+            dmg(HitSource::Obstacle(30), def(0, 0, false), 10_000, false),
+            30
+        );
+
+        // Reporting less damage than the source deals is bounded as well
+        assert_eq!(dmg(src, def(0, 0, false), 1, false), 50);
+        assert_eq!(dmg(src, def(0, 50, false), 1, false), 20);
+        assert_eq!(dmg(HitSource::Obstacle(30), def(0, 0, false), 1, false), 30);
+    }
+
+    #[test]
+    fn guard_and_avoid() {
+        let src = HitSource::MobTouch(mob(10, 100, 10));
+
+        // A guard with a shield blocks the hit
+        assert_eq!(dmg(src, def(0, 0, true), 0, true), 0);
+        assert_eq!(def(10_000, 0, false).avoid_rate(&src), MAX_AVOID_RATE);
+
+        // A guard without a shield is rejected
+        assert!(dmg(src, def(0, 0, false), 0, true) > 0);
+
+        // Guards are tracked like misses
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut tracker = AvoidTracker::default();
+        let rejected = (0..100)
+            .map(|_| verify_hit_damage(src, &def(0, 0, true), 0, true, &mut tracker, &mut rng))
+            .position(|hit| hit.rejected_miss)
+            .unwrap();
+        assert_eq!(rejected + 1, MIN_TRACKED_HITS as usize);
+    }
+
+    #[test]
+    fn track_misses() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let src = HitSource::MobTouch(mob(10, 100, 10));
+
+        // Misses within the avoid rate are accepted
+        let mut tracker = AvoidTracker::default();
+        let high_avoid = def(10_000, 0, false);
+        for i in 0..100 {
+            let hit = if i % 4 == 0 { 50 } else { 0 };
+            let hit = verify_hit_damage(src, &high_avoid, hit, false, &mut tracker, &mut rng);
+            assert!(!hit.rejected_miss);
+        }
+
+        // Always missing without any avoid is rejected after the tracked hits
+        let mut tracker = AvoidTracker::default();
+        let rejected = (0..100)
+            .map(|_| verify_hit_damage(src, &def(0, 0, false), 0, false, &mut tracker, &mut rng))
+            .position(|hit| hit.rejected_miss)
+            .unwrap();
+        assert_eq!(rejected + 1, MIN_TRACKED_HITS as usize);
+    }
+}
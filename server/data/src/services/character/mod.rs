@@ -1,3 +1,4 @@
 mod character;
+pub mod damage;
 
 pub use self::character::*;
\ No newline at end of file
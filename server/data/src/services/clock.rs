@@ -0,0 +1,48 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Source of the current time for scheduled events, so the schedules can be tested with a mock
+pub trait Clock: Debug + Send + Sync {
+    /// Time since the unix epoch
+    fn now(&self) -> Duration;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time after the epoch")
+    }
+}
+
+/// Clock which only moves when it is advanced
+#[derive(Debug, Default)]
+pub struct MockClock(Mutex<Duration>);
+
+impl MockClock {
+    pub fn new(now: Duration) -> Self {
+        Self(Mutex::new(now))
+    }
+
+    pub fn set(&self, now: Duration) {
+        *self.0.lock().expect("Mock clock") = now;
+    }
+
+    pub fn advance(&self, dur: Duration) {
+        *self.0.lock().expect("Mock clock") += dur;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.0.lock().expect("Mock clock")
+    }
+}
@@ -1,5 +1,6 @@
-use std::net::IpAddr;
+use std::ops::RangeInclusive;
 
+use chrono::NaiveDate;
 use constant_time_eq::constant_time_eq;
 use rand::{thread_rng, RngCore};
 use sea_orm::{ActiveModelTrait, DbErr, TryIntoModel};
@@ -11,8 +12,9 @@ use crate::entities::account::{ActiveModel, Column, Entity, Model};
 use crate::entities::ban;
 use crate::entities::sea_orm_active_enums::GenderTy;
 
+use super::ban::{machine_id_str, parse_machine_id, MachineId};
+
 pub type AccountId = i32;
-pub type HardwareInfo = u32;
 
 #[derive(Debug)]
 #[repr(u8)]
@@ -34,14 +36,18 @@ pub enum AccountServiceError {
     PasswordWrongSize,
     #[error("Password is only supposed to contain ASCII characters")]
     PasswordWrongChar,
-    #[error("Password size is wrong")]
+    #[error("Username size is wrong")]
     UsernameWrongSize,
-    #[error("Password is only supposed to contain ASCII characters")]
+    #[error("Username is only supposed to contain ASCII letters and digits")]
     UsernameWrongChar,
     #[error("Account is banned")]
-    AccountIsBanned,
+    AccountIsBanned(ban::Model),
+    #[error("Too many attempts from the address")]
+    RateLimited,
     #[error("database")]
     Disconnect(#[from] DbErr),
+    #[error("ban lookup")]
+    BanLookup(#[from] anyhow::Error),
 }
 
 //MAybe use passwords crate
@@ -55,6 +61,29 @@ pub struct AccountService {
 
 type PasswordSalt = [u8; 16];
 
+pub const USERNAME_LEN: RangeInclusive<usize> = 4..=12;
+pub const PASSWORD_LEN: RangeInclusive<usize> = 4..=12;
+
+pub fn check_username(username: &str) -> AccResult<()> {
+    if !USERNAME_LEN.contains(&username.len()) {
+        return Err(AccountServiceError::UsernameWrongSize);
+    }
+    if !username.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AccountServiceError::UsernameWrongChar);
+    }
+    Ok(())
+}
+
+pub fn check_password(password: &str) -> AccResult<()> {
+    if !PASSWORD_LEN.contains(&password.len()) {
+        return Err(AccountServiceError::PasswordWrongSize);
+    }
+    if !password.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(AccountServiceError::PasswordWrongChar);
+    }
+    Ok(())
+}
+
 const HASH_COST: u32 = 9;
 
 fn gen_salt() -> PasswordSalt {
@@ -79,15 +108,26 @@ impl AccountService {
         Ok(Entity::find_by_id(id).one(&self.db).await?)
     }
 
+    pub async fn get_by_username(&self, username: &str) -> anyhow::Result<Option<Model>> {
+        Ok(Entity::find()
+            .filter(Column::Username.eq(username))
+            .one(&self.db)
+            .await?)
+    }
+
     pub async fn create(
         &self,
-        username: impl ToString,
+        username: &str,
         password: &str,
         region: Region,
         accepted_tos: bool,
         gender: Option<GenderTy>,
-    ) -> anyhow::Result<AccountId> {
-        //TODO check username + password
+    ) -> AccResult<AccountId> {
+        check_username(username)?;
+        check_password(password)?;
+        if self.get_by_username(username).await?.is_some() {
+            return Err(AccountServiceError::UsernameAlreadyExists);
+        }
         let hash = hash_password(password);
 
         let acc = ActiveModel {
@@ -114,19 +154,13 @@ impl AccountService {
     pub async fn try_login(&self, username: &str, password: &str) -> AccResult<Model> {
         let res = Entity::find()
             .filter(Column::Username.eq(username))
-            //TODO find latest ban
-            .find_also_related(ban::Entity)
             .one(&self.db)
             .await?;
 
-        let Some((acc, last_ban)) = res else {
+        let Some(acc) = res else {
             return Err(AccountServiceError::UsernameNotFound)
         };
 
-        if let Some(_last_ban) = last_ban {
-            return Err(AccountServiceError::AccountIsBanned);
-        }
-
         let verfiy_password = self.verify_password(password, &acc.password_hash).unwrap();
         if !verfiy_password {
             return Err(AccountServiceError::PasswordMismatch);
@@ -155,6 +189,26 @@ impl AccountService {
         .await
     }
 
+    pub async fn reset_password(&self, username: &str, password: &str) -> AccResult<()> {
+        check_password(password)?;
+        let Some(acc) = self.get_by_username(username).await? else {
+            return Err(AccountServiceError::UsernameNotFound);
+        };
+        let hash = hash_password(password);
+        self.update(acc, |acc| {
+            acc.password_hash = Set(hash);
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_gm_level(&self, acc: Model, gm_level: i32) -> anyhow::Result<Model> {
+        self.update(acc, |acc| {
+            acc.gm_level = Set(gm_level);
+        })
+        .await
+    }
+
     pub async fn set_pic(&self, acc: Model, pic: String) -> anyhow::Result<Model> {
         self.update(acc, |acc| {
             acc.pic = Set(Some(pic));
@@ -169,6 +223,35 @@ impl AccountService {
         .await
     }
 
+    pub async fn set_birthday(&self, acc: Model, birthday: NaiveDate) -> anyhow::Result<Model> {
+        self.update(acc, |acc| {
+            acc.birthday = Set(Some(birthday));
+        })
+        .await
+    }
+
+    /// Records the machine of the login, so it can be banned later
+    pub async fn set_last_machine_id(
+        &self,
+        acc: Model,
+        machine_id: &MachineId,
+    ) -> anyhow::Result<Model> {
+        let machine_id = machine_id_str(machine_id);
+        self.update(acc, |acc| {
+            acc.last_machine_id = Set(Some(machine_id));
+        })
+        .await
+    }
+
+    /// Machine of the last login, if it was recorded
+    pub async fn get_last_machine_id(&self, id: AccountId) -> anyhow::Result<Option<MachineId>> {
+        Ok(self
+            .get(id)
+            .await?
+            .and_then(|acc| acc.last_machine_id)
+            .and_then(|machine_id| parse_machine_id(&machine_id)))
+    }
+
     pub async fn accept_tos(&self, acc: Model) -> anyhow::Result<Model> {
         self.update(acc, |acc| {
             acc.accepted_tos = Set(true);
@@ -176,6 +259,21 @@ impl AccountService {
         .await
     }
 
+    pub async fn set_cash(
+        &self,
+        acc: Model,
+        nx_credit: i32,
+        nx_prepaid: i32,
+        maple_points: i32,
+    ) -> anyhow::Result<Model> {
+        self.update(acc, |acc| {
+            acc.nx_credit = Set(nx_credit);
+            acc.nx_prepaid = Set(nx_prepaid);
+            acc.maple_points = Set(maple_points);
+        })
+        .await
+    }
+
     pub async fn delete_acc(&self, _id: AccountId) -> anyhow::Result<()> {
         todo!()
     }
@@ -200,23 +298,25 @@ impl AccountService {
         Ok(constant_time_eq(acc_pic.as_bytes(), pic.as_bytes()))
     }
 
-    pub fn check_hardware_info(
-        &self,
-        _acc: &Model,
-        _hw_info: &HardwareInfo,
-        _ip: IpAddr,
-    ) -> anyhow::Result<()> {
-        Ok(())
+    /// Checks the birthday in the `yyyymmdd` format,
+    /// accounts without a birthday reject any date
+    pub fn check_birthday(&self, acc: &Model, birthday: &str) -> bool {
+        let Some(acc_birthday) = acc.birthday else {
+            return false;
+        };
+
+        NaiveDate::parse_from_str(birthday, "%Y%m%d").is_ok_and(|date| date == acc_birthday)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::NaiveDate;
     use sea_orm::DatabaseConnection;
 
     use crate::{entities::sea_orm_active_enums::GenderTy, services::data::account::Region};
 
-    use super::AccountService;
+    use super::{AccountService, AccountServiceError};
 
     pub(crate) async fn get_test_db() -> anyhow::Result<DatabaseConnection> {
         let db = crate::gen_sqlite(crate::SQL_OPT_MEMORY).await?;
@@ -249,4 +349,60 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn account_register() -> anyhow::Result<()> {
+        let svc = get_test_svc().await?;
+        let create = |username, pw| svc.create(username, pw, Region::Europe, false, None);
+
+        assert!(matches!(
+            create("abc", "abc123").await,
+            Err(AccountServiceError::UsernameWrongSize)
+        ));
+        assert!(matches!(
+            create("abc d", "abc123").await,
+            Err(AccountServiceError::UsernameWrongChar)
+        ));
+        assert!(matches!(
+            create("abcd", "abc").await,
+            Err(AccountServiceError::PasswordWrongSize)
+        ));
+        assert!(matches!(
+            create("abcd", "abc 123").await,
+            Err(AccountServiceError::PasswordWrongChar)
+        ));
+        create("abcd", "abc123").await?;
+        assert!(matches!(
+            create("abcd", "abc123").await,
+            Err(AccountServiceError::UsernameAlreadyExists)
+        ));
+
+        svc.reset_password("abcd", "def456").await?;
+        assert!(matches!(
+            svc.try_login("abcd", "abc123").await,
+            Err(AccountServiceError::PasswordMismatch)
+        ));
+        svc.try_login("abcd", "def456").await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn check_birthday() -> anyhow::Result<()> {
+        let svc = get_test_svc().await?;
+        let acc_id = svc.create("bday", "abc123", Region::Europe, true, None).await?;
+        let acc = svc.get(acc_id).await?.unwrap();
+
+        // Without a birthday no date is accepted
+        assert!(!svc.check_birthday(&acc, "19990101"));
+
+        let acc = svc
+            .set_birthday(acc, NaiveDate::from_ymd_opt(1999, 1, 1).unwrap())
+            .await?;
+        assert!(svc.check_birthday(&acc, "19990101"));
+        assert!(!svc.check_birthday(&acc, "19990102"));
+        assert!(!svc.check_birthday(&acc, "1999-01-01"));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,286 @@
+use std::net::IpAddr;
+
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, Set,
+};
+
+use crate::entities::ban;
+
+use super::account::{AccResult, AccountId, AccountServiceError};
+
+pub type MachineId = [u8; 16];
+pub type BanReasonCode = u8;
+
+/// Hex representation of the machine id, as it's stored in the db
+pub fn machine_id_str(machine_id: &MachineId) -> String {
+    machine_id.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Parses the hex representation of the machine id
+pub fn parse_machine_id(s: &str) -> Option<MachineId> {
+    if s.len() != 32 || !s.is_ascii() {
+        return None;
+    }
+    let mut machine_id = MachineId::default();
+    for (b, hex) in machine_id.iter_mut().zip(s.as_bytes().chunks(2)) {
+        *b = u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+    }
+    Some(machine_id)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BanInfo {
+    /// Reason code shown by the client
+    pub reason_code: BanReasonCode,
+    pub reason: Option<String>,
+    /// End of the ban, `None` bans permanently
+    pub until: Option<NaiveDateTime>,
+    /// Blocks any login from this IP
+    pub ip: Option<IpAddr>,
+    /// Blocks any login from this machine
+    pub machine_id: Option<MachineId>,
+}
+
+/// Temporary and permanent account, IP and machine bans
+#[derive(Debug, Clone)]
+pub struct BanService {
+    db: DatabaseConnection,
+}
+
+fn active_cond() -> Condition {
+    Condition::any()
+        .add(ban::Column::BanTime.is_null())
+        .add(ban::Column::BanTime.gt(Utc::now().naive_utc()))
+}
+
+impl BanService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn ban(&self, acc_id: AccountId, info: BanInfo) -> anyhow::Result<i32> {
+        let ban = ban::ActiveModel {
+            acc_id: Set(acc_id),
+            ban_reason: Set(info.reason),
+            ban_time: Set(info.until),
+            reason_code: Set(info.reason_code as i32),
+            ip: Set(info.ip.map(|ip| ip.to_string())),
+            machine_id: Set(info.machine_id.as_ref().map(machine_id_str)),
+            created_at: Set(Some(Utc::now().naive_utc())),
+            ..Default::default()
+        };
+        let res = ban::Entity::insert(ban).exec(&self.db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    /// Latest active ban of the account or for the given IP or machine
+    pub async fn get_active_ban(
+        &self,
+        acc_id: AccountId,
+        ip: Option<IpAddr>,
+        machine_id: Option<&MachineId>,
+    ) -> anyhow::Result<Option<ban::Model>> {
+        let mut target = Condition::any().add(ban::Column::AccId.eq(acc_id));
+        if let Some(ip) = ip {
+            target = target.add(ban::Column::Ip.eq(ip.to_string()));
+        }
+        if let Some(machine_id) = machine_id {
+            target = target.add(ban::Column::MachineId.eq(machine_id_str(machine_id)));
+        }
+        self.find_active_ban(target).await
+    }
+
+    async fn find_active_ban(&self, target: Condition) -> anyhow::Result<Option<ban::Model>> {
+        Ok(ban::Entity::find()
+            .filter(target)
+            .filter(active_cond())
+            .order_by_desc(ban::Column::Id)
+            .one(&self.db)
+            .await?)
+    }
+
+    /// Checks the login for account, IP or machine bans,
+    /// IP and machine bans also block accounts without a ban
+    pub async fn check_login(
+        &self,
+        acc_id: AccountId,
+        machine_id: &MachineId,
+        ip: IpAddr,
+    ) -> AccResult<()> {
+        match self
+            .get_active_ban(acc_id, Some(ip), Some(machine_id))
+            .await?
+        {
+            Some(ban) => Err(AccountServiceError::AccountIsBanned(ban)),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks a registration for IP or machine bans, before the account is created
+    pub async fn check_register(&self, machine_id: &MachineId, ip: IpAddr) -> AccResult<()> {
+        let target = Condition::any()
+            .add(ban::Column::Ip.eq(ip.to_string()))
+            .add(ban::Column::MachineId.eq(machine_id_str(machine_id)));
+        match self.find_active_ban(target).await? {
+            Some(ban) => Err(AccountServiceError::AccountIsBanned(ban)),
+            None => Ok(()),
+        }
+    }
+
+    /// All bans of the account, the newest first
+    pub async fn get_bans(&self, acc_id: AccountId) -> anyhow::Result<Vec<ban::Model>> {
+        Ok(ban::Entity::find()
+            .filter(ban::Column::AccId.eq(acc_id))
+            .order_by_desc(ban::Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
+    /// Ends the active bans matching the condition, the history is kept
+    async fn expire(&self, target: Condition) -> anyhow::Result<u64> {
+        let res = ban::Entity::update_many()
+            .col_expr(ban::Column::BanTime, Expr::value(Utc::now().naive_utc()))
+            .filter(target)
+            .filter(active_cond())
+            .exec(&self.db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    /// Removes the column from the active bans with the value,
+    /// the account bans of those rows stay active
+    async fn clear(&self, col: ban::Column, value: String) -> anyhow::Result<u64> {
+        let res = ban::Entity::update_many()
+            .col_expr(col, Expr::value(Option::<String>::None))
+            .filter(col.eq(value))
+            .filter(active_cond())
+            .exec(&self.db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    pub async fn unban_account(&self, acc_id: AccountId) -> anyhow::Result<u64> {
+        self.expire(Condition::all().add(ban::Column::AccId.eq(acc_id)))
+            .await
+    }
+
+    pub async fn unban_ip(&self, ip: IpAddr) -> anyhow::Result<u64> {
+        self.clear(ban::Column::Ip, ip.to_string()).await
+    }
+
+    pub async fn unban_machine(&self, machine_id: &MachineId) -> anyhow::Result<u64> {
+        self.clear(ban::Column::MachineId, machine_id_str(machine_id))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::services::data::{
+        account::{AccountServiceError, Region},
+        AccountService,
+    };
+
+    use super::{machine_id_str, parse_machine_id, BanInfo, BanService};
+
+    #[tokio::test]
+    async fn ban_unban() -> anyhow::Result<()> {
+        let db = crate::gen_sqlite(crate::SQL_OPT_MEMORY).await?;
+        let accs = AccountService::new(db.clone());
+        let svc = BanService::new(db);
+        let acc = accs
+            .create("ban1", "abc123", Region::Europe, true, None)
+            .await?;
+        let other = accs
+            .create("ban2", "abc123", Region::Europe, true, None)
+            .await?;
+        let ip = "127.0.0.1".parse()?;
+        let machine_id = [7; 16];
+
+        // Expired bans are ignored
+        let expired = Utc::now().naive_utc() - Duration::days(1);
+        svc.ban(
+            acc,
+            BanInfo {
+                until: Some(expired),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert!(svc.get_active_ban(acc, None, None).await?.is_none());
+
+        let until = Utc::now().naive_utc() + Duration::days(1);
+        svc.ban(
+            acc,
+            BanInfo {
+                reason_code: 1,
+                until: Some(until),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let id = svc
+            .ban(
+                acc,
+                BanInfo {
+                    reason_code: 2,
+                    ip: Some(ip),
+                    machine_id: Some(machine_id),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        // The latest ban is selected
+        let ban = svc.get_active_ban(acc, None, None).await?.unwrap();
+        assert_eq!((ban.id, ban.reason_code, ban.ban_time), (id, 2, None));
+
+        // Other accounts are blocked by the IP and the machine
+        assert!(svc.get_active_ban(other, None, None).await?.is_none());
+        assert!(svc.get_active_ban(other, Some(ip), None).await?.is_some());
+        assert!(svc
+            .get_active_ban(other, None, Some(&machine_id))
+            .await?
+            .is_some());
+
+        // New accounts are blocked by the IP and the machine as well
+        assert!(matches!(
+            svc.check_register(&machine_id, ip).await,
+            Err(AccountServiceError::AccountIsBanned(_))
+        ));
+
+        // Unbanning the IP and the machine keeps the account ban
+        assert_eq!(svc.unban_ip(ip).await?, 1);
+        assert!(svc.get_active_ban(other, Some(ip), None).await?.is_none());
+        assert!(svc
+            .get_active_ban(other, None, Some(&machine_id))
+            .await?
+            .is_some());
+        assert_eq!(svc.unban_machine(&machine_id).await?, 1);
+        assert!(svc
+            .get_active_ban(other, Some(ip), Some(&machine_id))
+            .await?
+            .is_none());
+        assert!(svc.check_register(&machine_id, ip).await.is_ok());
+        assert_eq!(svc.get_active_ban(acc, None, None).await?.unwrap().id, id);
+
+        assert_eq!(svc.unban_account(acc).await?, 2);
+        assert!(svc.get_active_ban(acc, None, None).await?.is_none());
+        assert_eq!(svc.get_bans(acc).await?.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn machine_id() {
+        let machine_id = [0xab; 16];
+        let s = machine_id_str(&machine_id);
+        assert_eq!(parse_machine_id(&s), Some(machine_id));
+        assert_eq!(parse_machine_id(&s[1..]), None);
+        assert_eq!(parse_machine_id(&s.replace('a', "x")), None);
+    }
+}
@@ -0,0 +1,408 @@
+use chrono::{Duration, Utc};
+use game_data::wz2::Commodity;
+use proto95::{
+    cash_shop::{CashType, WISH_LIST_LEN},
+    id::ItemId,
+};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+
+use crate::{
+    created_at,
+    entities::{account, cash_item, wish_list},
+    services::{
+        helper::intentory::inv::{InventorySet, InventoryType}, mini_room::shop::ShopItemKind,
+        model::item::StackItem,
+    },
+};
+
+use super::{account::AccountId, character::CharacterID, item::ItemService};
+
+/// Id of an item in the cash locker, which also serves as cash id of the item
+pub type CashItemId = i32;
+
+/// Items the locker of an account can hold
+pub const LOCKER_SLOTS: u16 = 4;
+
+#[derive(Debug)]
+pub enum BuyItemResult {
+    /// The item is in the locker, with the account after the payment
+    Bought(account::Model, cash_item::Model),
+    NotEnoughCash,
+    LockerFull,
+}
+
+#[derive(Debug, Clone)]
+pub struct CashShopService {
+    db: DatabaseConnection,
+    item: ItemService,
+}
+
+impl CashShopService {
+    pub fn new(db: DatabaseConnection, item: ItemService) -> Self {
+        Self { db, item }
+    }
+
+    /// Loads all items in the locker, items which were moved into the inventory are skipped
+    pub async fn load_locker(&self, acc_id: AccountId) -> anyhow::Result<Vec<cash_item::Model>> {
+        Ok(cash_item::Entity::find()
+            .filter(cash_item::Column::AccId.eq(acc_id))
+            .filter(cash_item::Column::InInventory.eq(false))
+            .order_by_asc(cash_item::Column::Id)
+            .all(&self.db)
+            .await?)
+    }
+
+    fn new_item(
+        &self,
+        acc_id: AccountId,
+        commodity: &Commodity,
+        buyer: &str,
+        gift_msg: Option<&str>,
+    ) -> cash_item::ActiveModel {
+        let period_days = commodity.period;
+        let expires_at =
+            (period_days > 0).then(|| Utc::now().naive_utc() + Duration::days(period_days as i64));
+
+        cash_item::ActiveModel {
+            id: NotSet,
+            item_id: Set(commodity.item_id as i32),
+            commodity_sn: Set(commodity.sn as i32),
+            quantity: Set(commodity.count as i32),
+            buyer: Set(buyer.to_string()),
+            gift_msg: Set(gift_msg.map(str::to_string)),
+            in_inventory: Set(false),
+            expires_at: Set(expires_at),
+            created_at: created_at(&self.db),
+            acc_id: Set(acc_id),
+            pet_item_id: Set(None),
+        }
+    }
+
+    /// Deducts the price from the balance of the payer and puts the item into the locker
+    /// of the receiver in one transaction, nothing is bought If the balance is too low
+    /// or the locker is full. The balance is checked and deducted in the DB, so changes
+    /// from elsewhere are kept. A period of zero days means the item never expires,
+    /// gifts carry a message
+    pub async fn buy_item(
+        &self,
+        payer: AccountId,
+        cash_type: CashType,
+        receiver: AccountId,
+        commodity: &Commodity,
+        buyer: &str,
+        gift_msg: Option<&str>,
+    ) -> anyhow::Result<BuyItemResult> {
+        let balance = match cash_type {
+            CashType::NxCredit => account::Column::NxCredit,
+            CashType::MaplePoints => account::Column::MaplePoints,
+            CashType::NxPrepaid => account::Column::NxPrepaid,
+        };
+        let price = i32::try_from(commodity.price)?;
+
+        let txn = self.db.begin().await?;
+        let locker_items = cash_item::Entity::find()
+            .filter(cash_item::Column::AccId.eq(receiver))
+            .filter(cash_item::Column::InInventory.eq(false))
+            .count(&txn)
+            .await?;
+        if locker_items >= LOCKER_SLOTS as u64 {
+            return Ok(BuyItemResult::LockerFull);
+        }
+
+        let res = account::Entity::update_many()
+            .col_expr(balance, Expr::col(balance).sub(price))
+            .filter(account::Column::Id.eq(payer))
+            .filter(balance.gte(price))
+            .exec(&txn)
+            .await?;
+        if res.rows_affected == 0 {
+            return Ok(BuyItemResult::NotEnoughCash);
+        }
+
+        let item = self
+            .new_item(receiver, commodity, buyer, gift_msg)
+            .insert(&txn)
+            .await?;
+        let acc = account::Entity::find_by_id(payer)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| anyhow::format_err!("No account with id: {payer}"))?;
+        txn.commit().await?;
+        Ok(BuyItemResult::Bought(acc, item))
+    }
+
+    /// Takes the gifts, which weren't shown to the receiver yet. The gifts are taken
+    /// in one transaction, so they are only shown once
+    pub async fn take_gifts(&self, acc_id: AccountId) -> anyhow::Result<Vec<cash_item::Model>> {
+        let txn = self.db.begin().await?;
+        let gifts = cash_item::Entity::find()
+            .filter(cash_item::Column::AccId.eq(acc_id))
+            .filter(cash_item::Column::GiftMsg.is_not_null())
+            .order_by_asc(cash_item::Column::Id)
+            .all(&txn)
+            .await?;
+
+        if !gifts.is_empty() {
+            let res = cash_item::Entity::update_many()
+                .col_expr(
+                    cash_item::Column::GiftMsg,
+                    Expr::value(Option::<String>::None),
+                )
+                .filter(cash_item::Column::Id.is_in(gifts.iter().map(|gift| gift.id)))
+                .filter(cash_item::Column::GiftMsg.is_not_null())
+                .exec(&txn)
+                .await?;
+            // Another login took the gifts in the meantime
+            if res.rows_affected != gifts.len() as u64 {
+                return Ok(Vec::new());
+            }
+        }
+        txn.commit().await?;
+        Ok(gifts)
+    }
+
+    /// Finds an item, which is still in the locker
+    pub async fn get_locker_item(
+        &self,
+        acc_id: AccountId,
+        id: CashItemId,
+    ) -> anyhow::Result<Option<cash_item::Model>> {
+        Ok(cash_item::Entity::find_by_id(id)
+            .filter(cash_item::Column::AccId.eq(acc_id))
+            .filter(cash_item::Column::InInventory.eq(false))
+            .one(&self.db)
+            .await?)
+    }
+
+    async fn set_in_inventory<C: ConnectionTrait>(
+        db: &C,
+        acc_id: AccountId,
+        id: CashItemId,
+        in_inventory: bool,
+        quantity: Option<u16>,
+    ) -> anyhow::Result<Option<cash_item::Model>> {
+        let item = cash_item::Entity::find_by_id(id)
+            .filter(cash_item::Column::AccId.eq(acc_id))
+            .filter(cash_item::Column::InInventory.eq(!in_inventory))
+            .one(db)
+            .await?;
+        let Some(item) = item else {
+            return Ok(None);
+        };
+
+        let mut item: cash_item::ActiveModel = item.into();
+        item.in_inventory = Set(in_inventory);
+        if let Some(quantity) = quantity {
+            item.quantity = Set(quantity as i32);
+        }
+        Ok(Some(item.update(db).await?))
+    }
+
+    /// Takes the item out of the locker and adds it to the inventory, the locker and the
+    /// inventory are written in one transaction. The id of the locker item becomes the cash id,
+    /// pets are created once and kept with the locker item. Returns the slot and the item,
+    /// the inventory is only changed after the commit
+    pub async fn take_item(
+        &self,
+        acc_id: AccountId,
+        id: CashItemId,
+        pet_name: &str,
+        char_id: CharacterID,
+        inv: &mut InventorySet,
+    ) -> anyhow::Result<Option<(usize, ShopItemKind)>> {
+        let txn = self.db.begin().await?;
+        let Some(cash_item) = Self::set_in_inventory(&txn, acc_id, id, true, None).await? else {
+            return Ok(None);
+        };
+
+        let item_id = ItemId(cash_item.item_id as u32);
+        let mut item = if item_id.is_pet() {
+            let mut pet: StackItem = match cash_item.pet_item_id {
+                // A returned pet keeps its name and progress
+                Some(pet_id) => self
+                    .item
+                    .load_pet_with(&txn, pet_id)
+                    .await?
+                    .ok_or_else(|| anyhow::format_err!("No pet with id: {pet_id}"))?
+                    .into(),
+                None => {
+                    let pet = self
+                        .item
+                        .create_pet_with(&txn, item_id, pet_name.to_string())
+                        .await?;
+                    cash_item::Entity::update_many()
+                        .col_expr(cash_item::Column::PetItemId, Expr::value(pet.db_id))
+                        .filter(cash_item::Column::Id.eq(cash_item.id))
+                        .exec(&txn)
+                        .await?;
+                    pet
+                }
+            };
+            pet.cash_id = Some(cash_item.id as u64);
+            pet.last_update = 1;
+            ShopItemKind::Stack(pet)
+        } else if InventoryType::from_item_id(item_id) == Some(InventoryType::Equip) {
+            let mut equip = self.item.get_eq_item_from_id(item_id)?;
+            equip.cash_id = Some(cash_item.id as u64);
+            ShopItemKind::Equip(equip)
+        } else {
+            let mut stack = StackItem::from_item_id(item_id, cash_item.quantity as u16);
+            stack.cash_id = Some(cash_item.id as u64);
+            ShopItemKind::Stack(stack)
+        };
+        match &mut item {
+            ShopItemKind::Equip(equip) => equip.expiration = cash_item.expires_at,
+            ShopItemKind::Stack(stack) => stack.expiration = cash_item.expires_at,
+        }
+
+        let mut saved = inv.clone();
+        let slot = match item.clone() {
+            ShopItemKind::Equip(equip) => saved.try_add_equip(equip).ok(),
+            ShopItemKind::Stack(stack) => saved.try_add_stack(stack).ok().map(|(_, slot)| slot),
+        };
+        let Some(slot) = slot else {
+            return Ok(None);
+        };
+        self.item.save_inventory_with(&txn, &mut saved, char_id).await?;
+        txn.commit().await?;
+
+        *inv = saved;
+        Ok(Some((slot, item)))
+    }
+
+    /// Returns a previously taken item back into the locker, the quantity
+    /// might have changed while the item was in the inventory. The inventory without
+    /// the item is written in the same transaction
+    pub async fn return_item(
+        &self,
+        acc_id: AccountId,
+        id: CashItemId,
+        quantity: u16,
+        char_id: CharacterID,
+        inv: &mut InventorySet,
+    ) -> anyhow::Result<Option<cash_item::Model>> {
+        let txn = self.db.begin().await?;
+        let Some(item) = Self::set_in_inventory(&txn, acc_id, id, false, Some(quantity)).await?
+        else {
+            return Ok(None);
+        };
+        self.item.save_inventory_with(&txn, inv, char_id).await?;
+        txn.commit().await?;
+        Ok(Some(item))
+    }
+
+    pub async fn load_wish_list(
+        &self,
+        char_id: CharacterID,
+    ) -> anyhow::Result<[u32; WISH_LIST_LEN]> {
+        let entries = wish_list::Entity::find()
+            .filter(wish_list::Column::CharId.eq(char_id))
+            .order_by_asc(wish_list::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        let mut wish_list = [0; WISH_LIST_LEN];
+        for (sn, entry) in wish_list.iter_mut().zip(entries) {
+            *sn = entry.commodity_sn as u32;
+        }
+        Ok(wish_list)
+    }
+
+    pub async fn save_wish_list(
+        &self,
+        char_id: CharacterID,
+        wish_list: &[u32; WISH_LIST_LEN],
+    ) -> anyhow::Result<()> {
+        wish_list::Entity::delete_many()
+            .filter(wish_list::Column::CharId.eq(char_id))
+            .exec(&self.db)
+            .await?;
+
+        let entries = wish_list
+            .iter()
+            .filter(|sn| **sn != 0)
+            .map(|sn| wish_list::ActiveModel {
+                id: NotSet,
+                commodity_sn: Set(*sn as i32),
+                char_id: Set(char_id),
+            })
+            .collect::<Vec<_>>();
+        if !entries.is_empty() {
+            wish_list::Entity::insert_many(entries)
+                .exec(&self.db)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_data::wz2::Commodity;
+    use proto95::cash_shop::CashType;
+
+    use crate::{
+        gen_sqlite,
+        services::{
+            data::{account::Region, AccountService, ItemService},
+            meta::meta_service::{MetaData, MetaService},
+        },
+        SQL_OPT_MEMORY,
+    };
+
+    use super::{BuyItemResult, CashShopService, LOCKER_SLOTS};
+
+    #[tokio::test]
+    async fn buy_with_current_balance() -> anyhow::Result<()> {
+        let db = gen_sqlite(SQL_OPT_MEMORY).await?;
+        let meta = Box::leak(Box::new(MetaService::new(MetaData::default())));
+        let account = AccountService::new(db.clone());
+        let cash_shop = CashShopService::new(db.clone(), ItemService::new(db, meta));
+
+        let acc_id = account
+            .create("buyer", "hunter3", Region::Europe, true, None)
+            .await?;
+        // The balance changes after the session loaded the account
+        let acc = account.get(acc_id).await?.unwrap();
+        account.set_cash(acc, 100, 0, 0).await?;
+
+        let commodity = Commodity {
+            sn: 1,
+            item_id: 5041000,
+            count: 1,
+            price: 60,
+            period: 0,
+            priority: 0,
+            gender: 2,
+            on_sale: 1,
+        };
+        let buy = || {
+            cash_shop.buy_item(acc_id, CashType::NxCredit, acc_id, &commodity, "Buyer", None)
+        };
+
+        let BuyItemResult::Bought(acc, _) = buy().await? else {
+            panic!("Expected a purchase");
+        };
+        assert_eq!(acc.nx_credit, 40);
+        assert!(matches!(buy().await?, BuyItemResult::NotEnoughCash));
+        assert_eq!(account.get(acc_id).await?.unwrap().nx_credit, 40);
+        assert_eq!(cash_shop.load_locker(acc_id).await?.len(), 1);
+
+        // A full locker takes no more items and nothing is paid
+        account.set_cash(acc, 1000, 0, 0).await?;
+        for _ in 1..LOCKER_SLOTS {
+            assert!(matches!(buy().await?, BuyItemResult::Bought(..)));
+        }
+        assert!(matches!(buy().await?, BuyItemResult::LockerFull));
+        assert_eq!(
+            account.get(acc_id).await?.unwrap().nx_credit,
+            1000 - 60 * (LOCKER_SLOTS as i32 - 1)
+        );
+
+        Ok(())
+    }
+}
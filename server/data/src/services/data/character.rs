@@ -1,17 +1,32 @@
+use std::collections::BTreeMap;
+
 use proto95::{
-    id::{job_id::JobGroup, FaceId, HairId, ItemId, MapId, Skin},
-    login::char::{DeleteCharResult, SelectCharResultCode},
+    game::mini_room::MiniRoomType,
+    id::{job_id::JobGroup, FaceId, HairId, ItemId, MapId, SkillId, Skin},
+    login::{
+        char::{DeleteCharResult, SelectCharResultCode},
+        world::WorldId,
+    },
     shared::Gender,
 };
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect, Set, ActiveModelTrait};
+use chrono::{Duration, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait,
+    DatabaseConnection, DatabaseTransaction, EntityTrait, PaginatorTrait, QueryFilter, Set,
+    TransactionTrait,
+};
 
 use crate::{
     created_at,
     entities::{
         account,
-        character::{ActiveModel, Column, Entity, Model, self},
-        skill,
+        character::{ActiveModel, Column, Entity, Model},
+        entrusted_shop, entrusted_shop_item, equip_item, fame_log, inventory_slot,
+        item_stack, key_binding, mini_game_record, pet_consume_item, pet_item, quick_slot, skill,
+        skill_macro, wish_list,
     },
+    entity_ext::changed_active_model,
+    services::mini_room::game::{game_id, MiniGameRecord},
 };
 
 use super::{account::AccountService, item::ItemService};
@@ -52,6 +67,17 @@ impl ItemStarterSet {
 
 pub type CharacterID = i32;
 
+/// Days until the same target can receive fame from a character again
+pub const FAME_TARGET_COOLDOWN_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GiveFameResult {
+    /// Fame was given, contains the new fame of the target
+    Done(i32),
+    AlreadyGivenToday,
+    AlreadyGivenToTarget,
+}
+
 #[derive(Debug, Clone)]
 pub struct CharacterCreateDTO {
     pub name: String,
@@ -61,6 +87,7 @@ pub struct CharacterCreateDTO {
     pub hair: HairId,
     pub starter_set: ItemStarterSet,
     pub gender: Gender,
+    pub world_id: WorldId,
 }
 
 impl CharacterCreateDTO {
@@ -104,6 +131,24 @@ pub fn check_contains<T: PartialEq + std::fmt::Debug>(
     Ok(check_id)
 }
 
+/// How the owner confirms the deletion of a character
+#[derive(Debug, Clone, Copy)]
+pub enum CharDeleteAuth<'a> {
+    Pic(&'a str),
+    /// Birthday in the `yyyymmdd` format, used when the PIC is disabled
+    Birthday(&'a str),
+}
+
+/// Deletes the rows of the character in the table
+async fn delete_char_rows<E: EntityTrait>(
+    txn: &DatabaseTransaction,
+    col: E::Column,
+    char_id: CharacterID,
+) -> anyhow::Result<()> {
+    E::delete_many().filter(col.eq(char_id)).exec(txn).await?;
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct CharacterService {
     db: DatabaseConnection,
@@ -123,19 +168,32 @@ impl CharacterService {
             return Ok(false);
         }
 
-        let other_id = Entity::find()
-            .select_only()
-            .column(Column::Id)
+        let others = Entity::find()
             .filter(Column::Name.eq(name))
-            .one(&self.db)
+            .count(&self.db)
             .await?;
 
-        Ok(other_id.is_none())
+        Ok(others == 0)
     }
 
     pub async fn get_characters_for_account(&self, acc_id: i32) -> anyhow::Result<Vec<Model>> {
         Ok(Entity::find()
             .filter(Column::AccId.eq(acc_id))
+            .filter(Column::DeleteAt.is_null())
+            .all(&self.db)
+            .await?)
+    }
+
+    /// Characters of the account in the world
+    pub async fn get_world_characters_for_account(
+        &self,
+        acc_id: i32,
+        world_id: WorldId,
+    ) -> anyhow::Result<Vec<Model>> {
+        Ok(Entity::find()
+            .filter(Column::AccId.eq(acc_id))
+            .filter(Column::WorldId.eq(world_id as i32))
+            .filter(Column::DeleteAt.is_null())
             .all(&self.db)
             .await?)
     }
@@ -144,6 +202,13 @@ impl CharacterService {
         Ok(Entity::find_by_id(char_id).one(&self.db).await?)
     }
 
+    pub async fn get_by_name(&self, name: &str) -> anyhow::Result<Option<Model>> {
+        Ok(Entity::find()
+            .filter(Column::Name.eq(name))
+            .one(&self.db)
+            .await?)
+    }
+
     pub async fn must_get(&self, char_id: CharacterID) -> anyhow::Result<Model> {
         self.get(char_id)
             .await?
@@ -162,6 +227,20 @@ impl CharacterService {
             anyhow::bail!("Name is not valid");
         }
 
+        let char_id = self.insert_character(acc_id, &create).await?;
+        item_svc
+            .create_starter_set(char_id, create.starter_set)
+            .await?;
+
+        Ok(char_id)
+    }
+
+    /// Inserts the character without any items
+    pub(crate) async fn insert_character(
+        &self,
+        acc_id: i32,
+        create: &CharacterCreateDTO,
+    ) -> anyhow::Result<CharacterID> {
         let job = create.job_group;
         let map_id = MapId::AMHERST.0 as i32; //job.get_start_map().0 as i32;
         let job = job.get_noob_job_id() as u32;
@@ -170,7 +249,7 @@ impl CharacterService {
             acc_id: Set(acc_id),
             created_at: created_at(&self.db),
             gender: Set((create.gender).into()),
-            name: Set(create.name),
+            name: Set(create.name.clone()),
             map_id: Set(map_id),
             job: Set(job as i32),
             level: Set(1),
@@ -192,6 +271,7 @@ impl CharacterService {
             skin: Set(create.skin as u8 as i32),
             face: Set(create.face.0 as i32),
             hair: Set(create.hair.0 as i32),
+            world_id: Set(create.world_id as i32),
             exp: Set(0),
             gacha_exp: Set(0),
             mesos: Set(50_000),
@@ -204,26 +284,30 @@ impl CharacterService {
             ..Default::default()
         };
 
-        let char_id = Entity::insert(char).exec(&self.db).await?.last_insert_id;
-        item_svc
-            .create_starter_set(char_id, create.starter_set)
-            .await?;
-
-        Ok(char_id)
+        Ok(Entity::insert(char).exec(&self.db).await?.last_insert_id)
     }
 
+    /// Deletes the character after the grace period,
+    /// a zero grace period deletes the character right away
     pub async fn delete_character(
         &self,
         acc: &account::Model,
         char_id: CharacterID,
-        pic: &str,
+        auth: CharDeleteAuth<'_>,
+        grace: Duration,
     ) -> anyhow::Result<DeleteCharResult> {
-        if !self.account.check_pic(acc, pic)? {
-            return Ok(DeleteCharResult::InvalidPic);
+        match auth {
+            CharDeleteAuth::Pic(pic) if !self.account.check_pic(acc, pic)? => {
+                return Ok(DeleteCharResult::InvalidPic);
+            }
+            CharDeleteAuth::Birthday(birthday) if !self.account.check_birthday(acc, birthday) => {
+                return Ok(DeleteCharResult::InvalidBirthday);
+            }
+            _ => (),
         }
 
         let char = self.must_get(char_id).await?;
-        if char.acc_id != acc.id {
+        if char.acc_id != acc.id || char.delete_at.is_some() {
             return Ok(DeleteCharResult::UnknownErr);
         }
 
@@ -233,29 +317,151 @@ impl CharacterService {
         - guild
         */
 
+        // Open shops are spawned in the world and still own their items
+        let open_shop = entrusted_shop::Entity::find()
+            .filter(entrusted_shop::Column::CharId.eq(char_id))
+            .filter(entrusted_shop::Column::Open.eq(true))
+            .one(&self.db)
+            .await?;
+        if open_shop.is_some() {
+            return Ok(DeleteCharResult::UnknownErr);
+        }
+
+        if grace <= Duration::zero() {
+            self.purge_character(char_id).await?;
+        } else {
+            ActiveModel {
+                id: Set(char_id),
+                delete_at: Set(Some(Utc::now().naive_utc() + grace)),
+                ..Default::default()
+            }
+            .update(&self.db)
+            .await?;
+        }
+
         Ok(DeleteCharResult::Success)
     }
 
+    /// Removes the characters, whose grace period is over
+    pub async fn purge_expired(&self) -> anyhow::Result<usize> {
+        let expired = Entity::find()
+            .filter(Column::DeleteAt.lte(Utc::now().naive_utc()))
+            .all(&self.db)
+            .await?;
+        for char in expired.iter() {
+            self.purge_character(char.id).await?;
+        }
+        Ok(expired.len())
+    }
+
+    /// Removes the character together with all rows, which belong to it
+    async fn purge_character(&self, char_id: CharacterID) -> anyhow::Result<()> {
+        let txn = self.db.begin().await?;
+
+        let slots = inventory_slot::Entity::find()
+            .filter(inventory_slot::Column::CharId.eq(char_id))
+            .all(&txn)
+            .await?;
+        delete_char_rows::<inventory_slot::Entity>(&txn, inventory_slot::Column::CharId, char_id)
+            .await?;
+
+        let shop_ids: Vec<i32> = entrusted_shop::Entity::find()
+            .filter(entrusted_shop::Column::CharId.eq(char_id))
+            .all(&txn)
+            .await?
+            .into_iter()
+            .map(|shop| shop.id)
+            .collect();
+        let shop_items = entrusted_shop_item::Entity::find()
+            .filter(entrusted_shop_item::Column::ShopId.is_in(shop_ids.clone()))
+            .all(&txn)
+            .await?;
+        entrusted_shop_item::Entity::delete_many()
+            .filter(entrusted_shop_item::Column::ShopId.is_in(shop_ids))
+            .exec(&txn)
+            .await?;
+        delete_char_rows::<entrusted_shop::Entity>(&txn, entrusted_shop::Column::CharId, char_id)
+            .await?;
+
+        // Items are only referenced by the slots and the shop items
+        let equip_ids = slots
+            .iter()
+            .filter_map(|slot| slot.equip_item_id)
+            .chain(shop_items.iter().filter_map(|item| item.equip_item_id));
+        equip_item::Entity::delete_many()
+            .filter(equip_item::Column::Id.is_in(equip_ids))
+            .exec(&txn)
+            .await?;
+        let stack_ids = slots
+            .iter()
+            .filter_map(|slot| slot.stack_item_id)
+            .chain(shop_items.iter().filter_map(|item| item.stack_item_id));
+        item_stack::Entity::delete_many()
+            .filter(item_stack::Column::Id.is_in(stack_ids))
+            .exec(&txn)
+            .await?;
+        pet_item::Entity::delete_many()
+            .filter(pet_item::Column::Id.is_in(slots.iter().filter_map(|slot| slot.pet_item_id)))
+            .exec(&txn)
+            .await?;
+
+        // Guilds, parties and buddies are not stored yet, so there is nothing to remove for them
+        delete_char_rows::<skill::Entity>(&txn, skill::Column::CharId, char_id).await?;
+        delete_char_rows::<mini_game_record::Entity>(
+            &txn,
+            mini_game_record::Column::CharId,
+            char_id,
+        )
+        .await?;
+        delete_char_rows::<wish_list::Entity>(&txn, wish_list::Column::CharId, char_id).await?;
+        delete_char_rows::<key_binding::Entity>(&txn, key_binding::Column::CharId, char_id).await?;
+        delete_char_rows::<quick_slot::Entity>(&txn, quick_slot::Column::CharId, char_id).await?;
+        delete_char_rows::<skill_macro::Entity>(&txn, skill_macro::Column::CharId, char_id).await?;
+        delete_char_rows::<pet_consume_item::Entity>(
+            &txn,
+            pet_consume_item::Column::CharId,
+            char_id,
+        )
+        .await?;
+        fame_log::Entity::delete_many()
+            .filter(
+                Condition::any()
+                    .add(fame_log::Column::CharId.eq(char_id))
+                    .add(fame_log::Column::TargetId.eq(char_id)),
+            )
+            .exec(&txn)
+            .await?;
+
+        Entity::delete_by_id(char_id).exec(&txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
     pub async fn select_char_with_pic(
         &self,
         acc: &account::Model,
         char_id: CharacterID,
+        world_id: WorldId,
         pic: &str,
     ) -> anyhow::Result<SelectCharResultCode> {
         if !self.account.check_pic(acc, pic)? {
             return Ok(SelectCharResultCode::InvalidPic);
         }
 
-        self.select_char(acc, char_id).await
+        self.select_char(acc, char_id, world_id).await
     }
 
+    /// Only characters of the account in the given world can be selected
     pub async fn select_char(
         &self,
         acc: &account::Model,
         char_id: CharacterID,
+        world_id: WorldId,
     ) -> anyhow::Result<SelectCharResultCode> {
-        let char = self.must_get(char_id).await?;
-        if char.acc_id != acc.id {
+        let Some(char) = self.get(char_id).await? else {
+            return Ok(SelectCharResultCode::UnknownErr);
+        };
+        if char.acc_id != acc.id || char.world_id != world_id as i32 || char.delete_at.is_some() {
             return Ok(SelectCharResultCode::UnknownErr);
         }
         Ok(SelectCharResultCode::Success)
@@ -268,8 +474,393 @@ impl CharacterService {
             .await?)
     }
 
-    pub async fn save_char(&self, char: character::ActiveModel) -> anyhow::Result<()> {
-        char.save(&self.db).await?;
+    pub async fn load_mini_game_records(
+        &self,
+        id: CharacterID,
+    ) -> anyhow::Result<Vec<mini_game_record::Model>> {
+        Ok(mini_game_record::Entity::find()
+            .filter(mini_game_record::Column::CharId.eq(id))
+            .all(&self.db)
+            .await?)
+    }
+
+    /// Stores the record of the character for the given game
+    pub async fn save_mini_game_record(
+        &self,
+        id: CharacterID,
+        ty: MiniRoomType,
+        record: &MiniGameRecord,
+    ) -> anyhow::Result<()> {
+        let game_id = game_id(ty) as i32;
+        let existing = mini_game_record::Entity::find()
+            .filter(mini_game_record::Column::CharId.eq(id))
+            .filter(mini_game_record::Column::GameId.eq(game_id))
+            .one(&self.db)
+            .await?;
+
+        let mut model = mini_game_record::ActiveModel {
+            char_id: Set(id),
+            game_id: Set(game_id),
+            win: Set(record.win as i32),
+            draw: Set(record.draw as i32),
+            lose: Set(record.lose as i32),
+            score: Set(record.score as i32),
+            ..Default::default()
+        };
+        if let Some(existing) = existing {
+            model.id = Set(existing.id);
+        }
+        model.save(&self.db).await?;
+        Ok(())
+    }
+
+    /// Gives or takes one fame from the target, a character can give fame
+    /// once per day and to the same target once per month
+    pub async fn give_fame(
+        &self,
+        id: CharacterID,
+        target: CharacterID,
+        up: bool,
+    ) -> anyhow::Result<GiveFameResult> {
+        let now = Utc::now().naive_utc();
+        let txn = self.db.begin().await?;
+        let given_today = fame_log::Entity::find()
+            .filter(fame_log::Column::CharId.eq(id))
+            .filter(fame_log::Column::CreatedAt.gt(now - Duration::days(1)))
+            .one(&txn)
+            .await?;
+        if given_today.is_some() {
+            return Ok(GiveFameResult::AlreadyGivenToday);
+        }
+
+        let given_target = fame_log::Entity::find()
+            .filter(fame_log::Column::CharId.eq(id))
+            .filter(fame_log::Column::TargetId.eq(target))
+            .filter(
+                fame_log::Column::CreatedAt.gt(now - Duration::days(FAME_TARGET_COOLDOWN_DAYS)),
+            )
+            .one(&txn)
+            .await?;
+        if given_target.is_some() {
+            return Ok(GiveFameResult::AlreadyGivenToTarget);
+        }
+
+        let delta = if up { 1 } else { -1 };
+        Entity::update_many()
+            .col_expr(Column::Fame, Expr::col(Column::Fame).add(delta))
+            .filter(Column::Id.eq(target))
+            .exec(&txn)
+            .await?;
+
+        fame_log::ActiveModel {
+            char_id: Set(id),
+            target_id: Set(target),
+            created_at: created_at(&self.db),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let fame = Entity::find_by_id(target)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| anyhow::format_err!("No character with id: {target}"))?
+            .fame;
+        txn.commit().await?;
+        Ok(GiveFameResult::Done(fame))
+    }
+
+    /// Writes the columns, which changed since the character was saved,
+    /// returns false If nothing changed
+    pub async fn update_char(&self, saved: &Model, char: &Model) -> anyhow::Result<bool> {
+        self.update_char_with(&self.db, saved, char).await
+    }
+
+    /// Writes the changed columns on the connection, so It can be part of a transaction
+    pub async fn update_char_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        saved: &Model,
+        char: &Model,
+    ) -> anyhow::Result<bool> {
+        let Some(update) = changed_active_model::<ActiveModel>(saved, char) else {
+            return Ok(false);
+        };
+        update.update(db).await?;
+        Ok(true)
+    }
+
+    /// Writes the changed skills in one transaction, new skills are inserted and get their db id
+    pub async fn save_skills(
+        &self,
+        id: CharacterID,
+        saved: &BTreeMap<SkillId, skill::Model>,
+        skills: &mut BTreeMap<SkillId, skill::Model>,
+    ) -> anyhow::Result<()> {
+        let mut written = skills.clone();
+        let txn = self.db.begin().await?;
+        self.save_skills_with(&txn, id, saved, &mut written).await?;
+        txn.commit().await?;
+        *skills = written;
+        Ok(())
+    }
+
+    /// Writes the changed skills on the connection, so It can be part of a transaction
+    pub async fn save_skills_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        id: CharacterID,
+        saved: &BTreeMap<SkillId, skill::Model>,
+        skills: &mut BTreeMap<SkillId, skill::Model>,
+    ) -> anyhow::Result<()> {
+        for (skill_id, skill) in skills.iter_mut() {
+            match saved.get(skill_id) {
+                Some(saved) => {
+                    if let Some(update) = changed_active_model::<skill::ActiveModel>(saved, skill) {
+                        update.update(db).await?;
+                    }
+                }
+                None => {
+                    let model = skill::ActiveModel {
+                        skill_id: Set(skill.skill_id),
+                        skill_level: Set(skill.skill_level),
+                        master_level: Set(skill.master_level),
+                        expires_at: Set(skill.expires_at),
+                        cooldown: Set(skill.cooldown),
+                        char_id: Set(id),
+                        ..Default::default()
+                    };
+                    skill.id = skill::Entity::insert(model)
+                        .exec(db)
+                        .await?
+                        .last_insert_id;
+                    skill.char_id = id;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::{Duration, NaiveDate, Utc};
+    use proto95::{
+        id::{job_id::JobGroup, FaceId, HairId, SkillId, Skin},
+        login::char::{DeleteCharResult, SelectCharResultCode},
+        shared::Gender,
+    };
+    use sea_orm::{ActiveModelTrait, EntityTrait, PaginatorTrait, Set};
+
+    use crate::{
+        entities::{character, inventory_slot, item_stack, skill},
+        gen_sqlite,
+        services::data::{account::Region, AccountService, GmLogService},
+    };
+
+    use super::{CharDeleteAuth, CharacterCreateDTO, CharacterService, ItemStarterSet};
+
+    fn test_char(name: &str) -> CharacterCreateDTO {
+        CharacterCreateDTO {
+            name: name.to_string(),
+            job_group: JobGroup::Adventurer,
+            face: FaceId::LEISURE_LOOK_M,
+            skin: Skin::Normal,
+            hair: HairId::BLACK_TOBEN,
+            starter_set: ItemStarterSet::default_starter_set(JobGroup::Adventurer),
+            gender: Gender::Male,
+            world_id: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn save_char_skills() -> anyhow::Result<()> {
+        let db = gen_sqlite(crate::SQL_OPT_MEMORY).await?;
+        let acc_id = AccountService::new(db.clone())
+            .create("test", "hunter3", Region::Europe, true, None)
+            .await?;
+        let svc = CharacterService::new(db);
+        let char_id = svc.insert_character(acc_id, &test_char("Tester")).await?;
+
+        let saved = svc.must_get(char_id).await?;
+        assert!(!svc.update_char(&saved, &saved).await?);
+
+        let mut char = saved.clone();
+        char.hp = 20;
+        char.mesos = 100;
+        assert!(svc.update_char(&saved, &char).await?);
+        assert_eq!(svc.must_get(char_id).await?, char);
+
+        let skill_id = SkillId(1000);
+        let mut skills = BTreeMap::from([(
+            skill_id,
+            skill::Model {
+                id: 0,
+                skill_id: skill_id.0 as i32,
+                skill_level: 1,
+                master_level: 10,
+                expires_at: None,
+                cooldown: None,
+                char_id,
+            },
+        )]);
+        svc.save_skills(char_id, &BTreeMap::new(), &mut skills)
+            .await?;
+        assert_eq!(
+            svc.load_skills(char_id).await?,
+            vec![skills[&skill_id].clone()]
+        );
+
+        let saved_skills = skills.clone();
+        skills.get_mut(&skill_id).unwrap().skill_level = 2;
+        svc.save_skills(char_id, &saved_skills, &mut skills).await?;
+        assert_eq!(svc.load_skills(char_id).await?[0].skill_level, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_char() -> anyhow::Result<()> {
+        let db = gen_sqlite(crate::SQL_OPT_MEMORY).await?;
+        let accs = AccountService::new(db.clone());
+        let acc_id = accs
+            .create("test", "hunter3", Region::Europe, true, None)
+            .await?;
+        let acc = accs.get(acc_id).await?.unwrap();
+        let acc = accs.set_pic(acc, "123456".to_string()).await?;
+        let svc = CharacterService::new(db.clone());
+
+        let char_id = svc.insert_character(acc_id, &test_char("Tester")).await?;
+        let gm_log = GmLogService::new(db.clone());
+        gm_log
+            .log_command(&svc.must_get(char_id).await?, "@help", true)
+            .await?;
+        let stack_id = item_stack::Entity::insert(item_stack::ActiveModel {
+            item_id: Set(2000000),
+            quantity: Set(1),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await?
+        .last_insert_id;
+        inventory_slot::Entity::insert(inventory_slot::ActiveModel {
+            inv_type: Set(2),
+            slot: Set(0),
+            char_id: Set(char_id),
+            stack_item_id: Set(Some(stack_id)),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await?;
+
+        let res = svc
+            .delete_character(
+                &acc,
+                char_id,
+                CharDeleteAuth::Pic("654321"),
+                Duration::zero(),
+            )
+            .await?;
+        assert!(matches!(res, DeleteCharResult::InvalidPic));
+
+        // Without a grace period the items are deleted with the character
+        let res = svc
+            .delete_character(
+                &acc,
+                char_id,
+                CharDeleteAuth::Pic("123456"),
+                Duration::zero(),
+            )
+            .await?;
+        assert!(matches!(res, DeleteCharResult::Success));
+        assert!(svc.get(char_id).await?.is_none());
+        assert!(item_stack::Entity::find_by_id(stack_id)
+            .one(&db)
+            .await?
+            .is_none());
+        assert_eq!(inventory_slot::Entity::find().count(&db).await?, 0);
+        // The GM log is kept for audits
+        let log = gm_log.get_log(char_id, 10).await?;
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].char_name, "Tester");
+
+        // Pending characters are hidden until they are purged
+        let acc = accs
+            .set_birthday(acc, NaiveDate::from_ymd_opt(1999, 1, 1).unwrap())
+            .await?;
+        let char_id = svc.insert_character(acc_id, &test_char("Pending")).await?;
+        let res = svc
+            .delete_character(
+                &acc,
+                char_id,
+                CharDeleteAuth::Birthday("19990101"),
+                Duration::days(7),
+            )
+            .await?;
+        assert!(matches!(res, DeleteCharResult::Success));
+        assert!(svc.get_characters_for_account(acc_id).await?.is_empty());
+        assert!(matches!(
+            svc.select_char(&acc, char_id, 0).await?,
+            SelectCharResultCode::UnknownErr
+        ));
+        assert!(!svc.check_name("Pending").await?);
+        assert_eq!(svc.purge_expired().await?, 0);
+
+        character::ActiveModel {
+            id: Set(char_id),
+            delete_at: Set(Some(Utc::now().naive_utc() - Duration::days(1))),
+            ..Default::default()
+        }
+        .update(&db)
+        .await?;
+        assert_eq!(svc.purge_expired().await?, 1);
+        assert!(svc.get(char_id).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn world_chars() -> anyhow::Result<()> {
+        let db = gen_sqlite(crate::SQL_OPT_MEMORY).await?;
+        let accs = AccountService::new(db.clone());
+        let acc_id = accs
+            .create("test", "hunter3", Region::Europe, true, None)
+            .await?;
+        let svc = CharacterService::new(db);
+        let first = svc.insert_character(acc_id, &test_char("First")).await?;
+        let second = svc
+            .insert_character(
+                acc_id,
+                &CharacterCreateDTO {
+                    world_id: 1,
+                    ..test_char("Second")
+                },
+            )
+            .await?;
+
+        let ids = |chars: Vec<character::Model>| chars.iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(svc.get_world_characters_for_account(acc_id, 1).await?),
+            vec![second]
+        );
+        assert_eq!(
+            ids(svc.get_characters_for_account(acc_id).await?),
+            vec![first, second]
+        );
+
+        // Characters can only be selected in their world
+        let acc = accs.get(acc_id).await?.unwrap();
+        assert!(matches!(
+            svc.select_char(&acc, second, 1).await?,
+            SelectCharResultCode::Success
+        ));
+        assert!(matches!(
+            svc.select_char(&acc, second, 0).await?,
+            SelectCharResultCode::UnknownErr
+        ));
+
         Ok(())
     }
 }
@@ -0,0 +1,58 @@
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+
+use crate::{
+    created_at,
+    entities::{character, gm_log},
+};
+
+use super::character::CharacterID;
+
+/// Audit log of the executed GM commands
+#[derive(Debug, Clone)]
+pub struct GmLogService {
+    db: DatabaseConnection,
+}
+
+impl GmLogService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Logs the command, denied and unknown commands are logged as not allowed.
+    /// The account and name are stored as well, so the entry outlives the character
+    pub async fn log_command(
+        &self,
+        char: &character::Model,
+        command: &str,
+        allowed: bool,
+    ) -> anyhow::Result<()> {
+        let entry = gm_log::ActiveModel {
+            acc_id: Set(char.acc_id),
+            char_id: Set(char.id),
+            char_name: Set(char.name.clone()),
+            map_id: Set(char.map_id),
+            command: Set(command.to_string()),
+            allowed: Set(allowed),
+            created_at: created_at(&self.db),
+            ..Default::default()
+        };
+        gm_log::Entity::insert(entry).exec(&self.db).await?;
+        Ok(())
+    }
+
+    /// Latest commands of the character, the newest first
+    pub async fn get_log(
+        &self,
+        char_id: CharacterID,
+        limit: u64,
+    ) -> anyhow::Result<Vec<gm_log::Model>> {
+        Ok(gm_log::Entity::find()
+            .filter(gm_log::Column::CharId.eq(char_id))
+            .order_by_desc(gm_log::Column::Id)
+            .limit(limit)
+            .all(&self.db)
+            .await?)
+    }
+}
@@ -1,11 +1,11 @@
-use crate::{entities::{equip_item, inventory_slot, item_stack}, services::{helper::intentory::{inv::{EquipInventory, InventorySet, InventoryExt, InventoryType, EquipItemSlot, StackInventory}, Inventory}, meta::meta_service::MetaService, model::item::{EquipItem, EquipStat, StackItem}}};
+use crate::{entities::{equip_item, inventory_slot, item_stack, pet_item}, services::{helper::intentory::{inv::{EquipInventory, InventorySet, InventoryExt, InventoryType, EquipItemSlot, StackInventory}, Inventory}, meta::meta_service::MetaService, model::item::{EquipItem, EquipStat, StackItem}}};
 use anyhow::anyhow;
 use itertools::Itertools;
 use num_enum::TryFromPrimitive;
 use proto95::{id::ItemId, shared::inventory::EquippedSlot};
 use sea_orm::{
-    ActiveValue::NotSet, ColumnTrait, DatabaseConnection, DeriveColumn, EntityTrait, EnumIter,
-    QueryFilter, QuerySelect, Set,
+    ActiveValue::NotSet, ColumnTrait, ConnectionTrait, DatabaseConnection, DeriveColumn,
+    EntityTrait, EnumIter, QueryFilter, QuerySelect, Set, TransactionTrait,
 };
 
 use super::character::{ItemStarterSet, CharacterID};
@@ -85,12 +85,32 @@ fn map_stack_to_active_model(item: &StackItem) -> item_stack::ActiveModel {
     }
 }
 
+fn map_pet_to_active_model(item: &StackItem) -> Option<pet_item::ActiveModel> {
+    let pet = item.pet.as_ref()?;
+    let id = item.db_id.map(Set).unwrap_or(NotSet);
+
+    Some(pet_item::ActiveModel {
+        id,
+        expires_at: Set(item.expiration),
+        cash_id: Set(item.cash_id.map(|i| i as i64)),
+        item_id: Set(item.item_id.0 as i32),
+        flags: Set(item.flags.bits() as i32),
+        name: Set(pet.name.clone()),
+        level: Set(pet.level as i32),
+        tameness: Set(pet.tameness as i32),
+        fullness: Set(pet.fullness as i32),
+        skill: Set(pet.skill as i32),
+        remaining_life: Set(pet.remaining_life as i32),
+        summoned: Set(pet.summoned),
+    })
+}
+
 impl ItemService {
     pub fn new(db: DatabaseConnection, meta: &'static MetaService) -> Self {
         Self { db, meta }
     }
 
-    fn get_eq_item_from_id(&self, item_id: ItemId) -> anyhow::Result<EquipItem> {
+    pub fn get_eq_item_from_id(&self, item_id: ItemId) -> anyhow::Result<EquipItem> {
         let item_meta = self
             .meta
             .get_eq_data(item_id)
@@ -104,29 +124,41 @@ impl ItemService {
         Ok(item)
     }
 
-    pub async fn create_equip(&self, item: &EquipItem) -> anyhow::Result<DbItemId> {
+    pub async fn create_equip<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &EquipItem,
+    ) -> anyhow::Result<DbItemId> {
         if item.db_id.is_some() {
             anyhow::bail!("DB id already set");
         }
         let res = equip_item::Entity::insert(map_equip_to_active_model(item))
-            .exec(&self.db)
+            .exec(db)
             .await?;
 
         Ok(res.last_insert_id)
     }
 
-    pub async fn update_equip(&self, item: &EquipItem) -> anyhow::Result<()> {
+    pub async fn update_equip<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &EquipItem,
+    ) -> anyhow::Result<()> {
         if item.db_id.is_none() {
             anyhow::bail!("DB id not set");
         }
         equip_item::Entity::update(map_equip_to_active_model(item))
-            .exec(&self.db)
+            .exec(db)
             .await?;
 
         Ok(())
     }
 
-    pub async fn create_stack(&self, item: &StackItem) -> anyhow::Result<DbItemId> {
+    pub async fn create_stack<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &StackItem,
+    ) -> anyhow::Result<DbItemId> {
         let stack = item_stack::ActiveModel {
             id: NotSet,
             expires_at: Set(item.expiration),
@@ -136,21 +168,90 @@ impl ItemService {
             quantity: Set(item.quantity as i32),
         };
 
-        let res = item_stack::Entity::insert(stack).exec(&self.db).await?;
+        let res = item_stack::Entity::insert(stack).exec(db).await?;
         Ok(res.last_insert_id)
     }
 
-    pub async fn update_stack(&self, item: &StackItem) -> anyhow::Result<()> {
+    pub async fn update_stack<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &StackItem,
+    ) -> anyhow::Result<()> {
         if item.db_id.is_none() {
             anyhow::bail!("DB id not set");
         }
         item_stack::Entity::update(map_stack_to_active_model(item))
-            .exec(&self.db)
+            .exec(db)
             .await?;
 
         Ok(())
     }
 
+    pub async fn create_pet_item<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &StackItem,
+    ) -> anyhow::Result<DbItemId> {
+        if item.db_id.is_some() {
+            anyhow::bail!("DB id already set");
+        }
+        let pet = map_pet_to_active_model(item).ok_or_else(|| anyhow!("Item is not a pet"))?;
+        let res = pet_item::Entity::insert(pet).exec(db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    pub async fn update_pet_item<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item: &StackItem,
+    ) -> anyhow::Result<()> {
+        if item.db_id.is_none() {
+            anyhow::bail!("DB id not set");
+        }
+        let pet = map_pet_to_active_model(item).ok_or_else(|| anyhow!("Item is not a pet"))?;
+        pet_item::Entity::update(pet).exec(db).await?;
+        Ok(())
+    }
+
+    pub async fn load_pet(&self, id: DbItemId) -> anyhow::Result<Option<pet_item::Model>> {
+        self.load_pet_with(&self.db, id).await
+    }
+
+    /// Loads the pet on the connection, so It can be part of a transaction
+    pub async fn load_pet_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        id: DbItemId,
+    ) -> anyhow::Result<Option<pet_item::Model>> {
+        Ok(pet_item::Entity::find_by_id(id).one(db).await?)
+    }
+
+    /// Creates a new pet, the db id of the pet also serves as cash id
+    pub async fn create_pet(&self, item_id: ItemId, name: String) -> anyhow::Result<StackItem> {
+        self.create_pet_with(&self.db, item_id, name).await
+    }
+
+    /// Creates a new pet on the connection, so It can be part of a transaction
+    pub async fn create_pet_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        item_id: ItemId,
+        name: String,
+    ) -> anyhow::Result<StackItem> {
+        if !item_id.is_pet() {
+            anyhow::bail!("Invalid pet: {item_id:?}");
+        }
+        let mut item = StackItem::new_pet(item_id, 0, name);
+        let pet = map_pet_to_active_model(&item).ok_or_else(|| anyhow!("Item is not a pet"))?;
+        let id = pet_item::Entity::insert(pet).exec(db).await?.last_insert_id;
+        item.db_id = Some(id);
+        item.cash_id = Some(id as u64);
+
+        let pet = map_pet_to_active_model(&item).ok_or_else(|| anyhow!("Item is not a pet"))?;
+        pet_item::Entity::update(pet).exec(db).await?;
+        Ok(item)
+    }
+
     pub async fn create_starter_set(
         &self,
         char_id: i32,
@@ -174,7 +275,7 @@ impl ItemService {
 
         let mut inv = InventorySet::with_default_slots();
         for (mut item, slot) in items.into_iter().zip(slots) {
-            let id = self.create_equip(&item).await?;
+            let id = self.create_equip(&self.db, &item).await?;
             item.db_id = Some(id);
 
             //TODO maybe document that allocation behind the scenes
@@ -184,7 +285,7 @@ impl ItemService {
         inv.etc
             .set(0, self.get_stack_item_from_id(starter_set.guide, 1)?.into());
 
-        self.save_inventory(inv, char_id).await?;
+        self.save_inventory(&mut inv, char_id).await?;
 
         Ok(())
     }
@@ -198,8 +299,9 @@ impl ItemService {
         Ok(())
     }
 
-    async fn save_eq_inventory_type<'a, const CAP: usize>(
+    async fn save_eq_inventory_type<C: ConnectionTrait, const CAP: usize>(
         &self,
+        db: &C,
         inv_type: InventoryType,
         char_id: i32,
         inv: &mut Inventory<CAP, EquipItemSlot>,
@@ -212,10 +314,10 @@ impl ItemService {
         for item_slot in inv.items_mut() {
             let item = &mut item_slot.item;
             if item.db_id.is_none() {
-                let id = self.create_equip(item).await?;
+                let id = self.create_equip(db, item).await?;
                 item.db_id = Some(id);
             } else if item.last_update > 0 {
-                self.update_equip(item).await?;
+                self.update_equip(db, item).await?;
                 item.last_update = 0;
             }
         }
@@ -235,14 +337,15 @@ impl ItemService {
         let slots = slots.collect_vec();
 
         inventory_slot::Entity::insert_many(slots)
-            .exec(&self.db)
+            .exec(db)
             .await?;
 
         Ok(())
     }
 
-    async fn save_stack_inventory_type<'a>(
+    async fn save_stack_inventory_type<C: ConnectionTrait>(
         &self,
+        db: &C,
         inv_type: InventoryType,
         char_id: i32,
         inv: &mut StackInventory,
@@ -254,43 +357,74 @@ impl ItemService {
         // Update items
         for item_slot in inv.items_mut() {
             let item = item_slot.item.as_mut();
+            let is_pet = item.pet.is_some();
             if item.db_id.is_none() {
-                let id = self.create_stack(item).await?;
+                let id = if is_pet {
+                    self.create_pet_item(db, item).await?
+                } else {
+                    self.create_stack(db, item).await?
+                };
                 item.db_id = Some(id);
             } else if item.last_update > 0 {
-                self.update_stack(item).await?;
+                if is_pet {
+                    self.update_pet_item(db, item).await?;
+                } else {
+                    self.update_stack(db, item).await?;
+                }
                 item.last_update = 0;
             }
         }
 
-        let slots = inv.iter().map(|(slot, item)| inventory_slot::ActiveModel {
-            id: NotSet,
-            equip_item_id: Set(None),
-            char_id: Set(char_id),
-            slot: Set(slot as i32),
-            inv_type: Set(inv_type as i32),
-            stack_item_id: Set(Some(item.item.db_id.unwrap())),
-            pet_item_id: Set(None),
+        let slots = inv.iter().map(|(slot, item)| {
+            let db_id = item.item.db_id.unwrap();
+            let is_pet = item.item.pet.is_some();
+            inventory_slot::ActiveModel {
+                id: NotSet,
+                equip_item_id: Set(None),
+                char_id: Set(char_id),
+                slot: Set(slot as i32),
+                inv_type: Set(inv_type as i32),
+                stack_item_id: Set((!is_pet).then_some(db_id)),
+                pet_item_id: Set(is_pet.then_some(db_id)),
+            }
         });
 
         inventory_slot::Entity::insert_many(slots)
-            .exec(&self.db)
+            .exec(db)
             .await?;
 
         Ok(())
     }
 
+    /// Writes the inventories in a transaction, new items get their db id.
+    /// The inventories are only updated after the commit, so a failed write can be retried
     pub async fn save_inventory(
         &self,
-        mut invs: InventorySet,
+        invs: &mut InventorySet,
+        char_id: CharacterID,
+    ) -> anyhow::Result<()> {
+        let mut saved = invs.clone();
+        let txn = self.db.begin().await?;
+        self.save_inventory_with(&txn, &mut saved, char_id).await?;
+        txn.commit().await?;
+        *invs = saved;
+        Ok(())
+    }
+
+    /// Writes the inventories on the connection, so It can be part of a transaction
+    pub async fn save_inventory_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        invs: &mut InventorySet,
         char_id: CharacterID,
     ) -> anyhow::Result<()> {
         inventory_slot::Entity::delete_many()
             .filter(inventory_slot::Column::CharId.eq(char_id))
-            .exec(&self.db)
+            .exec(db)
             .await?;
 
         self.save_eq_inventory_type(
+            db,
             InventoryType::Equipped,
             char_id,
             invs.equipped.get_inner_mut(),
@@ -300,22 +434,28 @@ impl ItemService {
         log::info!("Saved acc");
 
         self.save_eq_inventory_type(
+            db,
             InventoryType::MaskedEquipped,
             char_id,
             invs.masked_equipped.get_inner_mut(),
         )
         .await?;
 
-        self.save_eq_inventory_type(InventoryType::Equip, char_id, invs.equip.get_inner_mut())
-            .await?;
+        self.save_eq_inventory_type(
+            db,
+            InventoryType::Equip,
+            char_id,
+            invs.equip.get_inner_mut(),
+        )
+        .await?;
 
-        self.save_stack_inventory_type(InventoryType::Use, char_id, &mut invs.use_)
+        self.save_stack_inventory_type(db, InventoryType::Use, char_id, &mut invs.use_)
             .await?;
-        self.save_stack_inventory_type(InventoryType::Misc, char_id, &mut invs.misc)
+        self.save_stack_inventory_type(db, InventoryType::Misc, char_id, &mut invs.misc)
             .await?;
-        self.save_stack_inventory_type(InventoryType::Etc, char_id, &mut invs.etc)
+        self.save_stack_inventory_type(db, InventoryType::Etc, char_id, &mut invs.etc)
             .await?;
-        self.save_stack_inventory_type(InventoryType::Cash, char_id, &mut invs.cash)
+        self.save_stack_inventory_type(db, InventoryType::Cash, char_id, &mut invs.cash)
             .await?;
         Ok(())
     }
@@ -335,6 +475,13 @@ impl ItemService {
             .all(&self.db)
             .await?;
 
+        let pet_item_slots = inventory_slot::Entity::find()
+            .filter(inventory_slot::Column::CharId.eq(char_id))
+            .inner_join(pet_item::Entity)
+            .select_also(pet_item::Entity)
+            .all(&self.db)
+            .await?;
+
         let mut inv = InventorySet::with_default_slots();
 
         for (slot_info, equip_item) in equip_item_slots {
@@ -379,6 +526,14 @@ impl ItemService {
                 .set(slot, stack_item.into());
         }
 
+        for (slot_info, pet_item) in pet_item_slots {
+            let Some(pet_item) = pet_item else {
+                anyhow::bail!("Invalid no pet item");
+            };
+            let pet_item: StackItem = pet_item.into();
+            inv.cash.set(slot_info.slot as usize, pet_item.into());
+        }
+
         Ok(inv)
     }
 
@@ -468,6 +623,7 @@ mod tests {
                         guide: job.get_guide_item(),
                     },
                     gender: Gender::Male,
+                    world_id: 0,
                 },
                 &item_svc,
             )
@@ -496,7 +652,7 @@ mod tests {
         stack_1.item.quantity += 5;
         stack_1.item.last_update += 1;
 
-        svc.save_inventory(inv, char_id).await.unwrap();
+        svc.save_inventory(&mut inv, char_id).await.unwrap();
         let inv = svc.load_inventory_for_character(char_id).await.unwrap();
         assert_eq!(inv.equipped.len(), 3);
         assert_eq!(inv.etc.get(0).unwrap().quantity, 1 + 5);
@@ -0,0 +1,288 @@
+use proto95::{
+    game::{
+        keymaps::{KeyBinding, KeyBindingChange, FUNC_KEYS, QUICK_SLOT_KEYS},
+        macros::SingleMacro,
+    },
+    id::{ItemId, SkillId},
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+
+use crate::entities::{key_binding, pet_consume_item, quick_slot, skill_macro};
+
+use super::character::CharacterID;
+
+pub type KeyMap = [KeyBinding; FUNC_KEYS];
+
+/// Default bindings of the client as (key, type, action), the stored key map
+/// starts with these, as the client only sends the modified keys
+const DEFAULT_KEY_BINDINGS: [(usize, u8, u32); 40] = [
+    (2, 4, 10),
+    (3, 4, 12),
+    (4, 4, 13),
+    (5, 4, 18),
+    (6, 4, 24),
+    (7, 4, 21),
+    (16, 4, 8),
+    (17, 4, 5),
+    (18, 4, 0),
+    (19, 4, 4),
+    (23, 4, 1),
+    (24, 4, 25),
+    (25, 4, 19),
+    (26, 4, 14),
+    (27, 4, 15),
+    (29, 5, 52),
+    (31, 4, 2),
+    (33, 4, 26),
+    (34, 4, 17),
+    (35, 4, 11),
+    (37, 4, 3),
+    (38, 4, 20),
+    (39, 4, 27),
+    (40, 4, 16),
+    (41, 4, 23),
+    (43, 4, 9),
+    (44, 5, 50),
+    (45, 5, 51),
+    (46, 4, 6),
+    (48, 4, 22),
+    (50, 4, 7),
+    (56, 5, 53),
+    (57, 5, 54),
+    (59, 6, 100),
+    (60, 6, 101),
+    (61, 6, 102),
+    (62, 6, 103),
+    (63, 6, 104),
+    (64, 6, 105),
+    (65, 6, 106),
+];
+
+pub fn default_key_map() -> KeyMap {
+    let mut key_map = [KeyBinding::default(); FUNC_KEYS];
+    for (key, ty, action_id) in DEFAULT_KEY_BINDINGS {
+        key_map[key] = KeyBinding { ty, action_id };
+    }
+    key_map
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyMapService {
+    db: DatabaseConnection,
+}
+
+impl KeyMapService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Loads the key map, None If the character never modified the default key map
+    pub async fn load_key_map(&self, char_id: CharacterID) -> anyhow::Result<Option<KeyMap>> {
+        let bindings = key_binding::Entity::find()
+            .filter(key_binding::Column::CharId.eq(char_id))
+            .all(&self.db)
+            .await?;
+        if bindings.is_empty() {
+            return Ok(None);
+        }
+
+        let mut key_map = [KeyBinding::default(); FUNC_KEYS];
+        for binding in bindings {
+            let Some(key) = key_map.get_mut(binding.key as usize) else {
+                continue;
+            };
+            *key = KeyBinding {
+                ty: binding.ty as u8,
+                action_id: binding.action_id as u32,
+            };
+        }
+        Ok(Some(key_map))
+    }
+
+    /// Applies the changes to the stored key map and stores the whole map
+    pub async fn update_key_map(
+        &self,
+        char_id: CharacterID,
+        changes: &[KeyBindingChange],
+    ) -> anyhow::Result<()> {
+        let mut key_map = self
+            .load_key_map(char_id)
+            .await?
+            .unwrap_or_else(default_key_map);
+        for change in changes {
+            let key = key_map
+                .get_mut(change.key as usize)
+                .ok_or_else(|| anyhow::format_err!("Invalid key: {}", change.key))?;
+            *key = change.binding;
+        }
+
+        let txn = self.db.begin().await?;
+        key_binding::Entity::delete_many()
+            .filter(key_binding::Column::CharId.eq(char_id))
+            .exec(&txn)
+            .await?;
+
+        let bindings = key_map
+            .iter()
+            .enumerate()
+            .filter(|(_, binding)| binding.ty != 0)
+            .map(|(key, binding)| key_binding::ActiveModel {
+                id: NotSet,
+                key: Set(key as i32),
+                ty: Set(binding.ty as i32),
+                action_id: Set(binding.action_id as i32),
+                char_id: Set(char_id),
+            })
+            .collect::<Vec<_>>();
+        if !bindings.is_empty() {
+            key_binding::Entity::insert_many(bindings)
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Loads the quick slots, None If the default quick slots are used
+    pub async fn load_quick_slots(
+        &self,
+        char_id: CharacterID,
+    ) -> anyhow::Result<Option<[u32; QUICK_SLOT_KEYS]>> {
+        let slots = quick_slot::Entity::find()
+            .filter(quick_slot::Column::CharId.eq(char_id))
+            .all(&self.db)
+            .await?;
+        if slots.is_empty() {
+            return Ok(None);
+        }
+
+        let mut keys = [0; QUICK_SLOT_KEYS];
+        for slot in slots {
+            if let Some(key) = keys.get_mut(slot.slot as usize) {
+                *key = slot.key as u32;
+            }
+        }
+        Ok(Some(keys))
+    }
+
+    pub async fn save_quick_slots(
+        &self,
+        char_id: CharacterID,
+        keys: &[u32; QUICK_SLOT_KEYS],
+    ) -> anyhow::Result<()> {
+        let txn = self.db.begin().await?;
+        quick_slot::Entity::delete_many()
+            .filter(quick_slot::Column::CharId.eq(char_id))
+            .exec(&txn)
+            .await?;
+
+        let slots = keys
+            .iter()
+            .enumerate()
+            .map(|(slot, key)| quick_slot::ActiveModel {
+                id: NotSet,
+                slot: Set(slot as i32),
+                key: Set(*key as i32),
+                char_id: Set(char_id),
+            });
+        quick_slot::Entity::insert_many(slots).exec(&txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    pub async fn load_macros(&self, char_id: CharacterID) -> anyhow::Result<Vec<SingleMacro>> {
+        Ok(skill_macro::Entity::find()
+            .filter(skill_macro::Column::CharId.eq(char_id))
+            .order_by_asc(skill_macro::Column::Ix)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|m| SingleMacro {
+                name: m.name,
+                mute: m.mute,
+                skills: [m.skill1, m.skill2, m.skill3].map(|skill| SkillId(skill as u32)),
+            })
+            .collect())
+    }
+
+    /// Replaces all macros of the character
+    pub async fn save_macros(
+        &self,
+        char_id: CharacterID,
+        macros: &[SingleMacro],
+    ) -> anyhow::Result<()> {
+        let txn = self.db.begin().await?;
+        skill_macro::Entity::delete_many()
+            .filter(skill_macro::Column::CharId.eq(char_id))
+            .exec(&txn)
+            .await?;
+
+        let macros = macros
+            .iter()
+            .enumerate()
+            .map(|(ix, m)| skill_macro::ActiveModel {
+                id: NotSet,
+                ix: Set(ix as i32),
+                name: Set(m.name.clone()),
+                mute: Set(m.mute),
+                skill1: Set(m.skills[0].0 as i32),
+                skill2: Set(m.skills[1].0 as i32),
+                skill3: Set(m.skills[2].0 as i32),
+                char_id: Set(char_id),
+            })
+            .collect::<Vec<_>>();
+        if !macros.is_empty() {
+            skill_macro::Entity::insert_many(macros).exec(&txn).await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Loads the HP and MP items, which the pets consume
+    pub async fn load_pet_consume_items(
+        &self,
+        char_id: CharacterID,
+    ) -> anyhow::Result<(ItemId, ItemId)> {
+        let items = pet_consume_item::Entity::find()
+            .filter(pet_consume_item::Column::CharId.eq(char_id))
+            .one(&self.db)
+            .await?;
+        Ok(items.map_or((ItemId(0), ItemId(0)), |items| {
+            (
+                ItemId(items.hp_item_id as u32),
+                ItemId(items.mp_item_id as u32),
+            )
+        }))
+    }
+
+    /// Sets either the HP or the MP item, which the pets consume
+    pub async fn save_pet_consume_item(
+        &self,
+        char_id: CharacterID,
+        item_id: ItemId,
+        mp: bool,
+    ) -> anyhow::Result<()> {
+        let existing = pet_consume_item::Entity::find()
+            .filter(pet_consume_item::Column::CharId.eq(char_id))
+            .one(&self.db)
+            .await?;
+
+        let mut model = pet_consume_item::ActiveModel {
+            char_id: Set(char_id),
+            ..Default::default()
+        };
+        if let Some(existing) = existing {
+            model.id = Set(existing.id);
+        }
+        if mp {
+            model.mp_item_id = Set(item_id.0 as i32);
+        } else {
+            model.hp_item_id = Set(item_id.0 as i32);
+        }
+        model.save(&self.db).await?;
+        Ok(())
+    }
+}
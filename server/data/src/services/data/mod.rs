@@ -1,27 +1,55 @@
 pub mod account;
+pub mod ban;
+pub mod cash_shop;
 pub mod character;
+pub mod gm;
 pub mod item;
+pub mod key_map;
+pub mod shop;
 
 pub use account::AccountService;
+pub use ban::BanService;
+pub use cash_shop::CashShopService;
 pub use character::CharacterService;
+pub use gm::GmLogService;
 pub use item::ItemService;
-use sea_orm::DatabaseConnection;
+pub use key_map::KeyMapService;
+pub use shop::ShopService;
+use sea_orm::{DatabaseConnection, DatabaseTransaction, TransactionTrait};
 
 use super::meta::meta_service::MetaService;
 
 #[derive(Debug)]
 pub struct DataServices {
+    db: DatabaseConnection,
     pub account: AccountService,
+    pub ban: BanService,
+    pub cash_shop: CashShopService,
     pub char: CharacterService,
+    pub gm_log: GmLogService,
     pub item: ItemService,
+    pub key_map: KeyMapService,
+    pub shop: ShopService,
 }
 
 impl DataServices {
     pub fn new(db: DatabaseConnection, meta: &'static MetaService) -> Self {
+        let item = ItemService::new(db.clone(), meta);
         DataServices {
             account: AccountService::new(db.clone()),
+            ban: BanService::new(db.clone()),
+            cash_shop: CashShopService::new(db.clone(), item.clone()),
             char: CharacterService::new(db.clone()),
-            item: ItemService::new(db, meta),
+            gm_log: GmLogService::new(db.clone()),
+            key_map: KeyMapService::new(db.clone()),
+            shop: ShopService::new(db.clone(), item.clone()),
+            item,
+            db,
         }
     }
+
+    /// Starts a transaction, so the changes of several services are written together
+    pub async fn begin(&self) -> anyhow::Result<DatabaseTransaction> {
+        Ok(self.db.begin().await?)
+    }
 }
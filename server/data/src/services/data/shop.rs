@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use proto95::{
+    game::mini_room::MiniRoomType,
+    id::{ItemId, MapId},
+    shared::{FootholdId, Vec2},
+};
+use sea_orm::{
+    sea_query::Expr, ActiveValue::NotSet, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+
+use crate::{
+    created_at,
+    entities::{entrusted_shop, entrusted_shop_item, equip_item, item_stack},
+    services::{
+        mini_room::{
+            shop::{PlayerShop, ShopItem, ShopItemKind},
+            MiniRoom,
+        },
+        model::item::{EquipItem, StackItem},
+    },
+};
+
+use super::{character::CharacterID, item::ItemService};
+
+/// Hired merchant as It's stored in the DB
+#[derive(Debug, Clone)]
+pub struct EntrustedShopData {
+    pub char_id: CharacterID,
+    pub map_id: MapId,
+    pub pos: Vec2,
+    pub fh: FootholdId,
+    pub permit: ItemId,
+    pub title: String,
+    pub open: bool,
+    pub shop: PlayerShop,
+}
+
+#[derive(Debug, Clone)]
+pub struct ShopService {
+    db: DatabaseConnection,
+    item: ItemService,
+}
+
+impl ShopService {
+    pub fn new(db: DatabaseConnection, item: ItemService) -> Self {
+        Self { db, item }
+    }
+
+    async fn save_items<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        shop_id: i32,
+        shop: &mut PlayerShop,
+    ) -> anyhow::Result<()> {
+        entrusted_shop_item::Entity::delete_many()
+            .filter(entrusted_shop_item::Column::ShopId.eq(shop_id))
+            .exec(db)
+            .await?;
+
+        let mut rows = Vec::new();
+        for shop_item in shop.items.iter_mut().filter(|item| !item.is_sold_out()) {
+            let (equip_item_id, stack_item_id) = match &mut shop_item.item {
+                ShopItemKind::Equip(item) => {
+                    if item.db_id.is_none() {
+                        item.db_id = Some(self.item.create_equip(db, item).await?);
+                    }
+                    (item.db_id, None)
+                }
+                ShopItemKind::Stack(item) => {
+                    if item.db_id.is_none() {
+                        item.db_id = Some(self.item.create_stack(db, item).await?);
+                    } else if item.last_update > 0 {
+                        self.item.update_stack(db, item).await?;
+                        item.last_update = 0;
+                    }
+                    (None, item.db_id)
+                }
+            };
+
+            rows.push(entrusted_shop_item::ActiveModel {
+                id: NotSet,
+                shop_id: Set(shop_id),
+                equip_item_id: Set(equip_item_id),
+                stack_item_id: Set(stack_item_id),
+                bundles: Set(shop_item.bundles as i32),
+                bundle_size: Set(shop_item.bundle_size as i32),
+                price: Set(shop_item.price as i32),
+            });
+        }
+
+        if !rows.is_empty() {
+            entrusted_shop_item::Entity::insert_many(rows)
+                .exec(db)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores the shop, the db id of the shop is set after the first save
+    pub async fn save_shop(&self, room: &mut MiniRoom) -> anyhow::Result<()> {
+        self.save_shop_with(&self.db, room).await
+    }
+
+    /// Stores the shop on the connection, so It can be part of a transaction.
+    /// Personal shops are stored as closed, so their items end up in the store bank
+    /// If the server stops while the shop is open. A closed shop is deleted once It's empty
+    pub async fn save_shop_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        room: &mut MiniRoom,
+    ) -> anyhow::Result<()> {
+        if !room.open && room.shop.is_empty() {
+            return self.save_store_bank_with(db, &mut room.shop).await;
+        }
+
+        let mut model = entrusted_shop::ActiveModel {
+            id: NotSet,
+            char_id: Set(room.owner().char_id),
+            map_id: Set(room.location.map_id.0 as i32),
+            pos_x: Set(room.location.pos.x as i32),
+            pos_y: Set(room.location.pos.y as i32),
+            fh: Set(room.location.fh as i32),
+            permit_id: Set(room.permit.0 as i32),
+            title: Set(room.title.clone()),
+            mesos: Set(room.shop.mesos as i32),
+            open: Set(room.open && room.ty == MiniRoomType::EntrustedShop),
+            created_at: NotSet,
+        };
+
+        let shop = &mut room.shop;
+        let shop_id = match shop.db_id {
+            Some(id) => {
+                model.id = Set(id);
+                entrusted_shop::Entity::update(model).exec(db).await?;
+                id
+            }
+            None => {
+                model.created_at = created_at(&self.db);
+                let id = entrusted_shop::Entity::insert(model)
+                    .exec(db)
+                    .await?
+                    .last_insert_id;
+                shop.db_id = Some(id);
+                id
+            }
+        };
+
+        self.save_items(db, shop_id, shop).await
+    }
+
+    /// Writes what's left of a stored shop, the shop is deleted once It's empty
+    pub async fn save_store_bank_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        shop: &mut PlayerShop,
+    ) -> anyhow::Result<()> {
+        let Some(shop_id) = shop.db_id else {
+            return Ok(());
+        };
+
+        if shop.is_empty() {
+            self.delete_shop_with(db, shop_id).await?;
+            shop.db_id = None;
+            return Ok(());
+        }
+
+        entrusted_shop::Entity::update_many()
+            .col_expr(entrusted_shop::Column::Mesos, Expr::value(shop.mesos as i32))
+            .filter(entrusted_shop::Column::Id.eq(shop_id))
+            .exec(db)
+            .await?;
+        self.save_items(db, shop_id, shop).await
+    }
+
+    async fn delete_shop_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        shop_id: i32,
+    ) -> anyhow::Result<()> {
+        entrusted_shop_item::Entity::delete_many()
+            .filter(entrusted_shop_item::Column::ShopId.eq(shop_id))
+            .exec(db)
+            .await?;
+        entrusted_shop::Entity::delete_by_id(shop_id).exec(db).await?;
+        Ok(())
+    }
+
+    async fn load_shops(
+        &self,
+        shops: Vec<entrusted_shop::Model>,
+    ) -> anyhow::Result<Vec<EntrustedShopData>> {
+        let shop_ids = shops.iter().map(|shop| shop.id).collect_vec();
+
+        let equip_items = entrusted_shop_item::Entity::find()
+            .filter(entrusted_shop_item::Column::ShopId.is_in(shop_ids.clone()))
+            .inner_join(equip_item::Entity)
+            .select_also(equip_item::Entity)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|(row, item)| {
+                let item: EquipItem = item.expect("Equip item").into();
+                (row, ShopItemKind::Equip(item))
+            });
+
+        let stack_items = entrusted_shop_item::Entity::find()
+            .filter(entrusted_shop_item::Column::ShopId.is_in(shop_ids))
+            .inner_join(item_stack::Entity)
+            .select_also(item_stack::Entity)
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|(row, item)| {
+                let item: StackItem = item.expect("Stack item").into();
+                (row, ShopItemKind::Stack(item))
+            });
+
+        let mut items: HashMap<i32, Vec<_>> = HashMap::new();
+        for (row, item) in equip_items
+            .chain(stack_items)
+            .sorted_by_key(|(row, _)| row.id)
+        {
+            items.entry(row.shop_id).or_default().push(ShopItem {
+                item,
+                bundles: row.bundles as u16,
+                bundle_size: row.bundle_size as u16,
+                price: row.price as u32,
+            });
+        }
+
+        Ok(shops
+            .into_iter()
+            .map(|shop| EntrustedShopData {
+                char_id: shop.char_id,
+                map_id: MapId(shop.map_id as u32),
+                pos: Vec2::from((shop.pos_x as i16, shop.pos_y as i16)),
+                fh: shop.fh as FootholdId,
+                permit: ItemId(shop.permit_id as u32),
+                open: shop.open,
+                shop: PlayerShop {
+                    db_id: Some(shop.id),
+                    items: items.remove(&shop.id).unwrap_or_default(),
+                    mesos: shop.mesos as u32,
+                    sold: Vec::new(),
+                },
+                title: shop.title,
+            })
+            .collect())
+    }
+
+    /// Loads all hired merchants which are still open
+    pub async fn load_open_shops(&self) -> anyhow::Result<Vec<EntrustedShopData>> {
+        let shops = entrusted_shop::Entity::find()
+            .filter(entrusted_shop::Column::Open.eq(true))
+            .all(&self.db)
+            .await?;
+
+        self.load_shops(shops).await
+    }
+
+    /// Finds all shops of the character, the open hired merchant as well as
+    /// every closed shop whose items are kept in the store bank, the oldest first
+    pub async fn find_shops(&self, char_id: CharacterID) -> anyhow::Result<Vec<EntrustedShopData>> {
+        let shops = entrusted_shop::Entity::find()
+            .filter(entrusted_shop::Column::CharId.eq(char_id))
+            .order_by_asc(entrusted_shop::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        self.load_shops(shops).await
+    }
+}
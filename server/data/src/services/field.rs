@@ -1,4 +1,12 @@
-use std::{ops::Deref, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::{Duration, Instant},
+};
 
 use dashmap::DashMap;
 use moople_net::service::{packet_buffer::PacketBuffer, session_svc::SharedSessionHandle};
@@ -6,21 +14,40 @@ use proto95::{
     game::{
         chat::UserChatMsgResp,
         drop::DropId,
-        mob::{MobLeaveType, MobMoveReq},
+        field::{
+            BlowWeatherResp, ClockResp, DestroyClockResp, FieldEffectMobHpTag, FieldEffectResp,
+            FieldEffectTremble, FieldObstacle, FieldObstacleAllResetResp, FieldObstacleOnOffResp,
+            FieldObstacleOnOffStatusResp,
+        },
+        mini_room::{
+            EmployeeBalloon, EmployeeMiniRoomBalloonResp, MiniRoomBalloon, MiniRoomSN,
+            MiniRoomType,
+        },
+        mob::{MobId, MobLeaveType, MobMoveReq},
+        npc::{NpcId, NpcMoveReq},
+        pet::{PetInitInfo, PetIx, PetLeaveReason, PetMoveReq, MAX_PETS},
         user::UserMoveReq,
         ObjectId,
     },
-    id::MapId,
+    id::{ItemId, MapId},
     shared::{char::AvatarData, FootholdId, Range2, Vec2},
 };
+use moople_packet::{EncodePacket, HasOpcode};
 use ractor::{Actor, ActorProcessingErr, ActorRef, RpcReplyPort};
 
 use super::{
     data::character::CharacterID,
-    helper::pool::{drop::DropLeaveParam, reactor::Reactor, user::User, Drop, Mob, Npc, Pool},
+    field_instance::{FieldInstance, FieldInstanceId, FieldInstanceScript},
+    helper::pool::{
+        drop::{DropLeaveParam, DropTypeValue},
+        employee::Employee,
+        reactor::{Reactor, ReactorEventKind, ReactorTrigger},
+        user::User,
+        Drop, Mob, Npc, Pool,
+    },
     meta::{
         fh_tree::FhTree,
-        meta_service::{FieldMeta, MetaService},
+        meta_service::{FieldMeta, MetaService, MobMeta},
     },
     session::MoopleSessionSet,
 };
@@ -28,14 +55,48 @@ use super::{
 #[derive(Debug)]
 pub struct FieldData {
     _meta: &'static MetaService,
+    field_id: MapId,
     field_meta: FieldMeta,
     field_fh: &'static FhTree,
     drop_pool: Pool<Drop>,
+    employee_pool: Pool<Employee>,
     mob_pool: Pool<Mob>,
     npc_pool: Pool<Npc>,
     reactor_pool: Pool<Reactor>,
     user_pool: Pool<User>,
     sessions: MoopleSessionSet,
+    instance: Option<Weak<FieldInstance>>,
+    /// End of the map-wide clock
+    clock: Mutex<Option<Instant>>,
+    /// Obstacles, which were switched from their default state
+    obstacles: Mutex<BTreeMap<String, u32>>,
+}
+
+/// State of a field for the inspection by admins
+#[derive(Debug, Clone)]
+pub struct FieldSnapshot {
+    pub field_id: MapId,
+    pub instance_id: Option<FieldInstanceId>,
+    pub users: Vec<CharacterID>,
+    pub mobs: Vec<MobSnapshot>,
+    pub drops: Vec<DropSnapshot>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MobSnapshot {
+    pub id: ObjectId,
+    pub tmpl_id: MobId,
+    pub hp: u32,
+    pub max_hp: u32,
+    pub pos: Vec2,
+}
+
+#[derive(Debug, Clone)]
+pub struct DropSnapshot {
+    pub id: ObjectId,
+    pub value: DropTypeValue,
+    pub quantity: usize,
+    pub pos: Vec2,
 }
 
 pub struct FieldJoinHandle {
@@ -51,6 +112,26 @@ impl Deref for FieldJoinHandle {
     }
 }
 
+impl FieldJoinHandle {
+    /// Triggers the reactor, the drops of a destroyed reactor are spawned after its delay
+    pub fn trigger_reactor(
+        &self,
+        id: ObjectId,
+        kind: ReactorEventKind,
+        animation_delay: Duration,
+    ) -> anyhow::Result<Option<ReactorTrigger>> {
+        let Some(trigger) = self
+            .reactor_pool
+            .trigger(id, kind, animation_delay, &self.sessions)?
+        else {
+            return Ok(None);
+        };
+
+        self.field_data.on_reactor_trigger(&trigger, Some(self.char_id))?;
+        Ok(Some(trigger))
+    }
+}
+
 impl std::ops::Drop for FieldJoinHandle {
     fn drop(&mut self) {
         self.field_data.leave_field(self.char_id)
@@ -60,8 +141,10 @@ impl std::ops::Drop for FieldJoinHandle {
 impl FieldData {
     pub fn new(
         meta: &'static MetaService,
+        field_id: MapId,
         field_meta: FieldMeta,
         fh_meta: &'static FhTree,
+        instance: Option<Weak<FieldInstance>>,
     ) -> Self {
         let npcs = field_meta
             .life
@@ -77,6 +160,7 @@ impl FieldData {
                     high: npc.rx_1 as i16,
                 },
                 enabled: true,
+                controller: None,
             });
 
         let mobs = field_meta
@@ -97,25 +181,57 @@ impl FieldData {
                 }
             });
 
-        let reactors = field_meta.reactor.values().map(|r| Reactor {
-            pos: Vec2::from((r.x as i16, r.y as i16)),
-            tmpl_id: r.id.parse().unwrap(),
-            state: 0,
+        let reactors = field_meta.reactor.values().map(|r| {
+            let tmpl_id = r.id.parse().unwrap();
+            Reactor {
+                pos: Vec2::from((r.x as i16, r.y as i16)),
+                tmpl_id,
+                state: 0,
+                flipped: r.f != 0,
+                name: r.name.clone(),
+                meta: meta.get_reactor_data(tmpl_id),
+                respawn: (r.reactor_time >= 0)
+                    .then(|| Duration::from_secs(r.reactor_time as u64)),
+                state_changed_at: Instant::now(),
+            }
         });
 
         Self {
             _meta: meta,
+            field_id,
             field_meta,
             field_fh: fh_meta,
             drop_pool: Pool::new(meta),
+            employee_pool: Pool::new(meta),
             sessions: MoopleSessionSet::new(),
             mob_pool: Pool::from_elems(meta, mobs),
             npc_pool: Pool::from_elems(meta, npcs),
             reactor_pool: Pool::from_elems(meta, reactors),
             user_pool: Pool::new(meta),
+            instance,
+            clock: Mutex::default(),
+            obstacles: Mutex::default(),
         }
     }
 
+    /// Instance this field belongs to, None for the shared fields
+    pub fn instance(&self) -> Option<Arc<FieldInstance>> {
+        self.instance.as_ref().and_then(Weak::upgrade)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    pub fn has_user(&self, id: CharacterID) -> bool {
+        self.user_pool.get(id as u32, |_| ()).is_some()
+    }
+
+    /// Characters, which are on the field
+    pub fn users(&self) -> Vec<CharacterID> {
+        self.sessions.keys()
+    }
+
     pub async fn enter_field(
         &self,
         char_id: CharacterID,
@@ -129,17 +245,33 @@ impl FieldData {
                 pos: Vec2::from((0, 0)),
                 fh: 1,
                 avatar_data,
+                mini_room: None,
+                pets: Default::default(),
+                portable_chair: None,
+                hidden: false,
             },
             &self.sessions,
         )?;
         let mut buf = PacketBuffer::new();
         self.user_pool.on_enter(&mut buf)?;
+        self.user_pool.on_enter_balloons(&mut buf)?;
         self.drop_pool.on_enter(&mut buf)?;
+        self.employee_pool.on_enter(&mut buf)?;
         self.npc_pool.on_enter(&mut buf)?;
         self.mob_pool.on_enter(&mut buf)?;
         self.reactor_pool.on_enter(&mut buf)?;
+        let remaining = self
+            .clock_remaining()
+            .or_else(|| self.instance().and_then(|i| i.remaining_time()));
+        if let Some(remaining) = remaining {
+            buf.write_packet(ClockResp::Timer(remaining.as_secs() as u32))?;
+        }
+        if let Some(status) = self.obstacle_status() {
+            buf.write_packet(status)?;
+        }
 
         session.try_send_buf(&buf)?;
+        self.npc_pool.assign_controller(char_id, &self.sessions)?;
 
         Ok(())
     }
@@ -149,6 +281,14 @@ impl FieldData {
         self.user_pool
             .remove(id as u32, (), &self.sessions)
             .expect("Must remove user");
+
+        // The npcs are passed on to the next character on the field
+        self.npc_pool.remove_controller(id);
+        if let Some(next) = self.sessions.keys().first() {
+            if let Err(err) = self.npc_pool.assign_controller(*next, &self.sessions) {
+                log::error!("Unable to assign npc controller: {err:?}");
+            }
+        }
     }
 
     pub fn add_user(&self, user: User) -> anyhow::Result<()> {
@@ -161,6 +301,98 @@ impl FieldData {
         Ok(())
     }
 
+    pub fn set_user_mini_room(
+        &self,
+        id: CharacterID,
+        mini_room: Option<(MiniRoomType, MiniRoomBalloon)>,
+    ) -> anyhow::Result<()> {
+        self.user_pool.set_mini_room(id, mini_room, &self.sessions)
+    }
+
+    /// Summons the pet or removes it with the given reason
+    pub fn set_user_pet(
+        &self,
+        id: CharacterID,
+        pet_ix: PetIx,
+        pet: Result<PetInitInfo, PetLeaveReason>,
+        initial: bool,
+    ) -> anyhow::Result<()> {
+        self.user_pool
+            .set_pet(id, pet_ix, pet, initial, &self.sessions)
+    }
+
+    pub fn update_pet_pos(
+        &self,
+        movement: PetMoveReq,
+        id: CharacterID,
+        pet_ix: PetIx,
+    ) -> anyhow::Result<()> {
+        self.user_pool.pet_move(id, pet_ix, movement, &self.sessions)
+    }
+
+    /// Shows the portable chair the user sits on, None If the user stands up
+    pub fn set_user_portable_chair(
+        &self,
+        id: CharacterID,
+        chair: Option<ItemId>,
+    ) -> anyhow::Result<()> {
+        self.user_pool.set_portable_chair(id, chair, &self.sessions)
+    }
+
+    pub fn set_user_hidden(&self, id: CharacterID, hidden: bool) -> anyhow::Result<()> {
+        self.user_pool.set_hidden(id, hidden, &self.sessions)
+    }
+
+    pub fn update_user_hp(&self, id: CharacterID, hp: u32, max_hp: u32) -> anyhow::Result<()> {
+        self.user_pool.user_hp(id, hp, max_hp, &self.sessions)
+    }
+
+    /// Summoned pets of the user, None If the user is not on the field
+    pub fn get_user_pets(&self, id: CharacterID) -> Option<[Option<PetInitInfo>; MAX_PETS]> {
+        self.user_pool.get(id as u32, |user| user.pets.clone())
+    }
+
+    pub async fn send_to<T: EncodePacket + HasOpcode>(
+        &self,
+        pkt: T,
+        id: CharacterID,
+    ) -> anyhow::Result<()> {
+        self.sessions.send_pkt_to(id, pkt).await
+    }
+
+    pub fn broadcast<T: EncodePacket + HasOpcode>(
+        &self,
+        pkt: T,
+        src: CharacterID,
+    ) -> anyhow::Result<()> {
+        self.sessions.broadcast_pkt(pkt, src)
+    }
+
+    pub fn add_employee(&self, employee: Employee) -> anyhow::Result<()> {
+        self.employee_pool.add(employee, &self.sessions)?;
+        Ok(())
+    }
+
+    pub fn remove_employee(&self, sn: MiniRoomSN) -> anyhow::Result<()> {
+        self.employee_pool.remove(sn, (), &self.sessions)?;
+        Ok(())
+    }
+
+    pub fn update_employee_balloon(&self, balloon: EmployeeBalloon) -> anyhow::Result<()> {
+        let id = balloon.sn;
+        self.employee_pool
+            .update(id, |employee| employee.balloon = balloon.clone());
+        self.sessions.broadcast_pkt(
+            EmployeeMiniRoomBalloonResp {
+                id,
+                ty: MiniRoomType::EntrustedShop,
+                balloon: Some(balloon).into(),
+            },
+            -1,
+        )?;
+        Ok(())
+    }
+
     pub fn add_npc(&self, npc: Npc) -> anyhow::Result<()> {
         self.npc_pool.add(npc, &self.sessions)?;
         Ok(())
@@ -171,11 +403,60 @@ impl FieldData {
         Ok(())
     }
 
+    pub fn get_npc_tmpl(&self, id: ObjectId) -> Option<NpcId> {
+        self.npc_pool.get(id, |npc| npc.tmpl_id)
+    }
+
+    pub fn update_npc_pos(
+        &self,
+        movement: NpcMoveReq,
+        controller: CharacterID,
+    ) -> anyhow::Result<()> {
+        self.npc_pool.npc_move(movement, controller, &self.sessions)
+    }
+
+    /// Hides or shows all npcs with the template id
+    pub fn set_npc_enabled(&self, tmpl_id: NpcId, enabled: bool) -> anyhow::Result<()> {
+        for id in self.npc_pool.find_by_tmpl(tmpl_id) {
+            self.npc_pool.set_enabled(id, enabled, &self.sessions)?;
+        }
+        Ok(())
+    }
+
+    /// Plays the special action on all npcs with the template id
+    pub fn set_npc_special_action(&self, tmpl_id: NpcId, action: &str) -> anyhow::Result<()> {
+        for id in self.npc_pool.find_by_tmpl(tmpl_id) {
+            self.npc_pool
+                .set_special_action(id, action.to_string(), None, &self.sessions)?;
+        }
+        Ok(())
+    }
+
+    /// Action requested by a client, only the controller of the npc can set it
+    pub fn update_npc_special_action(
+        &self,
+        id: ObjectId,
+        action: String,
+        controller: CharacterID,
+    ) -> anyhow::Result<()> {
+        self.npc_pool
+            .set_special_action(id, action, Some(controller), &self.sessions)
+    }
+
     pub async fn add_mob(&self, drop: Mob) -> anyhow::Result<()> {
         self.mob_pool.add(drop, &self.sessions)?;
         Ok(())
     }
 
+    pub fn has_mobs(&self) -> bool {
+        !self.mob_pool.is_empty()
+    }
+
+    /// Template and meta data of the mob, None If the mob is not on the field
+    pub fn get_mob_meta(&self, id: ObjectId) -> Option<(MobId, MobMeta)> {
+        self.mob_pool.get(id, |mob| (mob.tmpl_id, mob.meta))
+    }
+
     pub fn remove_mob(&self, id: u32, param: MobLeaveType) -> anyhow::Result<()> {
         self.mob_pool.remove(id, param, &self.sessions)?;
         Ok(())
@@ -229,14 +510,29 @@ impl FieldData {
         Ok(())
     }
 
+    /// Picks up the drop If the character is allowed to and `can_take` accepts the drop
+    pub fn pick_up_drop(
+        &self,
+        id: DropId,
+        char_id: CharacterID,
+        param: DropLeaveParam,
+        can_take: impl FnOnce(&Drop) -> bool,
+    ) -> anyhow::Result<Option<Drop>> {
+        self.drop_pool.remove_if(
+            id,
+            param,
+            |drop| drop.can_pick_up(char_id) && can_take(drop),
+            &self.sessions,
+        )
+    }
+
     pub fn assign_mob_controller(&self, session: SharedSessionHandle) -> anyhow::Result<()> {
         self.mob_pool.assign_controller(session)?;
         Ok(())
     }
 
     pub fn add_chat(&self, chat: UserChatMsgResp) -> anyhow::Result<()> {
-        self.sessions.broadcast_pkt(chat, -1)?;
-        Ok(())
+        self.user_pool.user_chat(chat, &self.sessions)
     }
 
     pub async fn attack_mob(
@@ -263,14 +559,228 @@ impl FieldData {
 
             self.drop_pool
                 .add_mob_drops(mob.tmpl_id, mob.pos, fh, attacker, &self.sessions)?;
+
+            if self.mob_pool.is_empty() {
+                if let Some(instance) = self.instance() {
+                    instance.on_all_mobs_killed(self)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> FieldSnapshot {
+        FieldSnapshot {
+            field_id: self.field_id,
+            instance_id: self.instance().map(|instance| instance.id),
+            users: self.users(),
+            mobs: self.mob_pool.map(|(id, mob)| MobSnapshot {
+                id: *id,
+                tmpl_id: mob.tmpl_id,
+                hp: mob.hp,
+                max_hp: mob.meta.max_hp,
+                pos: mob.pos,
+            }),
+            drops: self.drop_pool.map(|(id, drop)| DropSnapshot {
+                id: *id,
+                value: drop.value,
+                quantity: drop.quantity,
+                pos: drop.pos,
+            }),
+        }
+    }
+
+    /// Removes all mobs without drops, returns the number of killed mobs
+    pub fn kill_all_mobs(&self) -> anyhow::Result<usize> {
+        let ids = self.mob_pool.ids();
+        for id in ids.iter() {
+            self.mob_pool
+                .remove(*id, MobLeaveType::Etc(()), &self.sessions)?;
+        }
+
+        if !ids.is_empty() {
+            if let Some(instance) = self.instance() {
+                instance.on_all_mobs_killed(self)?;
+            }
+        }
+        Ok(ids.len())
+    }
+
+    fn update_reactors(self: &Arc<Self>) -> anyhow::Result<()> {
+        for trigger in self.reactor_pool.update_reactors(&self.sessions)? {
+            self.on_reactor_trigger(&trigger, None)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the instance hook of a reactor, which changed its state. A destroyed reactor
+    /// runs its script and spawns its drops after its delay, the drops belong to the owner
+    fn on_reactor_trigger(
+        self: &Arc<Self>,
+        trigger: &ReactorTrigger,
+        owner: Option<CharacterID>,
+    ) -> anyhow::Result<()> {
+        // Named reactors can drive the stages of an instance
+        if let Some(instance) = self.instance() {
+            if !trigger.name.is_empty() {
+                instance.on_reactor_state(self, &trigger.name, trigger.state)?;
+            }
         }
 
+        if !trigger.destroyed {
+            return Ok(());
+        }
+
+        if let Some(script) = trigger.script {
+            //TODO run the script once scripting is supported
+            log::info!(
+                "Unhandled script {script} of reactor {:?} at {:?}",
+                trigger.tmpl_id,
+                trigger.pos
+            );
+        }
+
+        let field = self.clone();
+        let (tmpl_id, pos, delay) = (trigger.tmpl_id, trigger.pos, trigger.delay);
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let fh = field
+                .field_fh
+                .get_foothold_below((pos.x as f32, pos.y as f32 - 20.).into());
+            if let Err(err) =
+                field
+                    .drop_pool
+                    .add_reactor_drops(tmpl_id, pos, fh, owner, &field.sessions)
+            {
+                log::error!("Unable to spawn reactor drops: {err:?}");
+            }
+        });
         Ok(())
     }
 
+    pub fn field_id(&self) -> MapId {
+        self.field_id
+    }
+
     pub fn get_meta(&self) -> FieldMeta {
         self.field_meta
     }
+
+    pub fn field_effect(&self, effect: FieldEffectResp) -> anyhow::Result<()> {
+        self.broadcast(effect, -1)
+    }
+
+    /// Shows the screen effect, the path is relative to `Map/Effect.img`
+    pub fn show_screen_effect(&self, path: &str) -> anyhow::Result<()> {
+        self.field_effect(FieldEffectResp::Screen(path.to_string()))
+    }
+
+    /// Plays the sound, the path is relative to `Sound/Field.img`
+    pub fn play_sound(&self, path: &str) -> anyhow::Result<()> {
+        self.field_effect(FieldEffectResp::Sound(path.to_string()))
+    }
+
+    pub fn change_bgm(&self, path: &str) -> anyhow::Result<()> {
+        self.field_effect(FieldEffectResp::ChangeBgm(path.to_string()))
+    }
+
+    /// Shakes the screen after the delay in ms
+    pub fn tremble(&self, heavy: bool, delay: u32) -> anyhow::Result<()> {
+        self.field_effect(FieldEffectResp::Tremble(FieldEffectTremble { heavy, delay }))
+    }
+
+    /// Shows the boss HP bar of the mob, returns false If the mob is not on the field
+    pub fn show_mob_hp(&self, id: ObjectId, color: u8, bg_color: u8) -> anyhow::Result<bool> {
+        let Some(tag) = self.mob_pool.get(id, |mob| FieldEffectMobHpTag {
+            mob_id: mob.tmpl_id,
+            hp: mob.hp,
+            max_hp: mob.meta.max_hp,
+            color,
+            bg_color,
+        }) else {
+            return Ok(false);
+        };
+        self.field_effect(FieldEffectResp::MobHpTag(tag))?;
+        Ok(true)
+    }
+
+    /// Shows the weather of the weather item with the message, `None` removes the weather
+    pub fn blow_weather(&self, weather: Option<(ItemId, &str)>) -> anyhow::Result<()> {
+        let (item_id, msg) = weather.unzip();
+        self.broadcast(
+            BlowWeatherResp {
+                ty: 0,
+                item_id: item_id.unwrap_or(ItemId(0)),
+                msg: msg.map(str::to_string).into(),
+            },
+            -1,
+        )
+    }
+
+    fn clock_remaining(&self) -> Option<Duration> {
+        self.clock
+            .lock()
+            .expect("Field clock")
+            .map(|end| end.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Starts the map-wide clock, which is also shown to characters entering later
+    pub fn set_clock(&self, duration: Duration) -> anyhow::Result<()> {
+        *self.clock.lock().expect("Field clock") = Some(Instant::now() + duration);
+        self.broadcast(ClockResp::Timer(duration.as_secs() as u32), -1)
+    }
+
+    pub fn destroy_clock(&self) -> anyhow::Result<()> {
+        *self.clock.lock().expect("Field clock") = None;
+        self.broadcast(DestroyClockResp, -1)
+    }
+
+    pub fn get_obstacle(&self, name: &str) -> Option<u32> {
+        self.obstacles.lock().expect("Obstacles").get(name).copied()
+    }
+
+    /// Switches the state of the named obstacle of the map
+    pub fn set_obstacle(&self, name: &str, state: u32) -> anyhow::Result<()> {
+        self.obstacles
+            .lock()
+            .expect("Obstacles")
+            .insert(name.to_string(), state);
+        self.broadcast(
+            FieldObstacleOnOffResp {
+                obstacle: FieldObstacle {
+                    name: name.to_string(),
+                    state,
+                },
+            },
+            -1,
+        )
+    }
+
+    /// Resets all obstacles to their default state
+    pub fn reset_obstacles(&self) -> anyhow::Result<()> {
+        self.obstacles.lock().expect("Obstacles").clear();
+        self.broadcast(FieldObstacleAllResetResp, -1)
+    }
+
+    /// Status of the switched obstacles, None If all are in their default state
+    pub fn obstacle_status(&self) -> Option<FieldObstacleOnOffStatusResp> {
+        let obstacles = self.obstacles.lock().expect("Obstacles");
+        if obstacles.is_empty() {
+            return None;
+        }
+        let obstacles = obstacles
+            .iter()
+            .map(|(name, state)| FieldObstacle {
+                name: name.clone(),
+                state: *state,
+            })
+            .collect::<Vec<_>>();
+        Some(FieldObstacleOnOffStatusResp {
+            obstacles: obstacles.into(),
+        })
+    }
 }
 
 pub enum FieldMessage {
@@ -311,9 +821,16 @@ impl Actor for FieldActor {
     }
 }
 
+/// Interval in which the time limit and the teardown of the instances are checked
+const INSTANCE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+/// Interval in which the timeouts and the respawns of the reactors are checked
+const REACTOR_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 pub struct FieldService {
     fields: DashMap<MapId, Arc<FieldData>>,
+    instances: Arc<DashMap<FieldInstanceId, Arc<FieldInstance>>>,
+    next_instance_id: AtomicU32,
     meta: &'static MetaService,
 }
 
@@ -321,11 +838,17 @@ impl FieldService {
     pub fn new(meta: &'static MetaService) -> Self {
         Self {
             fields: DashMap::new(),
+            instances: Arc::default(),
+            next_instance_id: AtomicU32::new(1),
             meta,
         }
     }
 
-    fn create_field(&self, field_id: MapId) -> anyhow::Result<Arc<FieldData>> {
+    fn create_field(
+        &self,
+        field_id: MapId,
+        instance: Option<Weak<FieldInstance>>,
+    ) -> anyhow::Result<Arc<FieldData>> {
         let field_meta = self
             .meta
             .get_field_data(field_id)
@@ -333,17 +856,91 @@ impl FieldService {
 
         let field_fh = self.meta.get_field_fh_data(field_id).unwrap();
 
-        Ok(Arc::new(FieldData::new(self.meta, field_meta, field_fh)))
+        let field = Arc::new(FieldData::new(
+            self.meta, field_id, field_meta, field_fh, instance,
+        ));
+
+        // The reactors are updated as long as the field is alive, even without any characters
+        let update_field = Arc::downgrade(&field);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REACTOR_UPDATE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(field) = update_field.upgrade() else {
+                    break;
+                };
+                if let Err(err) = field.update_reactors() {
+                    log::error!("Unable to update reactors of {field_id:?}: {err:?}");
+                }
+            }
+        });
+
+        Ok(field)
     }
 
     pub fn get_field(&self, field_id: MapId) -> anyhow::Result<Arc<FieldData>> {
         Ok(self
             .fields
             .entry(field_id)
-            .or_try_insert_with(|| self.create_field(field_id))?
+            .or_try_insert_with(|| self.create_field(field_id, None))?
             .clone())
     }
 
+    /// Field of the map, None If nobody entered it yet
+    pub fn find_field(&self, field_id: MapId) -> Option<Arc<FieldData>> {
+        self.fields.get(&field_id).map(|field| field.clone())
+    }
+
+    /// Field the character is on, instances are searched as well
+    pub fn find_user_field(&self, char_id: CharacterID) -> Option<Arc<FieldData>> {
+        self.fields()
+            .into_iter()
+            .find(|field| field.has_user(char_id))
+    }
+
+    /// All fields, which were created so far, including the fields of the instances
+    pub fn fields(&self) -> Vec<Arc<FieldData>> {
+        self.fields
+            .iter()
+            .map(|field| field.clone())
+            .chain(self.instances.iter().flat_map(|instance| instance.fields()))
+            .collect()
+    }
+
+    /// Broadcasts the packet to the characters on all fields
+    pub fn broadcast_all<T: EncodePacket + HasOpcode>(
+        &self,
+        pkt: impl Fn() -> T,
+    ) -> anyhow::Result<()> {
+        for field in self.fields() {
+            field.broadcast(pkt(), -1)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the field, so it's created from the map data again once it's joined next,
+    /// returns the dropped field, whose characters have to re-join the map
+    pub fn reload_field(&self, field_id: MapId) -> Option<Arc<FieldData>> {
+        self.fields.remove(&field_id).map(|(_, field)| field)
+    }
+
+    /// Map a character, which was saved on the map, joins,
+    /// maps of instances can't be joined without the instance so the forced return is used
+    pub fn get_join_map(&self, map_id: MapId) -> MapId {
+        if let Some(instance) = self
+            .instances
+            .iter()
+            .find(|instance| instance.contains_map(map_id))
+        {
+            return instance.forced_return;
+        }
+
+        self.meta
+            .get_party_quest_by_map(map_id)
+            .map(|(_, pq)| MapId(pq.exit_map))
+            .unwrap_or(map_id)
+    }
+
     pub async fn join_field(
         &self,
         char_id: CharacterID,
@@ -359,4 +956,90 @@ impl FieldService {
             char_id,
         })
     }
+
+    /// Creates an instance of the maps, the instance is torn down once it is empty
+    pub fn create_instance(
+        &self,
+        maps: Vec<MapId>,
+        forced_return: MapId,
+        time_limit: Option<Duration>,
+        script: Box<dyn FieldInstanceScript>,
+    ) -> anyhow::Result<Arc<FieldInstance>> {
+        if let Some(map_id) = maps
+            .iter()
+            .find(|map_id| self.meta.get_field_data(**map_id).is_none())
+        {
+            anyhow::bail!("Invalid field id: {map_id:?}");
+        }
+
+        let id = self.next_instance_id.fetch_add(1, Ordering::SeqCst);
+        let instance = Arc::new(FieldInstance::new(
+            id,
+            maps,
+            forced_return,
+            time_limit,
+            script,
+        ));
+        self.instances.insert(id, instance.clone());
+
+        let instances = self.instances.clone();
+        let update_instance = instance.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(INSTANCE_UPDATE_INTERVAL);
+            loop {
+                interval.tick().await;
+                // The instance was destroyed
+                if !instances.contains_key(&id) {
+                    break;
+                }
+                if let Err(err) = update_instance.update() {
+                    log::error!("Unable to update instance {id}: {err:?}");
+                }
+                if update_instance.can_teardown() {
+                    instances.remove(&id);
+                    break;
+                }
+            }
+        });
+
+        Ok(instance)
+    }
+
+    pub fn get_instance(&self, id: FieldInstanceId) -> Option<Arc<FieldInstance>> {
+        self.instances.get(&id).map(|instance| instance.clone())
+    }
+
+    /// Closes the instance, the characters still in it are returned to the forced return map
+    pub fn destroy_instance(&self, id: FieldInstanceId) -> Option<Arc<FieldInstance>> {
+        let (_, instance) = self.instances.remove(&id)?;
+        instance.close();
+        Some(instance)
+    }
+
+    pub async fn join_field_instance(
+        &self,
+        char_id: CharacterID,
+        avatar_data: AvatarData,
+        session: SharedSessionHandle,
+        field_id: MapId,
+        instance_id: FieldInstanceId,
+    ) -> anyhow::Result<FieldJoinHandle> {
+        let instance = self
+            .get_instance(instance_id)
+            .filter(|instance| !instance.is_closed())
+            .ok_or_else(|| anyhow::format_err!("Invalid instance: {instance_id}"))?;
+        if !instance.can_enter(field_id) {
+            anyhow::bail!("Map {field_id:?} of instance {instance_id} can't be entered");
+        }
+        let field = instance.get_or_create_field(field_id, || {
+            self.create_field(field_id, Some(Arc::downgrade(&instance)))
+        })?;
+        field.enter_field(char_id, session, avatar_data).await?;
+        instance.on_enter(&field, char_id)?;
+
+        Ok(FieldJoinHandle {
+            field_data: field,
+            char_id,
+        })
+    }
 }
@@ -0,0 +1,248 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use proto95::id::MapId;
+
+use super::{data::character::CharacterID, field::FieldData};
+
+pub type FieldInstanceId = u32;
+
+/// Time an instance is kept alive until the first character enters it
+pub const INSTANCE_ENTER_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Hooks of the script running an instance, all hooks do nothing by default
+pub trait FieldInstanceScript: Debug + Send + Sync {
+    fn on_enter(
+        &self,
+        _instance: &FieldInstance,
+        _field: &FieldData,
+        _char_id: CharacterID,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once the last mob of a field of the instance was killed
+    fn on_all_mobs_killed(
+        &self,
+        _instance: &FieldInstance,
+        _field: &FieldData,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once a named reactor of a field of the instance changed its state
+    fn on_reactor_state(
+        &self,
+        _instance: &FieldInstance,
+        _field: &FieldData,
+        _reactor: &str,
+        _state: u8,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Whether the map of the instance can be entered right now
+    fn can_enter(&self, _instance: &FieldInstance, _map_id: MapId) -> bool {
+        true
+    }
+
+    /// Called once the time limit ran out, the characters are returned afterwards
+    fn on_timeout(&self, _instance: &FieldInstance) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct NoInstanceScript;
+
+impl FieldInstanceScript for NoInstanceScript {}
+
+/// Set of maps, which have their own fields for the characters of the instance
+#[derive(Debug)]
+pub struct FieldInstance {
+    pub id: FieldInstanceId,
+    /// Map the characters are returned to, once the instance is closed
+    pub forced_return: MapId,
+    maps: Vec<MapId>,
+    fields: DashMap<MapId, Arc<FieldData>>,
+    created_at: Instant,
+    time_limit: Option<Duration>,
+    script: Box<dyn FieldInstanceScript>,
+    entered: AtomicBool,
+    closed: AtomicBool,
+}
+
+impl FieldInstance {
+    pub fn new(
+        id: FieldInstanceId,
+        maps: Vec<MapId>,
+        forced_return: MapId,
+        time_limit: Option<Duration>,
+        script: Box<dyn FieldInstanceScript>,
+    ) -> Self {
+        Self {
+            id,
+            forced_return,
+            maps,
+            fields: DashMap::new(),
+            created_at: Instant::now(),
+            time_limit,
+            script,
+            entered: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn contains_map(&self, map_id: MapId) -> bool {
+        self.maps.contains(&map_id)
+    }
+
+    pub fn maps(&self) -> &[MapId] {
+        &self.maps
+    }
+
+    /// Fields of the instance, which were entered so far
+    pub fn fields(&self) -> Vec<Arc<FieldData>> {
+        self.fields.iter().map(|field| field.clone()).collect()
+    }
+
+    /// Field of the map within this instance, the field is created on first use
+    pub fn get_or_create_field(
+        &self,
+        map_id: MapId,
+        create: impl FnOnce() -> anyhow::Result<Arc<FieldData>>,
+    ) -> anyhow::Result<Arc<FieldData>> {
+        if !self.contains_map(map_id) {
+            anyhow::bail!("Map {map_id:?} is not part of instance {}", self.id);
+        }
+        Ok(self
+            .fields
+            .entry(map_id)
+            .or_try_insert_with(create)?
+            .clone())
+    }
+
+    /// Time left until the time limit runs out, None If there is no limit
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.time_limit
+            .map(|limit| limit.saturating_sub(self.created_at.elapsed()))
+    }
+
+    /// A closed instance can't be entered anymore and the characters have to leave it
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.iter().all(|field| field.is_empty())
+    }
+
+    /// An instance is torn down once it is empty, after it was entered or the grace period passed
+    pub fn can_teardown(&self) -> bool {
+        let entered = self.entered.load(Ordering::SeqCst);
+        self.is_empty() && (entered || self.created_at.elapsed() >= INSTANCE_ENTER_GRACE_PERIOD)
+    }
+
+    pub fn on_enter(&self, field: &FieldData, char_id: CharacterID) -> anyhow::Result<()> {
+        self.entered.store(true, Ordering::SeqCst);
+        self.script.on_enter(self, field, char_id)
+    }
+
+    pub fn on_all_mobs_killed(&self, field: &FieldData) -> anyhow::Result<()> {
+        self.script.on_all_mobs_killed(self, field)
+    }
+
+    pub fn on_reactor_state(
+        &self,
+        field: &FieldData,
+        reactor: &str,
+        state: u8,
+    ) -> anyhow::Result<()> {
+        self.script.on_reactor_state(self, field, reactor, state)
+    }
+
+    pub fn can_enter(&self, map_id: MapId) -> bool {
+        self.contains_map(map_id) && self.script.can_enter(self, map_id)
+    }
+
+    /// Closes the instance once the time limit ran out
+    pub fn update(&self) -> anyhow::Result<()> {
+        if self.is_closed() || self.remaining_time() != Some(Duration::ZERO) {
+            return Ok(());
+        }
+        self.close();
+        self.script.on_timeout(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::Ordering,
+        time::{Duration, Instant},
+    };
+
+    use proto95::id::MapId;
+
+    use super::{FieldInstance, NoInstanceScript, INSTANCE_ENTER_GRACE_PERIOD};
+
+    fn instance(time_limit: Option<Duration>) -> FieldInstance {
+        FieldInstance::new(
+            1,
+            vec![MapId(103000800)],
+            MapId(103000890),
+            time_limit,
+            Box::new(NoInstanceScript),
+        )
+    }
+
+    #[test]
+    fn time_limit() {
+        let unlimited = instance(None);
+        unlimited.update().unwrap();
+        assert_eq!(unlimited.remaining_time(), None);
+        assert!(!unlimited.is_closed());
+
+        let running = instance(Some(Duration::from_secs(60)));
+        running.update().unwrap();
+        assert!(running.remaining_time().unwrap() > Duration::from_secs(59));
+        assert!(!running.is_closed());
+
+        let timed_out = instance(Some(Duration::ZERO));
+        timed_out.update().unwrap();
+        assert!(timed_out.is_closed());
+    }
+
+    #[test]
+    fn teardown() {
+        let instance = instance(None);
+        assert!(instance.contains_map(MapId(103000800)));
+        assert!(!instance.contains_map(MapId(103000890)));
+        // Empty instances are kept for the grace period until they are entered
+        assert!(instance.is_empty());
+        assert!(!instance.can_teardown());
+
+        // An entered instance is torn down once it's empty
+        let entered = self::instance(None);
+        entered.entered.store(true, Ordering::SeqCst);
+        assert!(entered.can_teardown());
+
+        // An instance nobody entered is torn down after the grace period
+        let mut abandoned = self::instance(None);
+        if let Some(created_at) = Instant::now().checked_sub(INSTANCE_ENTER_GRACE_PERIOD) {
+            abandoned.created_at = created_at;
+            assert!(abandoned.can_teardown());
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use proto95::id::MapId;
+
+use super::data::character::CharacterID;
+
+/// GM level of an account, 0 is a normal player
+pub type GmLevel = i32;
+
+/// Can moderate players, but not change the game state
+pub const GM_LEVEL_HELPER: GmLevel = 1;
+pub const GM_LEVEL_GM: GmLevel = 2;
+/// Can ban accounts and change characters and fields arbitrarily
+pub const GM_LEVEL_ADMIN: GmLevel = 3;
+
+/// Actions, which were not taken within this time, are dropped as the target went offline
+const GM_ACTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Action a GM applies to another character, which is run by the session of that character
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GmAction {
+    Warp(MapId),
+    Heal,
+    Kick,
+}
+
+/// Queues the actions of GMs for the targeted characters, the actions are
+/// taken by the sessions of the characters on their next tick
+#[derive(Debug)]
+pub struct GmService {
+    actions: DashMap<CharacterID, Vec<(Instant, GmAction)>>,
+    timeout: Duration,
+}
+
+impl Default for GmService {
+    fn default() -> Self {
+        Self::with_timeout(GM_ACTION_TIMEOUT)
+    }
+}
+
+impl GmService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            actions: DashMap::new(),
+            timeout,
+        }
+    }
+
+    /// Queues the action, expired actions of characters, which went offline, are dropped
+    pub fn push_action(&self, char_id: CharacterID, action: GmAction) {
+        let now = Instant::now();
+        self.actions.retain(|_, actions| {
+            actions.retain(|(pushed_at, _)| now.duration_since(*pushed_at) < self.timeout);
+            !actions.is_empty()
+        });
+        self.actions.entry(char_id).or_default().push((now, action));
+    }
+
+    pub fn take_actions(&self, char_id: CharacterID) -> Vec<GmAction> {
+        self.actions
+            .remove(&char_id)
+            .map(|(_, actions)| actions)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(pushed_at, _)| pushed_at.elapsed() < self.timeout)
+            .map(|(_, action)| action)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use proto95::id::MapId;
+
+    use super::{GmAction, GmService};
+
+    #[test]
+    fn actions() {
+        let gm = GmService::new();
+        gm.push_action(1, GmAction::Warp(MapId(100000000)));
+        gm.push_action(1, GmAction::Kick);
+        gm.push_action(2, GmAction::Heal);
+
+        assert_eq!(
+            gm.take_actions(1),
+            vec![GmAction::Warp(MapId(100000000)), GmAction::Kick]
+        );
+        assert!(gm.take_actions(1).is_empty());
+        assert_eq!(gm.take_actions(2), vec![GmAction::Heal]);
+    }
+
+    #[test]
+    fn expired_actions() {
+        let gm = GmService::with_timeout(Duration::ZERO);
+        gm.push_action(1, GmAction::Kick);
+        // Pushing drops the expired actions of the other characters
+        gm.push_action(2, GmAction::Heal);
+        assert!(!gm.actions.contains_key(&1));
+        assert!(gm.take_actions(2).is_empty());
+    }
+}
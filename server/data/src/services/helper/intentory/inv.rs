@@ -1,5 +1,8 @@
 use num_enum::TryFromPrimitive;
-use proto95::{id::ItemId, shared::inventory::EquippedSlot};
+use proto95::{
+    id::ItemId,
+    shared::inventory::{EquippedSlot, InventoryType as ProtoInventoryType},
+};
 use crate::services::model::item::{EquipItem, StackItem};
 
 use super::{Inventory, InventoryError, InventoryItem};
@@ -244,7 +247,7 @@ impl<const CAP: usize> StackInventory<CAP> {
     }
 }
 
-#[derive(Debug, Clone, Copy, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
 #[repr(u8)]
 pub enum InventoryType {
     Equipped = 1,
@@ -267,6 +270,47 @@ impl InventoryType {
     pub fn is_stack(&self) -> bool {
         !self.is_equip()
     }
+
+    /// Inventory an item is stored in, based on the item id prefix
+    pub fn from_item_id(item_id: ItemId) -> Option<Self> {
+        Some(match item_id.0 / 1_000_000 {
+            1 => InventoryType::Equip,
+            2 => InventoryType::Use,
+            3 => InventoryType::Misc,
+            4 => InventoryType::Etc,
+            5 => InventoryType::Cash,
+            _ => return None,
+        })
+    }
+}
+
+impl TryFrom<ProtoInventoryType> for InventoryType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ProtoInventoryType) -> Result<Self, Self::Error> {
+        Ok(match value {
+            ProtoInventoryType::Equip => InventoryType::Equip,
+            ProtoInventoryType::Consume => InventoryType::Use,
+            ProtoInventoryType::Install => InventoryType::Misc,
+            ProtoInventoryType::Etc => InventoryType::Etc,
+            ProtoInventoryType::Cash => InventoryType::Cash,
+            ProtoInventoryType::Equipped => InventoryType::Equipped,
+            _ => anyhow::bail!("Unsupported inventory type: {value:?}"),
+        })
+    }
+}
+
+impl From<InventoryType> for ProtoInventoryType {
+    fn from(value: InventoryType) -> Self {
+        match value {
+            InventoryType::Equipped | InventoryType::MaskedEquipped => ProtoInventoryType::Equipped,
+            InventoryType::Equip => ProtoInventoryType::Equip,
+            InventoryType::Use => ProtoInventoryType::Consume,
+            InventoryType::Misc => ProtoInventoryType::Install,
+            InventoryType::Etc => ProtoInventoryType::Etc,
+            InventoryType::Cash => ProtoInventoryType::Cash,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -336,6 +380,41 @@ impl InventorySet {
         })
     }
 
+    /// Adds the equip to the first free slot, returns the slot
+    pub fn try_add_equip(&mut self, item: EquipItem) -> Result<usize, EquipItem> {
+        let Some(slot) = self.equip.get_inner().find_free_slot() else {
+            return Err(item);
+        };
+        self.equip.set(slot, item.into());
+        Ok(slot)
+    }
+
+    /// Adds the stack to the first free slot of the matching inventory, returns the slot
+    pub fn try_add_stack(&mut self, item: StackItem) -> Result<(InventoryType, usize), StackItem> {
+        let Some(ty) = InventoryType::from_item_id(item.item_id) else {
+            return Err(item);
+        };
+        let Ok(inv) = self.get_stack_inventory_mut(ty) else {
+            return Err(item);
+        };
+        let Some(slot) = inv.get_inner().find_free_slot() else {
+            return Err(item);
+        };
+        inv.set(slot, item.into());
+        Ok((ty, slot))
+    }
+
+    /// Checks whether the inventory of the item has at least one free slot
+    pub fn has_free_slot(&self, item_id: ItemId) -> bool {
+        match InventoryType::from_item_id(item_id) {
+            Some(InventoryType::Equip) => self.equip.len() < self.equip.slots(),
+            Some(ty) => self
+                .get_stack_inventory(ty)
+                .is_ok_and(|inv| inv.len() < inv.slots()),
+            None => false,
+        }
+    }
+
     pub fn slots(&self, ty: InventoryType) -> usize {
         if ty.is_stack() {
             self.get_stack_inventory(ty).unwrap().slots()
@@ -100,7 +100,7 @@ impl<const CAP: usize, Item: InventoryItem> SortedItemVec<CAP, Item> {
 
         let insert_ix = self.find_insert_index_by_id(&item.id());
         self.0.insert(insert_ix, item);
-        self.0.len() - 1
+        insert_ix
     }
 
     pub fn try_add(&mut self, item: Item) -> Result<usize, Item> {
@@ -110,7 +110,7 @@ impl<const CAP: usize, Item: InventoryItem> SortedItemVec<CAP, Item> {
 
         let insert_ix = self.find_insert_index_by_id(&item.id());
         self.0.insert(insert_ix, item);
-        Ok(self.0.len() - 1)
+        Ok(insert_ix)
     }
 
     pub fn remove(&mut self, ix: usize) -> Item {
@@ -323,6 +323,7 @@ where
         }
 
         let ix = self.items.add(item);
+        self.update_add(ix);
         self.slot_mapping[slot] = Some(ix as u8);
         Ok(())
     }
@@ -333,6 +334,7 @@ where
         }
 
         let ix = self.items.add(item);
+        self.update_add(ix);
         self.slot_mapping[slot] = Some(ix as u8);
     }
 
@@ -499,6 +501,21 @@ mod tests {
         assert!(inv.try_add(2).is_err());
     }
 
+    #[test]
+    fn inventory_slot_mapping() {
+        const SLOTS: usize = 4;
+        let mut inv = Inventory::<8, u32>::new(SLOTS);
+
+        // Insert ids out of order, mapping must still point to the right items
+        inv.set_slot(0, 5);
+        inv.set_slot(1, 3);
+        inv.try_add(1).unwrap();
+
+        assert_eq!(inv.get(0).unwrap(), Some(&5));
+        assert_eq!(inv.get(1).unwrap(), Some(&3));
+        assert_eq!(inv.get(2).unwrap(), Some(&1));
+    }
+
     #[test]
     fn test_insert() {
         const SLOTS: usize = 4;
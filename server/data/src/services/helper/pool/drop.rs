@@ -1,4 +1,7 @@
-use std::{ops::Add, time::Duration};
+use std::{
+    ops::Add,
+    time::{Duration, Instant},
+};
 
 use geo::coord;
 use moople_packet::proto::time::MapleExpiration;
@@ -9,6 +12,7 @@ use proto95::{
             DropType,
         },
         mob::MobId,
+        reactor::ReactorId,
         ObjectId,
     },
     id::ItemId,
@@ -16,11 +20,16 @@ use proto95::{
 };
 
 use crate::services::{
-    data::character::CharacterID, meta::fh_tree::Foothold, session::MoopleSessionSet,
+    data::character::CharacterID,
+    meta::{fh_tree::Foothold, meta_service::DropPool},
+    session::MoopleSessionSet,
 };
 
 use super::{next_id, Pool, PoolItem};
 
+/// Time the owner of a drop can pick it up exclusively
+const OWNER_PICKUP_TIME: Duration = Duration::from_secs(15);
+
 #[derive(Debug)]
 pub struct Drop {
     pub owner: DropOwner,
@@ -28,9 +37,24 @@ pub struct Drop {
     pub start_pos: Vec2,
     pub value: DropTypeValue,
     pub quantity: usize,
+    pub spawned_at: Instant,
 }
 
-#[derive(Debug)]
+impl Drop {
+    /// Checks whether the character is allowed to pick up the drop
+    pub fn can_pick_up(&self, char_id: CharacterID) -> bool {
+        match self.owner {
+            DropOwner::User(owner) => {
+                owner == char_id as u32 || self.spawned_at.elapsed() >= OWNER_PICKUP_TIME
+            }
+            //TODO check the party once there are parties
+            DropOwner::Party(_) | DropOwner::None => true,
+            DropOwner::Explosive => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DropTypeValue {
     Mesos(u32),
     Item(ItemId),
@@ -43,7 +67,8 @@ pub enum DropLeaveParam {
     UserPickup(u32),
     MobPickup(u32),
     Explode,
-    PetPickup(u32),
+    /// Character id and pet index
+    PetPickup(u32, u8),
     PassConvex,
     PetSkill,
 }
@@ -88,6 +113,10 @@ impl PoolItem for Drop {
     }
 
     fn get_leave_pkt(&self, id: Self::Id, param: Self::LeaveParam) -> Self::LeavePacket {
+        let pet_ix = match param {
+            DropLeaveParam::PetPickup(_, ix) => Some(ix as u32),
+            _ => None,
+        };
         let (leave_type, pickup_id) = match param {
             DropLeaveParam::Explode => (DropLeaveType::Explode, None),
             DropLeaveParam::PassConvex => (DropLeaveType::PassConvex, None),
@@ -96,13 +125,14 @@ impl PoolItem for Drop {
             DropLeaveParam::TimeOut => (DropLeaveType::TimeOut, None),
             DropLeaveParam::UserPickup(id) => (DropLeaveType::UserPickup, Some(id)),
             DropLeaveParam::MobPickup(id) => (DropLeaveType::MobPickup, Some(id)),
-            DropLeaveParam::PetPickup(id) => (DropLeaveType::PetPickup, Some(id)),
+            DropLeaveParam::PetPickup(id, _) => (DropLeaveType::PetPickup, Some(id)),
         };
 
         DropLeaveFieldResp {
             leave_type,
             id,
             pickup_id: pickup_id.into(),
+            pet_ix: pet_ix.into(),
         }
     }
 }
@@ -119,7 +149,33 @@ impl Pool<Drop> {
         let Some(drops) = self.meta.get_drops_for_mob(killed_mob)  else {
             return Ok(())
         };
+        self.add_drops(drops, pos, fh, DropOwner::User(killer as u32), sessions)
+    }
+
+    /// Spawns the drops of the reactor, drops of timed out reactors have no owner
+    pub fn add_reactor_drops(
+        &self,
+        reactor: ReactorId,
+        pos: Vec2,
+        fh: Option<&Foothold>,
+        owner: Option<CharacterID>,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        let Some(drops) = self.meta.get_drops_for_reactor(reactor) else {
+            return Ok(());
+        };
+        let owner = owner.map_or(DropOwner::None, |owner| DropOwner::User(owner as u32));
+        self.add_drops(drops, pos, fh, owner, sessions)
+    }
 
+    fn add_drops(
+        &self,
+        drops: &DropPool,
+        pos: Vec2,
+        fh: Option<&Foothold>,
+        owner: DropOwner,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
         let money = drops.get_money_drop(&mut rand::thread_rng());
         let items = drops.get_item_drops(&mut rand::thread_rng());
 
@@ -134,7 +190,7 @@ impl Pool<Drop> {
         if money > 0 {
             self.add(
                 Drop {
-                    owner: DropOwner::User(killer as u32),
+                    owner: owner.clone(),
                     pos: spread
                         .as_mut()
                         .and_then(|fh| fh.next().map(map_coord))
@@ -142,6 +198,7 @@ impl Pool<Drop> {
                     start_pos: pos,
                     value: DropTypeValue::Mesos(money),
                     quantity: 1,
+                    spawned_at: Instant::now(),
                 },
                 sessions,
             )?;
@@ -150,7 +207,7 @@ impl Pool<Drop> {
         for (item, quantity) in items {
             self.add(
                 Drop {
-                    owner: DropOwner::User(killer as u32),
+                    owner: owner.clone(),
                     pos: spread
                         .as_mut()
                         .and_then(|fh| fh.next().map(map_coord))
@@ -158,6 +215,7 @@ impl Pool<Drop> {
                     start_pos: pos,
                     value: DropTypeValue::Item(item),
                     quantity,
+                    spawned_at: Instant::now(),
                 },
                 sessions,
             )?;
@@ -0,0 +1,55 @@
+use proto95::{
+    game::{
+        mini_room::{
+            EmployeeBalloon, EmployeeEnterFieldResp, EmployeeLeaveFieldResp, MiniRoomSN,
+            MiniRoomType,
+        },
+        ObjectId,
+    },
+    id::ItemId,
+    shared::{FootholdId, Vec2},
+};
+
+use super::PoolItem;
+
+/// Hired merchant standing in a field
+#[derive(Debug)]
+pub struct Employee {
+    /// The mini room sn doubles as the object id
+    pub sn: MiniRoomSN,
+    pub tmpl_id: ItemId,
+    pub pos: Vec2,
+    pub fh: FootholdId,
+    pub owner_name: String,
+    pub balloon: EmployeeBalloon,
+}
+
+impl PoolItem for Employee {
+    type Id = ObjectId;
+
+    type EnterPacket = EmployeeEnterFieldResp;
+
+    type LeavePacket = EmployeeLeaveFieldResp;
+
+    type LeaveParam = ();
+
+    fn get_id(&self) -> Self::Id {
+        self.sn
+    }
+
+    fn get_enter_pkt(&self, id: Self::Id) -> Self::EnterPacket {
+        EmployeeEnterFieldResp {
+            id,
+            tmpl_id: self.tmpl_id,
+            pos: self.pos,
+            fh: self.fh,
+            owner_name: self.owner_name.clone(),
+            ty: MiniRoomType::EntrustedShop,
+            balloon: Some(self.balloon.clone()).into(),
+        }
+    }
+
+    fn get_leave_pkt(&self, id: Self::Id, _param: Self::LeaveParam) -> Self::LeavePacket {
+        EmployeeLeaveFieldResp { id }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod drop;
+pub mod employee;
 pub mod mob;
 pub mod npc;
 pub mod reactor;
@@ -35,6 +36,11 @@ pub trait PoolItem {
 
     fn get_enter_pkt(&self, id: Self::Id) -> Self::EnterPacket;
     fn get_leave_pkt(&self, id: Self::Id, param: Self::LeaveParam) -> Self::LeavePacket;
+
+    /// Invisible items are not shown to entering users
+    fn is_visible(&self) -> bool {
+        true
+    }
 }
 
 
@@ -74,6 +80,22 @@ where
         }
     }
 
+    pub fn get<R>(&self, id: ObjectId, get: impl FnOnce(&T) -> R) -> Option<R> {
+        self.items.read().expect("Pool get").get(&id).map(get)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.read().expect("Pool is empty").is_empty()
+    }
+
+    pub fn ids(&self) -> Vec<ObjectId> {
+        self.items.read().expect("Pool ids").keys().copied().collect()
+    }
+
+    pub fn map<R>(&self, f: impl FnMut((&ObjectId, &T)) -> R) -> Vec<R> {
+        self.items.read().expect("Pool map").iter().map(f).collect()
+    }
+
     pub fn add(&self, item: T, sessions: &MoopleSessionSet) -> anyhow::Result<u32> {
         let id = T::get_id(&item);
         let pkt = item.get_enter_pkt(id);
@@ -99,9 +121,32 @@ where
         Ok(item)
     }
 
+    /// Removes the item If the check passes, returns `None` otherwise
+    pub fn remove_if(
+        &self,
+        id: T::Id,
+        param: T::LeaveParam,
+        check: impl FnOnce(&T) -> bool,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<Option<T>> {
+        let item = {
+            let mut items = self.items.write().expect("Pool remove");
+            match items.get(&id) {
+                Some(item) if check(item) => items.remove(&id).unwrap(),
+                _ => return Ok(None),
+            }
+        };
+
+        let pkt = item.get_leave_pkt(id, param);
+        sessions.broadcast_pkt(pkt, -1)?;
+        Ok(Some(item))
+    }
+
     pub fn on_enter(&self, packet_buf: &mut PacketBuffer) -> anyhow::Result<()> {
-        for (id, pkt) in self.items.read().expect("Pool on enter").iter() {
-            packet_buf.write_packet(pkt.get_enter_pkt(*id))?;
+        for (id, item) in self.items.read().expect("Pool on enter").iter() {
+            if item.is_visible() {
+                packet_buf.write_packet(item.get_enter_pkt(*id))?;
+            }
         }
 
         Ok(())
@@ -1,12 +1,19 @@
+use moople_packet::{EncodePacket, HasOpcode, MaplePacketWriter};
 use proto95::{
     game::{
-        npc::{NpcEnterFieldResp, NpcId, NpcInitData, NpcLeaveFieldResp},
+        npc::{
+            NpcChangeControllerResp, NpcEnterFieldResp, NpcId, NpcInitData, NpcLeaveFieldResp,
+            NpcMoveReq, NpcMoveResp, NpcSetSpecialAction, NpcSetSpecialActionResp,
+            NpcUpdateLimitedInfo, NpcUpdateLimitedInfoResp,
+        },
         ObjectId,
     },
     shared::{FootholdId, Range2, Vec2},
 };
 
-use super::{PoolItem, next_id};
+use crate::services::{data::character::CharacterID, session::MoopleSessionSet};
+
+use super::{next_id, Pool, PoolItem};
 
 #[derive(Debug)]
 pub struct Npc {
@@ -16,6 +23,20 @@ pub struct Npc {
     pub move_action: u8,
     pub range_horz: Range2,
     pub enabled: bool,
+    /// Character, which controls the movement of the npc
+    pub controller: Option<CharacterID>,
+}
+
+impl Npc {
+    fn init_data(&self) -> NpcInitData {
+        NpcInitData {
+            pos: self.pos,
+            move_action: self.move_action,
+            fh: self.fh,
+            range_horz: self.range_horz,
+            enabled: self.enabled,
+        }
+    }
 }
 
 impl PoolItem for Npc {
@@ -35,13 +56,7 @@ impl PoolItem for Npc {
         NpcEnterFieldResp {
             id,
             template_id: self.tmpl_id,
-            init: NpcInitData {
-                pos: self.pos,
-                move_action: self.move_action,
-                fh: self.fh,
-                range_horz: self.range_horz,
-                enabled: self.enabled,
-            },
+            init: self.init_data(),
         }
     }
 
@@ -49,3 +64,129 @@ impl PoolItem for Npc {
         NpcLeaveFieldResp { id }
     }
 }
+
+impl Pool<Npc> {
+    /// Assigns all npcs without a controller to the given controller
+    pub fn assign_controller(
+        &self,
+        controller: CharacterID,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        let mut npcs = self.items.write().expect("Npc assign controller");
+        for (id, npc) in npcs.iter_mut().filter(|(_, npc)| npc.controller.is_none()) {
+            npc.controller = Some(controller);
+
+            let mut pw = MaplePacketWriter::default();
+            pw.write_opcode(NpcChangeControllerResp::OPCODE);
+            NpcChangeControllerResp {
+                local: true,
+                id: *id,
+                tmpl_id: npc.tmpl_id,
+                init_data: npc.init_data(),
+            }
+            .encode_packet(&mut pw)?;
+            sessions.send_packet_to(controller, pw.into_packet())?;
+        }
+        Ok(())
+    }
+
+    /// Releases all npcs controlled by the controller
+    pub fn remove_controller(&self, controller: CharacterID) {
+        let mut npcs = self.items.write().expect("Npc remove controller");
+        for npc in npcs.values_mut() {
+            if npc.controller == Some(controller) {
+                npc.controller = None;
+            }
+        }
+    }
+
+    pub fn find_by_tmpl(&self, tmpl_id: NpcId) -> Vec<ObjectId> {
+        self.items
+            .read()
+            .expect("Npc find")
+            .iter()
+            .filter(|(_, npc)| npc.tmpl_id == tmpl_id)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    pub fn npc_move(
+        &self,
+        req: NpcMoveReq,
+        controller: CharacterID,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        let id = req.id;
+        {
+            let mut npcs = self.items.write().expect("Npc move");
+            let Some(npc) = npcs.get_mut(&id) else {
+                return Ok(());
+            };
+            if npc.controller != Some(controller) {
+                return Ok(());
+            }
+
+            let last_pos_fh = req
+                .data
+                .move_path
+                .0
+                .as_ref()
+                .and_then(|path| path.get_last_pos_fh());
+            if let Some((pos, fh)) = last_pos_fh {
+                npc.pos = pos;
+                npc.fh = fh.unwrap_or(npc.fh);
+            }
+        }
+
+        // The controller only plays the action once it's sent back
+        sessions.broadcast_pkt(NpcMoveResp { id, data: req.data }, -1)?;
+        Ok(())
+    }
+
+    /// Plays the action, If a controller is given the npc must be controlled by it
+    pub fn set_special_action(
+        &self,
+        id: ObjectId,
+        action: String,
+        controller: Option<CharacterID>,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        let Some(npc_controller) = self.get(id, |npc| npc.controller) else {
+            return Ok(());
+        };
+        if controller.is_some() && npc_controller != controller {
+            return Ok(());
+        }
+
+        sessions.broadcast_pkt(
+            NpcSetSpecialActionResp {
+                id,
+                data: NpcSetSpecialAction { action },
+            },
+            -1,
+        )?;
+        Ok(())
+    }
+
+    /// Hides or shows the npc for all characters on the field
+    pub fn set_enabled(
+        &self,
+        id: ObjectId,
+        enabled: bool,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        if self.get(id, |_| ()).is_none() {
+            return Ok(());
+        }
+        self.update(id, |npc| npc.enabled = enabled);
+
+        sessions.broadcast_pkt(
+            NpcUpdateLimitedInfoResp {
+                id,
+                data: NpcUpdateLimitedInfo { enabled },
+            },
+            -1,
+        )?;
+        Ok(())
+    }
+}
@@ -1,18 +1,111 @@
+use std::time::{Duration, Instant};
+
+use game_data::wz2;
 use proto95::{
     game::{
-        reactor::{ReactorEnterFieldResp, ReactorId, ReactorLeaveFieldResp},
+        reactor::{
+            ReactorChangeStateResp, ReactorEnterFieldResp, ReactorId, ReactorLeaveFieldResp,
+        },
         ObjectId,
     },
     shared::Vec2,
 };
 
-use super::{PoolItem, next_id};
+use crate::services::{meta::meta_service::ReactorMeta, session::MoopleSessionSet};
+
+use super::{next_id, Pool, PoolItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactorEventKind {
+    Hit,
+    Touch,
+    TimeOut,
+}
+
+impl ReactorEventKind {
+    fn matches(&self, ty: u32) -> bool {
+        match self {
+            Self::Hit => ty <= 5,
+            Self::Touch => (6..=9).contains(&ty),
+            Self::TimeOut => ty == 101,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Reactor {
     pub pos: Vec2,
     pub tmpl_id: ReactorId,
     pub state: u8,
+    pub flipped: bool,
+    pub name: String,
+    pub meta: Option<ReactorMeta>,
+    /// Delay until a destroyed reactor respawns, None If it never respawns
+    pub respawn: Option<Duration>,
+    pub state_changed_at: Instant,
+}
+
+/// Result of an event, which changed the state of the reactor
+#[derive(Debug)]
+pub struct ReactorTrigger {
+    pub tmpl_id: ReactorId,
+    pub name: String,
+    pub state: u8,
+    pub pos: Vec2,
+    /// Set If the reactor reached its final state
+    pub destroyed: bool,
+    pub script: Option<&'static str>,
+    /// Delay until the drops and the script are triggered
+    pub delay: Duration,
+}
+
+impl Reactor {
+    fn state_events(&self) -> Option<&'static wz2::ReactorEvents> {
+        self.meta?
+            .states
+            .get(&self.state.to_string())?
+            .event
+            .as_ref()
+    }
+
+    fn find_event(&self, kind: ReactorEventKind) -> Option<(u8, &'static wz2::ReactorEvent)> {
+        self.state_events()?
+            .events
+            .iter()
+            .filter_map(|(ix, event)| Some((ix.parse().ok()?, event)))
+            .find(|(_, event)| kind.matches(event.ty))
+    }
+
+    /// The reactor is destroyed once it reaches a state without any events
+    pub fn is_destroyed(&self) -> bool {
+        self.meta.is_some()
+            && self.state != 0
+            && self
+                .state_events()
+                .map_or(true, |events| events.events.is_empty())
+    }
+
+    fn is_timed_out(&self) -> bool {
+        self.state_events().is_some_and(|events| {
+            events.time_out > 0
+                && self.state_changed_at.elapsed() >= Duration::from_millis(events.time_out as u64)
+        })
+    }
+
+    fn is_respawn_due(&self) -> bool {
+        self.is_destroyed()
+            && self
+                .respawn
+                .is_some_and(|respawn| self.state_changed_at.elapsed() >= respawn)
+    }
+
+    fn delay(&self) -> Duration {
+        let delay = self
+            .meta
+            .and_then(|meta| meta.info.as_ref())
+            .map_or(0, |info| info.delay);
+        Duration::from_millis(delay as u64)
+    }
 }
 
 impl PoolItem for Reactor {
@@ -34,8 +127,8 @@ impl PoolItem for Reactor {
             tmpl_id: self.tmpl_id,
             state: self.state,
             pos: self.pos,
-            flipped: false,
-            name: String::new(),
+            flipped: self.flipped,
+            name: self.name.clone(),
         }
     }
 
@@ -47,3 +140,90 @@ impl PoolItem for Reactor {
         }
     }
 }
+
+impl Pool<Reactor> {
+    /// Moves the reactor into the next state If the current state has an event of the kind
+    pub fn trigger(
+        &self,
+        id: ObjectId,
+        kind: ReactorEventKind,
+        animation_delay: Duration,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<Option<ReactorTrigger>> {
+        let (pkt, trigger) = {
+            let mut items = self.items.write().expect("Reactor trigger");
+            let Some(reactor) = items.get_mut(&id) else {
+                return Ok(None);
+            };
+            if reactor.is_destroyed() {
+                return Ok(None);
+            }
+            let Some((event_ix, event)) = reactor.find_event(kind) else {
+                return Ok(None);
+            };
+
+            reactor.state = event.state as u8;
+            reactor.state_changed_at = Instant::now();
+
+            let pkt = ReactorChangeStateResp {
+                id,
+                state: reactor.state,
+                pos: reactor.pos,
+                animation_delay: animation_delay.into(),
+                proper_event_id: event_ix,
+                end_state: 0,
+            };
+            let trigger = ReactorTrigger {
+                tmpl_id: reactor.tmpl_id,
+                name: reactor.name.clone(),
+                state: reactor.state,
+                pos: reactor.pos,
+                destroyed: reactor.is_destroyed(),
+                script: reactor.meta.and_then(|meta| meta.action.as_deref()),
+                delay: reactor.delay(),
+            };
+            (pkt, trigger)
+        };
+
+        sessions.broadcast_pkt(pkt, -1)?;
+        Ok(Some(trigger))
+    }
+
+    /// Triggers the timeout events and respawns destroyed reactors,
+    /// returns the reactors which changed their state by timing out
+    pub fn update_reactors(
+        &self,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<Vec<ReactorTrigger>> {
+        let (timed_out, respawn): (Vec<_>, Vec<_>) = {
+            let items = self.items.read().expect("Reactor update");
+            (
+                items
+                    .iter()
+                    .filter(|(_, reactor)| reactor.is_timed_out())
+                    .map(|(id, _)| *id)
+                    .collect(),
+                items
+                    .iter()
+                    .filter(|(_, reactor)| reactor.is_respawn_due())
+                    .map(|(id, _)| *id)
+                    .collect(),
+            )
+        };
+
+        let mut triggers = Vec::new();
+        for id in timed_out {
+            let trigger = self.trigger(id, ReactorEventKind::TimeOut, Duration::ZERO, sessions)?;
+            triggers.extend(trigger);
+        }
+
+        for id in respawn {
+            let mut reactor = self.remove(id, (), sessions)?;
+            reactor.state = 0;
+            reactor.state_changed_at = Instant::now();
+            self.add(reactor, sessions)?;
+        }
+
+        Ok(triggers)
+    }
+}
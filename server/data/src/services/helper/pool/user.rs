@@ -1,9 +1,17 @@
-use moople_packet::proto::list::MapleIndexListZ;
+use moople_net::service::packet_buffer::PacketBuffer;
+use moople_packet::{EncodePacket, HasOpcode, MaplePacketWriter};
+use either::Either;
 use proto95::{
+    game::chat::UserChatMsgResp,
+    game::mini_room::{MiniRoomBalloon, MiniRoomType, UserMiniRoomBalloonResp},
+    game::pet::{
+        PetActivatedInfo, PetActivatedResp, PetInitInfo, PetIx, PetLeaveReason, PetMoveReq,
+        PetMoveResp, MAX_PETS,
+    },
     game::user::{
         remote::{
             GuildMarkData, TamingMobData, UserEnterFieldResp, UserLeaveFieldResp, UserMoveResp,
-            UserRemoteInitData,
+            UserReceiveHPResp, UserRemoteInitData, UserSetActivePortablChairResp,
         },
         UserMoveReq,
     },
@@ -24,6 +32,11 @@ pub struct User {
     pub pos: Vec2,
     pub fh: u16,
     pub avatar_data: AvatarData,
+    pub mini_room: Option<(MiniRoomType, MiniRoomBalloon)>,
+    pub pets: [Option<PetInitInfo>; MAX_PETS],
+    pub portable_chair: Option<ItemId>,
+    /// Hidden users are only visible to themselves
+    pub hidden: bool,
 }
 
 impl PoolItem for User {
@@ -63,11 +76,17 @@ impl PoolItem for User {
                 choco_count: 0,
                 active_effect_item: ItemId(0),
                 completed_set_item_id: ItemId(0),
-                portable_chair: ItemId(0),
+                portable_chair: self.portable_chair.unwrap_or(ItemId(0)),
                 pos: self.pos,
                 fh: self.fh,
                 show_admin_effects: false,
-                pet_infos: MapleIndexListZ::default(),
+                pet_infos: self
+                    .pets
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(ix, pet)| pet.clone().map(|pet| (ix as u8 + 1, pet)))
+                    .collect::<Vec<_>>()
+                    .into(),
                 taming_mob: TamingMobData::default(),
                 mini_room: None.into(),
                 ad_board: None.into(),
@@ -90,15 +109,45 @@ impl PoolItem for User {
             char_id: self.char_id,
         }
     }
+
+    fn is_visible(&self) -> bool {
+        !self.hidden
+    }
 }
 
 impl Pool<User> {
+    fn is_hidden(&self, id: CharacterID) -> bool {
+        self.get(id as u32, |usr| usr.hidden).unwrap_or(false)
+    }
+
+    /// Broadcasts a packet of the user, packets of hidden users are only sent to themselves
+    fn broadcast_user_pkt<T: EncodePacket + HasOpcode>(
+        &self,
+        id: CharacterID,
+        pkt: T,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        if !self.is_hidden(id) {
+            return sessions.broadcast_pkt(pkt, -1);
+        }
+
+        let mut pw = MaplePacketWriter::default();
+        pw.write_opcode(T::OPCODE);
+        pkt.encode_packet(&mut pw)?;
+        sessions.send_packet_to(id, pw.into_packet())
+    }
+
+    /// The movement of hidden users is not shown to the other users
     pub fn user_move(
         &self,
         id: CharacterID,
         req: UserMoveReq,
         sessions: &MoopleSessionSet,
     ) -> anyhow::Result<()> {
+        if self.is_hidden(id) {
+            return Ok(());
+        }
+
         let pkt = UserMoveResp {
             char_id: id as u32,
             move_path: req.move_path,
@@ -107,4 +156,211 @@ impl Pool<User> {
         sessions.broadcast_pkt(pkt, id)?;
         Ok(())
     }
+
+    pub fn set_mini_room(
+        &self,
+        id: CharacterID,
+        mini_room: Option<(MiniRoomType, MiniRoomBalloon)>,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        let pkt = balloon_pkt(id as u32, mini_room.as_ref());
+        self.update(id as u32, |usr| usr.mini_room = mini_room.clone());
+        self.broadcast_user_pkt(id, pkt, sessions)
+    }
+
+    pub fn set_portable_chair(
+        &self,
+        id: CharacterID,
+        chair: Option<ItemId>,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        self.update(id as u32, |usr| usr.portable_chair = chair);
+        if self.is_hidden(id) {
+            return Ok(());
+        }
+
+        let pkt = UserSetActivePortablChairResp {
+            char_id: id as u32,
+            chair_id: chair.unwrap_or(ItemId(0)),
+        };
+        sessions.broadcast_pkt(pkt, id)?;
+        Ok(())
+    }
+
+    /// Chat of hidden users is only shown to themselves
+    pub fn user_chat(
+        &self,
+        chat: UserChatMsgResp,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        self.broadcast_user_pkt(chat.char as CharacterID, chat, sessions)
+    }
+
+    /// Hides the user from the other users on the field or shows it again,
+    /// together with its pets, chair and balloon
+    pub fn set_hidden(
+        &self,
+        id: CharacterID,
+        hidden: bool,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        self.update(id as u32, |usr| usr.hidden = hidden);
+        if hidden {
+            let pkt = self
+                .get(id as u32, |usr| usr.get_leave_pkt(usr.char_id, ()))
+                .ok_or_else(|| anyhow::format_err!("User {id} is not on the field"))?;
+            return sessions.broadcast_pkt(pkt, id);
+        }
+
+        let (enter, pets, chair, mini_room) = self
+            .get(id as u32, |usr| {
+                (
+                    usr.get_enter_pkt(usr.char_id),
+                    usr.pets.clone(),
+                    usr.portable_chair,
+                    usr.mini_room.clone(),
+                )
+            })
+            .ok_or_else(|| anyhow::format_err!("User {id} is not on the field"))?;
+        sessions.broadcast_pkt(enter, id)?;
+        for (ix, pet) in pets.into_iter().enumerate() {
+            if let Some(pet) = pet {
+                let data: Either<_, PetLeaveReason> = Either::Left(PetActivatedInfo {
+                    initial: false,
+                    pet,
+                });
+                let pkt = PetActivatedResp {
+                    char_id: id as u32,
+                    pet_ix: ix as PetIx,
+                    activated: true,
+                    data: data.into(),
+                };
+                sessions.broadcast_pkt(pkt, id)?;
+            }
+        }
+        if let Some(chair) = chair {
+            let pkt = UserSetActivePortablChairResp {
+                char_id: id as u32,
+                chair_id: chair,
+            };
+            sessions.broadcast_pkt(pkt, id)?;
+        }
+        if mini_room.is_some() {
+            sessions.broadcast_pkt(balloon_pkt(id as u32, mini_room.as_ref()), id)?;
+        }
+        Ok(())
+    }
+
+    /// Shows the hp of the user to the other users on the field
+    pub fn user_hp(
+        &self,
+        id: CharacterID,
+        hp: u32,
+        max_hp: u32,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        if self.is_hidden(id) {
+            return Ok(());
+        }
+
+        let pkt = UserReceiveHPResp {
+            char_id: id as u32,
+            hp,
+            max_hp,
+        };
+        sessions.broadcast_pkt(pkt, id)?;
+        Ok(())
+    }
+
+    pub fn set_pet(
+        &self,
+        id: CharacterID,
+        pet_ix: PetIx,
+        pet: Result<PetInitInfo, PetLeaveReason>,
+        initial: bool,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        let ix = pet_ix as usize;
+        if ix >= MAX_PETS {
+            anyhow::bail!("Invalid pet index: {pet_ix}");
+        }
+
+        self.update(id as u32, |usr| {
+            usr.pets[ix] = pet.as_ref().ok().cloned();
+            usr.avatar_data.pets[ix] = pet
+                .as_ref()
+                .map(|pet| pet.tmpl_id)
+                .unwrap_or(ItemId(0));
+        });
+        let data = match pet {
+            Ok(pet) => Either::Left(PetActivatedInfo { initial, pet }),
+            Err(reason) => Either::Right(reason),
+        };
+        let pkt = PetActivatedResp {
+            char_id: id as u32,
+            pet_ix,
+            activated: data.is_left(),
+            data: data.into(),
+        };
+        self.broadcast_user_pkt(id, pkt, sessions)
+    }
+
+    pub fn pet_move(
+        &self,
+        id: CharacterID,
+        pet_ix: PetIx,
+        req: PetMoveReq,
+        sessions: &MoopleSessionSet,
+    ) -> anyhow::Result<()> {
+        if let Some((pos, fh)) = req.move_path.get_last_pos_fh() {
+            self.update(id as u32, |usr| {
+                if let Some(pet) = usr.pets.get_mut(pet_ix as usize).and_then(Option::as_mut) {
+                    pet.pos = pos;
+                    pet.fh = fh.unwrap_or(pet.fh);
+                }
+            });
+        }
+        if self.is_hidden(id) {
+            return Ok(());
+        }
+
+        let pkt = PetMoveResp {
+            char_id: id as u32,
+            pet_ix,
+            move_path: req.move_path,
+        };
+        sessions.broadcast_pkt(pkt, id)?;
+        Ok(())
+    }
+
+    /// Balloons aren't part of the enter packet, so they are sent afterwards
+    pub fn on_enter_balloons(&self, packet_buf: &mut PacketBuffer) -> anyhow::Result<()> {
+        for usr in self.items.read().expect("Pool balloons").values() {
+            if usr.hidden {
+                continue;
+            }
+            if let Some(mini_room) = usr.mini_room.as_ref() {
+                packet_buf.write_packet(balloon_pkt(usr.char_id, Some(mini_room)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn balloon_pkt(
+    char_id: CharacterId,
+    mini_room: Option<&(MiniRoomType, MiniRoomBalloon)>,
+) -> UserMiniRoomBalloonResp {
+    match mini_room {
+        Some((ty, balloon)) => UserMiniRoomBalloonResp {
+            char_id,
+            ty: *ty,
+            balloon: Some(balloon.clone()).into(),
+        },
+        None => UserMiniRoomBalloonResp {
+            char_id,
+            ty: MiniRoomType::None,
+            balloon: None.into(),
+        },
+    }
 }
@@ -4,9 +4,9 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use game_data::{map, wz2};
+use game_data::{drops, map, pq, wz2};
 use proto95::{
-    game::mob::MobId,
+    game::{mob::MobId, npc::NpcId, reactor::ReactorId},
     id::{ItemId, MapId},
 };
 use rand::Rng;
@@ -29,6 +29,24 @@ pub struct DropPool {
     pub money_variance: u32,
 }
 
+impl From<drops::DropTable> for DropPool {
+    fn from(table: drops::DropTable) -> Self {
+        Self {
+            entries: table
+                .items
+                .into_iter()
+                .map(|item| DropEntry {
+                    item: ItemId(item.item),
+                    max_quantity: item.max_quantity.max(1),
+                    chance: item.chance.clamp(0.0, 1.0),
+                })
+                .collect(),
+            money: table.money,
+            money_variance: table.money_variance.min(table.money),
+        }
+    }
+}
+
 impl DropPool {
     pub fn get_item_drops<R: Rng>(&self, rng: &mut R) -> Vec<(ItemId, usize)> {
         let mut drops = Vec::new();
@@ -73,19 +91,26 @@ pub struct FieldMetaData {
     pub fh_tree: FhTree,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct MetaData {
     pub maps0: BTreeMap<i64, map::Map>,
     pub maps0_fh: BTreeMap<i64, FhTree>,
     pub mobs: BTreeMap<u32, wz2::Mob>,
     pub items: BTreeMap<u32, wz2::Item>,
     pub equips: BTreeMap<u32, wz2::Item>,
+    pub commodities: BTreeMap<u32, wz2::Commodity>,
+    pub reactors: BTreeMap<u32, wz2::Reactor>,
+    pub field_infos: BTreeMap<u32, wz2::FieldInfo>,
+    pub party_quests: BTreeMap<u32, pq::PartyQuest>,
+    pub reactor_drops: BTreeMap<u32, DropPool>,
 }
 
 pub type FieldMeta = &'static map::Map;
 pub type MobMeta = &'static wz2::Mob;
 pub type ItemMeta = &'static wz2::Item;
+pub type ReactorMeta = &'static wz2::Reactor;
 pub type DropsMeta = &'static DropPool;
+pub type PartyQuestMeta = &'static pq::PartyQuest;
 
 impl MetaData {
     fn load_from_file<T: serde::de::DeserializeOwned>(file: impl AsRef<Path>) -> anyhow::Result<T> {
@@ -104,8 +129,36 @@ impl MetaData {
             mobs: wz2::load_all(dir.join("wz/Mob"))?,
             items: wz2::load_all(dir.join("wz/Item"))?,
             equips: wz2::load_all(dir.join("wz/Equip"))?,
+            commodities: Self::load_commodities(dir.join("wz/Etc/Commodity"))?,
+            reactors: Self::load_optional(dir.join("wz/Reactor"))?,
+            field_infos: Self::load_optional(dir.join("wz/Map/Info"))?,
+            party_quests: Self::load_optional(dir.join("pq"))?,
+            reactor_drops: Self::load_optional::<drops::DropTable>(dir.join("drops/reactor"))?
+                .into_iter()
+                .map(|(id, table)| (id, table.into()))
+                .collect(),
         })
     }
+
+    /// Loads data which the server can run without, an empty map is returned If the dir is missing
+    fn load_optional<T: serde::de::DeserializeOwned>(
+        dir: PathBuf,
+    ) -> anyhow::Result<BTreeMap<u32, T>> {
+        if !dir.exists() {
+            log::warn!("No data found in {dir:?}");
+            return Ok(BTreeMap::new());
+        }
+
+        wz2::load_all(dir)
+    }
+
+    /// The commodities are keyed by their serial number, the cash shop is empty without them
+    fn load_commodities(dir: PathBuf) -> anyhow::Result<BTreeMap<u32, wz2::Commodity>> {
+        Ok(Self::load_optional::<wz2::Commodity>(dir)?
+            .into_values()
+            .map(|commodity| (commodity.sn, commodity))
+            .collect())
+    }
 }
 
 #[derive(Debug)]
@@ -152,6 +205,23 @@ impl MetaService {
         self.meta_data.maps0_fh.get(&(field_id.0 as i64))
     }
 
+    /// Rate of the natural recovery on the field, 1.0 If the field has no recovery bonus
+    pub fn get_field_recovery_rate(&self, field_id: MapId) -> f32 {
+        self.meta_data
+            .field_infos
+            .get(&field_id.0)
+            .and_then(|info| info.recovery)
+            .unwrap_or(1.0)
+    }
+
+    /// Damage of the obstacles on the field, None If the field has no obstacle data
+    pub fn get_field_obstacle_damage(&self, field_id: MapId) -> Option<u32> {
+        self.meta_data
+            .field_infos
+            .get(&field_id.0)
+            .and_then(|info| info.obstacle_damage)
+    }
+
     pub fn get_mob_data(&self, mob_id: MobId) -> Option<&wz2::Mob> {
         self.meta_data.mobs.get(&mob_id)
     }
@@ -164,7 +234,42 @@ impl MetaService {
         self.meta_data.equips.get(&id.0)
     }
 
+    pub fn get_commodity(&self, sn: u32) -> Option<&wz2::Commodity> {
+        self.meta_data.commodities.get(&sn)
+    }
+
+    pub fn get_reactor_data(&self, id: ReactorId) -> Option<&wz2::Reactor> {
+        self.meta_data.reactors.get(&id)
+    }
+
+    pub fn get_party_quest(&self, id: u32) -> Option<&pq::PartyQuest> {
+        self.meta_data.party_quests.get(&id)
+    }
+
+    /// Party quest, which is entered by talking to the npc
+    pub fn get_party_quest_by_npc(&self, npc: NpcId) -> Option<(u32, &pq::PartyQuest)> {
+        self.meta_data
+            .party_quests
+            .iter()
+            .find(|(_, pq)| pq.npc == npc)
+            .map(|(id, pq)| (*id, pq))
+    }
+
+    /// Party quest, whose instance contains the map
+    pub fn get_party_quest_by_map(&self, map_id: MapId) -> Option<(u32, &pq::PartyQuest)> {
+        self.meta_data
+            .party_quests
+            .iter()
+            .find(|(_, pq)| pq.maps().any(|map| map == map_id.0))
+            .map(|(id, pq)| (*id, pq))
+    }
+
     pub fn get_drops_for_mob(&self, _id: MobId) -> Option<&DropPool> {
         Some(&self.hard_coded_drop_pool)
     }
+
+    /// Drops of the reactor, reactors without a drop table don't drop anything
+    pub fn get_drops_for_reactor(&self, id: ReactorId) -> Option<&DropPool> {
+        self.meta_data.reactor_drops.get(&id)
+    }
 }
@@ -0,0 +1,660 @@
+use proto95::{
+    game::mini_room::{
+        MemoryGameFirstCard, MemoryGameSecondCard, MemoryGameTurn, MiniGameResultType,
+        MiniRoomType, OmokMove,
+    },
+    shared::char::MiniGameInfo,
+};
+use rand::{seq::SliceRandom, Rng};
+use thiserror::Error;
+
+use crate::entities::mini_game_record;
+
+use super::OWNER_IX;
+
+pub const OPPONENT_IX: u8 = 1;
+pub const OMOK_BOARD_SIZE: usize = 15;
+const OMOK_WIN_LEN: usize = 5;
+/// Score a new record starts with
+pub const DEFAULT_SCORE: u32 = 2000;
+/// Score the winner gains and the loser loses
+const SCORE_DELTA: u32 = 10;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MiniGameError {
+    #[error("Game is not running")]
+    NotRunning,
+    #[error("Game is already running")]
+    AlreadyRunning,
+    #[error("Only the players can do this")]
+    NotAPlayer,
+    #[error("Not the turn of the user")]
+    NotYourTurn,
+    #[error("Opponent is not ready")]
+    NotReady,
+    #[error("Invalid move")]
+    InvalidMove,
+    #[error("No tie was requested")]
+    NoTieRequest,
+}
+
+/// Win/Draw/Lose record of a character for one game type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MiniGameRecord {
+    pub win: u32,
+    pub draw: u32,
+    pub lose: u32,
+    pub score: u32,
+}
+
+impl Default for MiniGameRecord {
+    fn default() -> Self {
+        Self {
+            win: 0,
+            draw: 0,
+            lose: 0,
+            score: DEFAULT_SCORE,
+        }
+    }
+}
+
+impl From<&mini_game_record::Model> for MiniGameRecord {
+    fn from(value: &mini_game_record::Model) -> Self {
+        Self {
+            win: value.win as u32,
+            draw: value.draw as u32,
+            lose: value.lose as u32,
+            score: value.score as u32,
+        }
+    }
+}
+
+impl MiniGameRecord {
+    fn add_win(&mut self) {
+        self.win += 1;
+        self.score += SCORE_DELTA;
+    }
+
+    fn add_lose(&mut self) {
+        self.lose += 1;
+        self.score = self.score.saturating_sub(SCORE_DELTA);
+    }
+
+    fn add_draw(&mut self) {
+        self.draw += 1;
+    }
+
+    pub fn to_proto(&self, ty: MiniRoomType) -> MiniGameInfo {
+        MiniGameInfo {
+            game_id: game_id(ty),
+            win: self.win,
+            draw: self.draw,
+            lose: self.lose,
+            score: self.score,
+        }
+    }
+}
+
+/// Id of the game type in the records
+pub fn game_id(ty: MiniRoomType) -> u32 {
+    ty as u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiniGameOutcome {
+    Win(u8),
+    Draw,
+    GiveUp(u8),
+}
+
+impl MiniGameOutcome {
+    pub fn result_type(&self) -> MiniGameResultType {
+        match self {
+            MiniGameOutcome::Win(_) => MiniGameResultType::Win,
+            MiniGameOutcome::Draw => MiniGameResultType::Draw,
+            MiniGameOutcome::GiveUp(_) => MiniGameResultType::GiveUp,
+        }
+    }
+
+    pub fn winner(&self) -> Option<u8> {
+        match self {
+            MiniGameOutcome::Win(ix) | MiniGameOutcome::GiveUp(ix) => Some(*ix),
+            MiniGameOutcome::Draw => None,
+        }
+    }
+
+    /// Applies the outcome to the records of the owner and the opponent
+    pub fn apply(&self, owner: &mut MiniGameRecord, opponent: &mut MiniGameRecord) {
+        match self.winner() {
+            Some(OWNER_IX) => {
+                owner.add_win();
+                opponent.add_lose();
+            }
+            Some(_) => {
+                owner.add_lose();
+                opponent.add_win();
+            }
+            None => {
+                owner.add_draw();
+                opponent.add_draw();
+            }
+        }
+    }
+}
+
+fn other_player(ix: u8) -> u8 {
+    if ix == OWNER_IX {
+        OPPONENT_IX
+    } else {
+        OWNER_IX
+    }
+}
+
+fn check_player(ix: u8) -> Result<(), MiniGameError> {
+    if ix == OWNER_IX || ix == OPPONENT_IX {
+        Ok(())
+    } else {
+        Err(MiniGameError::NotAPlayer)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OmokBoard {
+    cells: [[u8; OMOK_BOARD_SIZE]; OMOK_BOARD_SIZE],
+}
+
+impl Default for OmokBoard {
+    fn default() -> Self {
+        Self {
+            cells: [[0; OMOK_BOARD_SIZE]; OMOK_BOARD_SIZE],
+        }
+    }
+}
+
+impl OmokBoard {
+    /// Places the piece, returns true If It completes a row
+    pub fn place(&mut self, x: usize, y: usize, piece: u8) -> Result<bool, MiniGameError> {
+        let cell = self
+            .cells
+            .get_mut(y)
+            .and_then(|row| row.get_mut(x))
+            .ok_or(MiniGameError::InvalidMove)?;
+        if *cell != 0 {
+            return Err(MiniGameError::InvalidMove);
+        }
+        *cell = piece;
+
+        Ok(self.is_row(x, y, piece))
+    }
+
+    fn count_dir(&self, x: usize, y: usize, dx: isize, dy: isize, piece: u8) -> usize {
+        let (mut x, mut y) = (x as isize, y as isize);
+        let mut n = 0;
+        loop {
+            x += dx;
+            y += dy;
+            let cell = usize::try_from(y)
+                .ok()
+                .zip(usize::try_from(x).ok())
+                .and_then(|(y, x)| self.cells.get(y)?.get(x));
+            if cell != Some(&piece) {
+                return n;
+            }
+            n += 1;
+        }
+    }
+
+    fn is_row(&self, x: usize, y: usize, piece: u8) -> bool {
+        [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|&(dx, dy)| {
+            1 + self.count_dir(x, y, dx, dy, piece) + self.count_dir(x, y, -dx, -dy, piece)
+                >= OMOK_WIN_LEN
+        })
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().flatten().all(|&cell| cell != 0)
+    }
+}
+
+/// Number of cards for the board size the room was created with
+pub fn memory_game_cards(spec: u8) -> usize {
+    match spec {
+        0 => 12,
+        1 => 20,
+        _ => 30,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryGameBoard {
+    cards: Vec<u32>,
+    matched: Vec<bool>,
+    first: Option<u8>,
+    points: [u32; 2],
+}
+
+impl MemoryGameBoard {
+    pub fn shuffled(spec: u8, rng: &mut impl Rng) -> Self {
+        let pairs = memory_game_cards(spec) as u32 / 2;
+        let mut cards: Vec<u32> = (0..pairs).flat_map(|card| [card, card]).collect();
+        cards.shuffle(rng);
+        Self::new(cards)
+    }
+
+    pub fn new(cards: Vec<u32>) -> Self {
+        Self {
+            matched: vec![false; cards.len()],
+            cards,
+            first: None,
+            points: [0; 2],
+        }
+    }
+
+    pub fn cards(&self) -> &[u32] {
+        &self.cards
+    }
+
+    fn check_card(&self, card_ix: u8) -> Result<(), MiniGameError> {
+        match self.matched.get(card_ix as usize) {
+            Some(false) => Ok(()),
+            _ => Err(MiniGameError::InvalidMove),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.matched.iter().all(|&m| m)
+    }
+
+    fn outcome(&self) -> MiniGameOutcome {
+        let [owner, opponent] = self.points;
+        match owner.cmp(&opponent) {
+            std::cmp::Ordering::Greater => MiniGameOutcome::Win(OWNER_IX),
+            std::cmp::Ordering::Less => MiniGameOutcome::Win(OPPONENT_IX),
+            std::cmp::Ordering::Equal => MiniGameOutcome::Draw,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GameBoard {
+    Omok(OmokBoard),
+    MemoryGame(MemoryGameBoard),
+}
+
+#[derive(Debug, Clone)]
+struct RunningGame {
+    turn: u8,
+    board: GameBoard,
+    tie_request: Option<u8>,
+}
+
+/// State of an omok or match cards room, the owner always plays against the
+/// user in the opponent slot, everyone else is a spectator
+#[derive(Debug, Clone)]
+pub struct MiniGame {
+    pub ty: MiniRoomType,
+    /// Omok piece type or match cards board size
+    pub spec: u8,
+    opponent_ready: bool,
+    /// Index of the user who starts the next game
+    first_turn: u8,
+    leave_after_game: [bool; 2],
+    running: Option<RunningGame>,
+}
+
+impl MiniGame {
+    pub fn new(ty: MiniRoomType, spec: u8) -> Self {
+        Self {
+            ty,
+            spec,
+            opponent_ready: false,
+            first_turn: OWNER_IX,
+            leave_after_game: [false; 2],
+            running: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn is_opponent_ready(&self) -> bool {
+        self.opponent_ready
+    }
+
+    pub fn turn(&self) -> Option<u8> {
+        self.running.as_ref().map(|game| game.turn)
+    }
+
+    fn running_mut(&mut self) -> Result<&mut RunningGame, MiniGameError> {
+        self.running.as_mut().ok_or(MiniGameError::NotRunning)
+    }
+
+    fn my_turn(&mut self, ix: u8) -> Result<&mut RunningGame, MiniGameError> {
+        check_player(ix)?;
+        let game = self.running_mut()?;
+        if game.turn != ix {
+            return Err(MiniGameError::NotYourTurn);
+        }
+        Ok(game)
+    }
+
+    pub fn set_ready(&mut self, ix: u8, ready: bool) -> Result<(), MiniGameError> {
+        if ix != OPPONENT_IX {
+            return Err(MiniGameError::NotAPlayer);
+        }
+        if self.is_running() {
+            return Err(MiniGameError::AlreadyRunning);
+        }
+        self.opponent_ready = ready;
+        Ok(())
+    }
+
+    /// Resets the state when the opponent leaves the room
+    pub fn opponent_left(&mut self) {
+        self.opponent_ready = false;
+        self.leave_after_game[OPPONENT_IX as usize] = false;
+    }
+
+    pub fn set_leave_after_game(&mut self, ix: u8, leave: bool) -> Result<(), MiniGameError> {
+        check_player(ix)?;
+        if !self.is_running() {
+            return Err(MiniGameError::NotRunning);
+        }
+        self.leave_after_game[ix as usize] = leave;
+        Ok(())
+    }
+
+    /// Starts a new game, only the owner can start once the opponent is ready
+    pub fn start(&mut self, ix: u8, rng: &mut impl Rng) -> Result<u8, MiniGameError> {
+        if ix != OWNER_IX {
+            return Err(MiniGameError::NotAPlayer);
+        }
+        if self.is_running() {
+            return Err(MiniGameError::AlreadyRunning);
+        }
+        if !self.opponent_ready {
+            return Err(MiniGameError::NotReady);
+        }
+
+        let board = match self.ty {
+            MiniRoomType::MemoryGame => {
+                GameBoard::MemoryGame(MemoryGameBoard::shuffled(self.spec, rng))
+            }
+            _ => GameBoard::Omok(OmokBoard::default()),
+        };
+        self.start_with(board);
+        Ok(self.first_turn)
+    }
+
+    fn start_with(&mut self, board: GameBoard) {
+        self.running = Some(RunningGame {
+            turn: self.first_turn,
+            board,
+            tie_request: None,
+        });
+    }
+
+    /// Cards of the running match cards game
+    pub fn cards(&self) -> Option<&[u32]> {
+        match self.running.as_ref().map(|game| &game.board) {
+            Some(GameBoard::MemoryGame(board)) => Some(board.cards()),
+            _ => None,
+        }
+    }
+
+    pub fn omok_move(
+        &mut self,
+        ix: u8,
+        x: u32,
+        y: u32,
+    ) -> Result<(OmokMove, Option<MiniGameOutcome>), MiniGameError> {
+        let game = self.my_turn(ix)?;
+        let GameBoard::Omok(board) = &mut game.board else {
+            return Err(MiniGameError::InvalidMove);
+        };
+
+        let piece = ix + 1;
+        let won = board.place(x as usize, y as usize, piece)?;
+        let outcome = if won {
+            Some(MiniGameOutcome::Win(ix))
+        } else if board.is_full() {
+            Some(MiniGameOutcome::Draw)
+        } else {
+            game.turn = other_player(ix);
+            None
+        };
+
+        Ok((OmokMove { x, y, piece }, outcome))
+    }
+
+    pub fn memory_game_turn(
+        &mut self,
+        ix: u8,
+        first: bool,
+        card_ix: u8,
+    ) -> Result<(MemoryGameTurn, Option<MiniGameOutcome>), MiniGameError> {
+        let game = self.my_turn(ix)?;
+        let GameBoard::MemoryGame(board) = &mut game.board else {
+            return Err(MiniGameError::InvalidMove);
+        };
+        board.check_card(card_ix)?;
+
+        if first {
+            if board.first.is_some() {
+                return Err(MiniGameError::InvalidMove);
+            }
+            board.first = Some(card_ix);
+            return Ok((MemoryGameTurn::First(MemoryGameFirstCard { card_ix }), None));
+        }
+
+        let first_ix = board.first.take().ok_or(MiniGameError::InvalidMove)?;
+        if first_ix == card_ix {
+            board.first = Some(first_ix);
+            return Err(MiniGameError::InvalidMove);
+        }
+
+        let matched = board.cards[first_ix as usize] == board.cards[card_ix as usize];
+        if matched {
+            // A match lets the user keep the turn
+            board.matched[first_ix as usize] = true;
+            board.matched[card_ix as usize] = true;
+            board.points[ix as usize] += 1;
+        } else {
+            game.turn = other_player(ix);
+        }
+
+        let outcome = board.is_done().then(|| board.outcome());
+        let turn = MemoryGameTurn::Second(MemoryGameSecondCard {
+            first_ix,
+            second_ix: card_ix,
+            result: ix + if matched { 2 } else { 0 },
+        });
+        Ok((turn, outcome))
+    }
+
+    /// Skips the turn, returns the index of the user who is next
+    pub fn skip(&mut self, ix: u8) -> Result<u8, MiniGameError> {
+        let game = self.my_turn(ix)?;
+        if let GameBoard::MemoryGame(board) = &mut game.board {
+            board.first = None;
+        }
+        game.turn = other_player(ix);
+        Ok(game.turn)
+    }
+
+    pub fn request_tie(&mut self, ix: u8) -> Result<(), MiniGameError> {
+        check_player(ix)?;
+        self.running_mut()?.tie_request = Some(ix);
+        Ok(())
+    }
+
+    /// Answers the tie request of the other player, accepting ends the game
+    pub fn answer_tie(
+        &mut self,
+        ix: u8,
+        accept: bool,
+    ) -> Result<Option<MiniGameOutcome>, MiniGameError> {
+        check_player(ix)?;
+        let game = self.running_mut()?;
+        if game.tie_request.take() != Some(other_player(ix)) {
+            return Err(MiniGameError::NoTieRequest);
+        }
+
+        Ok(accept.then_some(MiniGameOutcome::Draw))
+    }
+
+    pub fn give_up(&mut self, ix: u8) -> Result<MiniGameOutcome, MiniGameError> {
+        check_player(ix)?;
+        self.running_mut()?;
+        Ok(MiniGameOutcome::GiveUp(other_player(ix)))
+    }
+
+    /// Ends the running game, the loser starts the next game
+    ///
+    /// Returns the players who wanted to leave after the game
+    pub fn finish(&mut self, outcome: MiniGameOutcome) -> Vec<u8> {
+        self.running = None;
+        self.opponent_ready = false;
+        if let Some(winner) = outcome.winner() {
+            self.first_turn = other_player(winner);
+        }
+
+        let leave = std::mem::take(&mut self.leave_after_game);
+        [OWNER_IX, OPPONENT_IX]
+            .into_iter()
+            .filter(|ix| leave[*ix as usize])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto95::game::mini_room::{MemoryGameTurn, MiniRoomType};
+
+    use super::{
+        GameBoard, MemoryGameBoard, MiniGame, MiniGameError, MiniGameOutcome, MiniGameRecord,
+        OmokBoard, DEFAULT_SCORE, OPPONENT_IX,
+    };
+    use crate::services::mini_room::OWNER_IX;
+
+    fn started(ty: MiniRoomType) -> MiniGame {
+        let mut game = MiniGame::new(ty, 0);
+        assert_eq!(
+            game.start(OWNER_IX, &mut rand::thread_rng()),
+            Err(MiniGameError::NotReady)
+        );
+        game.set_ready(OPPONENT_IX, true).unwrap();
+        assert_eq!(game.start(OWNER_IX, &mut rand::thread_rng()), Ok(OWNER_IX));
+        game
+    }
+
+    #[test]
+    fn omok_row() {
+        let mut board = OmokBoard::default();
+        for i in 0..4 {
+            assert!(!board.place(i + 2, i + 2, 1).unwrap());
+        }
+        assert_eq!(board.place(2, 2, 2), Err(MiniGameError::InvalidMove));
+        assert_eq!(board.place(15, 0, 2), Err(MiniGameError::InvalidMove));
+        // Closes the diagonal in the middle
+        assert!(board.place(6, 6, 1).unwrap());
+    }
+
+    #[test]
+    fn omok_turns() {
+        let mut game = started(MiniRoomType::Omok);
+        assert_eq!(
+            game.omok_move(OPPONENT_IX, 0, 0).unwrap_err(),
+            MiniGameError::NotYourTurn
+        );
+
+        for x in 0..4 {
+            let (mv, outcome) = game.omok_move(OWNER_IX, x, 0).unwrap();
+            assert_eq!(mv.piece, 1);
+            assert_eq!(outcome, None);
+            game.omok_move(OPPONENT_IX, x, 1).unwrap();
+        }
+
+        let (_, outcome) = game.omok_move(OWNER_IX, 4, 0).unwrap();
+        assert_eq!(outcome, Some(MiniGameOutcome::Win(OWNER_IX)));
+        game.finish(outcome.unwrap());
+        assert!(!game.is_running());
+
+        // Loser starts the next game
+        game.set_ready(OPPONENT_IX, true).unwrap();
+        assert_eq!(
+            game.start(OWNER_IX, &mut rand::thread_rng()),
+            Ok(OPPONENT_IX)
+        );
+    }
+
+    #[test]
+    fn memory_game_turns() {
+        let mut game = MiniGame::new(MiniRoomType::MemoryGame, 0);
+        game.start_with(GameBoard::MemoryGame(MemoryGameBoard::new(vec![
+            0, 1, 0, 1,
+        ])));
+
+        // Miss passes the turn
+        game.memory_game_turn(OWNER_IX, true, 0).unwrap();
+        let (turn, outcome) = game.memory_game_turn(OWNER_IX, false, 1).unwrap();
+        assert!(matches!(turn, MemoryGameTurn::Second(ref c) if c.result == 0));
+        assert_eq!(outcome, None);
+        assert_eq!(game.turn(), Some(OPPONENT_IX));
+
+        // Match keeps the turn
+        game.memory_game_turn(OPPONENT_IX, true, 0).unwrap();
+        let (turn, _) = game.memory_game_turn(OPPONENT_IX, false, 2).unwrap();
+        assert!(matches!(turn, MemoryGameTurn::Second(ref c) if c.result == 3));
+        assert_eq!(game.turn(), Some(OPPONENT_IX));
+        assert_eq!(
+            game.memory_game_turn(OPPONENT_IX, true, 0).unwrap_err(),
+            MiniGameError::InvalidMove
+        );
+
+        game.memory_game_turn(OPPONENT_IX, true, 1).unwrap();
+        let (_, outcome) = game.memory_game_turn(OPPONENT_IX, false, 3).unwrap();
+        assert_eq!(outcome, Some(MiniGameOutcome::Win(OPPONENT_IX)));
+    }
+
+    #[test]
+    fn tie_and_give_up() {
+        let mut game = started(MiniRoomType::Omok);
+        assert_eq!(
+            game.answer_tie(OPPONENT_IX, true),
+            Err(MiniGameError::NoTieRequest)
+        );
+        game.request_tie(OWNER_IX).unwrap();
+        assert_eq!(game.answer_tie(OPPONENT_IX, false), Ok(None));
+        game.request_tie(OWNER_IX).unwrap();
+        assert_eq!(
+            game.answer_tie(OPPONENT_IX, true),
+            Ok(Some(MiniGameOutcome::Draw))
+        );
+
+        assert_eq!(game.give_up(2), Err(MiniGameError::NotAPlayer));
+        assert_eq!(
+            game.give_up(OWNER_IX),
+            Ok(MiniGameOutcome::GiveUp(OPPONENT_IX))
+        );
+
+        game.set_leave_after_game(OPPONENT_IX, true).unwrap();
+        assert_eq!(
+            game.finish(MiniGameOutcome::GiveUp(OPPONENT_IX)),
+            vec![OPPONENT_IX]
+        );
+    }
+
+    #[test]
+    fn records() {
+        let mut owner = MiniGameRecord::default();
+        let mut opponent = MiniGameRecord::default();
+        MiniGameOutcome::Win(OPPONENT_IX).apply(&mut owner, &mut opponent);
+        MiniGameOutcome::Draw.apply(&mut owner, &mut opponent);
+
+        assert_eq!((owner.win, owner.draw, owner.lose), (0, 1, 1));
+        assert_eq!((opponent.win, opponent.draw, opponent.lose), (1, 1, 0));
+        assert!(owner.score < DEFAULT_SCORE && opponent.score > DEFAULT_SCORE);
+    }
+}
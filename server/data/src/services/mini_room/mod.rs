@@ -0,0 +1,442 @@
+pub mod game;
+pub mod shop;
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use dashmap::DashMap;
+use moople_net::service::session_svc::SharedSessionHandle;
+use moople_packet::{EncodePacket, HasOpcode};
+use proto95::{
+    game::mini_room::{
+        EmployeeBalloon, MiniGameEnterResult, MiniGameResult, MiniRoomAvatar, MiniRoomBalloon,
+        MiniRoomEnterData, MiniRoomEnterError, MiniRoomEnterResult, MiniRoomEnterUser,
+        MiniRoomLeave, MiniRoomLeaveReason, MiniRoomResp, MiniRoomType, ShopEnterResult,
+    },
+    id::{ItemId, MapId},
+    shared::{char::AvatarData, FootholdId, Vec2},
+};
+use tokio::sync::Mutex;
+
+pub use proto95::game::mini_room::MiniRoomSN;
+
+use self::{
+    game::{MiniGame, MiniGameOutcome, MiniGameRecord, OPPONENT_IX},
+    shop::PlayerShop,
+};
+
+use super::{data::character::CharacterID, session::MoopleSessionSet};
+
+pub const OWNER_IX: u8 = 0;
+const SHOP_MAX_USERS: usize = 4;
+/// Owner, opponent and two spectators
+const GAME_MAX_USERS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct MiniRoomUser {
+    pub char_id: CharacterID,
+    pub name: String,
+    pub job: u16,
+    pub avatar: AvatarData,
+    /// Record for the game of the room, only set for mini games
+    pub game_record: Option<MiniGameRecord>,
+}
+
+impl MiniRoomUser {
+    pub fn to_avatar(&self) -> MiniRoomAvatar {
+        MiniRoomAvatar {
+            avatar: self.avatar.clone(),
+            name: self.name.clone(),
+            job: self.job,
+        }
+    }
+}
+
+/// Where a room was opened
+#[derive(Debug, Clone, Copy)]
+pub struct MiniRoomLocation {
+    pub map_id: MapId,
+    pub pos: Vec2,
+    pub fh: FootholdId,
+}
+
+/// A room users can enter, the owner always takes the first slot
+#[derive(Debug)]
+pub struct MiniRoom {
+    pub sn: MiniRoomSN,
+    pub ty: MiniRoomType,
+    pub title: String,
+    pub location: MiniRoomLocation,
+    /// Permit item the room was opened with
+    pub permit: ItemId,
+    /// Visitors can only enter an open room
+    pub open: bool,
+    pub password: Option<String>,
+    pub shop: PlayerShop,
+    pub game: Option<MiniGame>,
+    users: Vec<Option<MiniRoomUser>>,
+    owner_present: bool,
+    sessions: MoopleSessionSet,
+}
+
+/// Result of a finished game
+#[derive(Debug)]
+pub struct MiniGameFinish {
+    pub result: MiniGameResult,
+    /// Players who wanted to leave after the game
+    pub leave: Vec<u8>,
+    /// Updated records of the owner and the opponent
+    pub records: [(CharacterID, MiniGameRecord); 2],
+}
+
+pub type SharedMiniRoom = Arc<Mutex<MiniRoom>>;
+
+impl MiniRoom {
+    pub fn new_shop(
+        sn: MiniRoomSN,
+        ty: MiniRoomType,
+        title: String,
+        location: MiniRoomLocation,
+        permit: ItemId,
+        owner: MiniRoomUser,
+    ) -> Self {
+        Self::new(sn, ty, title, location, permit, owner, SHOP_MAX_USERS)
+    }
+
+    /// Creates an omok or match cards room, which is open right away
+    pub fn new_game(
+        sn: MiniRoomSN,
+        game: MiniGame,
+        title: String,
+        location: MiniRoomLocation,
+        permit: ItemId,
+        owner: MiniRoomUser,
+    ) -> Self {
+        let mut room = Self::new(sn, game.ty, title, location, permit, owner, GAME_MAX_USERS);
+        room.game = Some(game);
+        room.open = true;
+        room
+    }
+
+    fn new(
+        sn: MiniRoomSN,
+        ty: MiniRoomType,
+        title: String,
+        location: MiniRoomLocation,
+        permit: ItemId,
+        owner: MiniRoomUser,
+        max_users: usize,
+    ) -> Self {
+        let mut users = vec![None; max_users];
+        users[OWNER_IX as usize] = Some(owner);
+        Self {
+            sn,
+            ty,
+            title,
+            location,
+            permit,
+            open: false,
+            password: None,
+            shop: PlayerShop::default(),
+            game: None,
+            users,
+            owner_present: false,
+            sessions: MoopleSessionSet::new(),
+        }
+    }
+
+    pub fn owner(&self) -> &MiniRoomUser {
+        self.users[OWNER_IX as usize].as_ref().expect("Room owner")
+    }
+
+    pub fn is_owner(&self, char_id: CharacterID) -> bool {
+        self.owner().char_id == char_id
+    }
+
+    pub fn owner_present(&self) -> bool {
+        self.owner_present
+    }
+
+    pub fn max_users(&self) -> u8 {
+        self.users.len() as u8
+    }
+
+    pub fn cur_users(&self) -> u8 {
+        let visitors = self.users.iter().skip(1).filter(|u| u.is_some()).count() as u8;
+        visitors + u8::from(self.owner_present)
+    }
+
+    pub fn user_ix(&self, char_id: CharacterID) -> Option<u8> {
+        self.users
+            .iter()
+            .position(|u| u.as_ref().map(|u| u.char_id) == Some(char_id))
+            .map(|ix| ix as u8)
+    }
+
+    pub fn user(&self, ix: u8) -> Option<&MiniRoomUser> {
+        self.users.get(ix as usize)?.as_ref()
+    }
+
+    pub fn check_password(&self, password: Option<&str>) -> bool {
+        self.password.is_none() || self.password.as_deref() == password
+    }
+
+    pub fn visitors(&self) -> impl Iterator<Item = &MiniRoomUser> {
+        self.users.iter().skip(1).flatten()
+    }
+
+    /// Enters the room, returns the slot index of the user
+    pub fn enter(
+        &mut self,
+        user: MiniRoomUser,
+        session: SharedSessionHandle,
+    ) -> Result<u8, MiniRoomEnterError> {
+        if self.user_ix(user.char_id).is_some() && !self.is_owner(user.char_id) {
+            return Err(MiniRoomEnterError::AlreadyInRoom);
+        }
+
+        let ix = if self.is_owner(user.char_id) {
+            if self.owner_present {
+                return Err(MiniRoomEnterError::AlreadyInRoom);
+            }
+            self.owner_present = true;
+            OWNER_IX
+        } else {
+            if !self.open {
+                return Err(if self.ty.is_shop() {
+                    MiniRoomEnterError::ShopClosed
+                } else {
+                    MiniRoomEnterError::NoRoom
+                });
+            }
+
+            let ix = self
+                .users
+                .iter()
+                .position(Option::is_none)
+                .ok_or(MiniRoomEnterError::Full)? as u8;
+            self.users[ix as usize] = Some(user.clone());
+            ix
+        };
+
+        let _ = self.sessions.broadcast_pkt(
+            MiniRoomResp::Enter(MiniRoomEnterUser {
+                ix,
+                user: user.to_avatar(),
+                record: user.game_record.map(|r| r.to_proto(self.ty)).into(),
+            }),
+            -1,
+        );
+        self.sessions.add(user.char_id, session);
+        Ok(ix)
+    }
+
+    /// Leaves the room, returns the slot index the user had
+    pub fn leave(&mut self, char_id: CharacterID, reason: MiniRoomLeaveReason) -> Option<u8> {
+        let ix = self.user_ix(char_id)?;
+        if ix == OWNER_IX {
+            if !self.owner_present {
+                return None;
+            }
+            self.owner_present = false;
+        } else {
+            self.users[ix as usize] = None;
+            if ix == OPPONENT_IX {
+                if let Some(game) = self.game.as_mut() {
+                    game.opponent_left();
+                }
+            }
+        }
+
+        // The leaving user gets the packet aswell
+        let _ = self
+            .sessions
+            .broadcast_pkt(MiniRoomResp::Leave(MiniRoomLeave { ix, reason }), -1);
+        self.sessions.remove(char_id);
+        Some(ix)
+    }
+
+    /// Removes every user from the room, returns the ids of the removed users
+    pub fn close(&mut self, reason: MiniRoomLeaveReason) -> Vec<CharacterID> {
+        self.open = false;
+        let mut ids: Vec<CharacterID> = self.visitors().map(|u| u.char_id).collect();
+        if self.owner_present {
+            ids.push(self.owner().char_id);
+        }
+
+        for &id in ids.iter() {
+            self.leave(id, reason);
+        }
+        ids
+    }
+
+    pub fn broadcast<T: EncodePacket + HasOpcode>(
+        &self,
+        pkt: T,
+        src: CharacterID,
+    ) -> anyhow::Result<()> {
+        self.sessions.broadcast_pkt(pkt, src)
+    }
+
+    pub async fn send_to<T: EncodePacket + HasOpcode>(&self, ix: u8, pkt: T) -> anyhow::Result<()> {
+        let user = self
+            .user(ix)
+            .ok_or_else(|| anyhow::format_err!("No user with index {ix}"))?;
+        self.sessions.send_pkt_to(user.char_id, pkt).await
+    }
+
+    pub fn enter_result(&self, my_ix: u8) -> MiniRoomEnterResult {
+        let room = MiniRoomEnterData {
+            max_users: self.max_users(),
+            my_ix,
+            users: self
+                .users
+                .iter()
+                .enumerate()
+                .filter_map(|(ix, u)| u.as_ref().map(|u| (ix as u8, u.to_avatar())))
+                .collect::<Vec<_>>()
+                .into(),
+        };
+
+        if let Some(game) = self.game.as_ref() {
+            let data = MiniGameEnterResult {
+                room,
+                records: self
+                    .users
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(ix, u)| {
+                        let record = u.as_ref()?.game_record.unwrap_or_default();
+                        Some((ix as u8, record.to_proto(self.ty)))
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+                title: self.title.clone(),
+                spec: game.spec,
+                u1: 0,
+            };
+            return match self.ty {
+                MiniRoomType::MemoryGame => MiniRoomEnterResult::MemoryGame(data),
+                _ => MiniRoomEnterResult::Omok(data),
+            };
+        }
+
+        let data = ShopEnterResult {
+            room,
+            shop: self.shop.enter_data(&self.title),
+        };
+        match self.ty {
+            MiniRoomType::EntrustedShop => MiniRoomEnterResult::EntrustedShop(data),
+            _ => MiniRoomEnterResult::PersonalShop(data),
+        }
+    }
+
+    /// Ends the running game and updates the records of both players
+    pub fn finish_game(&mut self, outcome: MiniGameOutcome) -> Option<MiniGameFinish> {
+        let leave = self.game.as_mut()?.finish(outcome);
+
+        let mut owner = self.users[OWNER_IX as usize].clone()?;
+        let mut opponent = self.users[OPPONENT_IX as usize].clone()?;
+        let mut owner_record = owner.game_record.unwrap_or_default();
+        let mut opponent_record = opponent.game_record.unwrap_or_default();
+        outcome.apply(&mut owner_record, &mut opponent_record);
+        owner.game_record = Some(owner_record);
+        opponent.game_record = Some(opponent_record);
+        let records = [
+            (owner.char_id, owner_record),
+            (opponent.char_id, opponent_record),
+        ];
+        self.users[OWNER_IX as usize] = Some(owner);
+        self.users[OPPONENT_IX as usize] = Some(opponent);
+
+        let result = MiniGameResult {
+            ty: outcome.result_type(),
+            winner: outcome.winner().unwrap_or(OWNER_IX),
+            owner_record: owner_record.to_proto(self.ty),
+            opponent_record: opponent_record.to_proto(self.ty),
+        };
+        Some(MiniGameFinish {
+            result,
+            leave,
+            records,
+        })
+    }
+
+    pub fn balloon(&self) -> MiniRoomBalloon {
+        MiniRoomBalloon {
+            sn: self.sn,
+            title: self.title.clone(),
+            private: self.password.is_some(),
+            spec: self.game.as_ref().map(|game| game.spec).unwrap_or(0),
+            cur_users: self.cur_users(),
+            max_users: self.max_users(),
+            game_on: self
+                .game
+                .as_ref()
+                .map(MiniGame::is_running)
+                .unwrap_or(false),
+        }
+    }
+
+    pub fn employee_balloon(&self) -> EmployeeBalloon {
+        EmployeeBalloon {
+            sn: self.sn,
+            title: self.title.clone(),
+            spec: 0,
+            cur_users: self.cur_users(),
+            max_users: self.max_users(),
+        }
+    }
+}
+
+/// Keeps track of all mini rooms on this server
+#[derive(Debug)]
+pub struct MiniRoomService {
+    rooms: DashMap<MiniRoomSN, SharedMiniRoom>,
+    next_sn: AtomicU32,
+}
+
+impl Default for MiniRoomService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MiniRoomService {
+    pub fn new() -> Self {
+        Self {
+            rooms: DashMap::new(),
+            next_sn: AtomicU32::new(1),
+        }
+    }
+
+    pub fn create(&self, create: impl FnOnce(MiniRoomSN) -> MiniRoom) -> SharedMiniRoom {
+        let sn = self.next_sn.fetch_add(1, Ordering::SeqCst);
+        let room = Arc::new(Mutex::new(create(sn)));
+        self.rooms.insert(sn, room.clone());
+        room
+    }
+
+    pub fn get(&self, sn: MiniRoomSN) -> Option<SharedMiniRoom> {
+        self.rooms.get(&sn).map(|room| room.clone())
+    }
+
+    pub fn remove(&self, sn: MiniRoomSN) -> Option<SharedMiniRoom> {
+        self.rooms.remove(&sn).map(|(_, room)| room)
+    }
+
+    /// Finds the hired merchant of the given owner
+    pub async fn find_entrusted_shop(&self, owner: CharacterID) -> Option<SharedMiniRoom> {
+        let rooms: Vec<SharedMiniRoom> = self.rooms.iter().map(|room| room.clone()).collect();
+        for room in rooms {
+            let r = room.lock().await;
+            if r.ty == MiniRoomType::EntrustedShop && r.is_owner(owner) {
+                drop(r);
+                return Some(room);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,435 @@
+use proto95::{
+    game::mini_room::{self as proto_room, ShopBuyResult, ShopEnterData, ShopRefresh},
+    id::ItemId,
+    shared::item::Item,
+};
+use thiserror::Error;
+
+use crate::services::model::item::{EquipItem, StackItem};
+
+pub const MAX_SHOP_ITEMS: usize = 16;
+/// Only the latest sales are kept for the sold item list
+pub const MAX_SOLD_ITEMS: usize = 64;
+/// Mesos are stored as i32 for characters, so a shop can't hold more
+pub const MAX_SHOP_MESOS: u32 = i32::MAX as u32;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ShopError {
+    #[error("Shop is full")]
+    Full,
+    #[error("Invalid shop item index: {0}")]
+    InvalidIndex(usize),
+    #[error("Invalid bundle")]
+    InvalidBundle,
+    #[error("Invalid price")]
+    InvalidPrice,
+    #[error("Item is not for sale")]
+    NotForSale,
+    #[error("Not enough bundles left")]
+    NoMoreItems,
+    #[error("Not enough mesos")]
+    NotEnoughMesos,
+    #[error("Too much mesos")]
+    TooMuchMesos,
+}
+
+impl ShopError {
+    pub fn buy_result(&self) -> ShopBuyResult {
+        match self {
+            ShopError::NotEnoughMesos => ShopBuyResult::NotEnoughMesos,
+            ShopError::TooMuchMesos => ShopBuyResult::TooMuchMesos,
+            _ => ShopBuyResult::NoMoreItems,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ShopItemKind {
+    Equip(EquipItem),
+    Stack(StackItem),
+}
+
+impl ShopItemKind {
+    pub fn is_equip(&self) -> bool {
+        matches!(self, ShopItemKind::Equip(_))
+    }
+
+    pub fn item_id(&self) -> ItemId {
+        match self {
+            ShopItemKind::Equip(item) => item.item_id,
+            ShopItemKind::Stack(item) => item.item_id,
+        }
+    }
+
+    pub fn to_proto(&self) -> Item {
+        match self {
+            ShopItemKind::Equip(item) => Item::Equip(item.into()),
+            ShopItemKind::Stack(item) => item.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShopItem {
+    pub item: ShopItemKind,
+    pub bundles: u16,
+    pub bundle_size: u16,
+    pub price: u32,
+}
+
+impl ShopItem {
+    pub fn new(
+        item: ShopItemKind,
+        bundles: u16,
+        bundle_size: u16,
+        price: u32,
+    ) -> Result<Self, ShopError> {
+        let quantity = match &item {
+            ShopItemKind::Equip(_) => 1,
+            ShopItemKind::Stack(stack) => stack.quantity,
+        };
+        Self::check_bundles(item.is_equip(), bundles, bundle_size)?;
+        if bundles as u32 * bundle_size as u32 != quantity as u32 {
+            return Err(ShopError::InvalidBundle);
+        }
+        // A price of zero marks the items kept by the store bank
+        if price == 0 {
+            return Err(ShopError::InvalidPrice);
+        }
+
+        Ok(Self {
+            item,
+            bundles,
+            bundle_size,
+            price,
+        })
+    }
+
+    /// Checks the bundles before the item is taken out of the inventory,
+    /// equips can only be sold one at a time
+    pub fn check_bundles(is_equip: bool, bundles: u16, bundle_size: u16) -> Result<(), ShopError> {
+        let valid = if is_equip {
+            bundles == 1 && bundle_size == 1
+        } else {
+            bundles > 0 && bundle_size > 0
+        };
+
+        if !valid {
+            return Err(ShopError::InvalidBundle);
+        }
+        Ok(())
+    }
+
+    pub fn is_sold_out(&self) -> bool {
+        self.bundles == 0
+    }
+
+    /// Stored items are kept by the store bank and can't be bought
+    pub fn is_stored(&self) -> bool {
+        self.price == 0
+    }
+
+    pub fn to_proto(&self) -> proto_room::ShopItem {
+        proto_room::ShopItem {
+            bundles: self.bundles,
+            bundle_size: self.bundle_size,
+            price: self.price,
+            item: self.item.to_proto(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SoldItem {
+    pub item_id: ItemId,
+    pub bundles: u16,
+    pub price: u32,
+    pub buyer: String,
+}
+
+#[derive(Debug)]
+pub struct ShopPurchase {
+    pub item: ShopItemKind,
+    pub cost: u32,
+}
+
+/// Items and earnings of a personal shop or hired merchant
+///
+/// Sold out items are kept in place with zero bundles,
+/// so the indices the clients know stay valid
+#[derive(Debug, Default, Clone)]
+pub struct PlayerShop {
+    pub db_id: Option<i32>,
+    pub items: Vec<ShopItem>,
+    pub mesos: u32,
+    pub sold: Vec<SoldItem>,
+}
+
+impl PlayerShop {
+    pub fn put_item(&mut self, item: ShopItem) -> Result<(), ShopError> {
+        if self.items.len() >= MAX_SHOP_ITEMS {
+            return Err(ShopError::Full);
+        }
+        self.items.push(item);
+        Ok(())
+    }
+
+    pub fn buy(
+        &mut self,
+        ix: usize,
+        bundles: u16,
+        buyer_mesos: u32,
+        buyer: &str,
+    ) -> Result<ShopPurchase, ShopError> {
+        let shop_item = self.items.get_mut(ix).ok_or(ShopError::InvalidIndex(ix))?;
+
+        if shop_item.is_stored() {
+            return Err(ShopError::NotForSale);
+        }
+        if bundles == 0 || bundles > shop_item.bundles {
+            return Err(ShopError::NoMoreItems);
+        }
+
+        let cost = shop_item
+            .price
+            .checked_mul(bundles as u32)
+            .ok_or(ShopError::TooMuchMesos)?;
+        if cost > buyer_mesos {
+            return Err(ShopError::NotEnoughMesos);
+        }
+        if self.mesos.saturating_add(cost) > MAX_SHOP_MESOS {
+            return Err(ShopError::TooMuchMesos);
+        }
+
+        let item = if bundles == shop_item.bundles {
+            // The whole item is transferred, including It's db id
+            shop_item.item.clone()
+        } else {
+            // Split the bought bundles off into a new item
+            let ShopItemKind::Stack(stack) = &mut shop_item.item else {
+                return Err(ShopError::InvalidBundle);
+            };
+            let quantity = bundles * shop_item.bundle_size;
+            let mut bought = stack.clone();
+            bought.db_id = None;
+            bought.quantity = quantity;
+            stack.quantity -= quantity;
+            stack.last_update = 1;
+            ShopItemKind::Stack(bought)
+        };
+
+        shop_item.bundles -= bundles;
+        self.mesos += cost;
+        if self.sold.len() >= MAX_SOLD_ITEMS {
+            self.sold.remove(0);
+        }
+        self.sold.push(SoldItem {
+            item_id: item.item_id(),
+            bundles,
+            price: cost,
+            buyer: buyer.to_string(),
+        });
+
+        Ok(ShopPurchase { item, cost })
+    }
+
+    /// Takes an item back out of the shop, the slot stays as sold out
+    pub fn take_item(&mut self, ix: usize) -> Result<ShopItemKind, ShopError> {
+        let shop_item = self.items.get_mut(ix).ok_or(ShopError::InvalidIndex(ix))?;
+
+        if shop_item.is_sold_out() {
+            return Err(ShopError::NoMoreItems);
+        }
+
+        shop_item.bundles = 0;
+        Ok(shop_item.item.clone())
+    }
+
+    /// Removes all sold out items
+    pub fn arrange(&mut self) {
+        self.items.retain(|item| !item.is_sold_out());
+    }
+
+    pub fn withdraw_mesos(&mut self) -> u32 {
+        std::mem::take(&mut self.mesos)
+    }
+
+    /// Keeps the items in the store bank, stored items are not for sale,
+    /// but they take up a slot of the shop like every other item
+    pub fn store_items(&mut self, items: Vec<ShopItemKind>) {
+        for item in items {
+            let bundle_size = match &item {
+                ShopItemKind::Equip(_) => 1,
+                ShopItemKind::Stack(stack) => stack.quantity,
+            };
+            self.items.push(ShopItem {
+                item,
+                bundles: 1,
+                bundle_size,
+                price: 0,
+            });
+        }
+    }
+
+    /// Takes everything which is still left in the shop
+    pub fn take_all(&mut self) -> (u32, Vec<ShopItemKind>) {
+        let items = self
+            .items
+            .drain(..)
+            .filter(|item| !item.is_sold_out())
+            .map(|item| item.item)
+            .collect();
+        (self.withdraw_mesos(), items)
+    }
+
+    pub fn available_items(&self) -> impl Iterator<Item = &ShopItem> {
+        self.items.iter().filter(|item| !item.is_sold_out())
+    }
+
+    pub fn is_sold_out(&self) -> bool {
+        self.items.iter().all(ShopItem::is_sold_out)
+    }
+
+    /// Neither items nor mesos are left
+    pub fn is_empty(&self) -> bool {
+        self.mesos == 0 && self.is_sold_out()
+    }
+
+    pub fn enter_data(&self, title: &str) -> ShopEnterData {
+        ShopEnterData {
+            title: title.to_string(),
+            max_items: MAX_SHOP_ITEMS as u8,
+            mesos: self.mesos,
+            items: self.items.iter().map(ShopItem::to_proto).collect(),
+        }
+    }
+
+    pub fn refresh(&self) -> ShopRefresh {
+        ShopRefresh {
+            mesos: self.mesos,
+            items: self.items.iter().map(ShopItem::to_proto).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proto95::id::ItemId;
+
+    use crate::services::model::item::StackItem;
+
+    use super::{
+        PlayerShop, ShopError, ShopItem, ShopItemKind, MAX_SHOP_ITEMS, MAX_SHOP_MESOS,
+        MAX_SOLD_ITEMS,
+    };
+
+    fn stack_item(bundles: u16, bundle_size: u16, price: u32) -> ShopItem {
+        let mut item = StackItem::from_item_id(ItemId(2000000), bundles * bundle_size);
+        item.db_id = Some(1);
+        ShopItem::new(ShopItemKind::Stack(item), bundles, bundle_size, price).unwrap()
+    }
+
+    #[test]
+    fn invalid_bundles() {
+        let item = StackItem::from_item_id(ItemId(2000000), 10);
+        assert_eq!(
+            ShopItem::new(ShopItemKind::Stack(item), 3, 3, 1).unwrap_err(),
+            ShopError::InvalidBundle
+        );
+        assert_eq!(
+            ShopItem::check_bundles(true, 2, 1).unwrap_err(),
+            ShopError::InvalidBundle
+        );
+        assert!(ShopItem::check_bundles(true, 1, 1).is_ok());
+        assert!(ShopItem::check_bundles(false, 0, 5).is_err());
+    }
+
+    #[test]
+    fn sold_list_is_capped() {
+        let mut shop = PlayerShop::default();
+        shop.put_item(stack_item(300, 1, 1)).unwrap();
+        for _ in 0..300 {
+            shop.buy(0, 1, 1, "buyer").unwrap();
+        }
+        assert_eq!(shop.sold.len(), MAX_SOLD_ITEMS);
+    }
+
+    #[test]
+    fn buy_partial_and_sold_out() {
+        let mut shop = PlayerShop::default();
+        shop.put_item(stack_item(5, 10, 100)).unwrap();
+
+        let purchase = shop.buy(0, 2, 1000, "buyer").unwrap();
+        assert_eq!(purchase.cost, 200);
+        let ShopItemKind::Stack(bought) = purchase.item else {
+            panic!("Expected stack");
+        };
+        // Split item is a new item
+        assert_eq!(bought.quantity, 20);
+        assert_eq!(bought.db_id, None);
+        assert_eq!(shop.items[0].bundles, 3);
+        assert_eq!(shop.mesos, 200);
+
+        assert_eq!(
+            shop.buy(0, 4, 1000, "buyer").unwrap_err(),
+            ShopError::NoMoreItems
+        );
+
+        let purchase = shop.buy(0, 3, 1000, "buyer").unwrap();
+        let ShopItemKind::Stack(bought) = purchase.item else {
+            panic!("Expected stack");
+        };
+        // The rest of the stack keeps the original item
+        assert_eq!(bought.quantity, 30);
+        assert_eq!(bought.db_id, Some(1));
+        assert!(shop.is_sold_out());
+        assert_eq!(shop.sold.len(), 2);
+    }
+
+    #[test]
+    fn buy_mesos_checks() {
+        let mut shop = PlayerShop::default();
+        shop.put_item(stack_item(2, 1, 100)).unwrap();
+        assert_eq!(
+            shop.buy(0, 2, 199, "buyer").unwrap_err(),
+            ShopError::NotEnoughMesos
+        );
+
+        shop.mesos = MAX_SHOP_MESOS - 50;
+        assert_eq!(
+            shop.buy(0, 1, 1000, "buyer").unwrap_err(),
+            ShopError::TooMuchMesos
+        );
+        assert_eq!(shop.items[0].bundles, 2);
+    }
+
+    #[test]
+    fn stored_items() {
+        let mut shop = PlayerShop::default();
+        let item = StackItem::from_item_id(ItemId(2000000), 10);
+        assert_eq!(
+            ShopItem::new(ShopItemKind::Stack(item.clone()), 1, 10, 0).unwrap_err(),
+            ShopError::InvalidPrice
+        );
+
+        shop.store_items(vec![ShopItemKind::Stack(item); MAX_SHOP_ITEMS]);
+        assert_eq!(
+            shop.buy(0, 1, 1000, "buyer").unwrap_err(),
+            ShopError::NotForSale
+        );
+        assert_eq!(shop.put_item(stack_item(1, 1, 100)).unwrap_err(), ShopError::Full);
+    }
+
+    #[test]
+    fn take_all() {
+        let mut shop = PlayerShop::default();
+        shop.put_item(stack_item(1, 1, 100)).unwrap();
+        shop.put_item(stack_item(1, 1, 100)).unwrap();
+        shop.buy(0, 1, 100, "buyer").unwrap();
+
+        let (mesos, items) = shop.take_all();
+        assert_eq!(mesos, 100);
+        assert_eq!(items.len(), 1);
+        assert!(shop.items.is_empty());
+    }
+}
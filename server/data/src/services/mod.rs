@@ -1,13 +1,22 @@
+pub mod char_sync;
 pub mod character;
+pub mod clock;
 pub mod data;
 pub mod field;
+pub mod field_instance;
+pub mod gm;
 pub mod helper;
 pub mod meta;
+pub mod mini_room;
 pub mod model;
+pub mod online;
+pub mod party_quest;
+pub mod rate_limit;
 pub mod server_info;
 pub mod session;
+pub mod transport;
 
-use std::{sync::Arc, time::Duration};
+use std::{net::IpAddr, sync::Arc, time::Duration};
 
 use proto95::{
     id::{job_id::JobGroup, FaceId, HairId, Skin},
@@ -19,24 +28,50 @@ use server_info::{ServerInfo, ServerService};
 use crate::entities::sea_orm_active_enums::GenderTy;
 
 use self::{
+    char_sync::CharSyncService,
+    clock::SystemClock,
     data::{
         account::{AccountId, Region},
+        ban::BanInfo,
         character::{CharacterCreateDTO, CharacterID, ItemStarterSet},
         DataServices,
     },
     field::FieldService,
+    gm::{GmAction, GmService, GM_LEVEL_ADMIN},
     meta::meta_service::MetaService,
+    mini_room::MiniRoomService,
+    online::OnlineService,
+    party_quest::PartyQuestService,
+    rate_limit::RateLimiter,
     session::{session_data::MoopleSessionBackend, GameSessionManager},
+    transport::{default_routes, TransportService},
 };
 
 pub type SharedServices = Arc<Services>;
 
+/// Registrations per IP within the window
+const REGISTER_LIMIT: usize = 3;
+const REGISTER_LIMIT_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Login attempts per IP within the window
+const LOGIN_LIMIT: usize = 10;
+const LOGIN_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
 #[derive(Debug)]
 pub struct Services {
     pub data: Arc<DataServices>,
     pub server_info: ServerService,
+    pub char_sync: CharSyncService,
     pub session_manager: GameSessionManager<MoopleSessionBackend>,
     pub field: FieldService,
+    pub gm: GmService,
+    pub mini_room: MiniRoomService,
+    pub online: OnlineService,
+    pub party_quest: PartyQuestService,
+    /// Limits the account registrations per IP
+    pub register_limit: RateLimiter<IpAddr>,
+    /// Limits the login attempts per IP
+    pub login_limit: RateLimiter<IpAddr>,
+    pub transport: TransportService,
     pub meta: &'static MetaService,
 }
 
@@ -49,16 +84,35 @@ impl Services {
         let data = Arc::new(DataServices::new(db, meta));
 
         let session_backend = MoopleSessionBackend { data: data.clone() };
+        let clock = Arc::new(SystemClock);
 
         Self {
             data,
             session_manager: GameSessionManager::new(session_backend, Duration::from_secs(30)),
             server_info: ServerService::new(servers),
+            char_sync: CharSyncService::new(),
             field: FieldService::new(meta),
+            gm: GmService::new(),
+            mini_room: MiniRoomService::new(),
+            online: OnlineService::new(),
+            party_quest: PartyQuestService::new(meta),
+            register_limit: RateLimiter::new(REGISTER_LIMIT, REGISTER_LIMIT_WINDOW, clock.clone()),
+            login_limit: RateLimiter::new(LOGIN_LIMIT, LOGIN_LIMIT_WINDOW, clock.clone()),
+            transport: TransportService::new(default_routes(), clock),
             meta,
         }
     }
 
+    /// Connects to the database at the url and applies the migrations
+    pub async fn connect(
+        db_url: &str,
+        servers: impl IntoIterator<Item = ServerInfo>,
+        meta: &'static MetaService,
+    ) -> Result<Self, DbErr> {
+        let db = crate::connect_db(db_url).await?;
+        Ok(Self::new(db, servers, meta))
+    }
+
     pub async fn seeded_in_memory(
         servers: impl IntoIterator<Item = ServerInfo>,
         meta: &'static MetaService,
@@ -71,69 +125,78 @@ impl Services {
         Arc::new(self)
     }
 
+    /// Bans the account and kicks its online characters
+    pub async fn ban_account(&self, acc_id: AccountId, info: BanInfo) -> anyhow::Result<()> {
+        self.data.ban.ban(acc_id, info).await?;
+        for char in self.online.by_account(acc_id) {
+            self.gm.push_action(char.id, GmAction::Kick);
+        }
+        Ok(())
+    }
+
+    /// Creates the test account and its characters, which already exist are kept
+    /// so the seeding can run on every start
     pub async fn seed_acc_char(&self) -> anyhow::Result<(AccountId, CharacterID)> {
-        let acc_id = self
-            .data
-            .account
-            .create(
-                "admin",
-                "test123",
-                Region::Europe,
-                true,
-                Some(GenderTy::Female),
-            )
-            .await?;
-
-        let job = JobGroup::Legend;
-        let _char_id = self
-            .data
-            .char
-            .create_character(
-                acc_id,
-                CharacterCreateDTO {
-                    name: "Aran".to_string(),
-                    job_group: JobGroup::Adventurer,
-                    face: FaceId::LEISURE_LOOK_M,
-                    skin: Skin::Normal,
-                    hair: HairId::BLACK_TOBEN,
-                    starter_set: ItemStarterSet {
-                        bottom: job.get_starter_bottoms().next().unwrap(),
-                        shoes: job.get_starter_shoes().next().unwrap(),
-                        top: job.get_starter_tops().next().unwrap(),
-                        weapon: job.get_starter_weapons().next().unwrap(),
-                        guide: job.get_guide_item(),
-                    },
-                    gender: Gender::Male,
-                },
-                &self.data.item,
-            )
-            .await?;
-
-        let job = JobGroup::Legend;
-        let char_id = self
-            .data
-            .char
-            .create_character(
-                acc_id,
-                CharacterCreateDTO {
-                    name: "Aran2".to_string(),
-                    job_group: JobGroup::Adventurer,
-                    face: FaceId::LEISURE_LOOK_M,
-                    skin: Skin::Normal,
-                    hair: HairId::BLACK_TOBEN,
-                    starter_set: ItemStarterSet {
-                        bottom: job.get_starter_bottoms().next().unwrap(),
-                        shoes: job.get_starter_shoes().next().unwrap(),
-                        top: job.get_starter_tops().next().unwrap(),
-                        weapon: job.get_starter_weapons().next().unwrap(),
-                        guide: job.get_guide_item(),
-                    },
-                    gender: Gender::Male,
-                },
-                &self.data.item,
-            )
-            .await?;
+        let acc = match self.data.account.get_by_username("admin").await? {
+            Some(acc) => acc,
+            None => {
+                let acc_id = self
+                    .data
+                    .account
+                    .create(
+                        "admin",
+                        "test123",
+                        Region::Europe,
+                        true,
+                        Some(GenderTy::Female),
+                    )
+                    .await?;
+                self.data
+                    .account
+                    .get(acc_id)
+                    .await?
+                    .ok_or_else(|| anyhow::format_err!("Seeded account is missing"))?
+            }
+        };
+        let acc_id = acc.id;
+        // The test account needs every GM command
+        if acc.gm_level != GM_LEVEL_ADMIN {
+            self.data.account.set_gm_level(acc, GM_LEVEL_ADMIN).await?;
+        }
+
+        let mut char_id = 0;
+        for name in ["Aran", "Aran2"] {
+            char_id = match self.data.char.get_by_name(name).await? {
+                Some(char) => char.id,
+                None => {
+                    self.data
+                        .char
+                        .create_character(acc_id, seed_char(name), &self.data.item)
+                        .await?
+                }
+            };
+        }
 
         Ok((acc_id, char_id))
     }
 }
+
+fn seed_char(name: &str) -> CharacterCreateDTO {
+    let job = JobGroup::Legend;
+    CharacterCreateDTO {
+        name: name.to_string(),
+        job_group: JobGroup::Adventurer,
+        face: FaceId::LEISURE_LOOK_M,
+        skin: Skin::Normal,
+        hair: HairId::BLACK_TOBEN,
+        starter_set: ItemStarterSet {
+            bottom: job.get_starter_bottoms().next().unwrap(),
+            shoes: job.get_starter_shoes().next().unwrap(),
+            top: job.get_starter_tops().next().unwrap(),
+            weapon: job.get_starter_weapons().next().unwrap(),
+            guide: job.get_guide_item(),
+        },
+        gender: Gender::Male,
+        world_id: 0,
+    }
+}
@@ -1,8 +1,12 @@
 use std::ops::{Deref, DerefMut, RangeInclusive};
 
+use arrayvec::ArrayString;
 use chrono::NaiveDateTime;
 use enum_map::{enum_map, Enum, EnumMap};
-use moople_packet::proto::time::{MapleExpiration, MapleTime};
+use moople_packet::proto::{
+    string::FixedPacketString,
+    time::{MapleExpiration, MapleTime},
+};
 use proto95::{
     id::ItemId,
     shared::item::{self as proto_item},
@@ -10,10 +14,12 @@ use proto95::{
 use rand::Rng;
 
 use crate::{
-    entities::{equip_item, item_stack},
+    entities::{equip_item, item_stack, pet_item},
     services::meta::meta_service::{get_equip_stats, ItemMeta},
 };
 
+use super::pet::PetData;
+
 #[derive(Debug, Enum, Clone)]
 pub enum EquipStat {
     Str,
@@ -195,6 +201,8 @@ impl EquipItem {
 pub struct StackItem {
     pub info: ItemInfo,
     pub quantity: u16,
+    /// Pets are stored as cash items with a quantity of one
+    pub pet: Option<Box<PetData>>,
 }
 
 impl Deref for StackItem {
@@ -224,6 +232,25 @@ impl From<item_stack::Model> for StackItem {
                 last_update: 0,
             },
             quantity: value.quantity as u16,
+            pet: None,
+        }
+    }
+}
+
+impl From<pet_item::Model> for StackItem {
+    fn from(value: pet_item::Model) -> Self {
+        Self {
+            info: ItemInfo {
+                db_id: Some(value.id),
+                item_id: ItemId(value.item_id as u32),
+                cash_id: value.cash_id.map(|i| i as u64),
+                expiration: value.expires_at,
+                owner: None,
+                flags: proto_item::ItemFlags::from_bits_truncate(value.flags as u16),
+                last_update: 0,
+            },
+            quantity: 1,
+            pet: Some(Box::new(PetData::from(&value))),
         }
     }
 }
@@ -233,6 +260,18 @@ impl StackItem {
         Self {
             info: ItemInfo::from_id(item_id),
             quantity,
+            pet: None,
+        }
+    }
+
+    pub fn new_pet(item_id: ItemId, cash_id: u64, name: String) -> Self {
+        Self {
+            info: ItemInfo {
+                cash_id: Some(cash_id),
+                ..ItemInfo::from_id(item_id)
+            },
+            quantity: 1,
+            pet: Some(Box::new(PetData::new(name))),
         }
     }
 }
@@ -304,6 +343,34 @@ impl From<&StackItem> for proto_item::ItemStackData {
     }
 }
 
+impl From<&StackItem> for proto_item::Item {
+    fn from(value: &StackItem) -> Self {
+        let Some(pet) = value.pet.as_ref() else {
+            return proto_item::Item::Stack(value.into());
+        };
+
+        proto_item::Item::Pet(proto_item::ItemPetData {
+            info: proto_item::ItemInfo {
+                item_id: value.item_id,
+                cash_id: value.cash_id.into(),
+                expiration: value.expiration.into(),
+            },
+            // Names are limited on creation, so this only truncates invalid names
+            name: FixedPacketString(
+                ArrayString::from(&pet.name).unwrap_or_else(|_| ArrayString::new()),
+            ),
+            level: pet.level,
+            tameness: pet.tameness,
+            fullness: pet.fullness,
+            expiration: value.expiration.into(),
+            attribute1: pet.attribute,
+            skill: pet.skill,
+            remain_life: pet.remaining_life,
+            attribute2: 0,
+        })
+    }
+}
+
 /*
 
 fn map_item_info(info: &services::model::item::ItemInfo) -> ItemInfo {
@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::entities::pet_item;
+
+pub const MAX_LEVEL: u8 = 30;
+pub const MAX_TAMENESS: u16 = 30000;
+pub const MAX_FULLNESS: u8 = 100;
+/// Fullness a pet gains by eating pet food
+pub const FOOD_FULLNESS: u8 = 30;
+/// A summoned pet loses one fullness point per interval
+pub const FULLNESS_DECAY_INTERVAL: Duration = Duration::from_secs(60);
+/// Chance for a pet to follow a command
+const COMMAND_SUCCESS_CHANCE: f64 = 0.5;
+
+/// Tameness required to reach the next level, index 0 is level 1
+const TAMENESS_TABLE: [u16; MAX_LEVEL as usize] = [
+    0, 1, 3, 6, 14, 31, 60, 108, 181, 287, 434, 632, 891, 1224, 1642, 2161, 2793, 3557, 4467, 5542,
+    6801, 8263, 9950, 11882, 14084, 16578, 19391, 22547, 26074, 30000,
+];
+
+fn level_for_tameness(tameness: u16) -> u8 {
+    TAMENESS_TABLE.iter().filter(|&&t| t <= tameness).count() as u8
+}
+
+/// State of a pet, the item info of the pet is stored in the `StackItem`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PetData {
+    pub name: String,
+    pub level: u8,
+    pub tameness: u16,
+    pub fullness: u8,
+    pub skill: u16,
+    pub remaining_life: u32,
+    pub attribute: u16,
+    pub summoned: bool,
+}
+
+impl From<&pet_item::Model> for PetData {
+    fn from(value: &pet_item::Model) -> Self {
+        Self {
+            name: value.name.clone(),
+            level: value.level as u8,
+            tameness: value.tameness as u16,
+            fullness: value.fullness as u8,
+            skill: value.skill as u16,
+            remaining_life: value.remaining_life as u32,
+            attribute: 0,
+            summoned: value.summoned,
+        }
+    }
+}
+
+impl PetData {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            level: 1,
+            tameness: 0,
+            fullness: MAX_FULLNESS,
+            skill: 0,
+            remaining_life: 0,
+            attribute: 0,
+            summoned: false,
+        }
+    }
+
+    pub fn is_hungry(&self) -> bool {
+        self.fullness == 0
+    }
+
+    /// Adds tameness, returns true if the level changed
+    pub fn add_tameness(&mut self, tameness: i32) -> bool {
+        let tameness = (self.tameness as i32 + tameness).clamp(0, MAX_TAMENESS as i32);
+        self.tameness = tameness as u16;
+        let level = level_for_tameness(self.tameness);
+        let changed = level != self.level;
+        self.level = level;
+        changed
+    }
+
+    /// Reduces the fullness, a pet which starves loses tameness
+    pub fn decay_fullness(&mut self, amount: u8) {
+        self.fullness = self.fullness.saturating_sub(amount);
+        if self.is_hungry() {
+            self.add_tameness(-1);
+        }
+    }
+
+    /// Feeds the pet, returns false If the pet is already full
+    pub fn feed(&mut self) -> bool {
+        if self.fullness >= MAX_FULLNESS {
+            return false;
+        }
+        self.fullness = self
+            .fullness
+            .saturating_add(FOOD_FULLNESS)
+            .min(MAX_FULLNESS);
+        self.add_tameness(1);
+        true
+    }
+
+    /// Lets the pet try to follow a command, a followed command raises the tameness
+    pub fn command(&mut self, rng: &mut impl Rng) -> bool {
+        if self.is_hungry() || !rng.gen_bool(COMMAND_SUCCESS_CHANCE) {
+            return false;
+        }
+        self.add_tameness(1);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{level_for_tameness, PetData, MAX_FULLNESS, MAX_LEVEL, MAX_TAMENESS};
+
+    #[test]
+    fn tameness_levels() {
+        assert_eq!(level_for_tameness(0), 1);
+        assert_eq!(level_for_tameness(2), 2);
+        assert_eq!(level_for_tameness(3), 3);
+        assert_eq!(level_for_tameness(MAX_TAMENESS), MAX_LEVEL);
+
+        let mut pet = PetData::new("Pet".to_string());
+        assert!(pet.add_tameness(1));
+        assert!(!pet.add_tameness(1));
+        assert_eq!(pet.level, 2);
+        assert!(pet.add_tameness(100_000));
+        assert_eq!((pet.tameness, pet.level), (MAX_TAMENESS, MAX_LEVEL));
+        assert!(pet.add_tameness(-100_000));
+        assert_eq!((pet.tameness, pet.level), (0, 1));
+    }
+
+    #[test]
+    fn fullness() {
+        let mut pet = PetData::new("Pet".to_string());
+        assert!(!pet.feed());
+
+        pet.decay_fullness(50);
+        assert_eq!(pet.fullness, MAX_FULLNESS - 50);
+        assert!(pet.feed());
+        assert_eq!(pet.fullness, MAX_FULLNESS - 20);
+        assert_eq!(pet.tameness, 1);
+
+        pet.decay_fullness(u8::MAX);
+        assert!(pet.is_hungry());
+        assert_eq!(pet.tameness, 0);
+        assert!(!pet.command(&mut rand::thread_rng()));
+    }
+}
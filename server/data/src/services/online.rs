@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use dashmap::DashMap;
+use proto95::login::world::{ChannelId, WorldId};
+
+use super::{
+    data::{account::AccountId, character::CharacterID},
+    server_info::ChannelLoad,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnlineChar {
+    pub id: CharacterID,
+    pub acc_id: AccountId,
+    pub name: String,
+    pub world_id: WorldId,
+    pub channel_id: ChannelId,
+}
+
+/// Characters, which are logged into a channel
+#[derive(Debug, Default)]
+pub struct OnlineService {
+    chars: DashMap<CharacterID, OnlineChar>,
+}
+
+impl OnlineService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn login(&self, char: OnlineChar) {
+        self.chars.insert(char.id, char);
+    }
+
+    pub fn logout(&self, id: CharacterID) {
+        self.chars.remove(&id);
+    }
+
+    pub fn get(&self, id: CharacterID) -> Option<OnlineChar> {
+        self.chars.get(&id).map(|char| char.clone())
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<OnlineChar> {
+        self.chars
+            .iter()
+            .find(|char| char.name.eq_ignore_ascii_case(name))
+            .map(|char| char.clone())
+    }
+
+    /// Online characters of the account
+    pub fn by_account(&self, acc_id: AccountId) -> Vec<OnlineChar> {
+        self.chars
+            .iter()
+            .filter(|char| char.acc_id == acc_id)
+            .map(|char| char.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Online characters grouped by world and channel, sorted by id
+    pub fn by_channel(&self) -> BTreeMap<(WorldId, ChannelId), Vec<OnlineChar>> {
+        let mut channels = BTreeMap::<_, Vec<_>>::new();
+        for char in self.chars.iter() {
+            channels
+                .entry((char.world_id, char.channel_id))
+                .or_default()
+                .push(char.clone());
+        }
+        for chars in channels.values_mut() {
+            chars.sort_by_key(|char| char.id);
+        }
+        channels
+    }
+
+    /// Number of online characters per world and channel
+    pub fn channel_load(&self) -> ChannelLoad {
+        let mut load = ChannelLoad::new();
+        for char in self.chars.iter() {
+            *load.entry((char.world_id, char.channel_id)).or_default() += 1;
+        }
+        load
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OnlineChar, OnlineService};
+
+    fn char(id: i32, name: &str, channel_id: u16) -> OnlineChar {
+        OnlineChar {
+            id,
+            acc_id: id,
+            name: name.to_string(),
+            world_id: 0,
+            channel_id,
+        }
+    }
+
+    #[test]
+    fn channels() {
+        let online = OnlineService::new();
+        online.login(char(2, "Bob", 1));
+        online.login(char(1, "Alice", 1));
+        online.login(char(3, "Carol", 0));
+
+        assert_eq!(online.find_by_name("alice"), Some(char(1, "Alice", 1)));
+        let channels = online.by_channel();
+        assert_eq!(channels[&(0, 0)], vec![char(3, "Carol", 0)]);
+        assert_eq!(
+            channels[&(0, 1)],
+            vec![char(1, "Alice", 1), char(2, "Bob", 1)]
+        );
+
+        assert_eq!(online.channel_load()[&(0, 1)], 2);
+        assert_eq!(online.by_account(2), vec![char(2, "Bob", 1)]);
+
+        online.logout(1);
+        assert_eq!(online.len(), 2);
+        assert!(online.get(1).is_none());
+    }
+}
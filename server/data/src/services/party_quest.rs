@@ -0,0 +1,295 @@
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use game_data::pq::{ClearCondition, PartyQuest, Reward, Stage};
+use proto95::id::MapId;
+
+use super::{
+    data::character::CharacterID,
+    field::{FieldData, FieldService},
+    field_instance::{FieldInstance, FieldInstanceId, FieldInstanceScript},
+    meta::meta_service::{MetaService, PartyQuestMeta},
+};
+
+const STAGE_CLEAR_SCREEN: &str = "quest/party/clear";
+const STAGE_CLEAR_SOUND: &str = "Party1/Clear";
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum PartyQuestEntryError {
+    #[error("The party must have {0} to {1} members")]
+    PartySize(usize, usize),
+    #[error("All members must be level {0} to {1}")]
+    Level(u32, u32),
+}
+
+/// Checks the party, which is given by the levels of the members
+pub fn check_party_quest_entry(
+    pq: &PartyQuest,
+    levels: &[u32],
+) -> Result<(), PartyQuestEntryError> {
+    if !(pq.min_party..=pq.max_party).contains(&levels.len()) {
+        return Err(PartyQuestEntryError::PartySize(pq.min_party, pq.max_party));
+    }
+    if !levels
+        .iter()
+        .all(|level| (pq.min_level..=pq.max_level).contains(level))
+    {
+        return Err(PartyQuestEntryError::Level(pq.min_level, pq.max_level));
+    }
+    Ok(())
+}
+
+/// Progress of a party in the instance of a party quest
+#[derive(Debug)]
+pub struct PartyQuestRun {
+    pub id: u32,
+    pub meta: PartyQuestMeta,
+    cleared: AtomicUsize,
+    claimed: Mutex<BTreeSet<CharacterID>>,
+}
+
+impl PartyQuestRun {
+    fn new(id: u32, meta: PartyQuestMeta) -> Self {
+        Self {
+            id,
+            meta,
+            cleared: AtomicUsize::new(0),
+            claimed: Mutex::default(),
+        }
+    }
+
+    pub fn cleared_stages(&self) -> usize {
+        self.cleared.load(Ordering::SeqCst)
+    }
+
+    /// All stages are cleared, which unlocks the bonus map and the rewards
+    pub fn is_completed(&self) -> bool {
+        self.cleared_stages() >= self.meta.stages.len()
+    }
+
+    /// Stage, which is played on the map right now
+    pub fn active_stage(&self, map_id: MapId) -> Option<&'static Stage> {
+        self.meta
+            .stages
+            .get(self.cleared_stages())
+            .filter(|stage| stage.map == map_id.0)
+    }
+
+    /// Stages are unlocked once the previous stage is cleared
+    pub fn can_enter(&self, map_id: MapId) -> bool {
+        match self
+            .meta
+            .stages
+            .iter()
+            .position(|stage| stage.map == map_id.0)
+        {
+            Some(ix) => ix <= self.cleared_stages(),
+            None => self.meta.bonus_map == Some(map_id.0) && self.is_completed(),
+        }
+    }
+
+    /// Clears the active stage of the field, returns false If the stage was not active
+    pub fn clear_stage(&self, field: &FieldData) -> anyhow::Result<bool> {
+        let cleared = self.cleared_stages();
+        if self.active_stage(field.field_id()).is_none()
+            || self
+                .cleared
+                .compare_exchange(cleared, cleared + 1, Ordering::SeqCst, Ordering::SeqCst)
+                .is_err()
+        {
+            return Ok(false);
+        }
+
+        field.show_screen_effect(STAGE_CLEAR_SCREEN)?;
+        field.play_sound(STAGE_CLEAR_SOUND)?;
+        Ok(true)
+    }
+
+    /// Each character can claim one of the rewards after the last stage
+    pub fn claim_reward(&self, char_id: CharacterID, ix: usize) -> Option<&'static Reward> {
+        if !self.is_completed() {
+            return None;
+        }
+        let reward = self.meta.rewards.get(ix)?;
+        self.claimed
+            .lock()
+            .expect("Claim reward")
+            .insert(char_id)
+            .then_some(reward)
+    }
+}
+
+/// Drives the stages of the party quest through the hooks of its instance
+#[derive(Debug)]
+pub struct PartyQuestScript(pub Arc<PartyQuestRun>);
+
+impl FieldInstanceScript for PartyQuestScript {
+    fn on_all_mobs_killed(
+        &self,
+        _instance: &FieldInstance,
+        field: &FieldData,
+    ) -> anyhow::Result<()> {
+        if let Some(stage) = self.0.active_stage(field.field_id()) {
+            if stage.clear == ClearCondition::KillMobs {
+                self.0.clear_stage(field)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_reactor_state(
+        &self,
+        _instance: &FieldInstance,
+        field: &FieldData,
+        reactor: &str,
+        state: u8,
+    ) -> anyhow::Result<()> {
+        let Some(stage) = self.0.active_stage(field.field_id()) else {
+            return Ok(());
+        };
+        match &stage.clear {
+            ClearCondition::ReactorState {
+                reactor: name,
+                state: clear_state,
+            } if name == reactor && *clear_state == state => {
+                self.0.clear_stage(field)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn can_enter(&self, _instance: &FieldInstance, map_id: MapId) -> bool {
+        self.0.can_enter(map_id)
+    }
+}
+
+#[derive(Debug)]
+pub struct PartyQuestService {
+    meta: &'static MetaService,
+    runs: DashMap<FieldInstanceId, Arc<PartyQuestRun>>,
+}
+
+impl PartyQuestService {
+    pub fn new(meta: &'static MetaService) -> Self {
+        Self {
+            meta,
+            runs: DashMap::new(),
+        }
+    }
+
+    /// Creates the instance for a party, the entry has to be checked beforehand
+    pub fn start(&self, field: &FieldService, id: u32) -> anyhow::Result<Arc<FieldInstance>> {
+        // Runs of instances, which were torn down, are dropped
+        self.runs
+            .retain(|instance_id, _| field.get_instance(*instance_id).is_some());
+
+        let meta = self
+            .meta
+            .get_party_quest(id)
+            .ok_or_else(|| anyhow::format_err!("Invalid party quest: {id}"))?;
+        let run = Arc::new(PartyQuestRun::new(id, meta));
+        let instance = field.create_instance(
+            meta.maps().map(MapId).collect(),
+            MapId(meta.exit_map),
+            Some(Duration::from_secs(meta.time_limit as u64)),
+            Box::new(PartyQuestScript(run.clone())),
+        )?;
+        self.runs.insert(instance.id, run);
+        Ok(instance)
+    }
+
+    pub fn get_run(&self, instance_id: FieldInstanceId) -> Option<Arc<PartyQuestRun>> {
+        self.runs.get(&instance_id).map(|run| run.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_data::pq::{ClearCondition, PartyQuest, Reward, Stage};
+    use proto95::id::MapId;
+
+    use super::{check_party_quest_entry, PartyQuestEntryError, PartyQuestRun};
+
+    fn pq() -> &'static PartyQuest {
+        Box::leak(Box::new(PartyQuest {
+            name: "Test".to_string(),
+            npc: 9020000,
+            min_party: 2,
+            max_party: 3,
+            min_level: 21,
+            max_level: 30,
+            time_limit: 60,
+            exit_map: 103000890,
+            stages: vec![
+                Stage {
+                    map: 103000800,
+                    clear: ClearCondition::CollectItem {
+                        item: 4001007,
+                        count: 10,
+                    },
+                    npc: Some(9020001),
+                },
+                Stage {
+                    map: 103000804,
+                    clear: ClearCondition::KillMobs,
+                    npc: None,
+                },
+            ],
+            bonus_map: Some(103000805),
+            rewards: vec![Reward {
+                item: 2000004,
+                quantity: 10,
+            }],
+        }))
+    }
+
+    #[test]
+    fn entry() {
+        let pq = pq();
+        assert_eq!(check_party_quest_entry(pq, &[21, 30]), Ok(()));
+        assert_eq!(
+            check_party_quest_entry(pq, &[25]),
+            Err(PartyQuestEntryError::PartySize(2, 3))
+        );
+        assert_eq!(
+            check_party_quest_entry(pq, &[25, 31]),
+            Err(PartyQuestEntryError::Level(21, 30))
+        );
+    }
+
+    #[test]
+    fn shipped_pq_can_start() {
+        // Until parties are supported the caller enters alone
+        let pq: PartyQuest =
+            serde_json::from_str(include_str!("../../../../data/game_data/pq/1.json")).unwrap();
+        assert_eq!(check_party_quest_entry(&pq, &[21]), Ok(()));
+    }
+
+    #[test]
+    fn stages_and_rewards() {
+        let run = PartyQuestRun::new(1, pq());
+        assert!(run.can_enter(MapId(103000800)));
+        assert!(!run.can_enter(MapId(103000804)));
+        assert!(!run.can_enter(MapId(103000805)));
+        assert!(run.active_stage(MapId(103000800)).is_some());
+        assert!(run.claim_reward(1, 0).is_none());
+
+        run.cleared.store(2, std::sync::atomic::Ordering::SeqCst);
+        assert!(run.is_completed());
+        assert!(run.can_enter(MapId(103000805)));
+        assert!(run.active_stage(MapId(103000804)).is_none());
+
+        // Each character can only claim once
+        assert!(run.claim_reward(1, 0).is_some());
+        assert!(run.claim_reward(1, 0).is_none());
+        assert!(run.claim_reward(2, 1).is_none());
+    }
+}
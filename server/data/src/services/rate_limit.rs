@@ -0,0 +1,86 @@
+use std::{hash::Hash, sync::Mutex, time::Duration};
+
+use dashmap::DashMap;
+
+use super::clock::SharedClock;
+
+/// Limits how often an action can be done per key within a sliding window
+#[derive(Debug)]
+pub struct RateLimiter<K: Eq + Hash> {
+    hits: DashMap<K, Vec<Duration>>,
+    limit: usize,
+    window: Duration,
+    clock: SharedClock,
+    last_sweep: Mutex<Duration>,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    pub fn new(limit: usize, window: Duration, clock: SharedClock) -> Self {
+        Self {
+            hits: DashMap::new(),
+            limit,
+            window,
+            last_sweep: Mutex::new(clock.now()),
+            clock,
+        }
+    }
+
+    /// Records the action If the key is still below the limit
+    pub fn try_acquire(&self, key: K) -> bool {
+        let now = self.clock.now();
+        self.sweep(now);
+        let mut hits = self.hits.entry(key).or_default();
+        hits.retain(|hit| now.saturating_sub(*hit) < self.window);
+        if hits.len() >= self.limit {
+            return false;
+        }
+        hits.push(now);
+        true
+    }
+
+    /// Drops the keys without hits in the window, at most once per window
+    fn sweep(&self, now: Duration) {
+        {
+            let mut last_sweep = self.last_sweep.lock().expect("Rate limit sweep");
+            if now.saturating_sub(*last_sweep) < self.window {
+                return;
+            }
+            *last_sweep = now;
+        }
+        self.hits.retain(|_, hits| {
+            hits.retain(|hit| now.saturating_sub(*hit) < self.window);
+            !hits.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::services::clock::MockClock;
+
+    use super::RateLimiter;
+
+    #[test]
+    fn rate_limit() {
+        let clock = Arc::new(MockClock::new(Duration::from_secs(100)));
+        let limiter = RateLimiter::new(2, Duration::from_secs(60), clock.clone());
+
+        assert!(limiter.try_acquire(1));
+        clock.advance(Duration::from_secs(30));
+        assert!(limiter.try_acquire(1));
+        assert!(!limiter.try_acquire(1));
+        assert!(limiter.try_acquire(2));
+
+        // The first hit leaves the window
+        clock.advance(Duration::from_secs(30));
+        assert!(limiter.try_acquire(1));
+        assert!(!limiter.try_acquire(1));
+
+        // Keys without hits in the window are dropped
+        clock.advance(Duration::from_secs(60));
+        assert!(limiter.try_acquire(3));
+        assert_eq!(limiter.hits.len(), 1);
+    }
+}
@@ -1,9 +1,21 @@
 use anyhow::anyhow;
+use std::collections::BTreeMap;
 use std::net::{IpAddr, SocketAddr};
 
 use moople_packet::proto::MapleList16;
 
-use proto95::login::world::{ChannelId, ChannelItem, WorldId, WorldInfoResp, WorldItem};
+use proto95::login::world::{
+    ChannelId, ChannelItem, RecommendWorldMessage, RecommendWorldMessageResp,
+    WorldCheckUserLimitResp, WorldId, WorldInfoResp, WorldItem,
+};
+
+/// Online users per world and channel
+pub type ChannelLoad = BTreeMap<(WorldId, ChannelId), usize>;
+
+/// User number at which the client shows a full load bar
+const LOAD_BAR_FULL: usize = 1000;
+/// Share of the capacity in percent from which a world counts as highly populated
+const HIGH_POPULATION_PERCENT: usize = 75;
 
 #[derive(Debug, Clone)]
 pub struct ChannelInfo {
@@ -12,12 +24,57 @@ pub struct ChannelInfo {
     pub name: String,
 }
 
+/// Settings of a world as shown in the world list, missing settings use the defaults
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct WorldSettings {
+    /// 0 = normal, 1 = event, 2 = new, 3 = hot
+    pub flag: u8,
+    pub event_message: String,
+    /// Exp rate in percent
+    pub event_exp: u16,
+    /// Drop rate in percent
+    pub event_drop_rate: u16,
+    pub block_char_creation: bool,
+    /// Shown on the world selection, if set
+    pub recommend_message: Option<String>,
+    /// Online users per channel, full channels can't be selected
+    pub channel_capacity: usize,
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            flag: 0,
+            event_message: String::new(),
+            event_exp: 100,
+            event_drop_rate: 100,
+            block_char_creation: false,
+            recommend_message: None,
+            channel_capacity: 200,
+        }
+    }
+}
+
+impl WorldSettings {
+    pub fn is_channel_full(&self, users: usize) -> bool {
+        users >= self.channel_capacity
+    }
+
+    /// Scales the users to the load bar of the channel list
+    pub fn load_bar(&self, users: usize) -> u32 {
+        let capacity = self.channel_capacity.max(1);
+        (users.min(capacity) * LOAD_BAR_FULL / capacity) as u32
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ServerInfo {
     pub ip: IpAddr,
     pub port: u16,
     pub channels: Vec<ChannelInfo>,
     pub name: String,
+    pub settings: WorldSettings,
 }
 
 impl ChannelInfo {
@@ -44,9 +101,15 @@ impl ServerInfo {
                 .map(|id| ChannelInfo::new(ip, port + 1 + id as u16, &name, id as ChannelId))
                 .collect(),
             name,
+            settings: WorldSettings::default(),
         }
     }
 
+    pub fn with_settings(mut self, settings: WorldSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
     pub fn get_channel_addr(&self, ch: ChannelId) -> anyhow::Result<SocketAddr> {
         self.channels
             .get(ch as usize)
@@ -58,7 +121,46 @@ impl ServerInfo {
         SocketAddr::new(self.ip, self.port)
     }
 
-    pub fn get_world_info(&self, world_id: WorldId) -> WorldItem {
+    /// The cash shop uses the port after the last channel
+    pub fn get_cash_shop_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.ip, self.port + 1 + self.channels.len() as u16)
+    }
+
+    fn channel_users(&self, world_id: WorldId, ch: ChannelId, load: &ChannelLoad) -> usize {
+        load.get(&(world_id, ch)).copied().unwrap_or(0)
+    }
+
+    /// The world is over the limit once every channel is full
+    pub fn check_user_limit(
+        &self,
+        world_id: WorldId,
+        load: &ChannelLoad,
+    ) -> WorldCheckUserLimitResp {
+        let users: usize = (0..self.channels.len() as ChannelId)
+            .map(|ch| self.channel_users(world_id, ch, load))
+            .sum();
+        let capacity = self.settings.channel_capacity * self.channels.len();
+        let over_user_limit = users >= capacity;
+        let populate_level = if over_user_limit {
+            2
+        } else if users * 100 >= capacity * HIGH_POPULATION_PERCENT {
+            1
+        } else {
+            0
+        };
+
+        WorldCheckUserLimitResp {
+            over_user_limit,
+            populate_level,
+        }
+    }
+
+    pub fn is_channel_full(&self, world_id: WorldId, ch: ChannelId, load: &ChannelLoad) -> bool {
+        self.settings
+            .is_channel_full(self.channel_users(world_id, ch, load))
+    }
+
+    pub fn get_world_info(&self, world_id: WorldId, load: &ChannelLoad) -> WorldItem {
         //TODO add some caching mechanismn so world item is not re-encoded each time
         // maybe a custom impl of encode for WorldItem
         // should look into making something like CachedPacket<Buf, WorldItem>
@@ -72,17 +174,21 @@ impl ServerInfo {
                 id: id as u8,
                 adult_channel: false,
                 world_id: world_id as u8,
-                user_number: 0,
+                user_number: self.settings.load_bar(self.channel_users(
+                    world_id,
+                    id as ChannelId,
+                    load,
+                )),
             })
             .collect();
 
         WorldItem {
             name: self.name.clone(),
-            state: 1,
-            event_desc: "Event!!".to_string(),
-            event_exp: 100,
-            event_drop_rate: 100,
-            block_char_creation: false,
+            state: self.settings.flag,
+            event_desc: self.settings.event_message.clone(),
+            event_exp: self.settings.event_exp,
+            event_drop_rate: self.settings.event_drop_rate,
+            block_char_creation: self.settings.block_char_creation,
             channels,
             balloons: MapleList16::default(),
         }
@@ -107,18 +213,94 @@ impl ServerService {
             .ok_or_else(|| anyhow!("Invalid world: {world}"))
     }
 
+    pub fn world_ids(&self) -> impl Iterator<Item = WorldId> {
+        0..self.servers.len() as WorldId
+    }
+
     pub fn get_channel_addr(&self, world: WorldId, ch: ChannelId) -> anyhow::Result<SocketAddr> {
         self.get_server(world)?.get_channel_addr(ch)
     }
 
-    pub fn get_world_info_packets(&self) -> Vec<WorldInfoResp> {
+    pub fn get_cash_shop_addr(&self, world: WorldId) -> anyhow::Result<SocketAddr> {
+        Ok(self.get_server(world)?.get_cash_shop_addr())
+    }
+
+    pub fn get_world_info_packets(&self, load: &ChannelLoad) -> Vec<WorldInfoResp> {
         self.servers
             .iter()
             .enumerate()
             .map(|(id, server)| {
-                WorldInfoResp::world(id as u8, server.get_world_info(id as WorldId))
+                WorldInfoResp::world(id as u8, server.get_world_info(id as WorldId, load))
             })
             .chain(std::iter::once(WorldInfoResp::end()))
             .collect()
     }
+
+    /// Messages of the worlds with a recommend message, `None` if there are none
+    pub fn get_recommend_world_messages(&self) -> Option<RecommendWorldMessageResp> {
+        let messages: Vec<_> = self
+            .servers
+            .iter()
+            .enumerate()
+            .filter_map(|(id, server)| Some((id, server.settings.recommend_message.clone()?)))
+            .map(|(id, message)| RecommendWorldMessage {
+                world_id: id as WorldId,
+                message,
+            })
+            .collect();
+
+        (!messages.is_empty()).then(|| RecommendWorldMessageResp {
+            messages: messages.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{ChannelLoad, ServerInfo, WorldSettings};
+
+    fn server() -> ServerInfo {
+        ServerInfo::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8484, "Test".to_string(), 2).with_settings(
+            WorldSettings {
+                channel_capacity: 10,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn channel_load() {
+        let server = server();
+        let load = ChannelLoad::from([((0, 0), 5), ((0, 1), 10)]);
+
+        let world = server.get_world_info(0, &load);
+        let user_numbers: Vec<_> = world.channels.iter().map(|ch| ch.user_number).collect();
+        assert_eq!(user_numbers, vec![500, 1000]);
+        assert!(!server.is_channel_full(0, 0, &load));
+        assert!(server.is_channel_full(0, 1, &load));
+        assert_eq!(server.settings.load_bar(50), 1000);
+    }
+
+    #[test]
+    fn user_limit() {
+        let server = server();
+        let check = |load: ChannelLoad| {
+            let resp = server.check_user_limit(0, &load);
+            (resp.over_user_limit, resp.populate_level)
+        };
+
+        assert_eq!(check(ChannelLoad::new()), (false, 0));
+        assert_eq!(
+            check(ChannelLoad::from([((0, 0), 10), ((0, 1), 5)])),
+            (false, 1)
+        );
+        assert_eq!(
+            check(ChannelLoad::from([((0, 0), 10), ((0, 1), 10)])),
+            (true, 2)
+        );
+        // Other worlds don't count
+        assert_eq!(check(ChannelLoad::from([((1, 0), 20)])), (false, 0));
+    }
 }
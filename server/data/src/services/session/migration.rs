@@ -93,6 +93,18 @@ where
             .insert(key, MigrationContext::new(data, self.timeout));
     }
 
+    /// Removes all entries, including the timed out ones
+    pub fn drain(&self) -> Vec<V>
+    where
+        K: Clone,
+    {
+        let keys: Vec<K> = self.pending.iter().map(|ctx| ctx.key().clone()).collect();
+        keys.iter()
+            .filter_map(|key| self.pending.remove(key))
+            .map(|(_, ctx)| ctx.data)
+            .collect()
+    }
+
     pub fn clean(&self) {
         //TODO figure out how to call clean, capping insert and executing It every x inserts would be nice
         self.pending.retain(|_, v| !v.is_timeout())
@@ -134,5 +146,14 @@ mod tests {
         assert_eq!(svc.pending(), 1);
         svc.clean();
         assert_eq!(svc.pending(), 0);
+
+        // Test drain, timed out entries are included
+        svc.push(key_1, 10);
+        svc.push(key_2, 20);
+        sleep(TIMEOUT * 2);
+        let mut drained = svc.drain();
+        drained.sort();
+        assert_eq!(drained, vec![10, 20]);
+        assert_eq!(svc.pending(), 0);
     }
 }
@@ -54,6 +54,19 @@ where
         self.session_man.close_session(session).await
     }
 
+    /// Writes the changes of the session, used for the periodic autosave
+    pub async fn save_session(
+        &self,
+        session: &mut OwnedSession<uuid::Uuid, Backend::SessionData>,
+    ) -> anyhow::Result<()> {
+        self.session_man.save_session(session).await
+    }
+
+    /// Number of failed session writes since the start
+    pub fn save_failures(&self) -> usize {
+        self.session_man.save_failures()
+    }
+
     pub async fn create_migration_session(
         &self,
         migration_key: MoopleMigrationKey,
@@ -67,6 +80,27 @@ where
         Ok(())
     }
 
+    pub fn session_count(&self) -> usize {
+        self.session_man.len()
+    }
+
+    /// Sessions, which wait to be claimed by the next server
+    pub fn pending_migrations(&self) -> usize {
+        self.migration.pending()
+    }
+
+    /// Saves the sessions, which were never claimed by the next server
+    pub async fn save_pending_migrations(&self) -> usize {
+        let sessions = self.migration.drain();
+        let n = sessions.len();
+        for session in sessions {
+            if let Err(err) = self.close_session(session).await {
+                log::error!("Unable to save migration session: {err:?}");
+            }
+        }
+        n
+    }
+
     pub fn migrate_session(
         &self,
         migration_key: MoopleMigrationKey,
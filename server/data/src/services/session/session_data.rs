@@ -1,9 +1,10 @@
 use std::{collections::BTreeMap, sync::Arc};
 
-use proto95::id::SkillId;
+use proto95::{id::SkillId, login::world::ChannelId};
+use sea_orm::ConnectionTrait;
 
 use crate::{
-    entities::{self, skill},
+    entities::{self, character, mini_game_record, skill},
     services::{
         character::Character,
         data::{character::CharacterID, DataServices},
@@ -13,12 +14,106 @@ use crate::{
 
 use super::session_manager::{OwnedSession, SessionBackend};
 
+bitflags::bitflags! {
+    /// Parts of the session, which changed since the last write
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct SessionDirty : u8 {
+        const Char = 0x01;
+        const Inv = 0x02;
+        const Skills = 0x04;
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MoopleSessionData {
     pub acc: entities::account::Model,
     pub char: Character,
-    pub inv: InventorySet,
+    inv: InventorySet,
     pub skills: BTreeMap<SkillId, skill::Model>,
+    pub mini_game_records: Vec<mini_game_record::Model>,
+    /// Channel the character is on, used to return from the cash shop
+    pub channel_id: ChannelId,
+    dirty: SessionDirty,
+    /// State of the character and the skills in the db
+    saved_char: character::Model,
+    saved_skills: BTreeMap<SkillId, skill::Model>,
+}
+
+impl MoopleSessionData {
+    pub fn mark_dirty(&mut self, dirty: SessionDirty) {
+        self.dirty |= dirty;
+    }
+
+    /// Takes over the fame, which was already stored by another character,
+    /// so the next save doesn't write It back
+    pub fn sync_fame(&mut self, fame: i32) {
+        self.char.model.fame = fame;
+        self.saved_char.fame = fame;
+    }
+
+    pub fn inv(&self) -> &InventorySet {
+        &self.inv
+    }
+
+    /// Mutable access to the inventory, which is written with the next save
+    pub fn inv_mut(&mut self) -> &mut InventorySet {
+        self.mark_dirty(SessionDirty::Inv);
+        &mut self.inv
+    }
+
+    /// Parts which must be written, the character and the skills are
+    /// compared against the saved state
+    pub fn dirty(&self) -> SessionDirty {
+        let mut dirty = self.dirty;
+        dirty.set(SessionDirty::Char, self.char.model != self.saved_char);
+        dirty.set(SessionDirty::Skills, self.skills != self.saved_skills);
+        dirty
+    }
+}
+
+/// Character and inventory written by `MoopleSessionData::write_with`
+#[derive(Debug)]
+pub struct SessionWrite {
+    char: character::Model,
+    inv: InventorySet,
+}
+
+impl MoopleSessionData {
+    /// Writes the character and the inventory on the connection, so they can be committed
+    /// together with a trade. The session only takes over the write with `apply_write`
+    /// after the commit, so a rolled back write is retried by the next save
+    pub async fn write_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        data: &DataServices,
+    ) -> anyhow::Result<SessionWrite> {
+        self.write_state_with(db, data, self.char.model.clone(), self.inv.clone())
+            .await
+    }
+
+    /// Writes a changed copy of the character and the inventory, so a trade can be
+    /// prepared without touching the session until `apply_write` after the commit
+    pub async fn write_state_with<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        data: &DataServices,
+        char: character::Model,
+        mut inv: InventorySet,
+    ) -> anyhow::Result<SessionWrite> {
+        data.char
+            .update_char_with(db, &self.saved_char, &char)
+            .await?;
+        data.item.save_inventory_with(db, &mut inv, char.id).await?;
+        Ok(SessionWrite { char, inv })
+    }
+
+    /// Takes over a committed write, new items keep their db ids
+    pub fn apply_write(&mut self, write: SessionWrite) {
+        self.char.model = write.char.clone();
+        self.saved_char = write.char;
+        self.inv = write.inv;
+        self.dirty.remove(SessionDirty::Inv);
+    }
 }
 
 pub type OwnedMoopleSession = OwnedSession<uuid::Uuid, MoopleSessionData>;
@@ -45,18 +140,175 @@ impl SessionBackend for MoopleSessionBackend {
             .load_skills(char_id)
             .await?
             .into_iter()
-            .map(|skill| (SkillId(skill.id as u32), skill))
-            .collect();
+            .map(|skill| (SkillId(skill.skill_id as u32), skill))
+            .collect::<BTreeMap<_, _>>();
+        let mini_game_records = self.data.char.load_mini_game_records(char_id).await?;
         Ok(MoopleSessionData {
             acc,
+            saved_char: char.model.clone(),
             char,
             inv,
+            saved_skills: skills.clone(),
             skills,
+            mini_game_records,
+            channel_id: 0,
+            dirty: SessionDirty::empty(),
         })
     }
-    async fn save(&self, session: Self::SessionData) -> anyhow::Result<()> {
+
+    async fn flush(&self, session: &mut Self::SessionData) -> anyhow::Result<()> {
+        self.write(session, false).await
+    }
+
+    async fn save(&self, mut session: Self::SessionData) -> anyhow::Result<()> {
+        self.write(&mut session, true).await
+    }
+}
+
+impl MoopleSessionBackend {
+    /// Writes the dirty parts of the session, a full write always includes the inventory.
+    /// If the write fails the session stays dirty, so the next write retries it
+    async fn write(&self, session: &mut MoopleSessionData, full: bool) -> anyhow::Result<()> {
+        let dirty = session.dirty();
+        let char_id = session.char.model.id;
+
+        if dirty.contains(SessionDirty::Char) {
+            self.data
+                .char
+                .update_char(&session.saved_char, &session.char.model)
+                .await?;
+            session.saved_char = session.char.model.clone();
+        }
+
+        if dirty.contains(SessionDirty::Skills) {
+            self.data
+                .char
+                .save_skills(char_id, &session.saved_skills, &mut session.skills)
+                .await?;
+            session.saved_skills = session.skills.clone();
+        }
+
+        if full || dirty.contains(SessionDirty::Inv) {
+            self.data
+                .item
+                .save_inventory(&mut session.inv, char_id)
+                .await?;
+        }
+
+        session.dirty = SessionDirty::empty();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use proto95::{
+        id::{job_id::JobGroup, FaceId, HairId, Skin},
+        shared::Gender,
+    };
+    use sea_orm::{ConnectionTrait, DatabaseConnection};
+
+    use crate::services::{
+        data::{
+            account::Region,
+            character::{CharacterCreateDTO, ItemStarterSet},
+            DataServices,
+        },
+        meta::meta_service::{MetaData, MetaService},
+        session::session_manager::SessionBackend,
+    };
+
+    use super::{MoopleSessionBackend, MoopleSessionData, SessionDirty};
+
+    async fn rename_table(db: &DatabaseConnection, from: &str, to: &str) -> anyhow::Result<()> {
+        db.execute_unprepared(&format!("ALTER TABLE {from} RENAME TO {to}"))
+            .await?;
+        Ok(())
+    }
+
+    async fn test_session(
+        db: &DatabaseConnection,
+    ) -> anyhow::Result<(MoopleSessionBackend, MoopleSessionData)> {
+        let meta = Box::leak(Box::new(MetaService::new(MetaData::default())));
+        let data = Arc::new(DataServices::new(db.clone(), meta));
+        let backend = MoopleSessionBackend { data: data.clone() };
+
+        let acc_id = data
+            .account
+            .create("session", "hunter3", Region::Europe, true, None)
+            .await?;
+        let acc = data.account.get(acc_id).await?.unwrap();
+        let char_id = data
+            .char
+            .insert_character(
+                acc_id,
+                &CharacterCreateDTO {
+                    name: "Sessioner".to_string(),
+                    job_group: JobGroup::Adventurer,
+                    face: FaceId::LEISURE_LOOK_M,
+                    skin: Skin::Normal,
+                    hair: HairId::BLACK_TOBEN,
+                    starter_set: ItemStarterSet::default_starter_set(JobGroup::Adventurer),
+                    gender: Gender::Male,
+                    world_id: 0,
+                },
+            )
+            .await?;
+
+        let session = backend.load((acc, char_id)).await?;
+        Ok((backend, session))
+    }
+
+    #[tokio::test]
+    async fn retry_dirty_write() -> anyhow::Result<()> {
+        let db = crate::gen_sqlite(crate::SQL_OPT_MEMORY).await?;
+        let (backend, mut session) = test_session(&db).await?;
         let char_id = session.char.model.id;
-        self.data.item.save_inventory(session.inv, char_id).await?;
+        assert_eq!(session.dirty(), SessionDirty::empty());
+
+        session.char.model.mesos = 100;
+        session.inv_mut();
+        assert_eq!(session.dirty(), SessionDirty::Char | SessionDirty::Inv);
+
+        // The inventory can't be written, so it stays dirty for the next write
+        rename_table(&db, "inventory_slot", "inventory_slot_off").await?;
+        assert!(backend.flush(&mut session).await.is_err());
+        assert_eq!(session.dirty(), SessionDirty::Inv);
+        assert_eq!(backend.data.char.must_get(char_id).await?.mesos, 100);
+
+        rename_table(&db, "inventory_slot_off", "inventory_slot").await?;
+        backend.flush(&mut session).await?;
+        assert_eq!(session.dirty(), SessionDirty::empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rolled_back_write() -> anyhow::Result<()> {
+        let db = crate::gen_sqlite(crate::SQL_OPT_MEMORY).await?;
+        let (backend, mut session) = test_session(&db).await?;
+        let char_id = session.char.model.id;
+        let data = backend.data.clone();
+        let mesos = session.char.model.mesos;
+
+        session.char.model.mesos = mesos + 100;
+        session.inv_mut();
+        let txn = data.begin().await?;
+        let _write = session.write_with(&txn, &data).await?;
+        txn.rollback().await?;
+
+        // Nothing was applied, so the next write retries everything
+        assert_eq!(session.dirty(), SessionDirty::Char | SessionDirty::Inv);
+        assert_eq!(data.char.must_get(char_id).await?.mesos, mesos);
+
+        let txn = data.begin().await?;
+        let write = session.write_with(&txn, &data).await?;
+        txn.commit().await?;
+        session.apply_write(write);
+        assert_eq!(session.dirty(), SessionDirty::empty());
+        assert_eq!(data.char.must_get(char_id).await?.mesos, mesos + 100);
 
         Ok(())
     }
@@ -1,7 +1,10 @@
 use dashmap::DashMap;
 use std::{
     hash::Hash,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant}, ops::{Deref, DerefMut},
 };
 use tokio::sync::Mutex;
@@ -12,6 +15,8 @@ pub trait SessionBackend {
     type SessionLoadParam;
 
     async fn load(&self, param: Self::SessionLoadParam) -> anyhow::Result<Self::SessionData>;
+    /// Writes the changes of a running session
+    async fn flush(&self, session: &mut Self::SessionData) -> anyhow::Result<()>;
     async fn save(&self, session: Self::SessionData) -> anyhow::Result<()>;
 }
 
@@ -41,7 +46,8 @@ pub type SessionMutex<SessionData> = Arc<Mutex<SessionData>>;
 #[derive(Debug)]
 pub struct SessionManager<Key: Eq + Hash, Backend: SessionBackend> {
     sessions: DashMap<Key, SessionMutex<Backend::SessionData>>,
-    backend: Backend
+    backend: Backend,
+    save_failures: AtomicUsize,
 }
 
 
@@ -53,11 +59,42 @@ where
     pub fn new(backend: Backend) -> Self {
         Self {
             sessions: DashMap::new(),
-            backend
+            backend,
+            save_failures: AtomicUsize::new(0),
         }
     }
 
 
+    /// Number of sessions, which are loaded right now
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Number of failed session writes since the start
+    pub fn save_failures(&self) -> usize {
+        self.save_failures.load(Ordering::Relaxed)
+    }
+
+    fn track_save<T>(&self, res: anyhow::Result<T>) -> anyhow::Result<T> {
+        if res.is_err() {
+            self.save_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        res
+    }
+
+    /// Writes the changes of the claimed session, without closing it
+    pub async fn save_session(
+        &self,
+        session: &mut OwnedSession<Key, Backend::SessionData>,
+    ) -> anyhow::Result<()> {
+        let res = self.backend.flush(session.session.deref_mut()).await;
+        self.track_save(res)
+    }
+
     // TODO: create proper house-cleaning process here and document it
     fn clear_closed_session(&self) {
         let mut held_locks = vec![];
@@ -98,7 +135,8 @@ where
 
         let session = Arc::<tokio::sync::Mutex<<Backend as SessionBackend>::SessionData>>::try_unwrap(session.1).unwrap();
         let session_data = session.into_inner();
-        self.backend.save(session_data).await?;
+        let res = self.backend.save(session_data).await;
+        self.track_save(res)?;
 
 
         Ok(())
@@ -0,0 +1,231 @@
+use std::{sync::Mutex, time::Duration};
+
+use proto95::id::MapId;
+
+use super::clock::SharedClock;
+
+const fn mins(mins: u64) -> Duration {
+    Duration::from_secs(mins * 60)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportState {
+    /// The ship is docked and the waiting room is open
+    Boarding,
+    /// The ship is on its way and the passengers are in the transport field
+    Moving,
+    /// The ship arrived and the next boarding did not start yet
+    Waiting,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportEvent {
+    BoardingStarted,
+    Departed,
+    Arrived,
+}
+
+/// Ship or train, which departs from the station in a fixed interval
+#[derive(Debug, Clone)]
+pub struct TransportRoute {
+    pub name: &'static str,
+    /// Field the ship docks at
+    pub station: MapId,
+    pub waiting_room: MapId,
+    /// Field of the ship, while it is moving
+    pub transport: MapId,
+    pub destination: MapId,
+    pub interval: Duration,
+    /// Time before the departure, in which the ship can be boarded
+    pub boarding: Duration,
+    /// Time of the ride, the ship waits at the station between the arrival and the boarding
+    pub ride: Duration,
+}
+
+impl TransportRoute {
+    /// Time since the last departure, departures happen at multiples of the interval
+    fn phase(&self, now: Duration) -> Duration {
+        Duration::from_millis((now.as_millis() % self.interval.as_millis()) as u64)
+    }
+
+    pub fn state_at(&self, now: Duration) -> TransportState {
+        let phase = self.phase(now);
+        if phase < self.ride {
+            TransportState::Moving
+        } else if phase >= self.interval - self.boarding {
+            TransportState::Boarding
+        } else {
+            TransportState::Waiting
+        }
+    }
+
+    pub fn next_departure(&self, now: Duration) -> Duration {
+        self.interval - self.phase(now)
+    }
+
+    /// Events, which happen when the state changes
+    fn transition_events(prev: TransportState, next: TransportState) -> Vec<TransportEvent> {
+        use TransportState::*;
+        let mut events = Vec::new();
+        if prev == Moving && next != Moving {
+            events.push(TransportEvent::Arrived);
+        }
+        if prev != Boarding && next == Boarding {
+            events.push(TransportEvent::BoardingStarted);
+        }
+        if prev != Moving && next == Moving {
+            events.push(TransportEvent::Departed);
+        }
+        events
+    }
+}
+
+pub fn default_routes() -> Vec<TransportRoute> {
+    let boat = |name, station, waiting_room, transport, destination| TransportRoute {
+        name,
+        station: MapId(station),
+        waiting_room: MapId(waiting_room),
+        transport: MapId(transport),
+        destination: MapId(destination),
+        interval: mins(15),
+        boarding: mins(4),
+        ride: mins(10),
+    };
+    let train = |name, station, waiting_room, transport, destination| TransportRoute {
+        interval: mins(10),
+        ride: mins(5),
+        ..boat(name, station, waiting_room, transport, destination)
+    };
+
+    vec![
+        boat("Ellinia to Orbis", 101000300, 101000301, 200090010, 200000100),
+        boat("Orbis to Ellinia", 200000111, 200000112, 200090000, 101000300),
+        train("Orbis to Ludibrium", 200000121, 200000122, 200090100, 220000110),
+        train("Ludibrium to Orbis", 220000110, 220000111, 200090110, 200000100),
+    ]
+}
+
+/// Runs the schedules of all transports based on the clock
+#[derive(Debug)]
+pub struct TransportService {
+    routes: Vec<TransportRoute>,
+    clock: SharedClock,
+    states: Mutex<Vec<TransportState>>,
+}
+
+impl TransportService {
+    pub fn new(routes: Vec<TransportRoute>, clock: SharedClock) -> Self {
+        let now = clock.now();
+        let states = routes.iter().map(|route| route.state_at(now)).collect();
+        Self {
+            routes,
+            clock,
+            states: Mutex::new(states),
+        }
+    }
+
+    pub fn routes(&self) -> &[TransportRoute] {
+        &self.routes
+    }
+
+    pub fn state(&self, route: &TransportRoute) -> TransportState {
+        route.state_at(self.clock.now())
+    }
+
+    pub fn find_by_station(&self, map_id: MapId) -> Option<&TransportRoute> {
+        self.routes.iter().find(|route| route.station == map_id)
+    }
+
+    pub fn find_by_waiting_room(&self, map_id: MapId) -> Option<&TransportRoute> {
+        self.routes.iter().find(|route| route.waiting_room == map_id)
+    }
+
+    pub fn find_by_transport(&self, map_id: MapId) -> Option<&TransportRoute> {
+        self.routes.iter().find(|route| route.transport == map_id)
+    }
+
+    /// Events of all routes since the last poll
+    pub fn poll_events(&self) -> Vec<(&TransportRoute, TransportEvent)> {
+        let now = self.clock.now();
+        let mut states = self.states.lock().expect("Transport states");
+        let mut events = Vec::new();
+        for (route, state) in self.routes.iter().zip(states.iter_mut()) {
+            let next = route.state_at(now);
+            events.extend(
+                TransportRoute::transition_events(*state, next)
+                    .into_iter()
+                    .map(|event| (route, event)),
+            );
+            *state = next;
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::services::clock::MockClock;
+
+    use super::{default_routes, mins, TransportEvent, TransportService, TransportState};
+
+    #[test]
+    fn route_states() {
+        let boat = &default_routes()[0];
+        assert_eq!(boat.state_at(Duration::ZERO), TransportState::Moving);
+        assert_eq!(boat.state_at(mins(9)), TransportState::Moving);
+        assert_eq!(boat.state_at(mins(10)), TransportState::Waiting);
+        assert_eq!(boat.state_at(mins(11)), TransportState::Boarding);
+        assert_eq!(boat.state_at(mins(15)), TransportState::Moving);
+        assert_eq!(boat.next_departure(mins(12)), mins(3));
+
+        let train = &default_routes()[2];
+        assert_eq!(train.state_at(mins(5)), TransportState::Waiting);
+        assert_eq!(train.state_at(mins(6)), TransportState::Boarding);
+        assert_eq!(train.state_at(mins(10) + mins(4)), TransportState::Moving);
+
+        // Every route passes all states within an interval
+        for route in default_routes() {
+            let states: Vec<_> = (0..route.interval.as_secs())
+                .map(|secs| route.state_at(Duration::from_secs(secs)))
+                .collect();
+            for state in [
+                TransportState::Moving,
+                TransportState::Waiting,
+                TransportState::Boarding,
+            ] {
+                assert!(states.contains(&state), "{}: {state:?}", route.name);
+            }
+        }
+    }
+
+    #[test]
+    fn poll_events() {
+        let clock = Arc::new(MockClock::new(mins(1)));
+        let svc = TransportService::new(default_routes()[..1].to_vec(), clock.clone());
+        let route = &svc.routes()[0];
+        let poll = || -> Vec<_> { svc.poll_events().into_iter().map(|(_, ev)| ev).collect() };
+        assert!(poll().is_empty());
+
+        clock.set(mins(10));
+        assert_eq!(poll(), [TransportEvent::Arrived]);
+        assert_eq!(svc.state(route), TransportState::Waiting);
+        assert!(poll().is_empty());
+
+        clock.advance(mins(1));
+        assert_eq!(poll(), [TransportEvent::BoardingStarted]);
+        assert_eq!(svc.state(route), TransportState::Boarding);
+
+        clock.advance(mins(4));
+        assert_eq!(poll(), [TransportEvent::Departed]);
+        assert_eq!(svc.state(route), TransportState::Moving);
+
+        // A skipped waiting time still reports all events
+        clock.advance(mins(12));
+        assert_eq!(
+            poll(),
+            [TransportEvent::Arrived, TransportEvent::BoardingStarted]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+use data::services::helper::intentory::inv::InventoryType;
+use moople_packet::proto::CondOption;
+use proto95::{
+    game::user::UserStatChangeReq,
+    id::{ItemId, MapId},
+    shared::{
+        char::{CharStatChangedResp, CharStatPartial},
+        inventory::{UserPortableChairSitReq, UserSitReq, UserSitResultResp},
+    },
+};
+
+use crate::GameHandler;
+
+/// Interval of the natural recovery of the client
+pub const RECOVERY_INTERVAL: Duration = Duration::from_secs(10);
+/// Allowed deviation of the client timer
+const RECOVERY_INTERVAL_TOLERANCE: Duration = Duration::from_millis(500);
+
+/// Recovery per interval while standing
+pub const BASE_HP_RECOVERY: u32 = 10;
+pub const BASE_MP_RECOVERY: u32 = 3;
+/// Additional recovery per interval while sitting on a seat or a chair
+pub const SIT_HP_RECOVERY: u32 = 10;
+pub const SIT_MP_RECOVERY: u32 = 3;
+
+/// Seat or chair the character sits on and the time of the last recovery ticks
+#[derive(Debug)]
+pub struct SitState {
+    seat: Option<u16>,
+    chair: Option<ItemId>,
+    last_hp_recovery: Instant,
+    last_mp_recovery: Instant,
+}
+
+impl Default for SitState {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            seat: None,
+            chair: None,
+            last_hp_recovery: now,
+            last_mp_recovery: now,
+        }
+    }
+}
+
+impl SitState {
+    pub fn is_sitting(&self) -> bool {
+        self.seat.is_some() || self.chair.is_some()
+    }
+
+    /// Checks If a recovery tick is due, the tick is consumed If it is
+    fn take_tick(last: &mut Instant) -> bool {
+        if last.elapsed() + RECOVERY_INTERVAL_TOLERANCE < RECOVERY_INTERVAL {
+            return false;
+        }
+        *last = Instant::now();
+        true
+    }
+}
+
+impl GameHandler {
+    /// Upper bound of the recovery per tick as (hp, mp)
+    fn recovery_limit(&self) -> (u32, u32) {
+        let (mut hp, mut mp) = (BASE_HP_RECOVERY, BASE_MP_RECOVERY);
+        if self.sit.is_sitting() {
+            hp += SIT_HP_RECOVERY;
+            mp += SIT_MP_RECOVERY;
+        }
+        if let Some(chair) = self
+            .sit
+            .chair
+            .and_then(|chair| self.services.meta.get_item_data(chair))
+        {
+            hp += chair.recovery_hp;
+            mp += chair.recovery_mp;
+        }
+
+        let rate = self
+            .services
+            .meta
+            .get_field_recovery_rate(MapId(self.session.char.model.map_id as u32));
+        ((hp as f32 * rate) as u32, (mp as f32 * rate) as u32)
+    }
+
+    pub(crate) fn stand_up(&mut self) -> anyhow::Result<()> {
+        if self.sit.chair.take().is_some() {
+            self.field
+                .set_user_portable_chair(self.session.char.model.id, None)?;
+        }
+        self.sit.seat = None;
+        Ok(())
+    }
+
+    pub async fn handle_sit(&mut self, req: UserSitReq) -> anyhow::Result<()> {
+        if req.seat_id == UserSitReq::get_up().seat_id {
+            self.stand_up()?;
+            return self.send_pkt(UserSitResultResp {
+                seat_id: None.into(),
+            });
+        }
+
+        let has_seat = self
+            .field
+            .get_meta()
+            .seat
+            .as_ref()
+            .is_some_and(|seats| seats.contains_key(&(req.seat_id as i64)));
+        if !has_seat {
+            let pkt = self.enable_char();
+            return self.send_pkt(pkt);
+        }
+
+        self.stand_up()?;
+        self.sit.seat = Some(req.seat_id);
+        self.send_pkt(UserSitResultResp {
+            seat_id: Some(req.seat_id).into(),
+        })
+    }
+
+    pub async fn handle_portable_chair_sit(
+        &mut self,
+        req: UserPortableChairSitReq,
+    ) -> anyhow::Result<()> {
+        let chair_id = req.chair_id;
+        let has_chair = chair_id.is_chair()
+            && self
+                .session
+                .inv()
+                .get_stack_inventory(InventoryType::Misc)?
+                .iter()
+                .any(|(_, item)| item.item_id == chair_id);
+        if has_chair {
+            self.stand_up()?;
+            self.sit.chair = Some(chair_id);
+            self.field
+                .set_user_portable_chair(self.session.char.model.id, Some(chair_id))?;
+        }
+
+        let pkt = self.enable_char();
+        self.send_pkt(pkt)
+    }
+
+    /// The client sends the natural recovery, the amount is bounded by the
+    /// recovery limit and ticks which come too early are ignored
+    pub async fn handle_stat_change(&mut self, req: UserStatChangeReq) -> anyhow::Result<()> {
+        if self.session.char.is_dead() {
+            return Ok(());
+        }
+
+        let (hp_limit, mp_limit) = self.recovery_limit();
+        let mut stats = CharStatPartial::default();
+
+        if req.hp > 0 && SitState::take_tick(&mut self.sit.last_hp_recovery) {
+            self.session
+                .char
+                .update_hp(hp_limit.min(req.hp as u32) as i32);
+            stats.hp = CondOption(Some(self.session.char.model.hp as u32));
+        }
+        if req.mp > 0 && SitState::take_tick(&mut self.sit.last_mp_recovery) {
+            self.session
+                .char
+                .update_mp(mp_limit.min(req.mp as u32) as i32);
+            stats.mp = CondOption(Some(self.session.char.model.mp as u32));
+        }
+
+        if stats.hp.0.is_some() {
+            let char = &self.session.char.model;
+            self.field
+                .update_user_hp(char.id, char.hp as u32, char.max_hp as u32)?;
+        }
+
+        self.send_pkt(CharStatChangedResp {
+            excl: true,
+            stats: stats.into(),
+            secondary_stat: false,
+            battle_recovery: false,
+        })
+    }
+}
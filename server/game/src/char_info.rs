@@ -0,0 +1,216 @@
+use data::services::{
+    data::character::{CharacterID, GiveFameResult},
+    helper::intentory::inv::InventoryExt,
+};
+use moople_packet::proto::CondOption;
+use proto95::{
+    game::{
+        user::{
+            CharGivePopularityResult, GivePopularityDone, GivePopularityReceived,
+            UserGivePopularityReq,
+        },
+        CharacterInfoMedal, CharacterInfoPet, CharacterInfoReq, CharacterInfoResp,
+    },
+    id::{job_id::JobId, ItemId},
+    shared::{
+        char::{CharStatChangedResp, CharStatPartial},
+        inventory::EquippedSlot,
+    },
+};
+
+use crate::GameHandler;
+
+/// Minimum level to give fame to other characters
+pub const FAME_MIN_LEVEL: i32 = 15;
+
+impl GameHandler {
+    /// The medal is taken from the session for the own character,
+    /// for other characters the stored equipment is used
+    async fn medal_id(&self, char_id: CharacterID) -> anyhow::Result<ItemId> {
+        if char_id == self.session.char.model.id {
+            return Ok(self
+                .session
+                .inv()
+                .equipped
+                .get(EquippedSlot::Medal)
+                .map(|item| item.item.item_id)
+                .unwrap_or(ItemId(0)));
+        }
+
+        Ok(self
+            .services
+            .data
+            .item
+            .load_equipped_items(char_id)
+            .await?
+            .equipped
+            .into_iter()
+            .find(|(slot, _)| *slot == EquippedSlot::Medal)
+            .map(|(_, item_id)| item_id)
+            .unwrap_or(ItemId(0)))
+    }
+
+    async fn char_info_pets(
+        &self,
+        char_id: CharacterID,
+    ) -> anyhow::Result<Vec<(u8, CharacterInfoPet)>> {
+        let mut pets = Vec::new();
+        let summoned = self.field.get_user_pets(char_id).unwrap_or_default();
+        for (ix, pet) in summoned.iter().enumerate() {
+            let Some(pet) = pet else {
+                continue;
+            };
+            let Some(pet_item) = self
+                .services
+                .data
+                .item
+                .load_pet(pet.locker_id as i32)
+                .await?
+            else {
+                continue;
+            };
+
+            pets.push((
+                ix as u8 + 1,
+                CharacterInfoPet {
+                    tmpl_id: pet.tmpl_id,
+                    name: pet.name.clone(),
+                    level: pet_item.level as u8,
+                    tameness: pet_item.tameness as u16,
+                    fullness: pet_item.fullness as u8,
+                    skill: pet_item.skill as u16,
+                    equip_item_id: ItemId(0),
+                },
+            ));
+        }
+        Ok(pets)
+    }
+
+    pub async fn handle_char_info(&mut self, req: CharacterInfoReq) -> anyhow::Result<()> {
+        let char_id = req.char_id as CharacterID;
+        let char = if char_id == self.session.char.model.id {
+            self.session.char.model.clone()
+        } else {
+            let Some(char) = self.services.data.char.get(char_id).await? else {
+                let pkt = self.enable_char();
+                return self.send_pkt(pkt);
+            };
+            char
+        };
+
+        let pets = if req.pet_info {
+            self.char_info_pets(char_id).await?
+        } else {
+            Vec::new()
+        };
+        let wish_list = self
+            .services
+            .data
+            .cash_shop
+            .load_wish_list(char_id)
+            .await?
+            .into_iter()
+            .filter(|sn| *sn != 0)
+            .collect::<Vec<_>>();
+
+        //TODO guild, alliance, marriage and mount once they are supported
+        let resp = CharacterInfoResp {
+            char_id: req.char_id,
+            level: char.level as u8,
+            job: JobId::try_from(char.job as u16)?,
+            fame: char.fame as i16,
+            married: false,
+            guild_name: String::new(),
+            alliance_name: String::new(),
+            medal_info: 0,
+            pets: pets.into(),
+            taming_mob: None.into(),
+            wish_list: wish_list.into(),
+            medal: CharacterInfoMedal {
+                medal_id: self.medal_id(char_id).await?,
+                quests: Vec::new().into(),
+            },
+            chairs: Vec::new().into(),
+        };
+        self.send_pkt(resp)
+    }
+
+    pub async fn handle_give_popularity(
+        &mut self,
+        req: UserGivePopularityReq,
+    ) -> anyhow::Result<()> {
+        let target_id = req.target as CharacterID;
+        let char = &self.session.char.model;
+
+        // Only characters on the same field can receive fame
+        if target_id == char.id || !self.field.has_user(target_id) {
+            return self.send_pkt(CharGivePopularityResult::InvalidTarget(()));
+        }
+        if char.level < FAME_MIN_LEVEL {
+            return self.send_pkt(CharGivePopularityResult::LevelTooLow(()));
+        }
+
+        let res = self
+            .services
+            .data
+            .char
+            .give_fame(char.id, target_id, req.up)
+            .await?;
+        let fame = match res {
+            GiveFameResult::Done(fame) => fame,
+            GiveFameResult::AlreadyGivenToday => {
+                return self.send_pkt(CharGivePopularityResult::AlreadyGivenToday(()))
+            }
+            GiveFameResult::AlreadyGivenToTarget => {
+                return self.send_pkt(CharGivePopularityResult::AlreadyGivenToTarget(()))
+            }
+        };
+        // The new fame is stored, the session of the target reloads It
+        self.services.char_sync.mark_fame(target_id);
+
+        let target = self.services.data.char.must_get(target_id).await?;
+        self.send_pkt(CharGivePopularityResult::Done(GivePopularityDone {
+            target_name: target.name,
+            up: req.up,
+            fame: fame as u32,
+        }))?;
+
+        let giver_name = self.session.char.model.name.clone();
+        self.field
+            .send_to(
+                CharGivePopularityResult::Received(GivePopularityReceived {
+                    giver_name,
+                    up: req.up,
+                }),
+                target_id,
+            )
+            .await
+    }
+
+    /// Reloads the fame, after It was changed by another character
+    pub(crate) async fn update_fame(&mut self) -> anyhow::Result<()> {
+        let char_id = self.session.char.model.id;
+        if !self.services.char_sync.take_fame(char_id) {
+            return Ok(());
+        }
+        let fame = match self.services.data.char.must_get(char_id).await {
+            Ok(char) => char.fame,
+            Err(err) => {
+                // Keep the mark, so the next tick retries
+                self.services.char_sync.mark_fame(char_id);
+                return Err(err);
+            }
+        };
+        self.session.sync_fame(fame);
+        self.send_pkt(CharStatChangedResp {
+            excl: false,
+            stats: CharStatPartial {
+                fame: CondOption(Some(fame as u16)),
+                ..Default::default()
+            }
+            .into(),
+            secondary_stat: false,
+            battle_recovery: false,
+        })
+    }
+}
@@ -0,0 +1,216 @@
+use data::services::{
+    character::damage::{verify_hit_damage, HitDefense, HitSource},
+    helper::intentory::inv::{InventoryExt, InventoryType},
+    model::item::EquipStat,
+};
+use moople_packet::proto::CondOption;
+use proto95::{
+    game::user::UserHitReq,
+    id::{ItemId, MapId},
+    shared::{
+        char::{
+            CharSecondaryStatFlags, CharStatChangedResp, CharStatPartial, CharTempStatResetResp,
+        },
+        inventory::EquippedSlot,
+    },
+};
+use rand::seq::IteratorRandom;
+
+use crate::{mini_room::item_quantity_op, GameHandler};
+
+/// Portal type of the spawn points
+const SPAWN_PORTAL_TYPE: i64 = 0;
+/// Return map of fields, which return to themselves
+const NO_RETURN_MAP: u32 = 999999999;
+
+impl GameHandler {
+    /// Uses a safety charm, returns true If the character had one
+    fn use_safety_charm(&mut self) -> anyhow::Result<bool> {
+        let Some(slot) = self
+            .session
+            .inv()
+            .cash
+            .iter()
+            .find(|(_, item)| item.item_id == ItemId::SAFETY_CHARM)
+            .map(|(slot, _)| slot)
+        else {
+            return Ok(false);
+        };
+
+        let stack = self
+            .session
+            .inv_mut()
+            .cash
+            .get_mut(slot)
+            .expect("Safety charm");
+        stack.quantity -= 1;
+        stack.item.quantity -= 1;
+        stack.item.last_update = 1;
+        let left = stack.item.quantity;
+        if left == 0 {
+            self.session.inv_mut().cash.remove(slot);
+        }
+        self.send_inv_ops(vec![item_quantity_op(InventoryType::Cash, slot, left)])?;
+        Ok(true)
+    }
+
+    /// The character loses exp unless a safety charm protects it and all buffs are removed
+    async fn on_death(&mut self) -> anyhow::Result<()> {
+        self.stand_up()?;
+
+        if !self.use_safety_charm()? {
+            let town = self.field.get_meta().info.town == Some(1);
+            self.session.char.decrease_exp(town);
+        }
+
+        self.send_pkt(CharTempStatResetResp {
+            flags: CharSecondaryStatFlags::all(),
+        })?;
+        self.send_pkt(CharStatChangedResp {
+            excl: true,
+            stats: CharStatPartial {
+                hp: CondOption(Some(0)),
+                exp: CondOption(Some(self.session.char.model.exp as u32)),
+                ..Default::default()
+            }
+            .into(),
+            secondary_stat: false,
+            battle_recovery: false,
+        })?;
+
+        Ok(())
+    }
+
+    fn hit_defense(&self) -> HitDefense {
+        let char = &self.session.char.model;
+        let equip_stat = |stat: EquipStat| -> u32 {
+            self.session
+                .inv()
+                .equipped
+                .iter()
+                .map(|(_, item)| item.item.stats[stat.clone()] as u32)
+                .sum()
+        };
+
+        HitDefense {
+            level: char.level as u32,
+            avoid: char.dex as u32 / 4 + char.luk as u32 / 2 + equip_stat(EquipStat::Avoid),
+            wdef: equip_stat(EquipStat::WeaponDef),
+            mdef: equip_stat(EquipStat::MagicDef),
+            has_shield: self
+                .session
+                .inv()
+                .equipped
+                .get(EquippedSlot::Shield)
+                .is_some(),
+        }
+    }
+
+    /// Source of the hit, None If the mob is not on the field anymore
+    /// or the field has no obstacle data. A mob with another template is an invalid hit
+    fn hit_source(&self, req: &UserHitReq) -> anyhow::Result<Option<HitSource>> {
+        let Some(hit_mob) = req.mob.0.as_ref() else {
+            return Ok(self
+                .services
+                .meta
+                .get_field_obstacle_damage(self.field.field_id())
+                .map(HitSource::Obstacle));
+        };
+        let Some((tmpl_id, mob)) = self.field.get_mob_meta(hit_mob.mob_id) else {
+            return Ok(None);
+        };
+        if tmpl_id != hit_mob.mob_tmpl_id {
+            anyhow::bail!(
+                "Hit by mob {} with template {:?}, expected {tmpl_id:?}",
+                hit_mob.mob_id,
+                hit_mob.mob_tmpl_id
+            );
+        }
+
+        // Without attack data the attack is bounded like a touch
+        Ok(Some(
+            match req.mob_attack_ix().and_then(|ix| mob.attacks.get(ix)) {
+                Some(attack) => HitSource::MobAttack(mob, attack),
+                None => HitSource::MobTouch(mob),
+            },
+        ))
+    }
+
+    pub async fn handle_user_hit(&mut self, req: UserHitReq) -> anyhow::Result<()> {
+        if self.session.char.is_dead() {
+            return Ok(());
+        }
+        let dmg = match self.hit_source(&req)? {
+            Some(src) => {
+                let guard = req.mob.0.as_ref().is_some_and(|mob| mob.guard);
+                let hit = verify_hit_damage(
+                    src,
+                    &self.hit_defense(),
+                    req.dmg_internal,
+                    guard,
+                    &mut self.avoid_tracker,
+                    &mut rand::thread_rng(),
+                );
+                if hit.rejected_miss {
+                    log::warn!(
+                        "Rejected miss of {}, the client reports too many misses",
+                        self.session.char.model.name
+                    );
+                }
+                hit.dmg
+            }
+            // The mob might have died just before the hit, so the reported damage is taken,
+            // but every hit deals at least one damage and never more than the max hp
+            None => req
+                .dmg_internal
+                .clamp(1, self.session.char.model.max_hp.max(1) as u32),
+        };
+
+        // The stat update below corrects the hp of the client
+        self.session.char.update_hp(-(dmg as i32));
+        let char = &self.session.char.model;
+        self.field
+            .update_user_hp(char.id, char.hp as u32, char.max_hp as u32)?;
+
+        if self.session.char.is_dead() {
+            return self.on_death().await;
+        }
+
+        self.send_pkt(CharStatChangedResp {
+            excl: false,
+            stats: CharStatPartial {
+                hp: CondOption(Some(self.session.char.model.hp as u32)),
+                ..Default::default()
+            }
+            .into(),
+            secondary_stat: false,
+            battle_recovery: false,
+        })
+    }
+
+    /// Revives the character in the return map of the field at a random spawn point
+    pub(crate) fn revive(&mut self) -> anyhow::Result<()> {
+        let field = self.field.get_meta();
+        let return_map = match field.info.return_map {
+            Some(map) if map as u32 != NO_RETURN_MAP => MapId(map as u32),
+            _ => MapId(self.session.char.model.map_id as u32),
+        };
+
+        let spawn_point = self
+            .services
+            .meta
+            .get_field_data(return_map)
+            .ok_or_else(|| anyhow::format_err!("Invalid return map: {return_map:?}"))?
+            .portal
+            .iter()
+            .filter(|(_, portal)| portal.pt == SPAWN_PORTAL_TYPE)
+            .map(|(id, _)| *id)
+            .choose(&mut rand::thread_rng())
+            .unwrap_or_default();
+
+        self.session
+            .char
+            .revive(return_map.0 as i32, spawn_point as i32);
+        Ok(())
+    }
+}
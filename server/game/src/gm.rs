@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use data::services::{
+    character::MAX_LEVEL, data::ban::BanInfo, field::FieldData, gm::GmAction, online::OnlineChar,
+};
+use proto95::{
+    id::job_id::JobId,
+    shared::char::{CharStatChangedResp, CharStatPartial},
+};
+
+use crate::{repl::Stat, GameHandler};
+
+impl GameHandler {
+    /// Character and its field, the message is returned If it's not online
+    fn find_online_char(&self, name: &str) -> Result<(OnlineChar, Arc<FieldData>), String> {
+        self.services
+            .online
+            .find_by_name(name)
+            .and_then(|char| {
+                let field = self.services.field.find_user_field(char.id)?;
+                Some((char, field))
+            })
+            .ok_or_else(|| format!("{name} is not online"))
+    }
+
+    pub(crate) async fn gm_warp_to(&mut self, name: &str) -> anyhow::Result<Option<String>> {
+        let (_, field) = match self.find_online_char(name) {
+            Ok(found) => found,
+            Err(msg) => return Ok(Some(msg)),
+        };
+        match field.instance() {
+            Some(instance) => self.enter_instance(instance.id, field.field_id()).await?,
+            None => self.warp(field.field_id(), 0).await?,
+        }
+        Ok(None)
+    }
+
+    pub(crate) async fn gm_summon(&mut self, name: &str) -> anyhow::Result<Option<String>> {
+        if self.field.instance().is_some() {
+            return Ok(Some(
+                "Characters can't be summoned into an instance".to_string(),
+            ));
+        }
+        let (char, _) = match self.find_online_char(name) {
+            Ok(found) => found,
+            Err(msg) => return Ok(Some(msg)),
+        };
+        self.services
+            .gm
+            .push_action(char.id, GmAction::Warp(self.field.field_id()));
+        Ok(None)
+    }
+
+    pub(crate) async fn gm_heal(&mut self, name: &str) -> anyhow::Result<Option<String>> {
+        let (char, _) = match self.find_online_char(name) {
+            Ok(found) => found,
+            Err(msg) => return Ok(Some(msg)),
+        };
+        self.services.gm.push_action(char.id, GmAction::Heal);
+        Ok(None)
+    }
+
+    /// Bans the account of the character and optionally the machine of its last login,
+    /// online characters are disconnected
+    pub(crate) async fn gm_ban(
+        &mut self,
+        name: &str,
+        days: Option<u32>,
+        reason_code: u8,
+        reason: Option<String>,
+        ban_machine: bool,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(char) = self.services.data.char.get_by_name(name).await? else {
+            return Ok(Some(format!("No character named {name}")));
+        };
+        if char.acc_id == self.session.acc.id {
+            return Ok(Some("The own account can't be banned".to_string()));
+        }
+
+        let machine_id = if ban_machine {
+            let account = &self.services.data.account;
+            let Some(machine_id) = account.get_last_machine_id(char.acc_id).await? else {
+                return Ok(Some(format!("No machine is recorded for {name}")));
+            };
+            Some(machine_id)
+        } else {
+            None
+        };
+        let until = days.map(|days| Utc::now().naive_utc() + Duration::days(days as i64));
+        let info = BanInfo {
+            reason_code,
+            reason,
+            until,
+            machine_id,
+            ..Default::default()
+        };
+        self.services.ban_account(char.acc_id, info).await?;
+        Ok(Some(format!("Banned {name}")))
+    }
+
+    pub(crate) async fn gm_unban(&mut self, name: &str) -> anyhow::Result<Option<String>> {
+        let Some(char) = self.services.data.char.get_by_name(name).await? else {
+            return Ok(Some(format!("No character named {name}")));
+        };
+        let ended = self.services.data.ban.unban_account(char.acc_id).await?;
+        Ok(Some(format!("Ended {ended} bans of {name}")))
+    }
+
+    /// The characters of the field re-join it on their next tick
+    pub(crate) fn gm_reload_field(&mut self) -> Option<String> {
+        if self.field.instance().is_some() {
+            return Some("Instances can't be reloaded".to_string());
+        }
+        let map = self.field.field_id();
+        let field = self.services.field.reload_field(map)?;
+        for char_id in field.users() {
+            self.services.gm.push_action(char_id, GmAction::Warp(map));
+        }
+        None
+    }
+
+    pub(crate) fn heal(&mut self) -> anyhow::Result<()> {
+        let char = &mut self.session.char.model;
+        char.hp = char.max_hp;
+        char.mp = char.max_mp;
+        self.field
+            .update_user_hp(char.id, char.hp as u32, char.max_hp as u32)?;
+
+        let stats = CharStatPartial {
+            hp: Some(char.hp as u32).into(),
+            mp: Some(char.mp as u32).into(),
+            ..Default::default()
+        };
+        self.send_pkt(CharStatChangedResp {
+            excl: true,
+            stats: stats.into(),
+            secondary_stat: false,
+            battle_recovery: false,
+        })
+    }
+
+    pub(crate) fn set_stat(&mut self, stat: Stat, value: u32) -> anyhow::Result<Option<String>> {
+        let char = &mut self.session.char.model;
+        let mut stats = CharStatPartial::default();
+        // The values are clamped to what the stats can hold
+        let short = value.min(u16::MAX as u32) as u16;
+        let int = value.min(i32::MAX as u32);
+        match stat {
+            Stat::Level => {
+                char.level = (int as i32).clamp(1, MAX_LEVEL);
+                stats.level = Some(char.level as u8).into();
+            }
+            Stat::Job => {
+                let Ok(job) = JobId::try_from(short) else {
+                    return Ok(Some(format!("Invalid job: {value}")));
+                };
+                char.job = short as i32;
+                stats.job = Some(job).into();
+            }
+            Stat::Str => {
+                char.str = short as i32;
+                stats.str = Some(short).into();
+            }
+            Stat::Dex => {
+                char.dex = short as i32;
+                stats.dex = Some(short).into();
+            }
+            Stat::Int => {
+                char.int = short as i32;
+                stats.int = Some(short).into();
+            }
+            Stat::Luk => {
+                char.luk = short as i32;
+                stats.luk = Some(short).into();
+            }
+            Stat::Hp => {
+                char.hp = (int as i32).min(char.max_hp);
+                stats.hp = Some(char.hp as u32).into();
+            }
+            Stat::MaxHp => {
+                char.max_hp = int as i32;
+                char.hp = char.hp.min(char.max_hp);
+                stats.maxhp = Some(int).into();
+                stats.hp = Some(char.hp as u32).into();
+            }
+            Stat::Mp => {
+                char.mp = (int as i32).min(char.max_mp);
+                stats.mp = Some(char.mp as u32).into();
+            }
+            Stat::MaxMp => {
+                char.max_mp = int as i32;
+                char.mp = char.mp.min(char.max_mp);
+                stats.maxmp = Some(int).into();
+                stats.mp = Some(char.mp as u32).into();
+            }
+            Stat::Ap => {
+                char.ap = short as i32;
+                stats.ap = Some(short).into();
+            }
+            Stat::Sp => {
+                char.sp = short as i32;
+                stats.sp = Some(short).into();
+            }
+            Stat::Exp => {
+                char.exp = int as i32;
+                stats.exp = Some(int).into();
+            }
+            Stat::Fame => {
+                char.fame = short as i32;
+                stats.fame = Some(short).into();
+            }
+            Stat::Mesos => {
+                char.mesos = int as i32;
+                stats.money = Some(int).into();
+            }
+        }
+
+        if stats.hp.is_some() {
+            self.field
+                .update_user_hp(char.id, char.hp as u32, char.max_hp as u32)?;
+        }
+        self.send_pkt(CharStatChangedResp {
+            excl: true,
+            stats: stats.into(),
+            secondary_stat: false,
+            battle_recovery: false,
+        })?;
+        Ok(None)
+    }
+
+    /// Runs the actions, which GMs applied to this character
+    pub(crate) async fn update_gm_actions(&mut self) -> anyhow::Result<()> {
+        let actions = self.services.gm.take_actions(self.session.char.model.id);
+        for action in actions {
+            match action {
+                GmAction::Warp(map) => self.warp(map, 0).await?,
+                GmAction::Heal => self.heal()?,
+                GmAction::Kick => self.sess_handle.ct.cancel(),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use data::services::field_instance::FieldInstanceId;
+use proto95::id::MapId;
+
+use crate::GameHandler;
+
+impl GameHandler {
+    /// Joins the field of the map, maps of the current instance are joined within the instance
+    pub(crate) async fn join_map(&mut self, map_id: MapId) -> anyhow::Result<()> {
+        let instance = self
+            .field
+            .instance()
+            .filter(|instance| !instance.is_closed() && instance.contains_map(map_id));
+
+        let char_id = self.session.char.model.id;
+        let (avatar_data, session) = (self.avatar_data.clone(), self.sess_handle.clone());
+        self.field = match instance {
+            Some(instance) => {
+                self.services
+                    .field
+                    .join_field_instance(char_id, avatar_data, session, map_id, instance.id)
+                    .await?
+            }
+            None => {
+                self.services
+                    .field
+                    .join_field(char_id, avatar_data, session, map_id)
+                    .await?
+            }
+        };
+        if self.hidden {
+            self.field
+                .set_user_hidden(self.session.char.model.id, true)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the character to the spawn point of the map
+    pub(crate) async fn warp(&mut self, map_id: MapId, spawn_point: u8) -> anyhow::Result<()> {
+        self.session.char.model.map_id = map_id.0 as i32;
+        self.session.char.model.spawn_point = spawn_point as i32;
+        self.join_map(map_id).await?;
+
+        let pkt = self.set_field();
+        self.send_pkt(pkt)?;
+        self.spawn_pets()
+    }
+
+    pub async fn enter_instance(
+        &mut self,
+        instance_id: FieldInstanceId,
+        map_id: MapId,
+    ) -> anyhow::Result<()> {
+        self.session.char.model.map_id = map_id.0 as i32;
+        self.session.char.model.spawn_point = 0;
+        self.field = self
+            .services
+            .field
+            .join_field_instance(
+                self.session.char.model.id,
+                self.avatar_data.clone(),
+                self.sess_handle.clone(),
+                map_id,
+                instance_id,
+            )
+            .await?;
+        if self.hidden {
+            self.field.set_user_hidden(self.session.char.model.id, true)?;
+        }
+
+        let pkt = self.set_field();
+        self.send_pkt(pkt)?;
+        self.spawn_pets()
+    }
+
+    /// Returns the character from a closed instance
+    pub(crate) async fn update_instance(&mut self) -> anyhow::Result<()> {
+        let Some(instance) = self.field.instance() else {
+            return Ok(());
+        };
+        if instance.is_closed() {
+            self.warp(instance.forced_return, 0).await?;
+        }
+        Ok(())
+    }
+}
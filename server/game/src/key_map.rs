@@ -0,0 +1,80 @@
+use proto95::game::{
+    keymaps::{
+        FuncKeyMapInitResp, FuncKeyMappedModifiedReq, PetConsumeItemInitResp,
+        PetConsumeMpItemInitResp, QuickSlotKeyMappedModifiedReq, QuickSlotMappedInitResp,
+    },
+    macros::{MacroSysDataInitResp, MacroSysDataModifiedReq},
+};
+
+use crate::GameHandler;
+
+impl GameHandler {
+    /// Sends the stored key map, quick slots, macros and pet items, the client
+    /// falls back to its defaults for everything that was never modified
+    pub(crate) async fn init_key_map(&mut self) -> anyhow::Result<()> {
+        let char_id = self.session.char.model.id;
+        let key_map = &self.services.data.key_map;
+
+        let key_bindings = key_map.load_key_map(char_id).await?;
+        let quick_slots = key_map.load_quick_slots(char_id).await?;
+        let macros = key_map.load_macros(char_id).await?;
+        let (pet_hp_item, pet_mp_item) = key_map.load_pet_consume_items(char_id).await?;
+
+        self.send_pkt(FuncKeyMapInitResp {
+            key_bindings: key_bindings.into(),
+        })?;
+        self.send_pkt(QuickSlotMappedInitResp {
+            keys: quick_slots.into(),
+        })?;
+        self.send_pkt(MacroSysDataInitResp {
+            data: macros.into(),
+        })?;
+        self.send_pkt(PetConsumeItemInitResp {
+            item_id: pet_hp_item,
+        })?;
+        self.send_pkt(PetConsumeMpItemInitResp {
+            item_id: pet_mp_item,
+        })
+    }
+
+    pub async fn handle_func_key_mapped_modified(
+        &mut self,
+        req: FuncKeyMappedModifiedReq,
+    ) -> anyhow::Result<()> {
+        let char_id = self.session.char.model.id;
+        let key_map = &self.services.data.key_map;
+        match req {
+            FuncKeyMappedModifiedReq::KeyChange(changes) => {
+                key_map.update_key_map(char_id, &changes.items).await
+            }
+            FuncKeyMappedModifiedReq::PetConsumeItem(item_id) => {
+                key_map.save_pet_consume_item(char_id, item_id, false).await
+            }
+            FuncKeyMappedModifiedReq::PetConsumeMpItem(item_id) => {
+                key_map.save_pet_consume_item(char_id, item_id, true).await
+            }
+        }
+    }
+
+    pub async fn handle_quick_slot_modified(
+        &mut self,
+        req: QuickSlotKeyMappedModifiedReq,
+    ) -> anyhow::Result<()> {
+        self.services
+            .data
+            .key_map
+            .save_quick_slots(self.session.char.model.id, &req.keys)
+            .await
+    }
+
+    pub async fn handle_macro_sys_data_modified(
+        &mut self,
+        req: MacroSysDataModifiedReq,
+    ) -> anyhow::Result<()> {
+        self.services
+            .data
+            .key_map
+            .save_macros(self.session.char.model.id, &req.data.items)
+            .await
+    }
+}
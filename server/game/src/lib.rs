@@ -1,16 +1,33 @@
+pub mod chair;
+pub mod char_info;
+pub mod death;
+pub mod gm;
+pub mod instance;
+pub mod key_map;
+pub mod mini_game;
+pub mod mini_room;
+pub mod party_quest;
+pub mod pet;
+pub mod reactor;
 pub mod repl;
 pub mod state;
+pub mod transport;
 
-use std::ops::Neg;
 use std::sync::Arc;
 
-use std::{net::IpAddr, time::Duration};
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 
 use data::entities::character;
+use data::services::character::damage::AvoidTracker;
 use data::services::field::FieldJoinHandle;
 use data::services::helper::pool::drop::{DropLeaveParam, DropTypeValue};
+use data::services::mini_room::SharedMiniRoom;
+use data::services::online::OnlineChar;
 use data::services::session::session_data::OwnedMoopleSession;
 use data::services::session::{ClientKey, MoopleMigrationKey};
 use data::services::SharedServices;
@@ -35,44 +52,50 @@ use moople_net::{
 
 use moople_packet::EncodePacket;
 
-use moople_packet::proto::list::{MapleIndexList8, MapleIndexListZ};
+use moople_packet::proto::list::MapleIndexList8;
 use moople_packet::proto::partial::PartialFlag;
 use moople_packet::proto::time::MapleExpiration;
-use moople_packet::proto::CondOption;
 use moople_packet::{
-    proto::{
-        list::{MapleIndexListZ16, MapleIndexListZ8},
-        time::MapleTime,
-        MapleList16,
-    },
+    proto::{time::MapleTime, MapleList16},
     DecodePacket, HasOpcode, MaplePacket, MaplePacketReader, MaplePacketWriter,
 };
 
 use data::services::helper::pool::Drop;
 
+use proto95::cash_shop::UserMigrateToCashShopReq;
+use proto95::game::drop::DropId;
+use proto95::game::mini_room::{EntrustedShopReq, MiniRoomReq, StoreBankReq};
 use proto95::game::mob::{MobMoveCtrlAckResp, MobMoveReq};
+use proto95::game::npc::{NpcMoveReq, NpcSpecialActionReq, UserSelectNpcReq};
+use proto95::game::party_quest::{UserRequestPQRewardReq, UserSelectPQRewardReq};
+use proto95::game::pet::{
+    PetActionReq, PetActivateReq, PetDropPickUpReq, PetFoodItemUseReq, PetInteractionReq,
+    PetMoveReq,
+};
+use proto95::game::reactor::{ReactorHitReq, ReactorTouchReq};
 use proto95::game::user::{
-    ChangeSkillRecordResp, UpdatedSkillRecord, UserDropMoneyReq, UserDropPickUpReq, UserHitReq,
-    UserMeleeAttackReq, UserSkillUpReq, UserStatChangeReq,
+    ChangeSkillRecordResp, UpdatedSkillRecord, UserDropMoneyReq, UserDropPickUpReq,
+    UserGivePopularityReq, UserHitReq, UserMeleeAttackReq, UserSkillUpReq, UserStatChangeReq,
 };
 
 use proto95::id::{FaceId, HairId, ItemId, Skin};
-use proto95::shared::char::{AvatarData, AvatarEquips, PetIds, SkillInfo, TeleportRockInfo};
+use proto95::shared::char::{AvatarData, AvatarEquips, PetIds};
 use proto95::shared::movement::Movement;
 use proto95::shared::{FootholdId, PongReq, Vec2};
-use proto95::shared::inventory::InvChangeSlotPosReq;
+use proto95::shared::inventory::{InvChangeSlotPosReq, UserPortableChairSitReq, UserSitReq};
 use proto95::{
     game::{
         chat::{ChatMsgReq, UserChatMsgResp},
         field::{
-            CrcSeed, LogoutGiftConfig, NotificationList, SetFieldCharData, SetFieldResp,
-            SetFieldResult,
+            ContiStateReq, CrcSeed, FieldObstacleStatusReq, LogoutGiftConfig, NotificationList,
+            SetFieldCharData, SetFieldResp, SetFieldResult, SetPassengerResultReq,
         },
         friend::{FriendList, FriendResultResp},
-        keymaps::FuncKeyMapInitResp,
+        keymaps::{FuncKeyMappedModifiedReq, QuickSlotKeyMappedModifiedReq},
+        macros::MacroSysDataModifiedReq,
         user::{UserMoveReq, UserPortalScriptReq, UserTransferFieldReq},
-        BroadcastMessageResp, ClaimSvrStatusChangedResp, CtxSetGenderResp, MigrateCommandResp,
-        MigrateInGameReq, TransferChannelReq,
+        BroadcastMessageResp, CharacterInfoReq, ClaimSvrStatusChangedResp, CtxSetGenderResp,
+        MigrateCommandResp, MigrateInGameReq, TransferChannelReq,
     },
     id::MapId,
     login::world::{ChannelId, WorldId},
@@ -80,19 +103,22 @@ use proto95::{
     send_opcodes::SendOpcodes,
     shared::{
         char::{
-            CharDataAll, CharDataEquipped, CharDataFlagsAll, CharDataHeader, CharDataStat,
-            CharStatChangedResp, CharStatPartial,
+            CharDataAll, CharDataFlagsAll, CharDataHeader, CharStatChangedResp, CharStatPartial,
         },
-        item::Item,
         UpdateScreenSettingReq,
     },
 };
+use chair::SitState;
+use pet::PetSlots;
 use repl::GameRepl;
 use tokio::net::TcpStream;
 
 pub type GameResponse<T> = ResponsePacket<SendOpcodes, T>;
 pub type GameResult<T> = Result<GameResponse<T>, anyhow::Error>;
 
+/// Time between the writes of the changed session state
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Debug, Clone)]
 pub struct MakeGameHandler {
     services: SharedServices,
@@ -152,6 +178,14 @@ pub struct GameHandler {
     repl: GameRepl,
     packet_buf: PacketBuffer,
     avatar_data: AvatarData,
+    mini_room: Option<SharedMiniRoom>,
+    pets: PetSlots,
+    sit: SitState,
+    /// Hidden from the other characters by a GM command
+    hidden: bool,
+    /// Misses the client reported, to reject too frequent misses
+    avoid_tracker: AvoidTracker,
+    last_save: Instant,
 }
 
 impl GameHandler {
@@ -180,7 +214,7 @@ impl GameHandler {
 
         dbg!(MoopleMigrationKey::new(req.client_key, addr));
 
-        let session = services
+        let mut session = services
             .session_manager
             .claim_migration_session(MoopleMigrationKey::new(req.client_key, addr))
             .await?;
@@ -191,15 +225,31 @@ impl GameHandler {
             session.char.model.name
         );
 
+        session.channel_id = channel_id;
+        services.online.login(OnlineChar {
+            id: session.char.model.id,
+            acc_id: session.acc.id,
+            name: session.char.model.name.clone(),
+            world_id,
+            channel_id,
+        });
         let avatar_data = map_char_to_avatar(&session.char.model);
 
+        // Characters saved within an instance are returned from it
+        let map_id = MapId(session.char.model.map_id as u32);
+        let join_map = services.field.get_join_map(map_id);
+        if join_map != map_id {
+            session.char.model.map_id = join_map.0 as i32;
+            session.char.model.spawn_point = 0;
+        }
+
         let join_field = services
             .field
             .join_field(
                 session.char.model.id,
                 avatar_data.clone(),
                 sess_handle.clone(),
-                MapId(session.char.model.map_id as u32),
+                join_map,
             )
             .await?;
 
@@ -217,6 +267,12 @@ impl GameHandler {
             repl: GameRepl::new(),
             avatar_data,
             packet_buf: PacketBuffer::new(),
+            mini_room: None,
+            pets: PetSlots::default(),
+            sit: SitState::default(),
+            hidden: false,
+            avoid_tracker: AvoidTracker::default(),
+            last_save: Instant::now(),
         })
     }
 }
@@ -244,22 +300,55 @@ impl MapleSessionHandler for GameHandler {
             UserPortalScriptReq => GameHandler::handle_portal_script,
             UserTransferFieldReq => GameHandler::handle_field_transfer,
             TransferChannelReq => GameHandler::handle_channel_transfer,
+            UserMigrateToCashShopReq => GameHandler::handle_migrate_to_cash_shop,
             UserDropPickUpReq => GameHandler::handle_drop_pick_up,
             UserDropMoneyReq => GameHandler::handle_drop_money,
             MobMoveReq => GameHandler::handle_mob_move,
+            NpcMoveReq => GameHandler::handle_npc_move,
+            NpcSpecialActionReq => GameHandler::handle_npc_special_action,
             UserMeleeAttackReq => GameHandler::handle_melee_attack,
             UserSkillUpReq => GameHandler::handle_skill_up,
             UserHitReq => GameHandler::handle_user_hit,
             UserStatChangeReq => GameHandler::handle_stat_change,
             InvChangeSlotPosReq => GameHandler::handle_inv_change_slot,
+            UserSitReq => GameHandler::handle_sit,
+            UserPortableChairSitReq => GameHandler::handle_portable_chair_sit,
+            MiniRoomReq => GameHandler::handle_mini_room,
+            EntrustedShopReq => GameHandler::handle_entrusted_shop,
+            StoreBankReq => GameHandler::handle_store_bank,
+            PetActivateReq => GameHandler::handle_pet_activate,
+            PetMoveReq => GameHandler::handle_pet_move,
+            PetActionReq => GameHandler::handle_pet_action,
+            PetInteractionReq => GameHandler::handle_pet_interaction,
+            PetFoodItemUseReq => GameHandler::handle_pet_food,
+            PetDropPickUpReq => GameHandler::handle_pet_drop_pick_up,
+            CharacterInfoReq => GameHandler::handle_char_info,
+            UserGivePopularityReq => GameHandler::handle_give_popularity,
+            FuncKeyMappedModifiedReq => GameHandler::handle_func_key_mapped_modified,
+            QuickSlotKeyMappedModifiedReq => GameHandler::handle_quick_slot_modified,
+            MacroSysDataModifiedReq => GameHandler::handle_macro_sys_data_modified,
+            ReactorHitReq => GameHandler::handle_reactor_hit,
+            ReactorTouchReq => GameHandler::handle_reactor_touch,
+            UserSelectNpcReq => GameHandler::handle_select_npc,
+            UserRequestPQRewardReq => GameHandler::handle_request_pq_reward,
+            UserSelectPQRewardReq => GameHandler::handle_select_pq_reward,
+            ContiStateReq => GameHandler::handle_conti_state,
+            SetPassengerResultReq => GameHandler::handle_set_passenger_result,
+            FieldObstacleStatusReq => GameHandler::handle_field_obstacle_status,
         );
 
         Ok(handler(self, session, packet.into_reader()).await?)
     }
 
-    async fn finish(self, is_migrating: bool) -> Result<(), Self::Error> {
+    async fn finish(mut self, is_migrating: bool) -> Result<(), Self::Error> {
         log::info!("Finishing game session...");
+        self.services.online.logout(self.session.char.model.id);
+        if let Err(err) = self.leave_mini_room().await {
+            log::error!("Unable to leave mini room: {err}");
+        }
         if is_migrating {
+            // Persist the state before the session is handed to the next server
+            self.save().await;
             self.services
                 .session_manager
                 .migrate_session(
@@ -277,6 +366,7 @@ impl MapleSessionHandler for GameHandler {
     }
 }
 
+#[async_trait]
 impl MapleServerSessionHandler for GameHandler {
     fn get_ping_interval() -> std::time::Duration {
         Duration::from_secs(30)
@@ -287,68 +377,71 @@ impl MapleServerSessionHandler for GameHandler {
         pw.write_opcode(SendOpcodes::AliveReq);
         Ok(pw.into_packet())
     }
-}
-
-impl GameHandler {
-    async fn handle_user_hit(&mut self, req: UserHitReq) -> GameResult<CharStatChangedResp> {
-        self.session.char.update_hp((req.dmg_internal as i32).neg());
-
-        let stats = CharStatPartial {
-            hp: CondOption(Some(self.session.char.model.hp.try_into().unwrap())),
-            ..Default::default()
-        };
-
-        _ = self
-            .services
-            .data
-            .char
-            .save_char(self.session.char.model.clone().into());
 
-        Ok(CharStatChangedResp {
-            excl: false,
-            stats: stats.into(),
-            secondary_stat: false,
-            battle_recovery: false,
+    async fn handle_tick(&mut self) -> Result<(), Self::Error> {
+        // A failed update is retried with the next tick instead of closing the session
+        if let Err(err) = self.update_gm_actions().await {
+            log::error!("Unable to apply the gm actions: {err:?}");
         }
-        .into())
+        if let Err(err) = self.update_fame().await {
+            log::error!("Unable to update the fame: {err:?}");
+        }
+        if let Err(err) = self.update_instance().await {
+            log::error!("Unable to update the instance: {err:?}");
+        }
+        if let Err(err) = self.update_transport().await {
+            log::error!("Unable to update the transport: {err:?}");
+        }
+        if let Err(err) = self.update_pets() {
+            log::error!("Unable to update the pets: {err:?}");
+        }
+        if self.last_save.elapsed() >= AUTOSAVE_INTERVAL {
+            self.save().await;
+        }
+        Ok(())
     }
+}
 
-    async fn handle_stat_change(
-        &mut self,
-        req: UserStatChangeReq,
-    ) -> GameResult<CharStatChangedResp> {
-        self.session.char.update_hp(req.hp as i32);
-        self.session.char.update_mp(req.mp as i32);
-
-        let stats = CharStatPartial {
-            hp: CondOption(Some(self.session.char.model.hp.try_into().unwrap())),
-            mp: CondOption(Some(self.session.char.model.mp.try_into().unwrap())),
-            ..Default::default()
-        };
-
-        _ = self
+impl GameHandler {
+    /// Writes the changed session state, failures are retried with the next save
+    async fn save(&mut self) {
+        self.last_save = Instant::now();
+        if let Err(err) = self
             .services
-            .data
-            .char
-            .save_char(self.session.char.model.clone().into());
-
-        Ok(CharStatChangedResp {
-            excl: false,
-            stats: stats.into(),
-            secondary_stat: false,
-            battle_recovery: false,
+            .session_manager
+            .save_session(&mut self.session)
+            .await
+        {
+            log::error!("Unable to save the session: {err:?}");
         }
-        .into())
     }
 
     async fn handle_inv_change_slot(&mut self, req: InvChangeSlotPosReq) -> anyhow::Result<()>  {
         Ok(())
     }
 
+    fn send_pkt<T: EncodePacket + HasOpcode>(&mut self, pkt: T) -> anyhow::Result<()> {
+        let mut pw = MaplePacketWriter::default();
+        pw.write_opcode(T::OPCODE);
+        pkt.encode_packet(&mut pw)?;
+        self.sess_handle.tx.try_send(&pw.into_packet().data)?;
+        Ok(())
+    }
+
     async fn handle_pong(&mut self, _req: PongReq) -> anyhow::Result<PongResponse> {
         Ok(PongResponse)
     }
 
+    async fn handle_field_obstacle_status(
+        &mut self,
+        _req: FieldObstacleStatusReq,
+    ) -> anyhow::Result<()> {
+        if let Some(status) = self.field.obstacle_status() {
+            self.send_pkt(status)?;
+        }
+        Ok(())
+    }
+
     async fn handle_skill_up(&mut self, req: UserSkillUpReq) -> GameResult<ChangeSkillRecordResp> {
         Ok(ChangeSkillRecordResp {
             reset_excl: true,
@@ -390,7 +483,7 @@ impl GameHandler {
     async fn init_char(&mut self, sess: &mut MapleSession<TcpStream>) -> anyhow::Result<()> {
         sess.send_packet(FriendResultResp::Reset3(FriendList::empty()))
             .await?;
-        sess.send_packet(FuncKeyMapInitResp::default_map()).await?;
+        self.init_key_map().await?;
         sess.send_packet(ClaimSvrStatusChangedResp { connected: true })
             .await?;
         sess.send_packet(CtxSetGenderResp {
@@ -402,82 +495,14 @@ impl GameHandler {
             .await?;
 
         sess.send_packet(self.enable_char()).await?;
+        self.spawn_pets()?;
 
         Ok(())
     }
 
     fn set_field(&mut self) -> SetFieldResp {
-        let char = &self.session.char;
-
-        let equipped: MapleIndexListZ16<Item> = self
-            .session
-            .inv
-            .equipped
-            .iter()
-            .map(|(slot, item)| (slot as u16, Item::Equip(item.item.as_ref().into())))
-            .collect();
-
-        let etc: MapleIndexListZ8<Item> = self
-            .session
-            .inv
-            .etc
-            .iter()
-            .map(|(slot, item)| (slot as u8 + 1, Item::Stack(item.item.as_ref().into())))
-            .collect();
-
-        let invsize = [
-            char.model.equip_slots as u8,
-            char.model.use_slots as u8,
-            char.model.setup_slots as u8,
-            char.model.etc_slots as u8,
-            char.model.cash_slots as u8,
-        ];
-
-        let char_equipped = CharDataEquipped {
-            equipped,
-            ..Default::default()
-        };
-
-        let skill_records: MapleList16<SkillInfo> = self
-            .session
-            .skills
-            .iter()
-            .map(|(id, skill)| SkillInfo {
-                id: *id,
-                level: skill.skill_level as u32,
-                expiration: skill.expires_at.into(),
-                master_level: skill.master_level as u32,
-            })
-            .collect();
-
-        let char_stat: &character::Model = &char.model.clone().into();
-
-        let char_data = CharDataAll {
-            stat: CharDataStat {
-                stat: char_stat.into(),
-                friend_max: 30,
-                linked_character: None.into(),
-            },
-            money: char.model.mesos as u32,
-            invsize,
-            equipextslotexpiration: MapleExpiration::never(),
-            equipped: char_equipped,
-            useinv: MapleIndexListZ::default(),
-            setupinv: MapleIndexListZ::default(),
-            etcinv: etc,
-            cashinv: MapleIndexListZ::default(),
-            skillrecords: skill_records,
-            skllcooltime: MapleList16::default(),
-            quests: MapleList16::default(),
-            questscompleted: MapleList16::default(),
-            minigamerecords: MapleList16::default(),
-            socialrecords: MapleList16::default(),
-            teleportrockinfo: TeleportRockInfo::default(),
-            newyearcards: MapleList16::default(),
-            questrecordsexpired: MapleList16::default(),
-            questcompleteold: MapleList16::default(),
-            visitorquestloginfo: MapleList16::default(),
-        };
+        self.sit = SitState::default();
+        let char_data = CharDataAll::from(&*self.session);
 
         let char_data = SetFieldCharData {
             notifications: NotificationList::default(),
@@ -536,15 +561,46 @@ impl GameHandler {
         &mut self,
         req: UserDropPickUpReq,
     ) -> GameResult<CharStatChangedResp> {
-        dbg!(&req);
-        self.field
-            .remove_drop(
-                req.drop_id,
-                DropLeaveParam::UserPickup(self.session.char.model.id as u32),
-            )?;
+        self.pick_up_drop(
+            req.drop_id,
+            DropLeaveParam::UserPickup(self.session.char.model.id as u32),
+        )?;
         Ok(self.enable_char().into())
     }
 
+    /// Picks up the drop for the user or one of the pets, drops
+    /// owned by other users or which don't fit into the inventory are left
+    pub(crate) fn pick_up_drop(
+        &mut self,
+        drop_id: DropId,
+        param: DropLeaveParam,
+    ) -> anyhow::Result<()> {
+        let drop = self.field.pick_up_drop(
+            drop_id,
+            self.session.char.model.id,
+            param,
+            |drop| match drop.value {
+                DropTypeValue::Mesos(_) => true,
+                DropTypeValue::Item(item_id) => self.session.inv().has_free_slot(item_id),
+            },
+        )?;
+        let Some(drop) = drop else {
+            return Ok(());
+        };
+
+        match drop.value {
+            DropTypeValue::Mesos(mesos) => {
+                let mesos = self.session.char.model.mesos.saturating_add(mesos as i32);
+                self.update_mesos(mesos)?;
+            }
+            DropTypeValue::Item(item_id) => {
+                let item = self.new_inv_item(item_id, drop.quantity as u16)?;
+                self.add_inv_items(vec![item])?;
+            }
+        }
+        Ok(())
+    }
+
     async fn handle_drop_money(
         &mut self,
         req: UserDropMoneyReq,
@@ -556,13 +612,14 @@ impl GameHandler {
                 start_pos: self.pos,
                 value: DropTypeValue::Mesos(req.money),
                 quantity: 1,
+                spawned_at: Instant::now(),
             })?;
         Ok(self.enable_char().into())
     }
 
     async fn handle_chat_msg(&mut self, req: ChatMsgReq) -> anyhow::Result<()> {
-        let admin = false;
-        if let Some(s) = req.msg.strip_prefix('@') {
+        let admin = self.session.acc.gm_level > 0;
+        if let Some(s) = req.msg.strip_prefix('@').filter(|_| admin) {
             let repl_resp = self.handle_repl(s).await?;
             let Some(msg) = repl_resp else {
                 return Ok(())
@@ -606,6 +663,18 @@ impl GameHandler {
         .into())
     }
 
+    async fn handle_npc_move(&mut self, req: NpcMoveReq) -> anyhow::Result<()> {
+        self.field.update_npc_pos(req, self.session.char.model.id)
+    }
+
+    async fn handle_npc_special_action(
+        &mut self,
+        req: NpcSpecialActionReq,
+    ) -> anyhow::Result<()> {
+        self.field
+            .update_npc_special_action(req.id, req.action, self.session.char.model.id)
+    }
+
     async fn handle_portal_script(
         &mut self,
         _req: UserPortalScriptReq,
@@ -613,87 +682,44 @@ impl GameHandler {
         Ok(self.enable_char().into())
     }
 
-    async fn handle_field_transfer(
-        &mut self,
-        req: UserTransferFieldReq,
-    ) -> GameResult<SetFieldResp> {
-        if self.session.char.model.hp.le(&0) {
-            let return_map =
-                MapId(self.field.get_meta().info.return_map.unwrap_or_default() as u32);
-
-            self.session.char.model.hp = 1;
-            self.session.char.model.mp = 1;
-            self.session.char.model.map_id = return_map.0 as i32;
-            self.session.char.model.spawn_point = self
-                .services
-                .meta
-                .get_field_data(return_map)
-                .unwrap()
-                .portal
-                .first_key_value()
-                .map(|(k, _)| *k)
-                .unwrap_or_default() as i32;
-
-        self.field = self
-            .services
-            .field
-            .join_field(
-                self.session.char.model.id,
-                self.avatar_data.clone(),
-                self.sess_handle.clone(),
-                MapId(self.session.char.model.map_id as u32),
-            )
-            .await?;
-
-            self.field = self
-                .services
-                .field
-                .join_field(
-                    self.session.char.model.id,
-                    self.avatar_data.clone(),
-                    self.sess_handle.clone(),
-                    MapId(self.session.char.model.map_id as u32),
-                )
-                .await?;
-
-            Ok(self.set_field().into())
-        } else {
-            let portal = self
-                .field
-                .get_meta()
-                .portal
-                .values()
-                .find(|p| p.pn == req.portal)
-                .ok_or_else(|| anyhow::format_err!("Invalid portal"))?;
-
-            // TODO(!) tm should be an option as mapid 999999 is invalid
-            let map_id = MapId(portal.tm as u32);
-            self.session.char.model.map_id = map_id.0 as i32;
-            self.session.char.model.spawn_point = self
-                .services
-                .meta
-                .get_field_data(map_id)
-                .unwrap()
-                .portal
-                .iter()
-                .find(|(_, p)| p.pn == portal.tn)
-                .map(|(id, _)| *id as u8)
-                .unwrap_or(0) as i32;
-
-            self.field = self
-                .services
-                .field
-                .join_field(
-                    self.session.char.model.id,
-                    self.avatar_data.clone(),
-                    self.sess_handle.clone(),
-                    MapId(self.session.char.model.map_id as u32),
-                )
-                .await?;
+    async fn handle_field_transfer(&mut self, req: UserTransferFieldReq) -> anyhow::Result<()> {
+        if self.session.char.is_dead() {
+            self.revive()?;
+            let char = &self.session.char.model;
+            return self
+                .warp(MapId(char.map_id as u32), char.spawn_point as u8)
+                .await;
+        }
 
-            let transfer_field = self.set_field();
-            Ok(transfer_field.into())
+        let portal = self
+            .field
+            .get_meta()
+            .portal
+            .values()
+            .find(|p| p.pn == req.portal)
+            .ok_or_else(|| anyhow::format_err!("Invalid portal"))?;
+
+        // TODO(!) tm should be an option as mapid 999999 is invalid
+        let map_id = MapId(portal.tm as u32);
+        // Locked maps of the instance, like uncleared stages, can't be entered yet
+        if let Some(instance) = self.field.instance() {
+            if instance.contains_map(map_id) && !instance.can_enter(map_id) {
+                let pkt = self.enable_char();
+                return self.send_pkt(pkt);
+            }
         }
+
+        let spawn_point = self
+            .services
+            .meta
+            .get_field_data(map_id)
+            .unwrap()
+            .portal
+            .iter()
+            .find(|(_, p)| p.pn == portal.tn)
+            .map(|(id, _)| *id as u8)
+            .unwrap_or(0);
+        self.warp(map_id, spawn_point).await
     }
 
     async fn handle_movement(&mut self, req: UserMoveReq) -> anyhow::Result<()> {
@@ -728,6 +754,24 @@ impl GameHandler {
 
         Ok(MigrateResponse(pkt))
     }
+
+    async fn handle_migrate_to_cash_shop(
+        &mut self,
+        _req: UserMigrateToCashShopReq,
+    ) -> anyhow::Result<MigrateResponse<ResponsePacket<SendOpcodes, MigrateCommandResp>>> {
+        let addr = self
+            .services
+            .server_info
+            .get_cash_shop_addr(self.world_id)?;
+
+        let pkt: ResponsePacket<_, _> = MigrateCommandResp {
+            unknown: true,
+            addr: addr.try_into()?,
+        }
+        .into_response(MigrateCommandResp::OPCODE);
+
+        Ok(MigrateResponse(pkt))
+    }
 }
 
 pub fn map_char_to_avatar(char: &character::Model) -> AvatarData {
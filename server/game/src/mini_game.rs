@@ -0,0 +1,318 @@
+use data::services::mini_room::{
+    game::{game_id, MiniGame, MiniGameOutcome, MiniGameRecord, OPPONENT_IX},
+    MiniRoom, MiniRoomLocation, SharedMiniRoom, OWNER_IX,
+};
+use proto95::{
+    game::mini_room::{
+        MemoryGameTurn, MemoryGameTurnReq, MiniGameStart, MiniRoomEnterError, MiniRoomGameCreate,
+        MiniRoomLeaveReason, MiniRoomResp, MiniRoomType, OmokMoveReq,
+    },
+    id::{ItemId, MapId},
+};
+
+use crate::GameHandler;
+
+impl GameHandler {
+    /// Record of the character for the given game type
+    pub(crate) fn mini_game_record(&self, ty: MiniRoomType) -> MiniGameRecord {
+        self.session
+            .mini_game_records
+            .iter()
+            .find(|record| record.game_id as u32 == game_id(ty))
+            .map(MiniGameRecord::from)
+            .unwrap_or_default()
+    }
+
+    /// Records are written by whoever ends a game, so they are reloaded from the db
+    pub(crate) async fn reload_mini_game_records(&mut self) -> anyhow::Result<()> {
+        self.session.mini_game_records = self
+            .services
+            .data
+            .char
+            .load_mini_game_records(self.session.char.model.id)
+            .await?;
+        Ok(())
+    }
+
+    /// Index of the user in the current game room
+    async fn mini_game_ix(&self, room: &SharedMiniRoom) -> anyhow::Result<u8> {
+        let r = room.lock().await;
+        if r.game.is_none() {
+            anyhow::bail!("Room is not a game room");
+        }
+        r.user_ix(self.session.char.model.id)
+            .ok_or_else(|| anyhow::format_err!("Not in the room"))
+    }
+
+    pub(crate) async fn create_mini_game(
+        &mut self,
+        ty: MiniRoomType,
+        req: MiniRoomGameCreate,
+    ) -> anyhow::Result<()> {
+        let permit = match ty {
+            MiniRoomType::Omok => ItemId(ItemId::MINI_GAME_BASE.0 + req.game_spec as u32),
+            _ => ItemId::MATCH_CARDS,
+        };
+        let has_permit = self
+            .session
+            .inv()
+            .etc
+            .iter()
+            .any(|(_, item)| item.item_id == permit);
+        if !has_permit {
+            anyhow::bail!("Missing mini game permit: {permit:?}");
+        }
+
+        let owner = self.mini_room_user(ty);
+        let location = MiniRoomLocation {
+            map_id: MapId(self.session.char.model.map_id as u32),
+            pos: self.pos,
+            fh: self.fh,
+        };
+        let password = req.private.then(|| req.password.0.clone()).flatten();
+        let room = self.services.mini_room.create(|sn| {
+            let mut room = MiniRoom::new_game(
+                sn,
+                MiniGame::new(ty, req.game_spec),
+                req.title,
+                location,
+                permit,
+                owner.clone(),
+            );
+            room.password = password;
+            room
+        });
+
+        let mut r = room.lock().await;
+        let ix = r
+            .enter(owner, self.sess_handle.clone())
+            .map_err(|err| anyhow::format_err!("Unable to enter own game: {err:?}"))?;
+        let result = r.enter_result(ix);
+        self.update_mini_room_balloon(&r)?;
+        drop(r);
+
+        self.mini_room = Some(room);
+        self.send_pkt(MiniRoomResp::EnterResult(result))
+    }
+
+    /// Broadcasts the result, stores the records of both players and
+    /// removes the players who wanted to leave after the game
+    async fn finish_mini_game(
+        &mut self,
+        room: &SharedMiniRoom,
+        outcome: MiniGameOutcome,
+    ) -> anyhow::Result<()> {
+        let mut r = room.lock().await;
+        let Some(finish) = r.finish_game(outcome) else {
+            anyhow::bail!("Unable to finish the game");
+        };
+        r.broadcast(MiniRoomResp::MgResult(finish.result), -1)?;
+
+        for (char_id, record) in finish.records.iter() {
+            self.services
+                .data
+                .char
+                .save_mini_game_record(*char_id, r.ty, record)
+                .await?;
+        }
+
+        let char_id = self.session.char.model.id;
+        for ix in finish.leave {
+            let Some(user_id) = r.user(ix).map(|u| u.char_id) else {
+                continue;
+            };
+            if ix == OWNER_IX {
+                r.close(MiniRoomLeaveReason::HostOut);
+                self.services.mini_room.remove(r.sn);
+                self.field.set_user_mini_room(user_id, None)?;
+                break;
+            }
+            r.leave(user_id, MiniRoomLeaveReason::UserRequest);
+        }
+        self.update_mini_room_balloon(&r)?;
+
+        let left = r.user_ix(char_id).is_none() || (r.is_owner(char_id) && !r.owner_present());
+        drop(r);
+
+        self.reload_mini_game_records().await?;
+        if left {
+            self.mini_room = None;
+        }
+        Ok(())
+    }
+
+    /// A player leaving a running game loses it
+    pub(crate) async fn give_up_mini_game(&mut self, room: &SharedMiniRoom) -> anyhow::Result<()> {
+        let ix = self.mini_game_ix(room).await?;
+        let outcome = {
+            let mut r = room.lock().await;
+            let game = r.game.as_mut().unwrap();
+            if !game.is_running() || ix > OPPONENT_IX {
+                return Ok(());
+            }
+            game.give_up(ix)?
+        };
+        self.finish_mini_game(room, outcome).await
+    }
+
+    pub(crate) async fn handle_mini_game_ready(&mut self, ready: bool) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let mut r = room.lock().await;
+        r.game.as_mut().unwrap().set_ready(ix, ready)?;
+        let pkt = if ready {
+            MiniRoomResp::MgReady(())
+        } else {
+            MiniRoomResp::MgCancelReady(())
+        };
+        r.broadcast(pkt, -1)
+    }
+
+    pub(crate) async fn handle_mini_game_start(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let mut r = room.lock().await;
+        let game = r.game.as_mut().unwrap();
+        let first_turn = game.start(ix, &mut rand::thread_rng())?;
+        let cards = game.cards().map(|cards| cards.to_vec().into());
+        r.broadcast(
+            MiniRoomResp::MgStart(MiniGameStart {
+                first_turn,
+                cards: cards.into(),
+            }),
+            -1,
+        )?;
+        self.update_mini_room_balloon(&r)
+    }
+
+    pub(crate) async fn handle_omok_move(&mut self, req: OmokMoveReq) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let outcome = {
+            let mut r = room.lock().await;
+            let (pkt, outcome) = r.game.as_mut().unwrap().omok_move(ix, req.x, req.y)?;
+            r.broadcast(MiniRoomResp::OmokMove(pkt), -1)?;
+            outcome
+        };
+
+        match outcome {
+            Some(outcome) => self.finish_mini_game(&room, outcome).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn handle_memory_game_turn(
+        &mut self,
+        req: MemoryGameTurnReq,
+    ) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let outcome = {
+            let mut r = room.lock().await;
+            let (pkt, outcome) =
+                r.game
+                    .as_mut()
+                    .unwrap()
+                    .memory_game_turn(ix, req.first, req.card_ix)?;
+            // The client flips its own first card
+            let src = match pkt {
+                MemoryGameTurn::First(_) => self.session.char.model.id,
+                MemoryGameTurn::Second(_) => -1,
+            };
+            r.broadcast(MiniRoomResp::MemoryGameTurn(pkt), src)?;
+            outcome
+        };
+
+        match outcome {
+            Some(outcome) => self.finish_mini_game(&room, outcome).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn handle_mini_game_skip(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let mut r = room.lock().await;
+        let next = r.game.as_mut().unwrap().skip(ix)?;
+        r.broadcast(MiniRoomResp::MgSkip(next), -1)
+    }
+
+    pub(crate) async fn handle_mini_game_tie_request(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let mut r = room.lock().await;
+        r.game.as_mut().unwrap().request_tie(ix)?;
+        let other = if ix == OWNER_IX {
+            OPPONENT_IX
+        } else {
+            OWNER_IX
+        };
+        r.send_to(other, MiniRoomResp::MgTieRequest(())).await
+    }
+
+    pub(crate) async fn handle_mini_game_tie_answer(&mut self, accept: bool) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let outcome = {
+            let mut r = room.lock().await;
+            let outcome = r.game.as_mut().unwrap().answer_tie(ix, accept)?;
+            if outcome.is_none() {
+                let other = if ix == OWNER_IX {
+                    OPPONENT_IX
+                } else {
+                    OWNER_IX
+                };
+                r.send_to(other, MiniRoomResp::MgTieDenied(())).await?;
+            }
+            outcome
+        };
+
+        match outcome {
+            Some(outcome) => self.finish_mini_game(&room, outcome).await,
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn handle_mini_game_give_up(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let outcome = room.lock().await.game.as_mut().unwrap().give_up(ix)?;
+        self.finish_mini_game(&room, outcome).await
+    }
+
+    pub(crate) async fn handle_mini_game_leave_after_game(
+        &mut self,
+        leave: bool,
+    ) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        room.lock()
+            .await
+            .game
+            .as_mut()
+            .unwrap()
+            .set_leave_after_game(ix, leave)?;
+        self.send_pkt(if leave {
+            MiniRoomResp::MgLeaveAfterGame(())
+        } else {
+            MiniRoomResp::MgCancelLeave(())
+        })
+    }
+
+    /// The owner can kick the opponent while no game is running
+    pub(crate) async fn handle_mini_game_ban(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let ix = self.mini_game_ix(&room).await?;
+        let mut r = room.lock().await;
+        if ix != OWNER_IX || r.game.as_ref().unwrap().is_running() {
+            drop(r);
+            return self.send_mini_room_error(MiniRoomEnterError::PermissionDenied);
+        }
+
+        let Some(opponent) = r.user(OPPONENT_IX).map(|u| u.char_id) else {
+            return Ok(());
+        };
+        r.leave(opponent, MiniRoomLeaveReason::Kicked);
+        self.update_mini_room_balloon(&r)
+    }
+}
@@ -0,0 +1,1025 @@
+use data::{
+    entities::character,
+    services::{
+        data::{character::CharacterID, shop::EntrustedShopData},
+        helper::{
+            intentory::inv::{InventoryExt, InventorySet, InventoryType},
+            pool::employee::Employee,
+        },
+        mini_room::{
+            shop::{PlayerShop, ShopItem, ShopItemKind, MAX_SHOP_ITEMS},
+            MiniRoom, MiniRoomLocation, MiniRoomUser, SharedMiniRoom, OWNER_IX,
+        },
+        model::item::{EquipItem, StackItem},
+        SharedServices,
+    },
+};
+use proto95::{
+    game::mini_room::{
+        EntrustedShopCheckResultResp, EntrustedShopReq, MiniRoomChatReq, MiniRoomCreateReq,
+        MiniRoomEnterError, MiniRoomEnterReq, MiniRoomEnterResult, MiniRoomLeaveReason,
+        MiniRoomReq, MiniRoomResp, MiniRoomShopCreate, MiniRoomType, MiniRoomUserChat,
+        ShopAddSoldItem, ShopBuyItemReq, ShopBuyResult, ShopMoveItemToInvReq, ShopPutItemReq,
+        StoreBankItems, StoreBankReq, StoreBankResp,
+    },
+    id::{ItemId, MapId},
+    shared::{
+        char::{CharStatChangedResp, CharStatPartial},
+        inventory::{
+            InvOpAdd, InvOpRemove, InvOpUpdateQuantity, InventoryOperation, InventoryOperationsResp,
+        },
+    },
+};
+
+use crate::{map_char_to_avatar, GameHandler, GameResult};
+
+/// Fredrick, the npc managing the store bank
+const STORE_BANK_NPC: u32 = 9030000;
+
+/// Respawns all hired merchants which were open when the server stopped
+pub async fn spawn_entrusted_shops(services: &SharedServices) -> anyhow::Result<()> {
+    for shop in services.data.shop.load_open_shops().await? {
+        let char = services.data.char.must_get(shop.char_id).await?;
+        let owner = MiniRoomUser {
+            char_id: char.id,
+            name: char.name.clone(),
+            job: char.job as u16,
+            avatar: map_char_to_avatar(&char),
+            game_record: None,
+        };
+        spawn_entrusted_shop(services, owner, shop)?;
+    }
+
+    Ok(())
+}
+
+fn spawn_entrusted_shop(
+    services: &SharedServices,
+    owner: MiniRoomUser,
+    data: EntrustedShopData,
+) -> anyhow::Result<()> {
+    let location = MiniRoomLocation {
+        map_id: data.map_id,
+        pos: data.pos,
+        fh: data.fh,
+    };
+    let owner_name = owner.name.clone();
+    let room = services.mini_room.create(|sn| {
+        let mut room = MiniRoom::new_shop(
+            sn,
+            MiniRoomType::EntrustedShop,
+            data.title,
+            location,
+            data.permit,
+            owner,
+        );
+        room.shop = data.shop;
+        room.open = data.open;
+        room
+    });
+
+    let room = room.try_lock()?;
+    services
+        .field
+        .get_field(location.map_id)?
+        .add_employee(Employee {
+            sn: room.sn,
+            tmpl_id: room.permit,
+            pos: location.pos,
+            fh: location.fh,
+            owner_name,
+            balloon: room.employee_balloon(),
+        })
+}
+
+pub(crate) fn item_quantity_op(
+    inv_type: InventoryType,
+    slot: usize,
+    quantity: u16,
+) -> InventoryOperation {
+    let inv_type = inv_type.into();
+    let pos = slot as u16 + 1;
+    if quantity == 0 {
+        InventoryOperation::Remove(InvOpRemove { inv_type, pos })
+    } else {
+        InventoryOperation::UpdateQuantity(InvOpUpdateQuantity {
+            inv_type,
+            pos,
+            quantity,
+        })
+    }
+}
+
+/// Adds the item to the inventory, the item is returned If there's no space left
+fn add_item_to_inv(
+    inv: &mut InventorySet,
+    item: ShopItemKind,
+) -> Result<InventoryOperation, ShopItemKind> {
+    let proto_item = item.to_proto();
+    let (inv_type, slot) = match item {
+        ShopItemKind::Equip(equip) => inv
+            .try_add_equip(equip)
+            .map(|slot| (InventoryType::Equip, slot))
+            .map_err(ShopItemKind::Equip)?,
+        ShopItemKind::Stack(stack) => inv.try_add_stack(stack).map_err(ShopItemKind::Stack)?,
+    };
+
+    Ok(InventoryOperation::Add(InvOpAdd {
+        inv_type: inv_type.into(),
+        pos: slot as u16 + 1,
+        item: proto_item,
+    }))
+}
+
+impl GameHandler {
+    pub(crate) fn mini_room_user(&self, ty: MiniRoomType) -> MiniRoomUser {
+        let char = &self.session.char.model;
+        MiniRoomUser {
+            char_id: char.id,
+            name: char.name.clone(),
+            job: char.job as u16,
+            avatar: self.avatar_data.clone(),
+            game_record: ty.is_game().then(|| self.mini_game_record(ty)),
+        }
+    }
+
+    pub(crate) fn current_mini_room(&self) -> anyhow::Result<SharedMiniRoom> {
+        self.mini_room
+            .clone()
+            .ok_or_else(|| anyhow::format_err!("Not in a mini room"))
+    }
+
+    /// Checks whether the user is still in the room, as the room might have been closed by the owner
+    async fn is_in_mini_room(&mut self) -> bool {
+        let Some(room) = self.mini_room.clone() else {
+            return false;
+        };
+
+        let r = room.lock().await;
+        let in_room = r
+            .user_ix(self.session.char.model.id)
+            .is_some_and(|ix| ix != OWNER_IX || r.owner_present());
+        let is_game = r.game.is_some();
+        drop(r);
+
+        if !in_room {
+            self.mini_room = None;
+            // Records might have changed while in the room
+            if is_game {
+                if let Err(err) = self.reload_mini_game_records().await {
+                    log::error!("Unable to reload mini game records: {err}");
+                }
+            }
+        }
+        in_room
+    }
+
+    pub(crate) fn send_mini_room_error(&mut self, err: MiniRoomEnterError) -> anyhow::Result<()> {
+        self.send_pkt(MiniRoomResp::EnterResult(MiniRoomEnterResult::Error(err)))
+    }
+
+    pub(crate) fn send_inv_ops(&mut self, ops: Vec<InventoryOperation>) -> anyhow::Result<()> {
+        self.send_pkt(InventoryOperationsResp {
+            reset_excl: true,
+            operations: ops.into(),
+            secondary_stat_changed: false,
+        })
+    }
+
+    pub(crate) fn update_mesos(&mut self, mesos: i32) -> anyhow::Result<()> {
+        self.session.char.model.mesos = mesos;
+        self.send_pkt(CharStatChangedResp {
+            excl: true,
+            stats: CharStatPartial {
+                money: Some(mesos as u32).into(),
+                ..Default::default()
+            }
+            .into(),
+            secondary_stat: false,
+            battle_recovery: false,
+        })
+    }
+
+    /// Adds the item to the inventory, the item is returned If there's no space left
+    pub(crate) fn add_inv_item(
+        &mut self,
+        item: ShopItemKind,
+    ) -> Result<InventoryOperation, ShopItemKind> {
+        add_item_to_inv(self.session.inv_mut(), item)
+    }
+
+    /// Adds all items to the inventory, items which don't fit are returned
+    pub(crate) fn add_inv_items(
+        &mut self,
+        items: Vec<ShopItemKind>,
+    ) -> anyhow::Result<Vec<ShopItemKind>> {
+        let mut ops = Vec::new();
+        let mut left = Vec::new();
+        for item in items {
+            match self.add_inv_item(item) {
+                Ok(op) => ops.push(op),
+                Err(item) => left.push(item),
+            }
+        }
+
+        if !ops.is_empty() {
+            self.send_inv_ops(ops)?;
+        }
+        Ok(left)
+    }
+
+    /// Creates a new item, equips are created with the base stats of the item
+    pub(crate) fn new_inv_item(
+        &self,
+        item_id: ItemId,
+        quantity: u16,
+    ) -> anyhow::Result<ShopItemKind> {
+        Ok(
+            if InventoryType::from_item_id(item_id) == Some(InventoryType::Equip) {
+                let meta = self
+                    .services
+                    .meta
+                    .get_eq_data(item_id)
+                    .ok_or_else(|| anyhow::format_err!("Invalid item: {item_id:?}"))?;
+                ShopItemKind::Equip(EquipItem::from_item_id(item_id, meta))
+            } else {
+                ShopItemKind::Stack(StackItem::from_item_id(item_id, quantity))
+            },
+        )
+    }
+
+    /// Checks whether the inventory holds at least the quantity of the stack item
+    pub(crate) fn has_inv_items(&self, item_id: ItemId, quantity: usize) -> anyhow::Result<bool> {
+        let inv_type = InventoryType::from_item_id(item_id)
+            .filter(|ty| ty.is_stack())
+            .ok_or_else(|| anyhow::format_err!("Not a stack item: {item_id:?}"))?;
+        let count: usize = self
+            .session
+            .inv()
+            .get_stack_inventory(inv_type)?
+            .iter()
+            .filter(|(_, item)| item.item_id == item_id)
+            .map(|(_, item)| item.quantity)
+            .sum();
+        Ok(count >= quantity)
+    }
+
+    /// Takes the quantity of the item out of the inventory,
+    /// nothing is taken If there are not enough items
+    pub(crate) fn take_inv_items(
+        &mut self,
+        item_id: ItemId,
+        quantity: usize,
+    ) -> anyhow::Result<bool> {
+        let inv_type = InventoryType::from_item_id(item_id)
+            .filter(|ty| ty.is_stack())
+            .ok_or_else(|| anyhow::format_err!("Not a stack item: {item_id:?}"))?;
+        let inv = self.session.inv_mut().get_stack_inventory_mut(inv_type)?;
+        let stacks: Vec<(usize, usize)> = inv
+            .iter()
+            .filter(|(_, item)| item.item_id == item_id)
+            .map(|(slot, item)| (slot, item.quantity))
+            .collect();
+        if stacks.iter().map(|(_, n)| n).sum::<usize>() < quantity {
+            return Ok(false);
+        }
+
+        let mut left = quantity;
+        let mut ops = Vec::new();
+        for (slot, stack_quantity) in stacks {
+            if left == 0 {
+                break;
+            }
+            let taken = left.min(stack_quantity);
+            left -= taken;
+            let rest = stack_quantity - taken;
+            if rest == 0 {
+                inv.remove(slot);
+            } else {
+                let stack = inv.get_mut(slot).expect("Stack");
+                stack.quantity = rest;
+                stack.item.quantity = rest as u16;
+                stack.item.last_update = 1;
+            }
+            ops.push(item_quantity_op(inv_type, slot, rest as u16));
+        }
+
+        self.send_inv_ops(ops)?;
+        Ok(true)
+    }
+
+    /// Takes the bundles for the shop out of the inventory
+    fn take_shop_item(&mut self, req: &ShopPutItemReq) -> anyhow::Result<ShopItem> {
+        let inv_type = InventoryType::try_from(req.inv_type)?;
+        let slot = (req.slot as usize)
+            .checked_sub(1)
+            .ok_or_else(|| anyhow::format_err!("Invalid slot"))?;
+        let slots = match inv_type {
+            InventoryType::Equip => self.session.inv().equip.slots(),
+            ty => self.session.inv().get_stack_inventory(ty)?.slots(),
+        };
+        if slot >= slots {
+            anyhow::bail!("Invalid slot: {slot}");
+        }
+        let is_equip = inv_type == InventoryType::Equip;
+        ShopItem::check_bundles(is_equip, req.bundles, req.bundle_size)?;
+        if req.price == 0 {
+            anyhow::bail!("Invalid shop item price");
+        }
+        let quantity = req.bundles as u32 * req.bundle_size as u32;
+
+        let (item, op) = match inv_type {
+            InventoryType::Equip => {
+                let item = self
+                    .session
+                    .inv_mut()
+                    .equip
+                    .remove(slot)
+                    .ok_or_else(|| anyhow::format_err!("No item in slot {slot}"))?;
+                (
+                    ShopItemKind::Equip(*item.item),
+                    item_quantity_op(inv_type, slot, 0),
+                )
+            }
+            _ => {
+                let inv = self.session.inv_mut().get_stack_inventory_mut(inv_type)?;
+                let stack = inv
+                    .get_mut(slot)
+                    .ok_or_else(|| anyhow::format_err!("No item in slot {slot}"))?;
+                if quantity == 0 || quantity > stack.quantity as u32 {
+                    anyhow::bail!("Invalid shop item quantity: {quantity}");
+                }
+
+                if quantity == stack.quantity as u32 {
+                    let item = inv.remove(slot).expect("Stack item");
+                    (
+                        ShopItemKind::Stack(*item.item),
+                        item_quantity_op(inv_type, slot, 0),
+                    )
+                } else {
+                    let quantity = quantity as u16;
+                    let mut split = stack.item.as_ref().clone();
+                    split.db_id = None;
+                    split.quantity = quantity;
+                    stack.quantity -= quantity as usize;
+                    stack.item.quantity -= quantity;
+                    stack.item.last_update = 1;
+                    let left = stack.item.quantity;
+                    (
+                        ShopItemKind::Stack(split),
+                        item_quantity_op(inv_type, slot, left),
+                    )
+                }
+            }
+        };
+
+        let item = ShopItem::new(item, req.bundles, req.bundle_size, req.price)?;
+        self.send_inv_ops(vec![op])?;
+        Ok(item)
+    }
+
+    /// Closed shops of the character, the items of an open merchant stay with the merchant
+    async fn store_bank_shops(&self, char_id: CharacterID) -> anyhow::Result<Vec<PlayerShop>> {
+        Ok(self
+            .services
+            .data
+            .shop
+            .find_shops(char_id)
+            .await?
+            .into_iter()
+            .filter(|shop| !shop.open)
+            .map(|shop| shop.shop)
+            .collect())
+    }
+
+    async fn has_entrusted_shop(&self) -> anyhow::Result<bool> {
+        let char_id = self.session.char.model.id;
+        Ok(self
+            .services
+            .mini_room
+            .find_entrusted_shop(char_id)
+            .await
+            .is_some()
+            || !self.services.data.shop.find_shops(char_id).await?.is_empty())
+    }
+
+    /// Updates the balloon of an opened room for the whole field
+    pub(crate) fn update_mini_room_balloon(&self, room: &MiniRoom) -> anyhow::Result<()> {
+        if !room.open {
+            return Ok(());
+        }
+
+        match room.ty {
+            MiniRoomType::EntrustedShop => {
+                self.field.update_employee_balloon(room.employee_balloon())
+            }
+            ty => self
+                .field
+                .set_user_mini_room(room.owner().char_id, Some((ty, room.balloon()))),
+        }
+    }
+
+    pub async fn handle_mini_room(&mut self, req: MiniRoomReq) -> anyhow::Result<()> {
+        match req {
+            MiniRoomReq::Create(req) => self.handle_mini_room_create(req).await,
+            MiniRoomReq::Enter(req) => self.handle_mini_room_enter(req).await,
+            MiniRoomReq::Chat(req) => self.handle_mini_room_chat(req).await,
+            MiniRoomReq::Leave(()) => self.leave_mini_room().await,
+            MiniRoomReq::Balloon(()) => self.handle_shop_open().await,
+            MiniRoomReq::PspPutItem(req) | MiniRoomReq::EspPutItem(req) => {
+                self.handle_shop_put_item(req).await
+            }
+            MiniRoomReq::PspBuyItem(req) | MiniRoomReq::EspBuyItem(req) => {
+                self.handle_shop_buy_item(req).await
+            }
+            MiniRoomReq::PspMoveItemToInventory(req) | MiniRoomReq::EspMoveItemToInventory(req) => {
+                self.handle_shop_move_item_to_inv(req).await
+            }
+            MiniRoomReq::EspGoOut(()) => self.handle_entrusted_shop_close().await,
+            MiniRoomReq::EspArrangeItem(()) => self.handle_shop_arrange().await,
+            MiniRoomReq::EspWithdrawAll(()) => self.handle_entrusted_shop_withdraw_all().await,
+            MiniRoomReq::EspWithdrawMoney(()) => self.handle_entrusted_shop_withdraw_money().await,
+            MiniRoomReq::MgTieRequest(()) => self.handle_mini_game_tie_request().await,
+            MiniRoomReq::MgTieAnswer(accept) => self.handle_mini_game_tie_answer(accept).await,
+            MiniRoomReq::MgGiveUp(()) => self.handle_mini_game_give_up().await,
+            MiniRoomReq::MgLeaveAfterGame(()) => self.handle_mini_game_leave_after_game(true).await,
+            MiniRoomReq::MgCancelLeave(()) => self.handle_mini_game_leave_after_game(false).await,
+            MiniRoomReq::MgReady(()) => self.handle_mini_game_ready(true).await,
+            MiniRoomReq::MgCancelReady(()) => self.handle_mini_game_ready(false).await,
+            MiniRoomReq::MgBan(()) => self.handle_mini_game_ban().await,
+            MiniRoomReq::MgStart(()) => self.handle_mini_game_start().await,
+            MiniRoomReq::MgSkip(()) => self.handle_mini_game_skip().await,
+            MiniRoomReq::OmokMove(req) => self.handle_omok_move(req).await,
+            MiniRoomReq::MemoryGameTurn(req) => self.handle_memory_game_turn(req).await,
+        }
+    }
+
+    async fn handle_mini_room_create(&mut self, req: MiniRoomCreateReq) -> anyhow::Result<()> {
+        if self.is_in_mini_room().await {
+            return self.send_mini_room_error(MiniRoomEnterError::AlreadyInRoom);
+        }
+
+        match req {
+            MiniRoomCreateReq::PersonalShop(req) => {
+                self.create_shop(MiniRoomType::PersonalShop, req).await
+            }
+            MiniRoomCreateReq::EntrustedShop(req) => {
+                self.create_shop(MiniRoomType::EntrustedShop, req).await
+            }
+            MiniRoomCreateReq::Omok(req) => self.create_mini_game(MiniRoomType::Omok, req).await,
+            MiniRoomCreateReq::MemoryGame(req) => {
+                self.create_mini_game(MiniRoomType::MemoryGame, req).await
+            }
+            MiniRoomCreateReq::Trading(()) => {
+                self.send_mini_room_error(MiniRoomEnterError::NoTrading)
+            }
+        }
+    }
+
+    async fn create_shop(
+        &mut self,
+        ty: MiniRoomType,
+        req: MiniRoomShopCreate,
+    ) -> anyhow::Result<()> {
+        let valid_permit = match ty {
+            MiniRoomType::PersonalShop => req.permit_item.is_personal_shop_permit(),
+            _ => req.permit_item.is_entrusted_shop_permit(),
+        };
+        let has_permit = (req.permit_slot as usize)
+            .checked_sub(1)
+            .and_then(|slot| self.session.inv().cash.get(slot))
+            .map(|item| item.item_id == req.permit_item)
+            .unwrap_or(false);
+
+        if !valid_permit || !has_permit {
+            anyhow::bail!("Invalid shop permit: {:?}", req.permit_item);
+        }
+
+        if ty == MiniRoomType::EntrustedShop && self.has_entrusted_shop().await? {
+            return self.send_mini_room_error(MiniRoomEnterError::PermissionDenied);
+        }
+
+        let owner = self.mini_room_user(ty);
+        let location = MiniRoomLocation {
+            map_id: MapId(self.session.char.model.map_id as u32),
+            pos: self.pos,
+            fh: self.fh,
+        };
+        let room = self.services.mini_room.create(|sn| {
+            MiniRoom::new_shop(sn, ty, req.title, location, req.permit_item, owner.clone())
+        });
+
+        let mut r = room.lock().await;
+        let ix = r
+            .enter(owner, self.sess_handle.clone())
+            .map_err(|err| anyhow::format_err!("Unable to enter own shop: {err:?}"))?;
+        let result = r.enter_result(ix);
+        drop(r);
+
+        self.mini_room = Some(room);
+        self.send_pkt(MiniRoomResp::EnterResult(result))
+    }
+
+    async fn handle_mini_room_enter(&mut self, req: MiniRoomEnterReq) -> anyhow::Result<()> {
+        if self.is_in_mini_room().await {
+            return self.send_mini_room_error(MiniRoomEnterError::AlreadyInRoom);
+        }
+
+        let Some(room) = self.services.mini_room.get(req.sn) else {
+            return self.send_mini_room_error(MiniRoomEnterError::NoRoom);
+        };
+
+        let mut r = room.lock().await;
+        if r.location.map_id.0 != self.session.char.model.map_id as u32 {
+            drop(r);
+            return self.send_mini_room_error(MiniRoomEnterError::NoRoom);
+        }
+
+        if !r.check_password(req.password.as_deref()) {
+            drop(r);
+            return self.send_mini_room_error(MiniRoomEnterError::InvalidPassword);
+        }
+
+        let user = self.mini_room_user(r.ty);
+        match r.enter(user, self.sess_handle.clone()) {
+            Ok(ix) => {
+                let result = r.enter_result(ix);
+                self.update_mini_room_balloon(&r)?;
+                drop(r);
+                self.mini_room = Some(room);
+                self.send_pkt(MiniRoomResp::EnterResult(result))
+            }
+            Err(err) => {
+                drop(r);
+                self.send_mini_room_error(err)
+            }
+        }
+    }
+
+    async fn handle_mini_room_chat(&mut self, req: MiniRoomChatReq) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let r = room.lock().await;
+        let char = &self.session.char.model;
+        let ix = r
+            .user_ix(char.id)
+            .ok_or_else(|| anyhow::format_err!("Not in room"))?;
+
+        r.broadcast(
+            MiniRoomResp::Chat(MiniRoomUserChat {
+                ix,
+                msg: format!("{} : {}", char.name, req.msg),
+            }),
+            -1,
+        )
+    }
+
+    /// Leaves the current room, a personal shop or game is closed when the owner leaves
+    pub async fn leave_mini_room(&mut self) -> anyhow::Result<()> {
+        let Some(room) = self.mini_room.take() else {
+            return Ok(());
+        };
+
+        let is_game = room.lock().await.game.is_some();
+        if is_game {
+            self.give_up_mini_game(&room).await?;
+        }
+        self.leave_room(&room).await?;
+        if is_game {
+            self.reload_mini_game_records().await?;
+        }
+        Ok(())
+    }
+
+    async fn leave_room(&mut self, room: &SharedMiniRoom) -> anyhow::Result<()> {
+        let char_id = self.session.char.model.id;
+        let mut r = room.lock().await;
+        if !r.is_owner(char_id) {
+            r.leave(char_id, MiniRoomLeaveReason::UserRequest);
+            return self.update_mini_room_balloon(&r);
+        }
+
+        match r.ty {
+            MiniRoomType::EntrustedShop if r.open => {
+                // The merchant keeps selling without the owner
+                r.leave(char_id, MiniRoomLeaveReason::UserRequest);
+                self.update_mini_room_balloon(&r)
+            }
+            MiniRoomType::EntrustedShop => {
+                // Setup was cancelled
+                r.close(MiniRoomLeaveReason::UserRequest);
+                self.services.mini_room.remove(r.sn);
+                self.return_shop_items(&mut r).await
+            }
+            _ => {
+                let was_open = r.open;
+                r.close(MiniRoomLeaveReason::HostOut);
+                self.services.mini_room.remove(r.sn);
+                if was_open {
+                    self.field.set_user_mini_room(char_id, None)?;
+                }
+                self.return_shop_items(&mut r).await
+            }
+        }
+    }
+
+    /// Mesos the character can still take, without going over the limit
+    fn free_mesos(&self) -> u32 {
+        (i32::MAX - self.session.char.model.mesos.max(0)) as u32
+    }
+
+    /// Adds the mesos up to the limit, returns the mesos which were added
+    fn take_mesos(&mut self, mesos: u32) -> anyhow::Result<u32> {
+        let taken = mesos.min(self.free_mesos());
+        if taken > 0 {
+            self.update_mesos(self.session.char.model.mesos + taken as i32)?;
+        }
+        Ok(taken)
+    }
+
+    /// Writes the shop together with the character and the inventory of the session
+    /// in one transaction, so neither side of a trade gets lost If the server stops
+    async fn save_shop_with_session(&mut self, room: &mut MiniRoom) -> anyhow::Result<()> {
+        let data = &self.services.data;
+        let txn = data.begin().await?;
+        let write = self.session.write_with(&txn, data).await?;
+        data.shop.save_shop_with(&txn, room).await?;
+        txn.commit().await?;
+        self.session.apply_write(write);
+        Ok(())
+    }
+
+    /// Writes a trade prepared on copies of the shop, the character and the inventory
+    /// in one transaction, the room and the session only take them over after the commit
+    async fn save_shop_trade(
+        &mut self,
+        room: &mut MiniRoom,
+        shop: PlayerShop,
+        char: character::Model,
+        inv: InventorySet,
+    ) -> anyhow::Result<()> {
+        let prev_shop = std::mem::replace(&mut room.shop, shop);
+        let data = &self.services.data;
+        let res = async {
+            let txn = data.begin().await?;
+            let write = self.session.write_state_with(&txn, data, char, inv).await?;
+            data.shop.save_shop_with(&txn, room).await?;
+            txn.commit().await?;
+            anyhow::Ok(write)
+        }
+        .await;
+
+        match res {
+            Ok(write) => {
+                self.session.apply_write(write);
+                Ok(())
+            }
+            Err(err) => {
+                room.shop = prev_shop;
+                Err(err)
+            }
+        }
+    }
+
+    /// Writes the store bank together with the character and the inventory of the session
+    async fn save_store_bank_with_session(&mut self, shop: &mut PlayerShop) -> anyhow::Result<()> {
+        let data = &self.services.data;
+        let txn = data.begin().await?;
+        let write = self.session.write_with(&txn, data).await?;
+        data.shop.save_store_bank_with(&txn, shop).await?;
+        txn.commit().await?;
+        self.session.apply_write(write);
+        Ok(())
+    }
+
+    /// Hands the items and mesos of a closed shop back to the owner,
+    /// whatever doesn't fit into the inventory or the mesos stays in the store bank
+    async fn return_shop_items(&mut self, room: &mut MiniRoom) -> anyhow::Result<()> {
+        if room.shop.is_empty() && room.shop.db_id.is_none() {
+            return Ok(());
+        }
+
+        let (mesos, items) = room.shop.take_all();
+        let left = self.add_inv_items(items)?;
+        if !left.is_empty() {
+            log::info!("No inventory space, storing {} shop items", left.len());
+        }
+        room.shop.store_items(left);
+        room.shop.mesos = mesos - self.take_mesos(mesos)?;
+
+        self.save_shop_with_session(room).await
+    }
+
+    async fn handle_shop_open(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let mut r = room.lock().await;
+        let char_id = self.session.char.model.id;
+        if !r.is_owner(char_id) || r.open || !r.ty.is_shop() {
+            anyhow::bail!("Unable to open the room");
+        }
+
+        r.open = true;
+        if r.ty == MiniRoomType::EntrustedShop {
+            self.services.data.shop.save_shop(&mut r).await?;
+            self.field.add_employee(Employee {
+                sn: r.sn,
+                tmpl_id: r.permit,
+                pos: r.location.pos,
+                fh: r.location.fh,
+                owner_name: r.owner().name.clone(),
+                balloon: r.employee_balloon(),
+            })?;
+            Ok(())
+        } else {
+            self.update_mini_room_balloon(&r)
+        }
+    }
+
+    async fn handle_shop_put_item(&mut self, req: ShopPutItemReq) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let mut r = room.lock().await;
+        if !r.is_owner(self.session.char.model.id) || !r.ty.is_shop() {
+            anyhow::bail!("Only the owner can put items into the shop");
+        }
+        if r.shop.items.len() >= MAX_SHOP_ITEMS {
+            anyhow::bail!("Shop is full");
+        }
+
+        let item = self.take_shop_item(&req)?;
+        r.shop.put_item(item)?;
+        self.save_shop_with_session(&mut r).await?;
+        self.send_shop_refresh(&r)
+    }
+
+    fn send_shop_refresh(&mut self, room: &MiniRoom) -> anyhow::Result<()> {
+        let refresh = match room.ty {
+            MiniRoomType::EntrustedShop => MiniRoomResp::EspRefresh(room.shop.refresh()),
+            _ => MiniRoomResp::PspRefresh(room.shop.refresh()),
+        };
+        room.broadcast(refresh, -1)
+    }
+
+    async fn handle_shop_buy_item(&mut self, req: ShopBuyItemReq) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let mut r = room.lock().await;
+        let entrusted = r.ty == MiniRoomType::EntrustedShop;
+        let buy_result = |res: ShopBuyResult| {
+            if entrusted {
+                MiniRoomResp::EspBuyResult(res)
+            } else {
+                MiniRoomResp::PspBuyResult(res)
+            }
+        };
+
+        let char = &self.session.char.model;
+        if r.is_owner(char.id) || !r.open {
+            anyhow::bail!("Unable to buy from this shop");
+        }
+
+        let Some(shop_item) = r.shop.items.get(req.ix as usize) else {
+            anyhow::bail!("Invalid shop item index: {}", req.ix);
+        };
+        if !self.has_inventory_space(std::slice::from_ref(&shop_item.item)) {
+            drop(r);
+            return self.send_pkt(buy_result(ShopBuyResult::InventoryFull));
+        }
+
+        // The trade is done on copies, which are only taken over after the commit
+        let mut char = self.session.char.model.clone();
+        let buyer = char.name.clone();
+        let mut shop = r.shop.clone();
+        let buyer_mesos = char.mesos.max(0) as u32;
+        let purchase = match shop.buy(req.ix as usize, req.bundles, buyer_mesos, &buyer) {
+            Ok(purchase) => purchase,
+            Err(err) => {
+                drop(r);
+                return self.send_pkt(buy_result(err.buy_result()));
+            }
+        };
+
+        let mut inv = self.session.inv().clone();
+        let op = add_item_to_inv(&mut inv, purchase.item)
+            .map_err(|_| anyhow::format_err!("No inventory space"))?;
+        char.mesos -= purchase.cost as i32;
+        let mesos = char.mesos;
+        self.save_shop_trade(&mut r, shop, char, inv).await?;
+        self.send_inv_ops(vec![op])?;
+        self.update_mesos(mesos)?;
+
+        let add_sold = ShopAddSoldItem {
+            ix: req.ix,
+            bundles: req.bundles,
+            buyer,
+        };
+        let add_sold = if entrusted {
+            MiniRoomResp::EspAddSoldItem(add_sold)
+        } else {
+            MiniRoomResp::PspAddSoldItem(add_sold)
+        };
+        r.broadcast(add_sold, -1)?;
+        self.send_shop_refresh(&r)?;
+
+        Ok(())
+    }
+
+    async fn handle_shop_move_item_to_inv(
+        &mut self,
+        req: ShopMoveItemToInvReq,
+    ) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let mut r = room.lock().await;
+        if !r.is_owner(self.session.char.model.id) {
+            anyhow::bail!("Only the owner can take items out of the shop");
+        }
+
+        let ix = req.ix as usize;
+        let Some(shop_item) = r.shop.items.get(ix) else {
+            anyhow::bail!("Invalid shop item index: {ix}");
+        };
+        if !self.has_inventory_space(std::slice::from_ref(&shop_item.item)) {
+            anyhow::bail!("No inventory space");
+        }
+
+        let item = r.shop.take_item(ix)?;
+        let op = self
+            .add_inv_item(item)
+            .map_err(|_| anyhow::format_err!("No inventory space"))?;
+        self.send_inv_ops(vec![op])?;
+
+        r.shop.arrange();
+        self.save_shop_with_session(&mut r).await?;
+        self.send_shop_refresh(&r)
+    }
+
+    async fn handle_shop_arrange(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let mut r = room.lock().await;
+        if !r.is_owner(self.session.char.model.id) {
+            anyhow::bail!("Only the owner can arrange the shop");
+        }
+
+        r.shop.arrange();
+        self.save_shop_with_session(&mut r).await?;
+        self.send_shop_refresh(&r)
+    }
+
+    /// Closes the hired merchant, everything left is kept by the store bank
+    async fn handle_entrusted_shop_close(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let mut r = room.lock().await;
+        if !r.is_owner(self.session.char.model.id) || r.ty != MiniRoomType::EntrustedShop {
+            anyhow::bail!("Only the owner can close the merchant");
+        }
+
+        let was_open = r.open;
+        r.close(MiniRoomLeaveReason::ShopClosed);
+        self.services.mini_room.remove(r.sn);
+        self.services.data.shop.save_shop(&mut r).await?;
+        if was_open {
+            self.field.remove_employee(r.sn)?;
+        }
+        self.mini_room = None;
+        Ok(())
+    }
+
+    async fn handle_entrusted_shop_withdraw_all(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let mut r = room.lock().await;
+        if !r.is_owner(self.session.char.model.id) || r.ty != MiniRoomType::EntrustedShop {
+            anyhow::bail!("Only the owner can withdraw items");
+        }
+
+        let items = r
+            .shop
+            .available_items()
+            .map(|item| item.item.clone())
+            .collect::<Vec<_>>();
+        if !self.has_inventory_space(&items) {
+            drop(r);
+            return self.send_pkt(MiniRoomResp::EspWithdrawAllResult(false));
+        }
+
+        let (mesos, items) = r.shop.take_all();
+        // Mesos are withdrawn separately below
+        r.shop.mesos = mesos;
+        let left = self.add_inv_items(items)?;
+        r.shop.store_items(left);
+        self.save_shop_with_session(&mut r).await?;
+        self.send_shop_refresh(&r)?;
+        drop(r);
+
+        self.send_pkt(MiniRoomResp::EspWithdrawAllResult(true))?;
+        self.handle_entrusted_shop_withdraw_money().await
+    }
+
+    async fn handle_entrusted_shop_withdraw_money(&mut self) -> anyhow::Result<()> {
+        let room = self.current_mini_room()?;
+        let mut r = room.lock().await;
+        if !r.is_owner(self.session.char.model.id) || r.ty != MiniRoomType::EntrustedShop {
+            anyhow::bail!("Only the owner can withdraw mesos");
+        }
+
+        // Mesos which don't fit stay in the shop
+        let mesos = r.shop.mesos;
+        r.shop.mesos -= self.take_mesos(mesos)?;
+        self.save_shop_with_session(&mut r).await?;
+        self.send_shop_refresh(&r)?;
+        drop(r);
+
+        self.send_pkt(MiniRoomResp::EspWithdrawMoneyResult(()))
+    }
+
+    pub async fn handle_entrusted_shop(
+        &mut self,
+        _req: EntrustedShopReq,
+    ) -> GameResult<EntrustedShopCheckResultResp> {
+        let char_id = self.session.char.model.id;
+        let res = if self
+            .services
+            .mini_room
+            .find_entrusted_shop(char_id)
+            .await
+            .is_some()
+        {
+            EntrustedShopCheckResultResp::AlreadyOpen
+        } else if !self.services.data.shop.find_shops(char_id).await?.is_empty() {
+            EntrustedShopCheckResultResp::RetrieveFromStoreBank
+        } else {
+            EntrustedShopCheckResultResp::OpenPossible
+        };
+
+        Ok(res.into())
+    }
+
+    pub async fn handle_store_bank(&mut self, req: StoreBankReq) -> anyhow::Result<()> {
+        let char_id = self.session.char.model.id;
+        match req {
+            StoreBankReq::Open(()) => {
+                let shops = self.store_bank_shops(char_id).await?;
+                let mesos = shops
+                    .iter()
+                    .fold(0u32, |mesos, shop| mesos.saturating_add(shop.mesos));
+                let items = shops
+                    .iter()
+                    .flat_map(PlayerShop::available_items)
+                    .map(|item| item.item.to_proto())
+                    .collect::<Vec<_>>();
+
+                self.send_pkt(StoreBankResp::Items(StoreBankItems {
+                    npc_tmpl_id: STORE_BANK_NPC,
+                    u1: 0,
+                    mesos,
+                    items: items.into(),
+                }))
+            }
+            StoreBankReq::GetAll(()) => {
+                let shops = self.store_bank_shops(char_id).await?;
+                let items = shops
+                    .iter()
+                    .flat_map(PlayerShop::available_items)
+                    .map(|item| item.item.clone())
+                    .collect::<Vec<_>>();
+                if !self.has_inventory_space(&items) {
+                    return self.send_pkt(StoreBankResp::InventoryFull(()));
+                }
+
+                for mut shop in shops {
+                    let (mesos, items) = shop.take_all();
+                    let left = self.add_inv_items(items)?;
+                    shop.store_items(left);
+                    // Mesos which don't fit stay in the store bank
+                    shop.mesos = mesos - self.take_mesos(mesos)?;
+                    self.save_store_bank_with_session(&mut shop).await?;
+                }
+                self.send_pkt(StoreBankResp::GetAllSuccess(()))
+            }
+            StoreBankReq::Exit(()) => Ok(()),
+        }
+    }
+
+    fn has_inventory_space(&self, items: &[ShopItemKind]) -> bool {
+        let inv = self.session.inv();
+        let free = |ty: InventoryType| match ty {
+            InventoryType::Equip => inv.equip.slots() - inv.equip.len(),
+            ty => inv
+                .get_stack_inventory(ty)
+                .map(|inv| inv.slots() - inv.len())
+                .unwrap_or(0),
+        };
+
+        [
+            InventoryType::Equip,
+            InventoryType::Use,
+            InventoryType::Misc,
+            InventoryType::Etc,
+            InventoryType::Cash,
+        ]
+        .into_iter()
+        .all(|ty| {
+            let needed = items
+                .iter()
+                .filter(|item| InventoryType::from_item_id(item.item_id()) == Some(ty))
+                .count();
+            needed <= free(ty)
+        })
+    }
+}
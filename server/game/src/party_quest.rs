@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use data::services::party_quest::{check_party_quest_entry, PartyQuestRun};
+use game_data::pq::{ClearCondition, PartyQuest};
+use proto95::{
+    game::{
+        npc::UserSelectNpcReq,
+        party_quest::{UserRequestPQRewardReq, UserSelectPQRewardReq, UserShowPQRewardResp},
+        BroadcastMessageResp,
+    },
+    id::{ItemId, MapId},
+};
+
+use crate::GameHandler;
+
+impl GameHandler {
+    /// Party quest run of the instance the character is in
+    fn party_quest_run(&self) -> Option<Arc<PartyQuestRun>> {
+        let instance = self.field.instance()?;
+        self.services.party_quest.get_run(instance.id)
+    }
+
+    async fn start_party_quest(&mut self, id: u32, pq: &PartyQuest) -> anyhow::Result<()> {
+        //TODO check all party members once parties are supported
+        let levels = [self.session.char.model.level as u32];
+        if let Err(err) = check_party_quest_entry(pq, &levels) {
+            return self.send_pkt(BroadcastMessageResp::PinkMessage(err.to_string()));
+        }
+
+        let instance = self.services.party_quest.start(&self.services.field, id)?;
+        self.enter_instance(instance.id, MapId(pq.stages[0].map))
+            .await
+    }
+
+    /// Checks the clear condition of the active stage, items are handed in here
+    fn try_clear_stage(&mut self, run: &PartyQuestRun) -> anyhow::Result<()> {
+        let Some(stage) = run.active_stage(self.field.field_id()) else {
+            return Ok(());
+        };
+
+        let cleared = match stage.clear {
+            ClearCondition::CollectItem { item, count } => {
+                self.has_inv_items(ItemId(item), count as usize)?
+            }
+            ClearCondition::KillMobs => !self.field.has_mobs(),
+            ClearCondition::ReactorState { .. } => false,
+        };
+        // Only the character, which actually cleared the stage, hands in the items
+        if !cleared || !run.clear_stage(&self.field)? {
+            return Ok(());
+        }
+        if let ClearCondition::CollectItem { item, count } = stage.clear {
+            self.take_inv_items(ItemId(item), count as usize)?;
+        }
+        Ok(())
+    }
+
+    pub async fn handle_select_npc(&mut self, req: UserSelectNpcReq) -> anyhow::Result<()> {
+        let Some(npc) = self.field.get_npc_tmpl(req.id) else {
+            return Ok(());
+        };
+
+        // Only the npcs of the stages check the clear conditions
+        if let Some(run) = self.party_quest_run() {
+            if run.meta.stages.iter().any(|stage| stage.npc == Some(npc)) {
+                return self.try_clear_stage(&run);
+            }
+        }
+
+        let services = self.services.clone();
+        if let Some((id, pq)) = services.meta.get_party_quest_by_npc(npc) {
+            return self.start_party_quest(id, pq).await;
+        }
+        Ok(())
+    }
+
+    pub async fn handle_request_pq_reward(
+        &mut self,
+        _req: UserRequestPQRewardReq,
+    ) -> anyhow::Result<()> {
+        let Some(run) = self.party_quest_run().filter(|run| run.is_completed()) else {
+            return Ok(());
+        };
+
+        let items = run
+            .meta
+            .rewards
+            .iter()
+            .map(|reward| ItemId(reward.item))
+            .collect::<Vec<_>>();
+        self.send_pkt(UserShowPQRewardResp {
+            items: items.into(),
+        })
+    }
+
+    /// The character receives the chosen reward and leaves the party quest
+    pub async fn handle_select_pq_reward(
+        &mut self,
+        req: UserSelectPQRewardReq,
+    ) -> anyhow::Result<()> {
+        let Some(run) = self.party_quest_run() else {
+            return Ok(());
+        };
+        let has_slot = run
+            .meta
+            .rewards
+            .get(req.ix as usize)
+            .is_some_and(|reward| self.session.inv().has_free_slot(ItemId(reward.item)));
+        if !has_slot {
+            let pkt = self.enable_char();
+            return self.send_pkt(pkt);
+        }
+        let Some(reward) = run.claim_reward(self.session.char.model.id, req.ix as usize) else {
+            let pkt = self.enable_char();
+            return self.send_pkt(pkt);
+        };
+
+        let item = self.new_inv_item(ItemId(reward.item), reward.quantity)?;
+        self.add_inv_items(vec![item])?;
+        self.warp(MapId(run.meta.exit_map), 0).await
+    }
+}
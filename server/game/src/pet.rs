@@ -0,0 +1,356 @@
+use std::time::Instant;
+
+use data::services::{
+    helper::{
+        intentory::inv::{InventoryExt, InventoryType},
+        pool::drop::DropLeaveParam,
+    },
+    model::pet::{PetData, FULLNESS_DECAY_INTERVAL},
+};
+use moople_packet::proto::CondOption;
+use proto95::{
+    game::pet::{
+        PetActionCommandResp, PetActionReq, PetActionResp, PetActivateReq, PetCommandType,
+        PetDropPickUpReq, PetFoodItemUseReq, PetInitInfo, PetInteractionReq, PetIx, PetLeaveReason,
+        PetLockerId, PetMoveReq, MAX_PETS,
+    },
+    shared::{
+        char::{CharStatChangedResp, CharStatPartial},
+        inventory::{InvOpAdd, InventoryOperation},
+    },
+};
+
+use crate::{mini_room::item_quantity_op, GameHandler};
+
+/// Summoned pets of a character, the pets are identified by their cash id
+#[derive(Debug)]
+pub struct PetSlots {
+    pets: [Option<PetLockerId>; MAX_PETS],
+    last_decay: Instant,
+}
+
+impl Default for PetSlots {
+    fn default() -> Self {
+        Self {
+            pets: [None; MAX_PETS],
+            last_decay: Instant::now(),
+        }
+    }
+}
+
+impl GameHandler {
+    /// Cash inventory slot of the pet
+    fn pet_slot(&self, locker_id: PetLockerId) -> Option<usize> {
+        self.session
+            .inv()
+            .cash
+            .iter()
+            .find(|(_, item)| item.item.pet.is_some() && item.item.cash_id == Some(locker_id))
+            .map(|(slot, _)| slot)
+    }
+
+    fn pet_ix(&self, locker_id: PetLockerId) -> anyhow::Result<PetIx> {
+        self.pets
+            .pets
+            .iter()
+            .position(|pet| *pet == Some(locker_id))
+            .map(|ix| ix as PetIx)
+            .ok_or_else(|| anyhow::format_err!("Pet is not summoned: {locker_id}"))
+    }
+
+    fn pet_mut(&mut self, slot: usize) -> anyhow::Result<&mut PetData> {
+        let item = self
+            .session
+            .inv_mut()
+            .cash
+            .get_mut(slot)
+            .ok_or_else(|| anyhow::format_err!("No item in slot {slot}"))?;
+        let item = item.item.as_mut();
+        // Mark the pet for the next save
+        item.last_update = 1;
+        let item_id = item.item_id;
+        item.pet
+            .as_deref_mut()
+            .ok_or_else(|| anyhow::format_err!("Item is not a pet: {item_id:?}"))
+    }
+
+    fn pet_init_info(&self, slot: usize) -> anyhow::Result<PetInitInfo> {
+        let item = self
+            .session
+            .inv()
+            .cash
+            .get(slot)
+            .ok_or_else(|| anyhow::format_err!("No item in slot {slot}"))?;
+        let pet = item
+            .item
+            .pet
+            .as_ref()
+            .ok_or_else(|| anyhow::format_err!("Item is not a pet"))?;
+
+        Ok(PetInitInfo {
+            tmpl_id: item.item_id,
+            name: pet.name.clone(),
+            locker_id: item.item.cash_id.unwrap_or(0),
+            pos: self.pos,
+            move_action: 0,
+            fh: self.fh,
+            name_tag: false,
+            chat_balloon: false,
+        })
+    }
+
+    /// Resends the pet item, which shows the updated tameness and fullness
+    fn send_pet_item(&mut self, slot: usize) -> anyhow::Result<()> {
+        let item = self
+            .session
+            .inv()
+            .cash
+            .get(slot)
+            .ok_or_else(|| anyhow::format_err!("No item in slot {slot}"))?;
+        let op = InventoryOperation::Add(InvOpAdd {
+            inv_type: InventoryType::Cash.into(),
+            pos: slot as u16 + 1,
+            item: item.item.as_ref().into(),
+        });
+        self.send_inv_ops(vec![op])
+    }
+
+    fn send_pet_stats(&mut self) -> anyhow::Result<()> {
+        let [pet1, pet2, pet3] = self.pets.pets.map(|pet| CondOption(Some(pet.unwrap_or(0))));
+        self.send_pkt(CharStatChangedResp {
+            excl: true,
+            stats: CharStatPartial {
+                pet1,
+                pet2,
+                pet3,
+                ..Default::default()
+            }
+            .into(),
+            secondary_stat: false,
+            battle_recovery: false,
+        })
+    }
+
+    fn summon_pet(&mut self, slot: usize) -> anyhow::Result<()> {
+        let Some(ix) = self.pets.pets.iter().position(Option::is_none) else {
+            let pkt = self.enable_char();
+            return self.send_pkt(pkt);
+        };
+
+        let locker_id = self
+            .session
+            .inv()
+            .cash
+            .get(slot)
+            .and_then(|item| item.item.cash_id);
+        let pet = self.pet_mut(slot)?;
+        if pet.is_hungry() {
+            let pkt = self.enable_char();
+            return self.send_pkt(pkt);
+        }
+        pet.summoned = true;
+        self.pets.pets[ix] = locker_id;
+
+        let info = self.pet_init_info(slot)?;
+        self.field
+            .set_user_pet(self.session.char.model.id, ix as PetIx, Ok(info), true)?;
+        self.send_pet_stats()
+    }
+
+    fn unsummon_pet(&mut self, ix: PetIx, reason: PetLeaveReason) -> anyhow::Result<()> {
+        let Some(locker_id) = self.pets.pets[ix as usize].take() else {
+            return Ok(());
+        };
+        if let Some(slot) = self.pet_slot(locker_id) {
+            self.pet_mut(slot)?.summoned = false;
+        }
+
+        self.field
+            .set_user_pet(self.session.char.model.id, ix, Err(reason), false)?;
+        self.send_pet_stats()
+    }
+
+    /// Shows the summoned pets in the current field, after the login
+    /// the pets which were summoned before are summoned again
+    pub(crate) fn spawn_pets(&mut self) -> anyhow::Result<()> {
+        if self.pets.pets.iter().all(Option::is_none) {
+            let summoned = self
+                .session
+                .inv()
+                .cash
+                .iter()
+                .filter(|(_, item)| item.item.pet.as_ref().is_some_and(|pet| pet.summoned))
+                .filter_map(|(_, item)| item.item.cash_id)
+                .take(MAX_PETS);
+            for (ix, locker_id) in summoned.enumerate() {
+                self.pets.pets[ix] = Some(locker_id);
+            }
+            self.pets.last_decay = Instant::now();
+        }
+
+        for ix in 0..MAX_PETS {
+            let Some(slot) = self.pets.pets[ix].and_then(|pet| self.pet_slot(pet)) else {
+                self.pets.pets[ix] = None;
+                continue;
+            };
+            let info = self.pet_init_info(slot)?;
+            self.field
+                .set_user_pet(self.session.char.model.id, ix as PetIx, Ok(info), false)?;
+        }
+        self.send_pet_stats()
+    }
+
+    /// Reduces the fullness of all summoned pets, hungry pets leave
+    pub(crate) fn update_pets(&mut self) -> anyhow::Result<()> {
+        let steps = self.pets.last_decay.elapsed().as_secs() / FULLNESS_DECAY_INTERVAL.as_secs();
+        if steps == 0 {
+            return Ok(());
+        }
+        self.pets.last_decay += FULLNESS_DECAY_INTERVAL * steps as u32;
+
+        for ix in 0..MAX_PETS {
+            let Some(slot) = self.pets.pets[ix].and_then(|pet| self.pet_slot(pet)) else {
+                continue;
+            };
+            let pet = self.pet_mut(slot)?;
+            pet.decay_fullness(steps.min(u8::MAX as u64) as u8);
+            let hungry = pet.is_hungry();
+            self.send_pet_item(slot)?;
+            if hungry {
+                self.unsummon_pet(ix as PetIx, PetLeaveReason::Hungry)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn handle_pet_activate(&mut self, req: PetActivateReq) -> anyhow::Result<()> {
+        let slot = (req.slot as usize)
+            .checked_sub(1)
+            .ok_or_else(|| anyhow::format_err!("Invalid slot"))?;
+        let locker_id = self
+            .session
+            .inv()
+            .cash
+            .get(slot)
+            .filter(|item| item.item.pet.is_some())
+            .and_then(|item| item.item.cash_id)
+            .ok_or_else(|| anyhow::format_err!("No pet in slot {slot}"))?;
+
+        match self.pet_ix(locker_id) {
+            Ok(ix) => self.unsummon_pet(ix, PetLeaveReason::UserRequest),
+            Err(_) => self.summon_pet(slot),
+        }
+    }
+
+    pub async fn handle_pet_move(&mut self, req: PetMoveReq) -> anyhow::Result<()> {
+        let ix = self.pet_ix(req.locker_id)?;
+        self.field
+            .update_pet_pos(req, self.session.char.model.id, ix)
+    }
+
+    pub async fn handle_pet_action(&mut self, req: PetActionReq) -> anyhow::Result<()> {
+        let ix = self.pet_ix(req.locker_id)?;
+        let char_id = self.session.char.model.id;
+        self.field.broadcast(
+            PetActionResp {
+                char_id: char_id as u32,
+                pet_ix: ix,
+                ty: req.ty,
+                action: req.action,
+                msg: req.msg,
+                chat_balloon: false,
+            },
+            char_id,
+        )
+    }
+
+    pub async fn handle_pet_interaction(&mut self, req: PetInteractionReq) -> anyhow::Result<()> {
+        let ix = self.pet_ix(req.locker_id)?;
+        let slot = self
+            .pet_slot(req.locker_id)
+            .ok_or_else(|| anyhow::format_err!("Pet not in inventory"))?;
+
+        let success = self.pet_mut(slot)?.command(&mut rand::thread_rng());
+        if success {
+            self.send_pet_item(slot)?;
+        }
+        self.field.broadcast(
+            PetActionCommandResp {
+                char_id: self.session.char.model.id as u32,
+                pet_ix: ix,
+                ty: PetCommandType::Interaction,
+                action: req.command,
+                success,
+                chat_balloon: false,
+            },
+            -1,
+        )
+    }
+
+    pub async fn handle_pet_food(&mut self, req: PetFoodItemUseReq) -> anyhow::Result<()> {
+        let slot = (req.slot as usize)
+            .checked_sub(1)
+            .ok_or_else(|| anyhow::format_err!("Invalid slot"))?;
+        let is_food = self
+            .session
+            .inv()
+            .use_
+            .get(slot)
+            .is_some_and(|item| item.item_id == req.item_id && req.item_id.is_pet_food());
+        if !is_food {
+            anyhow::bail!("Invalid pet food: {:?}", req.item_id);
+        }
+
+        // The hungriest pet eats the food
+        let hungriest = self
+            .pets
+            .pets
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, pet)| Some((ix, self.pet_slot((*pet)?)?)))
+            .min_by_key(|(_, slot)| {
+                self.session
+                    .inv()
+                    .cash
+                    .get(*slot)
+                    .and_then(|item| item.item.pet.as_ref())
+                    .map(|pet| pet.fullness)
+            });
+        let Some((ix, pet_slot)) = hungriest else {
+            let pkt = self.enable_char();
+            return self.send_pkt(pkt);
+        };
+
+        let stack = self.session.inv_mut().use_.get_mut(slot).expect("Pet food");
+        stack.quantity -= 1;
+        stack.item.quantity -= 1;
+        stack.item.last_update = 1;
+        let left = stack.item.quantity;
+        if left == 0 {
+            self.session.inv_mut().use_.remove(slot);
+        }
+        self.send_inv_ops(vec![item_quantity_op(InventoryType::Use, slot, left)])?;
+
+        let success = self.pet_mut(pet_slot)?.feed();
+        if success {
+            self.send_pet_item(pet_slot)?;
+        }
+        self.field.broadcast(
+            PetActionCommandResp {
+                char_id: self.session.char.model.id as u32,
+                pet_ix: ix as PetIx,
+                ty: PetCommandType::Food,
+                action: 0,
+                success,
+                chat_balloon: false,
+            },
+            -1,
+        )
+    }
+
+    pub async fn handle_pet_drop_pick_up(&mut self, req: PetDropPickUpReq) -> anyhow::Result<()> {
+        let ix = self.pet_ix(req.locker_id)?;
+        let char_id = self.session.char.model.id;
+        self.pick_up_drop(req.drop_id, DropLeaveParam::PetPickup(char_id as u32, ix))
+    }
+}
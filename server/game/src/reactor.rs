@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use data::services::helper::pool::reactor::ReactorEventKind;
+use proto95::game::reactor::{ReactorHitReq, ReactorTouchReq};
+
+use crate::GameHandler;
+
+impl GameHandler {
+    pub async fn handle_reactor_hit(&mut self, req: ReactorHitReq) -> anyhow::Result<()> {
+        self.field.trigger_reactor(req.id, ReactorEventKind::Hit, req.action_delay.into())?;
+        Ok(())
+    }
+
+    pub async fn handle_reactor_touch(&mut self, req: ReactorTouchReq) -> anyhow::Result<()> {
+        // The client also sends this when the character leaves the reactor area
+        if !req.has_reactor {
+            return Ok(());
+        }
+
+        self.field.trigger_reactor(req.id, ReactorEventKind::Touch, Duration::ZERO)?;
+        Ok(())
+    }
+}
@@ -1,26 +1,222 @@
-use clap::{Command, FromArgMatches, Parser, Subcommand};
-use data::services::helper::pool::{
-    drop::{Drop, DropTypeValue},
-    user::User,
-    Mob,
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
 };
-use proto95::id::ItemId;
+
+use clap::{Command, FromArgMatches, Parser, Subcommand, ValueEnum};
+use data::services::{
+    gm::{GmLevel, GM_LEVEL_ADMIN, GM_LEVEL_GM, GM_LEVEL_HELPER},
+    helper::pool::{
+        drop::{Drop, DropTypeValue},
+        user::User,
+        Mob,
+    },
+};
+use proto95::id::{ItemId, MapId};
 
 use crate::GameHandler;
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Stat {
+    Level,
+    Job,
+    Str,
+    Dex,
+    Int,
+    Luk,
+    Hp,
+    MaxHp,
+    Mp,
+    MaxMp,
+    Ap,
+    Sp,
+    Exp,
+    Fame,
+    Mesos,
+}
+
 #[derive(Parser, Debug)]
 pub enum ReplCmd {
-    Mob { id: Option<u32> },
-    Mesos { amount: u32 },
-    Item { id: Option<u32> },
-    Chat { msg: String },
-    FakeUser { id: u32 },
+    /// Lists the available commands, starting with the prefix
+    Help {
+        prefix: Option<String>,
+    },
+    /// Spawns a mob at the own position
+    Mob {
+        id: Option<u32>,
+    },
+    /// Drops mesos at the own position
+    Mesos {
+        amount: u32,
+    },
+    /// Drops an item at the own position
+    Item {
+        id: Option<u32>,
+    },
+    Chat {
+        msg: String,
+    },
+    FakeUser {
+        id: u32,
+    },
     Aggro,
     Dispose,
+    /// Screen effect, path relative to Map/Effect.img
+    Effect {
+        path: String,
+    },
+    /// Sound, path relative to Sound/Field.img
+    Sound {
+        path: String,
+    },
+    Bgm {
+        path: String,
+    },
+    /// Heavy tremble after the delay in ms
+    Tremble {
+        delay: Option<u32>,
+    },
+    /// Boss HP bar of the mob object
+    MobHp {
+        id: u32,
+    },
+    /// Weather of the weather item, 0 removes the weather
+    Weather {
+        id: u32,
+        msg: Option<String>,
+    },
+    /// Map-wide clock in seconds, 0 removes the clock
+    Clock {
+        secs: u64,
+    },
+    Obstacle {
+        name: String,
+        state: u32,
+    },
+    ResetObstacles,
+    /// Warps to the spawn point of the map
+    Warp {
+        map: u32,
+        spawn_point: Option<u8>,
+    },
+    /// Warps to the map of the character
+    WarpTo {
+        name: String,
+    },
+    /// Warps the character to the own map
+    Summon {
+        name: String,
+    },
+    /// Restores HP and MP of the character or the own
+    Heal {
+        name: Option<String>,
+    },
+    SetStat {
+        #[arg(value_enum)]
+        stat: Stat,
+        value: u32,
+    },
+    /// Adds the item to the own inventory
+    Give {
+        id: u32,
+        quantity: Option<u16>,
+    },
+    /// Bans the account of the character and disconnects it
+    Ban {
+        name: String,
+        #[arg(long)]
+        days: Option<u32>,
+        /// Reason code shown by the client
+        #[arg(long, default_value_t = 0)]
+        code: u8,
+        /// Also bans the machine of the last login
+        #[arg(long)]
+        machine: bool,
+        reason: Vec<String>,
+    },
+    /// Ends the active bans of the account of the character
+    Unban {
+        name: String,
+    },
+    /// Hides the own character from the other characters or shows it again
+    Hide,
+    /// Removes all mobs of the field without drops
+    KillAll,
+    /// Creates the field from the map data again, the characters re-join it
+    Reload,
+}
+
+impl ReplCmd {
+    /// Required GM level of the command
+    pub fn level(&self) -> GmLevel {
+        match self {
+            Self::Help { .. }
+            | Self::Chat { .. }
+            | Self::Dispose
+            | Self::Warp { .. }
+            | Self::WarpTo { .. }
+            | Self::Heal { .. }
+            | Self::Hide => GM_LEVEL_HELPER,
+            Self::Mob { .. }
+            | Self::Mesos { .. }
+            | Self::Item { .. }
+            | Self::Aggro
+            | Self::Effect { .. }
+            | Self::Sound { .. }
+            | Self::Bgm { .. }
+            | Self::Tremble { .. }
+            | Self::MobHp { .. }
+            | Self::Weather { .. }
+            | Self::Clock { .. }
+            | Self::Obstacle { .. }
+            | Self::ResetObstacles
+            | Self::Summon { .. }
+            | Self::Give { .. }
+            | Self::KillAll => GM_LEVEL_GM,
+            Self::FakeUser { .. }
+            | Self::SetStat { .. }
+            | Self::Ban { .. }
+            | Self::Unban { .. }
+            | Self::Reload => GM_LEVEL_ADMIN,
+        }
+    }
+}
+
+/// Reason a command was not run
+#[derive(Debug)]
+pub enum ReplError {
+    /// The command requires a higher GM level
+    Denied(String),
+    Unknown(String),
+    /// Invalid arguments or a requested help
+    Invalid(clap::Error),
+}
+
+/// Parses the command with placeholders for the required arguments,
+/// so the level of a command is known before its arguments are
+fn probe_level(cli: &mut Command, name: &str) -> Option<GmLevel> {
+    let args = cli
+        .find_subcommand(name)?
+        .get_positionals()
+        .filter(|arg| arg.is_required_set())
+        .map(|arg| {
+            arg.get_possible_values()
+                .first()
+                .map_or_else(|| "1".to_string(), |value| value.get_name().to_string())
+        })
+        .collect::<Vec<_>>();
+    let matches = cli
+        .try_get_matches_from_mut(std::iter::once(name.to_string()).chain(args))
+        .ok()?;
+    ReplCmd::from_arg_matches(&matches)
+        .ok()
+        .map(|cmd| cmd.level())
 }
 
 pub struct GameRepl {
     cli: Command,
+    /// Levels of the commands by their name
+    levels: BTreeMap<String, GmLevel>,
 }
 
 impl Default for GameRepl {
@@ -38,28 +234,82 @@ impl GameRepl {
             .multicall(true)
             .arg_required_else_help(true)
             .subcommand_required(true)
+            .disable_help_subcommand(true)
             .subcommand_value_name("APPLET")
             .subcommand_help_heading("APPLETS")
             .help_template(PARSER_TEMPLATE);
 
-        let cmd = ReplCmd::augment_subcommands(cmd);
+        let mut cli = ReplCmd::augment_subcommands(cmd);
+        let names = cli
+            .get_subcommands()
+            .map(|cmd| cmd.get_name().to_string())
+            .collect::<Vec<_>>();
+        let levels = names
+            .into_iter()
+            .filter_map(|name| probe_level(&mut cli, &name).map(|level| (name, level)))
+            .collect();
 
-        Self { cli: cmd }
+        Self { cli, levels }
     }
-    pub fn match_cmd(&mut self, s: &str) -> anyhow::Result<ReplCmd> {
-        let args = s.split_whitespace();
-        let matches = self.cli.try_get_matches_from_mut(args)?;
-        Ok(ReplCmd::from_arg_matches(&matches)?)
+
+    /// Level of the command, unknown commands require the highest level
+    fn command_level(&self, name: &str) -> GmLevel {
+        self.levels.get(name).copied().unwrap_or(GM_LEVEL_ADMIN)
+    }
+
+    /// Parses the command, the command must be permitted for the GM level
+    pub fn match_cmd(&mut self, s: &str, level: GmLevel) -> Result<ReplCmd, ReplError> {
+        let name = s.split_whitespace().next().unwrap_or_default().to_string();
+        if !self.levels.contains_key(&name) {
+            return Err(ReplError::Unknown(name));
+        }
+        // Denied commands don't reveal their usage
+        if self.command_level(&name) > level {
+            return Err(ReplError::Denied(name));
+        }
+
+        let matches = self
+            .cli
+            .try_get_matches_from_mut(s.split_whitespace())
+            .map_err(ReplError::Invalid)?;
+        let cmd = ReplCmd::from_arg_matches(&matches).map_err(ReplError::Invalid)?;
+        if cmd.level() > level {
+            return Err(ReplError::Denied(name));
+        }
+        Ok(cmd)
     }
 
-    pub fn help(&mut self) -> String {
-        self.cli.render_help().to_string()
+    /// Commands for the GM level, which start with the prefix
+    pub fn complete(&self, prefix: &str, level: GmLevel) -> Vec<String> {
+        self.levels
+            .iter()
+            .filter(|(name, cmd_level)| name.starts_with(prefix) && **cmd_level <= level)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Usage of the command or a list of the commands starting with the prefix
+    pub fn help(&mut self, prefix: &str, level: GmLevel) -> String {
+        let completions = self.complete(prefix, level);
+        if let [name] = completions.as_slice() {
+            if let Some(cmd) = self.cli.find_subcommand_mut(name) {
+                return cmd.render_help().to_string();
+            }
+        }
+        if completions.is_empty() {
+            return format!("No command starts with {prefix}");
+        }
+        completions.join(", ")
     }
 }
 
 impl GameHandler {
     pub async fn handle_repl_cmd(&mut self, cmd: ReplCmd) -> anyhow::Result<Option<String>> {
         Ok(match cmd {
+            ReplCmd::Help { prefix } => {
+                let level = self.session.acc.gm_level;
+                Some(self.repl.help(prefix.as_deref().unwrap_or_default(), level))
+            }
             ReplCmd::Mob { id } => {
                 let mob = id.unwrap_or(1110100);
                 let meta = self.services.meta.get_mob_data(mob).unwrap();
@@ -83,6 +333,7 @@ impl GameHandler {
                     start_pos: self.pos,
                     value: DropTypeValue::Mesos(amount),
                     quantity: 1,
+                    spawned_at: Instant::now(),
                 })?;
                 None
             }
@@ -94,6 +345,7 @@ impl GameHandler {
                     start_pos: self.pos,
                     value: DropTypeValue::Item(item),
                     quantity: 1,
+                    spawned_at: Instant::now(),
                 })?;
                 None
             }
@@ -103,6 +355,10 @@ impl GameHandler {
                     char_id: id,
                     pos: self.pos,
                     fh: self.fh,
+                    mini_room: None,
+                    pets: Default::default(),
+                    portable_chair: None,
+                    hidden: false,
                 })?;
                 None
             }
@@ -116,13 +372,188 @@ impl GameHandler {
                 None
             }
             ReplCmd::Chat { msg } => Some(msg),
+            ReplCmd::Effect { path } => {
+                self.field.show_screen_effect(&path)?;
+                None
+            }
+            ReplCmd::Sound { path } => {
+                self.field.play_sound(&path)?;
+                None
+            }
+            ReplCmd::Bgm { path } => {
+                self.field.change_bgm(&path)?;
+                None
+            }
+            ReplCmd::Tremble { delay } => {
+                self.field.tremble(true, delay.unwrap_or(0))?;
+                None
+            }
+            ReplCmd::MobHp { id } => {
+                let shown = self.field.show_mob_hp(id, 1, 5)?;
+                (!shown).then(|| format!("No mob {id} on the field"))
+            }
+            ReplCmd::Weather { id, msg } => {
+                let msg = msg.unwrap_or_default();
+                let weather = (id != 0).then_some((ItemId(id), msg.as_str()));
+                self.field.blow_weather(weather)?;
+                None
+            }
+            ReplCmd::Clock { secs: 0 } => {
+                self.field.destroy_clock()?;
+                None
+            }
+            ReplCmd::Clock { secs } => {
+                self.field.set_clock(Duration::from_secs(secs))?;
+                None
+            }
+            ReplCmd::Obstacle { name, state } => {
+                self.field.set_obstacle(&name, state)?;
+                None
+            }
+            ReplCmd::ResetObstacles => {
+                self.field.reset_obstacles()?;
+                None
+            }
+            ReplCmd::Warp { map, spawn_point } => {
+                let map = MapId(map);
+                if self.services.meta.get_field_data(map).is_none() {
+                    return Ok(Some(format!("Invalid map: {}", map.0)));
+                }
+                self.warp(map, spawn_point.unwrap_or(0)).await?;
+                None
+            }
+            ReplCmd::WarpTo { name } => self.gm_warp_to(&name).await?,
+            ReplCmd::Summon { name } => self.gm_summon(&name).await?,
+            ReplCmd::Heal { name: None } => {
+                self.heal()?;
+                None
+            }
+            ReplCmd::Heal { name: Some(name) } => self.gm_heal(&name).await?,
+            ReplCmd::SetStat { stat, value } => self.set_stat(stat, value)?,
+            ReplCmd::Give { id, quantity } => {
+                let item = self.new_inv_item(ItemId(id), quantity.unwrap_or(1))?;
+                let left = self.add_inv_items(vec![item])?;
+                (!left.is_empty()).then(|| "The inventory is full".to_string())
+            }
+            ReplCmd::Ban {
+                name,
+                days,
+                code,
+                machine,
+                reason,
+            } => {
+                let reason = (!reason.is_empty()).then(|| reason.join(" "));
+                self.gm_ban(&name, days, code, reason, machine).await?
+            }
+            ReplCmd::Unban { name } => self.gm_unban(&name).await?,
+            ReplCmd::Hide => {
+                self.hidden = !self.hidden;
+                self.field
+                    .set_user_hidden(self.session.char.model.id, self.hidden)?;
+                Some(if self.hidden { "Hidden" } else { "Visible" }.to_string())
+            }
+            ReplCmd::KillAll => {
+                let killed = self.field.kill_all_mobs()?;
+                Some(format!("Killed {killed} mobs"))
+            }
+            ReplCmd::Reload => self.gm_reload_field(),
         })
     }
 
+    /// Runs the command If the GM level of the account permits it, every command is logged
     pub async fn handle_repl(&mut self, s: &str) -> anyhow::Result<Option<String>> {
-        Ok(match self.repl.match_cmd(s) {
-            Err(_) => Some(self.repl.help()),
-            Ok(cmd) => self.handle_repl_cmd(cmd).await?,
-        })
+        let level = self.session.acc.gm_level;
+        if s.trim().is_empty() {
+            return Ok(Some(self.repl.help("", level)));
+        }
+        let cmd = self.repl.match_cmd(s, level);
+        self.services
+            .data
+            .gm_log
+            .log_command(&self.session.char.model, s, cmd.is_ok())
+            .await?;
+
+        let cmd = match cmd {
+            Ok(cmd) => cmd,
+            Err(ReplError::Denied(name)) => {
+                return Ok(Some(format!(
+                    "The command {name} requires a higher GM level"
+                )))
+            }
+            Err(ReplError::Invalid(err)) => return Ok(Some(err.to_string())),
+            Err(ReplError::Unknown(name)) => {
+                let completions = self.repl.complete(&name, level);
+                return Ok(Some(if completions.is_empty() {
+                    format!("Unknown command {name}, see @help")
+                } else {
+                    format!(
+                        "Unknown command {name}, did you mean: {}",
+                        completions.join(", ")
+                    )
+                }));
+            }
+        };
+        self.handle_repl_cmd(cmd).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data::services::gm::{GM_LEVEL_ADMIN, GM_LEVEL_GM, GM_LEVEL_HELPER};
+
+    use super::{GameRepl, ReplCmd, ReplError};
+
+    #[test]
+    fn all_commands_have_a_level() {
+        let repl = GameRepl::new();
+        let names = repl.cli.get_subcommands().count();
+        assert_eq!(repl.levels.len(), names);
+        assert_eq!(repl.complete("", GM_LEVEL_ADMIN).len(), names);
+    }
+
+    #[test]
+    fn gating() {
+        let mut repl = GameRepl::new();
+        assert!(matches!(
+            repl.match_cmd("warp 100000000", GM_LEVEL_HELPER),
+            Ok(ReplCmd::Warp { map: 100000000, .. })
+        ));
+        assert!(matches!(
+            repl.match_cmd("mob 100100", GM_LEVEL_HELPER),
+            Err(ReplError::Denied(name)) if name == "mob"
+        ));
+        assert!(matches!(
+            repl.match_cmd("mob 100100", GM_LEVEL_GM),
+            Ok(ReplCmd::Mob { .. })
+        ));
+        assert!(matches!(
+            repl.match_cmd("set-stat level 10", GM_LEVEL_GM),
+            Err(ReplError::Denied(_))
+        ));
+        assert!(matches!(
+            repl.match_cmd("ban someone --days 3", GM_LEVEL_ADMIN),
+            Ok(ReplCmd::Ban { days: Some(3), .. })
+        ));
+        assert!(matches!(
+            repl.match_cmd("nothing", GM_LEVEL_ADMIN),
+            Err(ReplError::Unknown(_))
+        ));
+        assert!(matches!(
+            repl.match_cmd("warp", GM_LEVEL_ADMIN),
+            Err(ReplError::Invalid(_))
+        ));
+        // Players can't run any command
+        assert!(matches!(
+            repl.match_cmd("help", 0),
+            Err(ReplError::Denied(_))
+        ));
+    }
+
+    #[test]
+    fn complete_by_level() {
+        let repl = GameRepl::new();
+        assert_eq!(repl.complete("war", GM_LEVEL_HELPER), ["warp", "warp-to"]);
+        assert!(repl.complete("ba", GM_LEVEL_GM).is_empty());
+        assert_eq!(repl.complete("ba", GM_LEVEL_ADMIN), ["ban"]);
     }
 }
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use data::services::{
+    transport::{TransportEvent, TransportState},
+    SharedServices,
+};
+use proto95::game::field::{ContiMoveResp, ContiStateReq, ContiStateResp, SetPassengerResultReq};
+
+use crate::GameHandler;
+
+const TRANSPORT_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runs the transport schedules and shows the moves of the ships at the stations
+pub fn spawn_transport_scheduler(services: &SharedServices) {
+    let services = services.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TRANSPORT_UPDATE_INTERVAL);
+        loop {
+            interval.tick().await;
+            for (route, event) in services.transport.poll_events() {
+                log::info!("Transport {}: {event:?}", route.name);
+                let (field_id, pkt) = match event {
+                    TransportEvent::Departed => (route.station, ContiMoveResp::start_ship_move()),
+                    TransportEvent::BoardingStarted => {
+                        (route.station, ContiMoveResp::end_ship_move())
+                    }
+                    TransportEvent::Arrived => continue,
+                };
+                let Some(field) = services.field.find_field(field_id) else {
+                    continue;
+                };
+                if let Err(err) = field.broadcast(pkt, -1) {
+                    log::error!("Unable to send transport move: {err:?}");
+                }
+            }
+        }
+    });
+}
+
+impl GameHandler {
+    /// Moves the passengers onto the ship once it departs and to the destination once it arrives
+    pub(crate) async fn update_transport(&mut self) -> anyhow::Result<()> {
+        let field_id = self.field.field_id();
+        let transport = &self.services.transport;
+
+        let target = if let Some(route) = transport.find_by_waiting_room(field_id) {
+            (transport.state(route) == TransportState::Moving).then_some(route.transport)
+        } else if let Some(route) = transport.find_by_transport(field_id) {
+            (transport.state(route) != TransportState::Moving).then_some(route.destination)
+        } else {
+            None
+        };
+
+        match target {
+            Some(map_id) => self.warp(map_id, 0).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn handle_conti_state(&mut self, req: ContiStateReq) -> anyhow::Result<()> {
+        let transport = &self.services.transport;
+        let Some(route) = transport.find_by_station(req.field_id) else {
+            return Ok(());
+        };
+        let docked = transport.state(route) == TransportState::Boarding;
+        self.send_pkt(ContiStateResp {
+            docked,
+            mob_ship: false,
+        })
+    }
+
+    /// The server never asks for passengers, so a result can't belong to a pending request
+    pub async fn handle_set_passenger_result(
+        &mut self,
+        req: SetPassengerResultReq,
+    ) -> anyhow::Result<()> {
+        log::debug!(
+            "Ignoring passenger result for {} without a request: {}",
+            req.requester_id,
+            req.accepted
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
 #[derive(Debug, Default)]
 pub struct LoginConfig {
     pub enable_pin: bool,
-    pub enable_pic: bool
+    pub enable_pic: bool,
+    /// Registers unknown usernames on their first login
+    pub auto_register: bool,
+    /// Days until a deleted character is removed, 0 removes it right away
+    pub char_delete_grace_days: u32,
 }
\ No newline at end of file
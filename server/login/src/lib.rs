@@ -5,10 +5,16 @@ use std::{net::IpAddr, time::Duration};
 
 use async_trait::async_trait;
 use config::LoginConfig;
-use data::services::data::account::AccountServiceError;
-use data::services::data::character::{CharacterCreateDTO, CharacterID, ItemStarterSet};
+use data::services::data::account::{AccResult, AccountServiceError, Region};
+use data::services::data::character::{
+    CharDeleteAuth, CharacterCreateDTO, CharacterID, ItemStarterSet,
+};
 use data::services::session::MoopleMigrationKey;
-use data::{entities::character, services};
+use data::{
+    entities::{account, ban, character},
+    services,
+};
+use either::Either;
 use login_state::LoginState;
 use moople_net::service::handler::SessionHandleResult;
 use moople_net::service::resp::PongResponse;
@@ -36,14 +42,17 @@ use proto95::{
         },
         char::{
             CharRankInfo, CheckDuplicateIDReq, CheckDuplicateIDResp, CheckDuplicateIDResult,
-            CreateCharReq, CreateCharResp, DeleteCharReq, DeleteCharResp, DeleteCharResult,
-            MigrateStageInfo, SelectCharReq, SelectCharResp, SelectCharResult, SelectWorldCharList,
-            SelectWorldResp, ViewChar, ViewCharWithRank,
+            CreateCharReq, CreateCharResp, DeleteCharReq, DeleteCharResp, MigrateStageInfo,
+            SelectCharReq, SelectCharReqVac, SelectCharResp, SelectCharResult,
+            SelectCharResultCode, SelectWorldCharList, SelectWorldResp, ViewAllCharFlagSet,
+            ViewAllCharList, ViewAllCharPrepare, ViewAllCharReq, ViewAllCharResp, ViewChar,
+            ViewCharWithRank,
         },
         pin::{CheckPinReq, CheckPinResp, UpdatePinReq, UpdatePinResp},
         world::{
-            ChannelId, LogoutWorldReq, SelectWorldReq, WorldCheckUserLimitReq,
-            WorldCheckUserLimitResp, WorldId, WorldInfoReq, WorldInfoResp, WorldReq,
+            ChannelId, LogoutWorldReq, RecommendWorldMessageResp, SelectWorldReq,
+            WorldCheckUserLimitReq, WorldCheckUserLimitResp, WorldId, WorldInfoReq, WorldInfoResp,
+            WorldReq,
         },
         CreateSecurityHandleReq, LoginOpt, LoginResultHeader,
     },
@@ -58,6 +67,14 @@ use tokio::net::TcpStream;
 
 pub type LoginResponse<T> = ResponsePacket<SendOpcodes, T>;
 pub type LoginResult<T> = Result<LoginResponse<T>, anyhow::Error>;
+/// Either the error of the selection or the migration to the channel
+pub type SelectCharResponse =
+    Either<LoginResponse<SelectCharResp>, MigrateResponse<LoginResponse<SelectCharResp>>>;
+/// World list followed by the recommended worlds
+type WorldListResponse = (
+    Vec<LoginResponse<WorldInfoResp>>,
+    Option<LoginResponse<RecommendWorldMessageResp>>,
+);
 
 pub struct LoginHandler {
     services: services::SharedServices,
@@ -114,6 +131,9 @@ impl MapleSessionHandler for LoginHandler {
             CreateCharReq => LoginHandler::handle_create_char,
             DeleteCharReq => LoginHandler::handle_delete_character,
             SelectCharReq => LoginHandler::handle_select_char,
+            ViewAllCharReq => LoginHandler::handle_view_all_char,
+            ViewAllCharFlagSet => LoginHandler::handle_view_all_char_flag_set,
+            SelectCharReqVac => LoginHandler::handle_select_char_vac,
             ExceptionLogReq => LoginHandler::handle_exception_log
         );
 
@@ -249,37 +269,44 @@ impl LoginHandler {
 
     async fn handle_world_check_user_limit(
         &mut self,
-        _req: WorldCheckUserLimitReq,
+        req: WorldCheckUserLimitReq,
     ) -> LoginResult<WorldCheckUserLimitResp> {
         let _acc = self.state.get_server_selection()?;
+        let load = self.services.online.channel_load();
+        let world = req.world as WorldId;
 
-        Ok(WorldCheckUserLimitResp {
-            over_user_limit: false,
-            populate_level: 0,
-        }
-        .into())
+        Ok(self
+            .services
+            .server_info
+            .get_server(world)?
+            .check_user_limit(world, &load)
+            .into())
     }
 
-    fn get_world_info(&self) -> Vec<LoginResponse<WorldInfoResp>> {
-        self.services
-            .server_info
-            .get_world_info_packets()
+    /// World list with the current channel load, followed by the recommended worlds
+    fn get_world_info(&self) -> WorldListResponse {
+        let server_info = &self.services.server_info;
+        let load = self.services.online.channel_load();
+        let worlds = server_info
+            .get_world_info_packets(&load)
             .into_iter()
             .map(|p| p.into())
-            .collect()
+            .collect();
+
+        (
+            worlds,
+            server_info.get_recommend_world_messages().map(|p| p.into()),
+        )
     }
 
     async fn handle_world_information(
         &mut self,
         _req: WorldInfoReq,
-    ) -> anyhow::Result<Vec<LoginResponse<WorldInfoResp>>> {
+    ) -> anyhow::Result<WorldListResponse> {
         Ok(self.get_world_info())
     }
 
-    async fn handle_world_request(
-        &mut self,
-        _req: WorldReq,
-    ) -> anyhow::Result<Vec<LoginResponse<WorldInfoResp>>> {
+    async fn handle_world_request(&mut self, _req: WorldReq) -> anyhow::Result<WorldListResponse> {
         Ok(self.get_world_info())
     }
 
@@ -287,17 +314,22 @@ impl LoginHandler {
         &mut self,
         req: CheckPasswordReq,
     ) -> LoginResult<CheckPasswordResp> {
-        let login_result = self.services.data.account.try_login(&req.id, &req.pw).await;
+        let login_result = self.login(&req).await;
         let hdr = LoginResultHeader::default();
 
         let res = match login_result {
-            Err(AccountServiceError::UsernameNotFound) => CheckPasswordResp::InvalidUserName(hdr),
-            Err(AccountServiceError::PasswordMismatch) => CheckPasswordResp::InvalidPassword(hdr),
-            Err(AccountServiceError::AccountIsBanned) => CheckPasswordResp::BlockedIp(BlockedIp {
-                hdr,
-                reason: 0,
-                ban_time: MapleTime::maple_default(),
-            }),
+            Err(
+                AccountServiceError::UsernameNotFound
+                | AccountServiceError::UsernameWrongSize
+                | AccountServiceError::UsernameWrongChar,
+            ) => CheckPasswordResp::InvalidUserName(hdr),
+            Err(
+                AccountServiceError::PasswordMismatch
+                | AccountServiceError::PasswordWrongSize
+                | AccountServiceError::PasswordWrongChar,
+            ) => CheckPasswordResp::InvalidPassword(hdr),
+            Err(AccountServiceError::AccountIsBanned(ref ban)) => ban_resp(hdr, ban),
+            Err(AccountServiceError::RateLimited) => CheckPasswordResp::UnableToLoginWithIp(hdr),
             Ok(acc) => {
                 let account_info = (&acc).into();
                 self.state.transition_login_with_acc(acc)?;
@@ -326,19 +358,76 @@ impl LoginHandler {
                     })
                 }
             }
-            _ => todo!("Unhandled Account Service Login Result: {:?}", login_result),
+            Err(err) => {
+                log::error!("Login failed: {err:?}");
+                CheckPasswordResp::SystemError(hdr)
+            }
         };
 
         Ok(res.into())
     }
 
+    /// Logs into the account, unknown usernames are registered If enabled
+    async fn login(&self, req: &CheckPasswordReq) -> AccResult<account::Model> {
+        if !self.services.login_limit.try_acquire(self.addr) {
+            log::info!("Login limit reached for {}", self.addr);
+            return Err(AccountServiceError::RateLimited);
+        }
+
+        let account = &self.services.data.account;
+        let acc = match account.try_login(&req.id, &req.pw).await {
+            Err(AccountServiceError::UsernameNotFound) if self.cfg.auto_register => {
+                self.register(req).await?
+            }
+            res => res?,
+        };
+        self.services
+            .data
+            .ban
+            .check_login(acc.id, &req.machine_id.0, self.addr)
+            .await?;
+        Ok(account.set_last_machine_id(acc, &req.machine_id.0).await?)
+    }
+
+    /// Registers the account, banned IPs and machines can't create accounts
+    async fn register(&self, req: &CheckPasswordReq) -> AccResult<account::Model> {
+        let (username, password) = (req.id.as_str(), req.pw.as_str());
+        if !self.services.register_limit.try_acquire(self.addr) {
+            log::info!("Registration limit reached for {}", self.addr);
+            return Err(AccountServiceError::RateLimited);
+        }
+        self.services
+            .data
+            .ban
+            .check_register(&req.machine_id.0, self.addr)
+            .await?;
+
+        let account = &self.services.data.account;
+        let acc_id = account
+            .create(username, password, Region::Europe, false, None)
+            .await?;
+        log::info!("Registered account {username}({acc_id}) from {}", self.addr);
+        account.try_login(username, password).await
+    }
+
     async fn handle_select_world(&mut self, req: SelectWorldReq) -> LoginResult<SelectWorldResp> {
         let acc = self.state.get_server_selection()?;
+        let (world, channel) = (req.world_id as WorldId, req.channel_id as ChannelId);
+        let load = self.services.online.channel_load();
+        if self
+            .services
+            .server_info
+            .get_server(world)?
+            .is_channel_full(world, channel, &load)
+        {
+            return Ok(SelectWorldResp::Err(()).into());
+        }
+
         let char_list = self
             .services
             .data
             .char
-            .get_characters_for_account(acc.id)
+            .get_world_characters_for_account(acc.id, world)
             .await?;
         let characters: MapleList8<_> = char_list.iter().map(map_char_with_rank).collect();
 
@@ -350,8 +439,7 @@ impl LoginHandler {
             //TODO get buy count
             buy_char_count: 3,
         };
-        self.state
-            .transition_char_select(req.world_id as WorldId, req.channel_id as ChannelId)?;
+        self.state.transition_char_select(world, channel)?;
 
         Ok(SelectWorldResp::Success(char_list).into())
     }
@@ -379,7 +467,11 @@ impl LoginHandler {
     }
 
     async fn handle_create_char(&mut self, req: CreateCharReq) -> LoginResult<CreateCharResp> {
-        let (acc, _, _) = self.state.get_char_select()?;
+        let (acc, world, _) = self.state.get_char_select()?;
+        let server = self.services.server_info.get_server(world)?;
+        if server.settings.block_char_creation {
+            return Ok(CreateCharResp::SystemError(()).into());
+        }
 
         let starter_set = ItemStarterSet {
             shoes: req.starter_set.shoes,
@@ -404,6 +496,7 @@ impl LoginHandler {
                     //TODO hair color
                     starter_set,
                     gender: req.gender,
+                    world_id: world,
                 },
                 &self.services.data.item,
             )
@@ -415,19 +508,20 @@ impl LoginHandler {
 
     async fn handle_delete_character(&mut self, req: DeleteCharReq) -> LoginResult<DeleteCharResp> {
         let (acc, _, _) = self.state.get_char_select()?;
-        let status = self
+        // Without a PIC the client asks for the birthday
+        let auth = if self.cfg.enable_pic {
+            CharDeleteAuth::Pic(&req.pic)
+        } else {
+            CharDeleteAuth::Birthday(&req.pic)
+        };
+        let grace = chrono::Duration::days(self.cfg.char_delete_grace_days as i64);
+        let result = self
             .services
             .data
             .char
-            .delete_character(acc, req.char_id as i32, &req.pic)
+            .delete_character(acc, req.char_id as i32, auth, grace)
             .await?;
 
-        let result = match status {
-            DeleteCharResult::Success => DeleteCharResult::Success,
-            //TODO add more
-            _ => DeleteCharResult::UnknownErr,
-        };
-
         Ok(DeleteCharResp {
             char_id: req.char_id,
             result,
@@ -435,9 +529,110 @@ impl LoginHandler {
         .into())
     }
 
+    async fn handle_view_all_char_flag_set(
+        &mut self,
+        _req: ViewAllCharFlagSet,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Announces the number of characters, then sends the characters of each world
+    async fn handle_view_all_char(
+        &mut self,
+        _req: ViewAllCharReq,
+    ) -> anyhow::Result<Vec<LoginResponse<ViewAllCharResp>>> {
+        let acc = self.state.get_server_selection()?;
+        let chars = self
+            .services
+            .data
+            .char
+            .get_characters_for_account(acc.id)
+            .await?;
+
+        let worlds: Vec<_> = self
+            .services
+            .server_info
+            .world_ids()
+            .map(|world_id| {
+                let world_chars: Vec<_> = chars
+                    .iter()
+                    .filter(|char| char.world_id == world_id as i32)
+                    .collect();
+                (world_id, world_chars)
+            })
+            .filter(|(_, world_chars)| !world_chars.is_empty())
+            .collect();
+
+        let prepare = ViewAllCharResp::Prepare(ViewAllCharPrepare {
+            count_related_servers: worlds.len() as u32,
+            count_chars: worlds.iter().map(|(_, chars)| chars.len() as u32).sum(),
+        });
+        let lists = worlds.into_iter().map(|(world_id, world_chars)| {
+            ViewAllCharResp::Success(ViewAllCharList {
+                world_id: world_id as u8,
+                characters: world_chars.into_iter().map(map_char).collect(),
+                login_opt: LoginOpt::NoSecondPassword1,
+            })
+        });
+
+        Ok(std::iter::once(prepare)
+            .chain(lists)
+            .map(|resp| resp.into())
+            .collect())
+    }
+
+    /// Picks the channel of the world with the fewest online characters,
+    /// `None` If every channel is full
+    fn least_populated_channel(&self, world: WorldId) -> anyhow::Result<Option<ChannelId>> {
+        let server = self.services.server_info.get_server(world)?;
+        let load = self.services.online.channel_load();
+        let ch = (0..server.channels.len() as ChannelId)
+            .min_by_key(|ch| load.get(&(world, *ch)).copied().unwrap_or(0))
+            .ok_or_else(|| anyhow::format_err!("World {world} has no channels"))?;
+        Ok((!server.is_channel_full(world, ch, &load)).then_some(ch))
+    }
+
+    /// Selects the character from the view all list, it joins a channel of its world
+    async fn handle_select_char_vac(
+        &mut self,
+        req: SelectCharReqVac,
+    ) -> anyhow::Result<SelectCharResponse> {
+        let acc = self.state.get_server_selection()?;
+        let char_svc = &self.services.data.char;
+        let char_id = req.char_id as CharacterID;
+        let code = char_svc.select_char(acc, char_id, req.world_id).await?;
+        if !matches!(code, SelectCharResultCode::Success) {
+            return Ok(select_char_err(code));
+        }
+
+        let Some(channel) = self.least_populated_channel(req.world_id)? else {
+            return Ok(select_char_err(SelectCharResultCode::UnknownErr));
+        };
+        self.state.transition_char_select(req.world_id, channel)?;
+        Ok(Either::Right(self.migrate_char(req.char_id).await?))
+    }
+
     async fn handle_select_char(
         &mut self,
         req: SelectCharReq,
+    ) -> anyhow::Result<SelectCharResponse> {
+        let (acc, world, _) = self.state.get_char_select()?;
+        let code = self
+            .services
+            .data
+            .char
+            .select_char(acc, req.char_id as CharacterID, world)
+            .await?;
+        if !matches!(code, SelectCharResultCode::Success) {
+            return Ok(select_char_err(code));
+        }
+        Ok(Either::Right(self.migrate_char(req.char_id).await?))
+    }
+
+    /// Hands the account over to the channel of the char selection
+    async fn migrate_char(
+        &mut self,
+        char_id: u32,
     ) -> anyhow::Result<MigrateResponse<ResponsePacket<SendOpcodes, SelectCharResp>>> {
         let (_, world, channel) = self.state.get_char_select()?;
 
@@ -448,14 +643,14 @@ impl LoginHandler {
             .session_manager
             .create_migration_session(
                 MoopleMigrationKey::new(client_key, self.addr),
-                (acc, req.char_id as CharacterID),
+                (acc, char_id as CharacterID),
             )
             .await?;
 
         let addr = self.services.server_info.get_channel_addr(world, channel)?;
         let migrate = MigrateStageInfo {
             socket_addr: addr.try_into()?,
-            char_id: req.char_id,
+            char_id,
             premium: false,
             premium_arg: 0,
         };
@@ -470,6 +665,27 @@ impl LoginHandler {
     }
 }
 
+fn select_char_err(code: SelectCharResultCode) -> SelectCharResponse {
+    Either::Left(
+        SelectCharResp {
+            error_code: code.into(),
+            result: SelectCharResult::Error(()),
+        }
+        .into(),
+    )
+}
+
+/// Shows the reason and the end of the ban, permanent bans end at the max time
+fn ban_resp(hdr: LoginResultHeader, ban: &ban::Model) -> CheckPasswordResp {
+    CheckPasswordResp::BlockedIp(BlockedIp {
+        hdr,
+        reason: ban.reason_code as u8,
+        ban_time: ban
+            .ban_time
+            .map_or_else(MapleTime::permanent, MapleTime::from),
+    })
+}
+
 pub fn map_char_to_avatar(char: &character::Model) -> AvatarData {
     AvatarData {
         gender: (&char.gender).into(),
@@ -0,0 +1,502 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Path, State},
+    headers::{authorization::Bearer, Authorization},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router, TypedHeader,
+};
+use chrono::{Duration, Utc};
+use constant_time_eq::constant_time_eq;
+use data::entities::ban;
+use data::services::{
+    data::ban::{parse_machine_id, BanInfo},
+    field::FieldSnapshot,
+    gm::GmAction,
+    helper::pool::drop::DropTypeValue,
+    online::OnlineChar,
+    SharedServices,
+};
+use proto95::{game::BroadcastMessageResp, id::MapId};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+/// Shorter tokens are rejected, so the API can't be guessed into
+const MIN_TOKEN_LEN: usize = 16;
+
+/// Shared state of the admin API, the shutdown is awaited by the main task
+#[derive(Clone)]
+pub struct AdminState {
+    services: SharedServices,
+    token: Arc<str>,
+    shutdown: Arc<Notify>,
+}
+
+impl AdminState {
+    pub fn new(
+        services: SharedServices,
+        token: String,
+        shutdown: Arc<Notify>,
+    ) -> anyhow::Result<Self> {
+        if token.trim().len() < MIN_TOKEN_LEN {
+            anyhow::bail!("The admin token must have at least {MIN_TOKEN_LEN} chars");
+        }
+
+        Ok(Self {
+            services,
+            token: token.into(),
+            shutdown,
+        })
+    }
+}
+
+pub struct AdminError(anyhow::Error);
+
+impl<E: Into<anyhow::Error>> From<E> for AdminError {
+    fn from(err: E) -> Self {
+        Self(err.into())
+    }
+}
+
+impl IntoResponse for AdminError {
+    /// The error is only logged, so no internals are leaked to the client
+    fn into_response(self) -> Response {
+        log::error!("Admin API error: {:?}", self.0);
+        let body = Json(ErrorResp {
+            error: "Internal server error".to_string(),
+        });
+        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+    }
+}
+
+type AdminResult<T> = Result<T, AdminError>;
+
+#[derive(Serialize)]
+struct ErrorResp {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct OnlineCharResp {
+    id: i32,
+    acc_id: i32,
+    name: String,
+    map_id: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct ChannelResp {
+    world_id: u32,
+    channel_id: u16,
+    chars: Vec<OnlineCharResp>,
+}
+
+#[derive(Deserialize)]
+struct BanReq {
+    reason: Option<String>,
+    /// Permanent ban If not set
+    days: Option<u32>,
+    #[serde(default)]
+    reason_code: u8,
+    /// Also blocks logins from this IP
+    ip: Option<IpAddr>,
+    /// Also blocks logins from the machine of the last login
+    #[serde(default)]
+    machine: bool,
+}
+
+#[derive(Serialize)]
+struct BanResp {
+    id: i32,
+    reason_code: i32,
+    reason: Option<String>,
+    /// End of the ban, permanent If not set
+    until: Option<String>,
+    ip: Option<String>,
+    machine_id: Option<String>,
+    created_at: Option<String>,
+}
+
+impl From<ban::Model> for BanResp {
+    fn from(ban: ban::Model) -> Self {
+        Self {
+            id: ban.id,
+            reason_code: ban.reason_code,
+            reason: ban.ban_reason,
+            until: ban.ban_time.map(|until| until.to_string()),
+            ip: ban.ip,
+            machine_id: ban.machine_id,
+            created_at: ban.created_at.map(|created_at| created_at.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct UnbanResp {
+    ended: u64,
+}
+
+#[derive(Deserialize)]
+struct NoticeReq {
+    msg: String,
+}
+
+#[derive(Serialize)]
+struct FieldSummaryResp {
+    field_id: u32,
+    instance_id: Option<u32>,
+    users: usize,
+    mobs: usize,
+    drops: usize,
+}
+
+#[derive(Serialize)]
+struct MobResp {
+    id: u32,
+    tmpl_id: u32,
+    hp: u32,
+    max_hp: u32,
+    x: i16,
+    y: i16,
+}
+
+#[derive(Serialize)]
+struct DropResp {
+    id: u32,
+    item_id: Option<u32>,
+    mesos: Option<u32>,
+    quantity: usize,
+    x: i16,
+    y: i16,
+}
+
+#[derive(Serialize)]
+struct FieldResp {
+    field_id: u32,
+    instance_id: Option<u32>,
+    users: Vec<i32>,
+    mobs: Vec<MobResp>,
+    drops: Vec<DropResp>,
+}
+
+impl From<FieldSnapshot> for FieldResp {
+    fn from(field: FieldSnapshot) -> Self {
+        Self {
+            field_id: field.field_id.0,
+            instance_id: field.instance_id,
+            users: field.users,
+            mobs: field
+                .mobs
+                .into_iter()
+                .map(|mob| MobResp {
+                    id: mob.id,
+                    tmpl_id: mob.tmpl_id,
+                    hp: mob.hp,
+                    max_hp: mob.max_hp,
+                    x: mob.pos.x,
+                    y: mob.pos.y,
+                })
+                .collect(),
+            drops: field
+                .drops
+                .into_iter()
+                .map(|drop| {
+                    let (item_id, mesos) = match drop.value {
+                        DropTypeValue::Item(item_id) => (Some(item_id.0), None),
+                        DropTypeValue::Mesos(mesos) => (None, Some(mesos)),
+                    };
+                    DropResp {
+                        id: drop.id,
+                        item_id,
+                        mesos,
+                        quantity: drop.quantity,
+                        x: drop.pos.x,
+                        y: drop.pos.y,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SessionsResp {
+    online: usize,
+    sessions: usize,
+    pending_migrations: usize,
+    save_failures: usize,
+}
+
+/// Every request has to carry the configured token as bearer token
+async fn auth<B>(
+    State(state): State<AdminState>,
+    auth: Option<TypedHeader<Authorization<Bearer>>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    match auth {
+        Some(TypedHeader(auth))
+            if constant_time_eq(auth.token().as_bytes(), state.token.as_bytes()) =>
+        {
+            Ok(next.run(req).await)
+        }
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+fn online_char_resp(state: &AdminState, char: OnlineChar) -> OnlineCharResp {
+    let map_id = state
+        .services
+        .field
+        .find_user_field(char.id)
+        .map(|field| field.field_id().0);
+    OnlineCharResp {
+        id: char.id,
+        acc_id: char.acc_id,
+        name: char.name,
+        map_id,
+    }
+}
+
+async fn list_chars(State(state): State<AdminState>) -> Json<Vec<ChannelResp>> {
+    let channels = state
+        .services
+        .online
+        .by_channel()
+        .into_iter()
+        .map(|((world_id, channel_id), chars)| ChannelResp {
+            world_id,
+            channel_id,
+            chars: chars
+                .into_iter()
+                .map(|char| online_char_resp(&state, char))
+                .collect(),
+        })
+        .collect();
+    Json(channels)
+}
+
+async fn kick_char(State(state): State<AdminState>, Path(name): Path<String>) -> StatusCode {
+    let Some(char) = state.services.online.find_by_name(&name) else {
+        return StatusCode::NOT_FOUND;
+    };
+    state.services.gm.push_action(char.id, GmAction::Kick);
+    StatusCode::NO_CONTENT
+}
+
+async fn ban_char(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(req): Json<BanReq>,
+) -> AdminResult<StatusCode> {
+    let Some(char) = state.services.data.char.get_by_name(&name).await? else {
+        return Ok(StatusCode::NOT_FOUND);
+    };
+
+    let machine_id = if req.machine {
+        let account = &state.services.data.account;
+        let Some(machine_id) = account.get_last_machine_id(char.acc_id).await? else {
+            return Ok(StatusCode::UNPROCESSABLE_ENTITY);
+        };
+        Some(machine_id)
+    } else {
+        None
+    };
+    let until = req
+        .days
+        .map(|days| Utc::now().naive_utc() + Duration::days(days as i64));
+    let info = BanInfo {
+        reason_code: req.reason_code,
+        reason: req.reason,
+        until,
+        ip: req.ip,
+        machine_id,
+    };
+    state.services.ban_account(char.acc_id, info).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_bans(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> AdminResult<Result<Json<Vec<BanResp>>, StatusCode>> {
+    let Some(char) = state.services.data.char.get_by_name(&name).await? else {
+        return Ok(Err(StatusCode::NOT_FOUND));
+    };
+    let bans = state.services.data.ban.get_bans(char.acc_id).await?;
+    Ok(Ok(Json(bans.into_iter().map(BanResp::from).collect())))
+}
+
+async fn unban_char(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> AdminResult<Result<Json<UnbanResp>, StatusCode>> {
+    let Some(char) = state.services.data.char.get_by_name(&name).await? else {
+        return Ok(Err(StatusCode::NOT_FOUND));
+    };
+    let ended = state.services.data.ban.unban_account(char.acc_id).await?;
+    Ok(Ok(Json(UnbanResp { ended })))
+}
+
+async fn unban_ip(
+    State(state): State<AdminState>,
+    Path(ip): Path<IpAddr>,
+) -> AdminResult<Json<UnbanResp>> {
+    let ended = state.services.data.ban.unban_ip(ip).await?;
+    Ok(Json(UnbanResp { ended }))
+}
+
+async fn unban_machine(
+    State(state): State<AdminState>,
+    Path(machine_id): Path<String>,
+) -> AdminResult<Result<Json<UnbanResp>, StatusCode>> {
+    let Some(machine_id) = parse_machine_id(&machine_id) else {
+        return Ok(Err(StatusCode::BAD_REQUEST));
+    };
+    let ended = state.services.data.ban.unban_machine(&machine_id).await?;
+    Ok(Ok(Json(UnbanResp { ended })))
+}
+
+async fn notice(
+    State(state): State<AdminState>,
+    Json(req): Json<NoticeReq>,
+) -> AdminResult<StatusCode> {
+    state
+        .services
+        .field
+        .broadcast_all(|| BroadcastMessageResp::Notice(req.msg.clone()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_fields(State(state): State<AdminState>) -> Json<Vec<FieldSummaryResp>> {
+    let fields = state
+        .services
+        .field
+        .fields()
+        .into_iter()
+        .map(|field| {
+            let field = field.snapshot();
+            FieldSummaryResp {
+                field_id: field.field_id.0,
+                instance_id: field.instance_id,
+                users: field.users.len(),
+                mobs: field.mobs.len(),
+                drops: field.drops.len(),
+            }
+        })
+        .collect();
+    Json(fields)
+}
+
+async fn get_field(
+    State(state): State<AdminState>,
+    Path(id): Path<u32>,
+) -> Result<Json<FieldResp>, StatusCode> {
+    let field = state
+        .services
+        .field
+        .find_field(MapId(id))
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(field.snapshot().into()))
+}
+
+async fn sessions(State(state): State<AdminState>) -> Json<SessionsResp> {
+    let services = &state.services;
+    Json(SessionsResp {
+        online: services.online.len(),
+        sessions: services.session_manager.session_count(),
+        pending_migrations: services.session_manager.pending_migrations(),
+        save_failures: services.session_manager.save_failures(),
+    })
+}
+
+async fn shutdown(State(state): State<AdminState>) -> StatusCode {
+    log::info!("Shutdown requested by the admin API");
+    state.shutdown.notify_one();
+    StatusCode::ACCEPTED
+}
+
+fn router(state: AdminState) -> Router {
+    Router::new()
+        .route("/chars", get(list_chars))
+        .route("/chars/:name/kick", post(kick_char))
+        .route("/chars/:name/ban", post(ban_char))
+        .route("/chars/:name/bans", get(list_bans))
+        .route("/chars/:name/unban", post(unban_char))
+        .route("/ips/:ip/unban", post(unban_ip))
+        .route("/machines/:id/unban", post(unban_machine))
+        .route("/notice", post(notice))
+        .route("/fields", get(list_fields))
+        .route("/fields/:id", get(get_field))
+        .route("/sessions", get(sessions))
+        .route("/shutdown", post(shutdown))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth))
+        .with_state(state)
+}
+
+pub async fn serve(addr: SocketAddr, state: AdminState) -> anyhow::Result<()> {
+    axum::Server::bind(&addr)
+        .serve(router(state).into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{header::AUTHORIZATION, Request, StatusCode},
+    };
+    use data::services::{
+        meta::meta_service::{MetaData, MetaService},
+        Services,
+    };
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    use super::{router, AdminState};
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    async fn state(token: &str) -> anyhow::Result<AdminState> {
+        let meta = Box::leak(Box::new(MetaService::new(MetaData::default())));
+        let services = Services::seeded_in_memory([], meta).await?.as_shared();
+        AdminState::new(services, token.to_string(), Arc::new(Notify::new()))
+    }
+
+    async fn status(state: &AdminState, token: Option<&str>) -> anyhow::Result<StatusCode> {
+        let mut req = Request::get("/sessions");
+        if let Some(token) = token {
+            req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let resp = router(state.clone()).oneshot(req.body(Body::empty())?).await?;
+        Ok(resp.status())
+    }
+
+    #[tokio::test]
+    async fn bearer_auth() -> anyhow::Result<()> {
+        let state = state(TOKEN).await?;
+        assert_eq!(status(&state, None).await?, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(&state, Some("fedcba9876543210")).await?,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(status(&state, Some(TOKEN)).await?, StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reject_short_token() {
+        assert!(state("").await.is_err());
+        assert!(state("short").await.is_err());
+    }
+}
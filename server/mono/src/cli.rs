@@ -0,0 +1,50 @@
+use clap::{Parser, Subcommand};
+use data::services::{data::account::Region, SharedServices};
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// The servers are run without a command
+    #[command(subcommand)]
+    pub cmd: Option<CliCmd>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCmd {
+    /// Registers a new account
+    CreateAccount {
+        username: String,
+        password: String,
+        #[arg(long, default_value_t = 0)]
+        gm_level: i32,
+    },
+    /// Sets a new password for the account
+    ResetPassword { username: String, password: String },
+}
+
+impl CliCmd {
+    pub async fn run(self, services: &SharedServices) -> anyhow::Result<()> {
+        let account = &services.data.account;
+        match self {
+            CliCmd::CreateAccount {
+                username,
+                password,
+                gm_level,
+            } => {
+                let acc_id = account
+                    .create(&username, &password, Region::Europe, false, None)
+                    .await?;
+                if gm_level > 0 {
+                    let acc = account.get(acc_id).await?.expect("Created account");
+                    account.set_gm_level(acc, gm_level).await?;
+                }
+                log::info!("Created account {username}({acc_id})");
+            }
+            CliCmd::ResetPassword { username, password } => {
+                account.reset_password(&username, &password).await?;
+                log::info!("Reset the password of {username}");
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use data::services::server_info::WorldSettings;
+
 #[derive(serde::Deserialize)]
 pub struct Config {
     pub version: String,
@@ -9,6 +11,26 @@ pub struct Config {
     pub client_version: usize,
     pub bind_ip: String,
     pub shrooming_port: u16,
+    pub admin_port: u16,
+    /// Address of the admin API, only reachable from the host by default
+    pub admin_bind_ip: String,
+    /// Bearer token of the admin API, the API is disabled without a token
+    pub admin_token: Option<String>,
+    /// Registers unknown usernames on their first login
+    pub auto_register: bool,
+    /// Days until a deleted character is removed
+    pub char_delete_grace_days: u32,
+    /// Time between the shutdown notice and closing the sessions
+    pub shutdown_countdown_secs: u64,
+    /// Time to wait for the sessions to be saved on shutdown
+    pub shutdown_timeout_secs: u64,
+    /// Sqlite or postgres url, the migrations are applied on startup
+    pub database_url: String,
+    /// Creates the test account and character on startup
+    pub seed_test_data: bool,
+    /// World list entry of the world
+    #[serde(default)]
+    pub world: WorldSettings,
 }
 
 pub fn get_configuration() -> Result<Config, config::ConfigError> {
@@ -1,7 +1,14 @@
-use std::net::{IpAddr, SocketAddr};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
 
+use clap::Parser;
 use data::services::{
-    meta::meta_service::MetaService, server_info::ServerInfo, Services, SharedServices,
+    meta::meta_service::MetaService,
+    server_info::ServerInfo,
+    Services, SharedServices,
 };
 use login::{config::LoginConfig, LoginHandler};
 use moople_net::service::{
@@ -9,16 +16,19 @@ use moople_net::service::{
     session_svc::{MapleServer, SharedSessionHandle},
     BasicHandshakeGenerator, HandshakeGenerator,
 };
-use tokio::{net::TcpStream, task::JoinSet};
+use shutdown::ShutdownCoordinator;
+use tokio::{net::TcpStream, sync::Notify, task::JoinSet};
+use tokio_util::sync::CancellationToken;
 
 use shrooming::{FileIndex, FileSvr};
 
+mod admin;
+mod cli;
 mod config;
+mod shutdown;
 
-static LOGIN_CFG: &LoginConfig = &LoginConfig {
-    enable_pic: true,
-    enable_pin: false,
-};
+/// Time between the purges of the deleted characters
+const CHAR_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Clone, Debug)]
 pub struct Shared;
@@ -26,6 +36,7 @@ pub struct Shared;
 #[derive(Debug, Clone)]
 pub struct MakeLoginHandler {
     services: SharedServices,
+    cfg: &'static LoginConfig,
 }
 
 #[async_trait::async_trait]
@@ -43,7 +54,7 @@ impl MakeServerSessionHandler for MakeLoginHandler {
     ) -> Result<Self::Handler, Self::Error> {
         Ok(LoginHandler::new(
             self.services.clone(),
-            LOGIN_CFG,
+            self.cfg,
             sess.peer_addr()?.ip(),
         ))
     }
@@ -53,8 +64,13 @@ async fn srv_login_server(
     addr: impl tokio::net::ToSocketAddrs,
     handshake_gen: impl HandshakeGenerator,
     services: SharedServices,
+    cfg: &'static LoginConfig,
+    shutdown: CancellationToken,
+    stop_accept: CancellationToken,
 ) -> anyhow::Result<()> {
-    let mut login_server = MapleServer::new(handshake_gen, MakeLoginHandler { services });
+    let mut login_server = MapleServer::new(handshake_gen, MakeLoginHandler { services, cfg })
+        .with_shutdown(shutdown)
+        .with_stop_accept(stop_accept);
     login_server.serve_tcp(addr).await?;
     Ok(())
 }
@@ -65,15 +81,53 @@ async fn srv_game_server(
     services: SharedServices,
     world_id: u32,
     channel_id: u16,
+    shutdown: CancellationToken,
+    stop_accept: CancellationToken,
 ) -> anyhow::Result<()> {
     let mut game_server = MapleServer::new(
         handshake_gen,
         game::MakeGameHandler::new(services, channel_id, world_id),
-    );
+    )
+    .with_shutdown(shutdown)
+    .with_stop_accept(stop_accept);
     game_server.serve_tcp(addr).await?;
     Ok(())
 }
 
+async fn srv_cash_shop_server(
+    addr: impl tokio::net::ToSocketAddrs,
+    handshake_gen: impl HandshakeGenerator,
+    services: SharedServices,
+    world_id: u32,
+    shutdown: CancellationToken,
+    stop_accept: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut cash_shop_server = MapleServer::new(
+        handshake_gen,
+        cash_shop::MakeCashShopHandler::new(services, world_id),
+    )
+    .with_shutdown(shutdown)
+    .with_stop_accept(stop_accept);
+    cash_shop_server.serve_tcp(addr).await?;
+    Ok(())
+}
+
+/// Removes the deleted characters once their grace period is over
+fn spawn_char_purge(services: &SharedServices) {
+    let services = services.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHAR_PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match services.data.char.purge_expired().await {
+                Ok(0) => (),
+                Ok(n) => log::info!("Purged {n} deleted characters"),
+                Err(err) => log::error!("Unable to purge the deleted characters: {err:?}"),
+            }
+        }
+    });
+}
+
 async fn srv_shrooming(addr: SocketAddr) -> anyhow::Result<()> {
     let file_ix = FileIndex::build_index(
         [
@@ -92,6 +146,7 @@ async fn srv_shrooming(addr: SocketAddr) -> anyhow::Result<()> {
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     pretty_env_logger::init();
+    let cli = cli::Cli::parse();
     // Load configuration
     let settings = config::get_configuration().expect("Failed to load configuration");
     log::info!("{0} - Mono - {1}", settings.server_name, settings.version);
@@ -109,7 +164,8 @@ async fn main() -> anyhow::Result<()> {
         settings.base_port,
         settings.server_name,
         settings.num_channels,
-    )];
+    )
+    .with_settings(settings.world)];
 
     // Create login server
     let handshake_gen = match settings.client_version {
@@ -121,17 +177,56 @@ async fn main() -> anyhow::Result<()> {
     // Meta will be available all the time
     let static_meta = Box::leak(meta);
 
-    let services = Services::seeded_in_memory(servers, static_meta)
+    let services = Services::connect(&settings.database_url, servers, static_meta)
         .await?
         .as_shared();
-    let (acc_id, char_id) = services.seed_acc_char().await?;
-    log::info!("Created test account {acc_id} - char: {char_id}");
+    if let Some(cmd) = cli.cmd {
+        return cmd.run(&services).await;
+    }
+
+    if settings.seed_test_data {
+        let (acc_id, char_id) = services.seed_acc_char().await?;
+        log::info!("Seeded test account {acc_id} - char: {char_id}");
+    }
+    game::mini_room::spawn_entrusted_shops(&services).await?;
+    game::transport::spawn_transport_scheduler(&services);
+    spawn_char_purge(&services);
+
+    let shutdown = Arc::new(Notify::new());
+    match settings.admin_token {
+        Some(token) => {
+            let state = admin::AdminState::new(services.clone(), token, shutdown.clone())?;
+            let addr = SocketAddr::new(settings.admin_bind_ip.parse()?, settings.admin_port);
+            tokio::spawn(async move {
+                if let Err(err) = admin::serve(addr, state).await {
+                    log::error!("Admin API failed: {err:?}");
+                }
+            });
+        }
+        None => log::info!("No admin token is configured, the admin API is disabled"),
+    }
+
+    let login_cfg: &'static LoginConfig = Box::leak(Box::new(LoginConfig {
+        enable_pic: true,
+        enable_pin: false,
+        auto_register: settings.auto_register,
+        char_delete_grace_days: settings.char_delete_grace_days,
+    }));
+
+    let coordinator = ShutdownCoordinator::new(
+        services.clone(),
+        Duration::from_secs(settings.shutdown_countdown_secs),
+        Duration::from_secs(settings.shutdown_timeout_secs),
+    );
 
     let mut set = JoinSet::new();
     set.spawn(srv_login_server(
         SocketAddr::new(bind_addr, settings.base_port),
         handshake_gen.clone(),
         services.clone(),
+        login_cfg,
+        coordinator.token(),
+        coordinator.stop_accept_token(),
     ));
     for ch in 0..settings.num_channels {
         set.spawn(srv_game_server(
@@ -140,13 +235,48 @@ async fn main() -> anyhow::Result<()> {
             services.clone(),
             0,
             ch as u16,
+            coordinator.token(),
+            coordinator.stop_accept_token(),
         ));
     }
+    // The cash shop listens on the port after the last channel
+    set.spawn(srv_cash_shop_server(
+        SocketAddr::new(
+            bind_addr,
+            settings.base_port + 1 + settings.num_channels as u16,
+        ),
+        handshake_gen.clone(),
+        services.clone(),
+        0,
+        coordinator.token(),
+        coordinator.stop_accept_token(),
+    ));
 
     log::info!("Listening ...");
-    while let Some(res) = set.join_next().await {
-        let _ = res?;
+    let signal = shutdown::signal();
+    tokio::pin!(signal);
+    loop {
+        tokio::select! {
+            // A failed server stops the others gracefully instead of dropping them
+            res = set.join_next() => match res {
+                Some(Ok(Ok(()))) => (),
+                Some(Ok(Err(err))) => {
+                    log::error!("Server failed: {err:?}");
+                    break;
+                }
+                Some(Err(err)) => {
+                    log::error!("Server task failed: {err:?}");
+                    break;
+                }
+                None => break,
+            },
+            _ = shutdown.notified() => break,
+            _ = &mut signal => break,
+        }
     }
 
+    log::info!("Shutting down ...");
+    coordinator.shutdown(&mut set).await;
+
     Ok(())
 }
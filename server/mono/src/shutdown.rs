@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use data::services::SharedServices;
+use proto95::game::BroadcastMessageResp;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+/// Time between the countdown notices
+const NOTICE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Resolves on ctrl-c or SIGTERM
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut term = signal(SignalKind::terminate()).expect("SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => (),
+            _ = term.recv() => (),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Stops all servers, so every session is saved before the process exits
+pub struct ShutdownCoordinator {
+    services: SharedServices,
+    token: CancellationToken,
+    stop_accept: CancellationToken,
+    countdown: Duration,
+    timeout: Duration,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(services: SharedServices, countdown: Duration, timeout: Duration) -> Self {
+        Self {
+            services,
+            token: CancellationToken::new(),
+            stop_accept: CancellationToken::new(),
+            countdown,
+            timeout,
+        }
+    }
+
+    /// Cancelled once the sessions should be closed
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Cancelled once the countdown starts, so no new sessions are accepted
+    pub fn stop_accept_token(&self) -> CancellationToken {
+        self.stop_accept.clone()
+    }
+
+    fn notice(&self, msg: String) {
+        let res = self
+            .services
+            .field
+            .broadcast_all(|| BroadcastMessageResp::Notice(msg.clone()));
+        if let Err(err) = res {
+            log::error!("Unable to broadcast the shutdown notice: {err:?}");
+        }
+    }
+
+    async fn countdown(&self) {
+        let mut left = self.countdown;
+        while !left.is_zero() {
+            self.notice(format!(
+                "The server shuts down in {} seconds",
+                left.as_secs()
+            ));
+            let step = left.min(NOTICE_INTERVAL);
+            tokio::time::sleep(step).await;
+            left -= step;
+        }
+    }
+
+    async fn join_servers(&self, servers: &mut JoinSet<anyhow::Result<()>>) {
+        while let Some(res) = servers.join_next().await {
+            match res {
+                Ok(Err(err)) => log::error!("Server exited with error: {err:?}"),
+                Err(err) => log::error!("Server task failed: {err:?}"),
+                Ok(Ok(())) => (),
+            }
+        }
+
+        let migrations = self
+            .services
+            .session_manager
+            .save_pending_migrations()
+            .await;
+        log::info!("Saved {migrations} pending migration sessions");
+    }
+
+    /// Stops accepting connections, announces the shutdown, closes all sessions
+    /// and waits until they are saved or the timeout is reached
+    pub async fn shutdown(self, servers: &mut JoinSet<anyhow::Result<()>>) {
+        self.stop_accept.cancel();
+        self.countdown().await;
+
+        log::info!("Closing all sessions ...");
+        self.token.cancel();
+        match tokio::time::timeout(self.timeout, self.join_servers(servers)).await {
+            Ok(()) => log::info!("All sessions are saved"),
+            Err(_) => log::error!(
+                "Shutdown timeout reached, {} sessions are not saved",
+                self.services.session_manager.session_count()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use data::services::{
+        meta::meta_service::{MetaData, MetaService},
+        Services,
+    };
+    use tokio::task::JoinSet;
+
+    use super::ShutdownCoordinator;
+
+    #[tokio::test]
+    async fn stop_accept_on_countdown() -> anyhow::Result<()> {
+        let meta = Box::leak(Box::new(MetaService::new(MetaData::default())));
+        let services = Services::seeded_in_memory([], meta).await?.as_shared();
+        let coordinator =
+            ShutdownCoordinator::new(services, Duration::from_millis(200), Duration::from_secs(1));
+        let (stop_accept, token) = (coordinator.stop_accept_token(), coordinator.token());
+
+        let mut servers = JoinSet::new();
+        let session = token.clone();
+        servers.spawn(async move {
+            session.cancelled().await;
+            Ok(())
+        });
+        let shutdown = tokio::spawn(async move { coordinator.shutdown(&mut servers).await });
+
+        // No connections are accepted during the countdown, the sessions stay open
+        tokio::time::timeout(Duration::from_millis(100), stop_accept.cancelled()).await?;
+        assert!(!token.is_cancelled());
+
+        shutdown.await?;
+        assert!(token.is_cancelled());
+        Ok(())
+    }
+}
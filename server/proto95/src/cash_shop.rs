@@ -0,0 +1,226 @@
+use moople_derive::MooplePacket;
+use moople_packet::{
+    maple_enum_code, maple_packet_enum, packet_opcode,
+    proto::{
+        string::FixedPacketString,
+        time::{MapleExpiration, Ticks},
+        MapleList16, MapleList32,
+    },
+};
+
+use crate::{
+    id::ItemId,
+    recv_opcodes::RecvOpcodes,
+    send_opcodes::SendOpcodes,
+    shared::{
+        char::{CharDataAll, CharDataFlagsAll, CharDataHeader, CharacterId},
+        inventory::InventoryType,
+        item::Item,
+        NameStr,
+    },
+};
+
+/// Serial number of a commodity in the cash shop catalogue
+pub type CommoditySN = u32;
+/// Serial number of a cash item, which is also the cash id of the item
+pub type CashItemSN = u64;
+
+pub const WISH_LIST_LEN: usize = 10;
+
+#[derive(MooplePacket, Debug)]
+pub struct UserMigrateToCashShopReq {
+    pub ticks: Ticks,
+}
+packet_opcode!(
+    UserMigrateToCashShopReq,
+    RecvOpcodes::UserMigrateToCashShopRequest
+);
+
+/// Sent by the client to leave the cash shop, in contrast to the field transfer
+/// in the game server the packet has no payload
+#[derive(MooplePacket, Debug)]
+pub struct CashShopTransferFieldReq;
+packet_opcode!(
+    CashShopTransferFieldReq,
+    RecvOpcodes::UserTransferFieldRequest
+);
+
+#[derive(MooplePacket, Debug, Default, Clone, Copy)]
+pub struct CashShopBestItem {
+    pub category: u32,
+    pub gender: u32,
+    pub sn: CommoditySN,
+}
+
+/// Categories * genders * items per category
+pub const BEST_ITEMS: usize = 9 * 2 * 5;
+
+#[derive(MooplePacket, Debug)]
+pub struct SetCashShopResp {
+    pub char_data_flags: CharDataFlagsAll,
+    pub char_data_hdr: CharDataHeader,
+    pub char_data: CharDataAll,
+    pub authorized: bool,
+    pub nexon_club_id: String,
+    pub not_sale: MapleList32<CommoditySN>,
+    //TODO modified commodities and discount rates, both are always empty for now
+    pub modified_commodities: u16,
+    pub discount_rates: u8,
+    pub best_items: [CashShopBestItem; BEST_ITEMS],
+    pub stock: u16,
+    pub limit_goods: u16,
+    pub zero_goods: u16,
+    pub event_on: bool,
+    pub highest_level: u32,
+}
+packet_opcode!(SetCashShopResp, SendOpcodes::SetCashShop);
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopQueryCashReq;
+packet_opcode!(CashShopQueryCashReq, RecvOpcodes::CashShopQueryCashRequest);
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopQueryCashResp {
+    pub nx_credit: u32,
+    pub maple_points: u32,
+    pub nx_prepaid: u32,
+}
+packet_opcode!(CashShopQueryCashResp, SendOpcodes::CashShopQueryCashResult);
+
+maple_enum_code!(CashType, u32, NxCredit = 1, MaplePoints = 2, NxPrepaid = 4);
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopBuyReq {
+    pub u1: u8,
+    pub cash_type: CashType,
+    pub sn: CommoditySN,
+    pub one_a_day: bool,
+    pub event_sn: u32,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopGiftReq {
+    pub spw: String,
+    pub sn: CommoditySN,
+    pub u1: u8,
+    pub receiver: String,
+    pub msg: String,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopSetWishReq {
+    pub wish_list: [CommoditySN; WISH_LIST_LEN],
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopMoveLToSReq {
+    pub sn: CashItemSN,
+    pub inv_type: InventoryType,
+    pub pos: u16,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopMoveSToLReq {
+    pub sn: CashItemSN,
+    pub inv_type: InventoryType,
+}
+
+maple_packet_enum!(
+    CashShopCashItemReq,
+    u8,
+    Buy(CashShopBuyReq) => 3,
+    Gift(CashShopGiftReq) => 4,
+    SetWish(CashShopSetWishReq) => 5,
+    MoveLToS(CashShopMoveLToSReq) => 0xE,
+    MoveSToL(CashShopMoveSToLReq) => 0xF,
+);
+packet_opcode!(CashShopCashItemReq, RecvOpcodes::CashShopCashItemRequest);
+
+/// Item in the cash locker
+#[derive(MooplePacket, Debug)]
+pub struct CashItemInfo {
+    pub sn: CashItemSN,
+    pub acc_id: u32,
+    pub char_id: CharacterId,
+    pub item_id: ItemId,
+    pub commodity_sn: CommoditySN,
+    pub quantity: u16,
+    pub buyer: NameStr,
+    pub expiration: MapleExpiration,
+    pub payback_rate: u32,
+    pub discount_rate: u32,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopLockerData {
+    pub items: MapleList16<CashItemInfo>,
+    pub trunk_slots: u16,
+    pub char_slots: u16,
+    pub buy_char_count: u16,
+    pub char_count: u16,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopWishList {
+    pub wish_list: [CommoditySN; WISH_LIST_LEN],
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopGiftDone {
+    pub receiver: String,
+    pub item_id: ItemId,
+    pub quantity: u16,
+    pub price: u32,
+}
+
+/// Max length of a gift message, including the terminator
+pub const GIFT_MSG_LEN: usize = 73;
+
+/// Received gift, which is shown once the receiver enters the cash shop
+#[derive(MooplePacket, Debug)]
+pub struct CashShopGiftInfo {
+    pub sn: CashItemSN,
+    pub item_id: ItemId,
+    pub sender: NameStr,
+    pub msg: FixedPacketString<GIFT_MSG_LEN>,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CashShopMoveLToSDone {
+    pub pos: u16,
+    pub item: Item,
+}
+
+maple_enum_code!(
+    CashShopFailReason,
+    u8,
+    Unknown = 0,
+    Timeout = 0xA3,
+    NotEnoughCash = 0xA4,
+    CantGiftWithPrepaid = 0xA5,
+    CantGiftOwnAccount = 0xA6,
+    InvalidReceiver = 0xA7,
+    GenderRestriction = 0xA8,
+    ReceiverInventoryFull = 0xA9,
+    TooManyCashItems = 0xAA,
+    NotAvailable = 0xB1
+);
+
+maple_packet_enum!(
+    CashShopCashItemResp,
+    u8,
+    LoadLockerDone(CashShopLockerData) => 0x56,
+    LoadGiftDone(MapleList16<CashShopGiftInfo>) => 0x58,
+    LoadWishDone(CashShopWishList) => 0x5A,
+    SetWishDone(CashShopWishList) => 0x5C,
+    SetWishFailed(CashShopFailReason) => 0x5D,
+    BuyDone(CashItemInfo) => 0x5E,
+    BuyFailed(CashShopFailReason) => 0x5F,
+    GiftDone(CashShopGiftDone) => 0x65,
+    GiftFailed(CashShopFailReason) => 0x66,
+    MoveLToSDone(CashShopMoveLToSDone) => 0x71,
+    MoveLToSFailed(CashShopFailReason) => 0x72,
+    MoveSToLDone(CashItemInfo) => 0x73,
+    MoveSToLFailed(CashShopFailReason) => 0x74,
+);
+packet_opcode!(CashShopCashItemResp, SendOpcodes::CashShopCashItemResult);
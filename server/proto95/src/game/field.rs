@@ -5,12 +5,14 @@ use moople_packet::{
         list::{MapleList, MapleListLen},
         option::MapleOption8,
         time::MapleTime,
-        MapleList16,
+        CondOption, MapleList16, MapleList32,
     },
 };
 
+use super::mob::MobId;
 use crate::{
-    id::MapId,
+    id::{ItemId, MapId},
+    recv_opcodes::RecvOpcodes,
     send_opcodes::SendOpcodes,
     shared::{
         char::{CharDataHeader, CharacterId, CharDataFlagsAll, CharDataAll},
@@ -98,3 +100,144 @@ pub struct SetFieldResp {
     pub extra: u32,
 }
 packet_opcode!(SetFieldResp, SendOpcodes::SetField);
+
+#[derive(MooplePacket, Debug)]
+pub struct ClockTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+maple_packet_enum!(
+    ClockResp,
+    u8,
+    HourMinSec(ClockTime) => 1,
+    // Remaining seconds of the timer
+    Timer(u32) => 2,
+);
+packet_opcode!(ClockResp, SendOpcodes::Clock);
+
+#[derive(MooplePacket, Debug)]
+pub struct DestroyClockResp;
+packet_opcode!(DestroyClockResp, SendOpcodes::DestroyClock);
+
+#[derive(MooplePacket, Debug)]
+pub struct FieldEffectTremble {
+    pub heavy: bool,
+    pub delay: u32,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct FieldEffectMobHpTag {
+    pub mob_id: MobId,
+    pub hp: u32,
+    pub max_hp: u32,
+    pub color: u8,
+    pub bg_color: u8,
+}
+
+maple_packet_enum!(
+    FieldEffectResp,
+    u8,
+    Tremble(FieldEffectTremble) => 1,
+    Object(String) => 2,
+    Screen(String) => 3,
+    Sound(String) => 4,
+    MobHpTag(FieldEffectMobHpTag) => 5,
+    ChangeBgm(String) => 6,
+);
+packet_opcode!(FieldEffectResp, SendOpcodes::FieldEffect);
+
+/// Move of a ship or a train between the stations
+#[derive(MooplePacket, Debug)]
+pub struct ContiMoveResp {
+    pub ty: u8,
+    pub target: u8,
+}
+packet_opcode!(ContiMoveResp, SendOpcodes::CONTIMOVE);
+
+impl ContiMoveResp {
+    /// The ship leaves the station
+    pub fn start_ship_move() -> Self {
+        Self { ty: 8, target: 2 }
+    }
+
+    /// The ship arrives at the station
+    pub fn end_ship_move() -> Self {
+        Self { ty: 12, target: 6 }
+    }
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ContiStateReq {
+    pub field_id: MapId,
+    //TODO unknown
+    pub unknown: u8,
+}
+packet_opcode!(ContiStateReq, RecvOpcodes::CONTISTATE);
+
+#[derive(MooplePacket, Debug)]
+pub struct ContiStateResp {
+    /// Set If the ship is docked at the station
+    pub docked: bool,
+    //TODO only used for the balrog ship
+    pub mob_ship: bool,
+}
+packet_opcode!(ContiStateResp, SendOpcodes::CONTISTATE);
+
+/// Answer to a request of another character, which wants to ride along as a passenger
+#[derive(MooplePacket, Debug)]
+pub struct SetPassengerResultReq {
+    pub requester_id: u32,
+    pub accepted: bool,
+}
+packet_opcode!(SetPassengerResultReq, RecvOpcodes::SetPassengerResult);
+
+fn has_weather(item_id: &ItemId) -> bool {
+    item_id.0 != 0
+}
+
+/// Weather effect of a weather item, the item id 0 removes the weather
+#[derive(MooplePacket, Debug)]
+pub struct BlowWeatherResp {
+    pub ty: u8,
+    pub item_id: ItemId,
+    #[pkt(if(field = "item_id", cond = "has_weather"))]
+    pub msg: CondOption<String>,
+}
+packet_opcode!(BlowWeatherResp, SendOpcodes::BlowWeather);
+
+#[derive(MooplePacket, Debug, Clone)]
+pub struct FieldObstacle {
+    pub name: String,
+    pub state: u32,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct FieldObstacleOnOffResp {
+    pub obstacle: FieldObstacle,
+}
+packet_opcode!(FieldObstacleOnOffResp, SendOpcodes::FieldObstacleOnOff);
+
+#[derive(MooplePacket, Debug)]
+pub struct FieldObstacleOnOffStatusResp {
+    pub obstacles: MapleList32<FieldObstacle>,
+}
+packet_opcode!(
+    FieldObstacleOnOffStatusResp,
+    SendOpcodes::FieldObstacleOnOffStatus
+);
+
+#[derive(MooplePacket, Debug)]
+pub struct FieldObstacleAllResetResp;
+packet_opcode!(
+    FieldObstacleAllResetResp,
+    SendOpcodes::FieldObstacleAllReset
+);
+
+#[derive(MooplePacket, Debug)]
+pub struct FieldObstacleStatusReq;
+packet_opcode!(
+    FieldObstacleStatusReq,
+    RecvOpcodes::RequireFieldObstacleStatus
+);
@@ -1,7 +1,16 @@
 use moople_derive::MooplePacket;
-use moople_packet::{packet_opcode, proto::option::MapleOptionR8};
+use moople_packet::{
+    maple_packet_enum, packet_opcode,
+    proto::{
+        option::{MapleOption8, MapleOptionR8},
+        MapleList32,
+    },
+};
 
-use crate::send_opcodes::SendOpcodes;
+use crate::{id::ItemId, recv_opcodes::RecvOpcodes, send_opcodes::SendOpcodes};
+
+pub const FUNC_KEYS: usize = 90;
+pub const QUICK_SLOT_KEYS: usize = 8;
 
 #[derive(Debug, MooplePacket, Default, Clone, Copy)]
 pub struct KeyBinding {
@@ -12,7 +21,7 @@ pub struct KeyBinding {
 #[derive(Debug, MooplePacket)]
 pub struct FuncKeyMapInitResp {
     // Reversed option, if set to none the default key map is used
-    pub key_bindings: MapleOptionR8<[KeyBinding; 90]>,
+    pub key_bindings: MapleOptionR8<[KeyBinding; FUNC_KEYS]>,
 }
 
 impl FuncKeyMapInitResp {
@@ -22,3 +31,48 @@ impl FuncKeyMapInitResp {
 }
 
 packet_opcode!(FuncKeyMapInitResp, SendOpcodes::FuncKeyMappedInit);
+
+#[derive(Debug, MooplePacket, Clone, Copy)]
+pub struct KeyBindingChange {
+    pub key: u32,
+    pub binding: KeyBinding,
+}
+
+maple_packet_enum!(
+    FuncKeyMappedModifiedReq,
+    u32,
+    KeyChange(MapleList32<KeyBindingChange>) => 0,
+    PetConsumeItem(ItemId) => 1,
+    PetConsumeMpItem(ItemId) => 2,
+);
+packet_opcode!(FuncKeyMappedModifiedReq, RecvOpcodes::FuncKeyMappedModified);
+
+#[derive(Debug, MooplePacket)]
+pub struct QuickSlotKeyMappedModifiedReq {
+    pub keys: [u32; QUICK_SLOT_KEYS],
+}
+packet_opcode!(
+    QuickSlotKeyMappedModifiedReq,
+    RecvOpcodes::QuickslotKeyMappedModified
+);
+
+#[derive(Debug, MooplePacket)]
+pub struct QuickSlotMappedInitResp {
+    // If set to none the default quick slots are used
+    pub keys: MapleOption8<[u32; QUICK_SLOT_KEYS]>,
+}
+packet_opcode!(QuickSlotMappedInitResp, SendOpcodes::QuickslotMappedInit);
+
+/// Item the pet uses to recover HP
+#[derive(Debug, MooplePacket)]
+pub struct PetConsumeItemInitResp {
+    pub item_id: ItemId,
+}
+packet_opcode!(PetConsumeItemInitResp, SendOpcodes::PetConsumeItemInit);
+
+/// Item the pet uses to recover MP
+#[derive(Debug, MooplePacket)]
+pub struct PetConsumeMpItemInitResp {
+    pub item_id: ItemId,
+}
+packet_opcode!(PetConsumeMpItemInitResp, SendOpcodes::PetConsumeMPItemInit);
@@ -1,7 +1,7 @@
 use moople_derive::MooplePacket;
 use moople_packet::{proto::MapleList8, packet_opcode};
 
-use crate::{id::SkillId, send_opcodes::SendOpcodes};
+use crate::{id::SkillId, recv_opcodes::RecvOpcodes, send_opcodes::SendOpcodes};
 
 #[derive(MooplePacket, Debug)]
 pub struct SingleMacro {
@@ -17,4 +17,10 @@ pub struct MacroSysDataInitResp {
     pub data: MacroSysData
     
 }
-packet_opcode!(MacroSysDataInitResp, SendOpcodes::MacroSysDataInit);
\ No newline at end of file
+packet_opcode!(MacroSysDataInitResp, SendOpcodes::MacroSysDataInit);
+
+#[derive(MooplePacket, Debug)]
+pub struct MacroSysDataModifiedReq {
+    pub data: MacroSysData,
+}
+packet_opcode!(MacroSysDataModifiedReq, RecvOpcodes::UserMacroSysDataModified);
@@ -0,0 +1,526 @@
+use bytes::BufMut;
+use moople_derive::MooplePacket;
+use moople_packet::{
+    maple_enum_code, maple_packet_enum, packet_opcode,
+    proto::{list::MapleIndexList8, time::Ticks, CondOption, MapleList8},
+    DecodePacket, EncodePacket, MaplePacketReader, MaplePacketWriter, NetResult,
+};
+
+use crate::{
+    id::ItemId,
+    recv_opcodes::RecvOpcodes,
+    send_opcodes::SendOpcodes,
+    shared::{
+        char::{AvatarData, CharacterId, MiniGameInfo},
+        inventory::InventoryType,
+        item::Item,
+        FootholdId, Vec2,
+    },
+};
+
+use super::ObjectId;
+
+pub type MiniRoomSN = u32;
+
+maple_enum_code!(
+    MiniRoomType,
+    u8,
+    None = 0,
+    Omok = 1,
+    MemoryGame = 2,
+    Trading = 3,
+    PersonalShop = 4,
+    EntrustedShop = 5
+);
+
+impl MiniRoomType {
+    pub fn is_shop(&self) -> bool {
+        matches!(self, Self::PersonalShop | Self::EntrustedShop)
+    }
+
+    pub fn is_game(&self) -> bool {
+        matches!(self, Self::Omok | Self::MemoryGame)
+    }
+}
+
+fn is_true(v: &bool) -> bool {
+    *v
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomShopCreate {
+    pub title: String,
+    pub private: bool,
+    pub permit_slot: u16,
+    pub permit_item: ItemId,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomGameCreate {
+    pub title: String,
+    pub private: bool,
+    #[pkt(if(field = "private", cond = "is_true"))]
+    pub password: CondOption<String>,
+    /// Piece type for omok, board size for match cards
+    pub game_spec: u8,
+}
+
+maple_packet_enum!(
+    MiniRoomCreateReq,
+    u8,
+    Omok(MiniRoomGameCreate) => 1,
+    MemoryGame(MiniRoomGameCreate) => 2,
+    Trading(()) => 3,
+    PersonalShop(MiniRoomShopCreate) => 4,
+    EntrustedShop(MiniRoomShopCreate) => 5,
+);
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomEnterReq {
+    pub sn: MiniRoomSN,
+    pub has_password: bool,
+    #[pkt(if(field = "has_password", cond = "is_true"))]
+    pub password: CondOption<String>,
+    //TODO: always 0?
+    pub u1: u8,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomChatReq {
+    pub ticks: Ticks,
+    pub msg: String,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopPutItemReq {
+    pub inv_type: InventoryType,
+    pub slot: u16,
+    pub bundles: u16,
+    pub bundle_size: u16,
+    pub price: u32,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopBuyItemReq {
+    pub ix: u8,
+    pub bundles: u16,
+    pub item_crc: u32,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopMoveItemToInvReq {
+    pub ix: u16,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct OmokMoveReq {
+    pub x: u32,
+    pub y: u32,
+    /// Piece type, 1 for the owner and 2 for the opponent
+    pub piece: u8,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MemoryGameTurnReq {
+    /// Set for the first card of a turn
+    pub first: bool,
+    pub card_ix: u8,
+}
+
+//TODO verify the codes past MRP_Leave for v95
+maple_packet_enum!(
+    MiniRoomReq,
+    u8,
+    Create(MiniRoomCreateReq) => 0,
+    Enter(MiniRoomEnterReq) => 4,
+    Chat(MiniRoomChatReq) => 6,
+    Leave(()) => 0xA,
+    // Opens the shop for visitors
+    Balloon(()) => 0xB,
+    PspPutItem(ShopPutItemReq) => 0x16,
+    PspBuyItem(ShopBuyItemReq) => 0x17,
+    PspMoveItemToInventory(ShopMoveItemToInvReq) => 0x1B,
+    EspPutItem(ShopPutItemReq) => 0x21,
+    EspBuyItem(ShopBuyItemReq) => 0x22,
+    EspMoveItemToInventory(ShopMoveItemToInvReq) => 0x26,
+    EspGoOut(()) => 0x27,
+    EspArrangeItem(()) => 0x28,
+    EspWithdrawAll(()) => 0x29,
+    EspWithdrawMoney(()) => 0x2B,
+    MgTieRequest(()) => 0x32,
+    MgTieAnswer(bool) => 0x33,
+    MgGiveUp(()) => 0x34,
+    MgLeaveAfterGame(()) => 0x38,
+    MgCancelLeave(()) => 0x39,
+    MgReady(()) => 0x3A,
+    MgCancelReady(()) => 0x3B,
+    MgBan(()) => 0x3C,
+    MgStart(()) => 0x3D,
+    MgSkip(()) => 0x3F,
+    OmokMove(OmokMoveReq) => 0x40,
+    MemoryGameTurn(MemoryGameTurnReq) => 0x44,
+);
+packet_opcode!(MiniRoomReq, RecvOpcodes::MiniRoom);
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomAvatar {
+    pub avatar: AvatarData,
+    pub name: String,
+    pub job: u16,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopItem {
+    pub bundles: u16,
+    pub bundle_size: u16,
+    pub price: u32,
+    pub item: Item,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopSoldItem {
+    pub item_id: ItemId,
+    pub bundles: u16,
+    pub price: u32,
+    pub buyer: String,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopEnterData {
+    pub title: String,
+    pub max_items: u8,
+    pub mesos: u32,
+    pub items: MapleList8<ShopItem>,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomEnterData {
+    pub max_users: u8,
+    pub my_ix: u8,
+    pub users: MapleIndexList8<MiniRoomAvatar>,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopEnterResult {
+    pub room: MiniRoomEnterData,
+    pub shop: ShopEnterData,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniGameEnterResult {
+    pub room: MiniRoomEnterData,
+    pub records: MapleIndexList8<MiniGameInfo>,
+    pub title: String,
+    pub spec: u8,
+    //TODO: always 0?
+    pub u1: u8,
+}
+
+maple_enum_code!(
+    MiniRoomEnterError,
+    u8,
+    NoRoom = 1,
+    Full = 2,
+    Busy = 3,
+    Dead = 4,
+    Event = 5,
+    PermissionDenied = 6,
+    NoTrading = 7,
+    AlreadyInRoom = 8,
+    NotAvailableField = 9,
+    ShopClosed = 0x12,
+    InvalidPassword = 0x16
+);
+
+maple_packet_enum!(
+    MiniRoomEnterResult,
+    u8,
+    Error(MiniRoomEnterError) => 0,
+    Omok(MiniGameEnterResult) => 1,
+    MemoryGame(MiniGameEnterResult) => 2,
+    PersonalShop(ShopEnterResult) => 4,
+    EntrustedShop(ShopEnterResult) => 5,
+);
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomEnterUser {
+    pub ix: u8,
+    pub user: MiniRoomAvatar,
+    /// Record of the user, only for mini games
+    pub record: Trailing<MiniGameInfo>,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomUserChat {
+    pub ix: u8,
+    pub msg: String,
+}
+
+maple_enum_code!(
+    MiniRoomLeaveReason,
+    u8,
+    UserRequest = 0,
+    HostOut = 1,
+    Kicked = 2,
+    ShopClosed = 3,
+    SoldOut = 4
+);
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniRoomLeave {
+    pub ix: u8,
+    pub reason: MiniRoomLeaveReason,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopRefresh {
+    pub mesos: u32,
+    pub items: MapleList8<ShopItem>,
+}
+
+maple_enum_code!(
+    ShopBuyResult,
+    u8,
+    Success = 0,
+    NoMoreItems = 1,
+    NotEnoughMesos = 2,
+    InventoryFull = 3,
+    TooMuchMesos = 4,
+    OnlyOneItem = 5
+);
+
+#[derive(MooplePacket, Debug)]
+pub struct ShopAddSoldItem {
+    pub ix: u8,
+    pub bundles: u16,
+    pub buyer: String,
+}
+
+/// Optional data at the end of a packet, only decoded If there's data left
+#[derive(Debug)]
+pub struct Trailing<T>(pub Option<T>);
+
+impl<T> From<Option<T>> for Trailing<T> {
+    fn from(value: Option<T>) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: EncodePacket> EncodePacket for Trailing<T> {
+    const SIZE_HINT: Option<usize> = None;
+
+    fn packet_len(&self) -> usize {
+        self.0.as_ref().map(|v| v.packet_len()).unwrap_or(0)
+    }
+
+    fn encode_packet<B: BufMut>(&self, pw: &mut MaplePacketWriter<B>) -> NetResult<()> {
+        if let Some(ref v) = self.0 {
+            v.encode_packet(pw)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de, T: DecodePacket<'de>> DecodePacket<'de> for Trailing<T> {
+    fn decode_packet(pr: &mut MaplePacketReader<'de>) -> NetResult<Self> {
+        Ok(Self(if pr.remaining_slice().is_empty() {
+            None
+        } else {
+            Some(T::decode_packet(pr)?)
+        }))
+    }
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniGameStart {
+    pub first_turn: u8,
+    /// Shuffled cards, only for match cards
+    pub cards: Trailing<MapleList8<u32>>,
+}
+
+maple_enum_code!(
+    MiniGameResultType,
+    u8,
+    Win = 0,
+    Draw = 1,
+    GiveUp = 2
+);
+
+#[derive(MooplePacket, Debug)]
+pub struct MiniGameResult {
+    pub ty: MiniGameResultType,
+    /// Index of the winner, ignored for a draw
+    pub winner: u8,
+    pub owner_record: MiniGameInfo,
+    pub opponent_record: MiniGameInfo,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct OmokMove {
+    pub x: u32,
+    pub y: u32,
+    pub piece: u8,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MemoryGameFirstCard {
+    pub card_ix: u8,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct MemoryGameSecondCard {
+    pub first_ix: u8,
+    pub second_ix: u8,
+    /// 0/1 for a miss by the owner/opponent, 2/3 for a match
+    pub result: u8,
+}
+
+maple_packet_enum!(
+    MemoryGameTurn,
+    u8,
+    Second(MemoryGameSecondCard) => 0,
+    First(MemoryGameFirstCard) => 1,
+);
+
+maple_packet_enum!(
+    MiniRoomResp,
+    u8,
+    EnterResult(MiniRoomEnterResult) => 5,
+    Enter(MiniRoomEnterUser) => 4,
+    Chat(MiniRoomUserChat) => 6,
+    Leave(MiniRoomLeave) => 0xA,
+    PspBuyResult(ShopBuyResult) => 0x18,
+    PspRefresh(ShopRefresh) => 0x19,
+    PspAddSoldItem(ShopAddSoldItem) => 0x1A,
+    EspBuyResult(ShopBuyResult) => 0x23,
+    EspRefresh(ShopRefresh) => 0x24,
+    EspAddSoldItem(ShopAddSoldItem) => 0x25,
+    EspWithdrawAllResult(bool) => 0x2A,
+    EspWithdrawMoneyResult(()) => 0x2C,
+    MgTieRequest(()) => 0x32,
+    MgTieDenied(()) => 0x33,
+    MgLeaveAfterGame(()) => 0x38,
+    MgCancelLeave(()) => 0x39,
+    MgReady(()) => 0x3A,
+    MgCancelReady(()) => 0x3B,
+    MgStart(MiniGameStart) => 0x3D,
+    MgResult(MiniGameResult) => 0x3E,
+    MgSkip(u8) => 0x3F,
+    OmokMove(OmokMove) => 0x40,
+    MemoryGameTurn(MemoryGameTurn) => 0x44,
+);
+packet_opcode!(MiniRoomResp, SendOpcodes::MiniRoom);
+
+#[derive(MooplePacket, Debug, Clone)]
+pub struct MiniRoomBalloon {
+    pub sn: MiniRoomSN,
+    pub title: String,
+    pub private: bool,
+    pub spec: u8,
+    pub cur_users: u8,
+    pub max_users: u8,
+    pub game_on: bool,
+}
+
+fn has_balloon(ty: &MiniRoomType) -> bool {
+    *ty != MiniRoomType::None
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct UserMiniRoomBalloonResp {
+    pub char_id: CharacterId,
+    pub ty: MiniRoomType,
+    #[pkt(if(field = "ty", cond = "has_balloon"))]
+    pub balloon: CondOption<MiniRoomBalloon>,
+}
+packet_opcode!(UserMiniRoomBalloonResp, SendOpcodes::UserMiniRoomBalloon);
+
+#[derive(MooplePacket, Debug, Clone)]
+pub struct EmployeeBalloon {
+    pub sn: MiniRoomSN,
+    pub title: String,
+    pub spec: u8,
+    pub cur_users: u8,
+    pub max_users: u8,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct EmployeeEnterFieldResp {
+    pub id: ObjectId,
+    pub tmpl_id: ItemId,
+    pub pos: Vec2,
+    pub fh: FootholdId,
+    pub owner_name: String,
+    pub ty: MiniRoomType,
+    #[pkt(if(field = "ty", cond = "has_balloon"))]
+    pub balloon: CondOption<EmployeeBalloon>,
+}
+packet_opcode!(EmployeeEnterFieldResp, SendOpcodes::EmployeeEnterField);
+
+#[derive(MooplePacket, Debug)]
+pub struct EmployeeLeaveFieldResp {
+    pub id: ObjectId,
+}
+packet_opcode!(EmployeeLeaveFieldResp, SendOpcodes::EmployeeLeaveField);
+
+#[derive(MooplePacket, Debug)]
+pub struct EmployeeMiniRoomBalloonResp {
+    pub id: ObjectId,
+    pub ty: MiniRoomType,
+    #[pkt(if(field = "ty", cond = "has_balloon"))]
+    pub balloon: CondOption<EmployeeBalloon>,
+}
+packet_opcode!(
+    EmployeeMiniRoomBalloonResp,
+    SendOpcodes::EmployeeMiniRoomBalloon
+);
+
+#[derive(MooplePacket, Debug)]
+pub struct EntrustedShopReq {
+    //TODO: 0 = check if a shop can be opened, anything else?
+    pub ty: u8,
+}
+packet_opcode!(EntrustedShopReq, RecvOpcodes::UserEntrustedShopRequest);
+
+//TODO verify
+maple_enum_code!(
+    EntrustedShopCheckResultResp,
+    u8,
+    OpenPossible = 7,
+    AlreadyOpen = 8,
+    RetrieveFromStoreBank = 9
+);
+packet_opcode!(
+    EntrustedShopCheckResultResp,
+    SendOpcodes::EntrustedShopCheckResult
+);
+
+//TODO verify for v95
+maple_packet_enum!(
+    StoreBankReq,
+    u8,
+    Open(()) => 0x19,
+    GetAll(()) => 0x1A,
+    Exit(()) => 0x1C,
+);
+packet_opcode!(StoreBankReq, RecvOpcodes::UserStoreBankRequest);
+
+#[derive(MooplePacket, Debug)]
+pub struct StoreBankItems {
+    pub npc_tmpl_id: u32,
+    pub u1: u32,
+    pub mesos: u32,
+    pub items: MapleList8<Item>,
+}
+
+//TODO verify for v95
+maple_packet_enum!(
+    StoreBankResp,
+    u8,
+    GetAllSuccess(()) => 0x1E,
+    NotEnoughMesos(()) => 0x1F,
+    InventoryFull(()) => 0x20,
+    OneOfAKind(()) => 0x21,
+    Items(StoreBankItems) => 0x23,
+);
+packet_opcode!(StoreBankResp, SendOpcodes::StoreBankResult);
@@ -1,19 +1,27 @@
 pub mod reactor;
 pub mod pet;
 pub mod npc;
+pub mod party_quest;
 pub mod chat;
 pub mod drop;
 pub mod field;
 pub mod friend;
 pub mod keymaps;
+pub mod mini_room;
 pub mod macros;
 pub mod mob;
 pub mod user;
 use moople_derive::MooplePacket;
-use moople_packet::{maple_packet_enum, packet_opcode, proto::time::Ticks};
+use moople_packet::{
+    maple_packet_enum, packet_opcode,
+    proto::{
+        list::MapleIndexListZ8, option::MapleOption8, time::Ticks, MapleList16, MapleList32,
+        MapleList8,
+    },
+};
 
 use crate::{
-    id::job_id::JobId,
+    id::{job_id::JobId, ItemId},
     login::MachineId,
     recv_opcodes::RecvOpcodes,
     send_opcodes::SendOpcodes,
@@ -32,11 +40,46 @@ pub struct CharacterInfoReq {
 }
 packet_opcode!(CharacterInfoReq, RecvOpcodes::UserCharacterInfoRequest);
 
+#[derive(MooplePacket, Debug)]
+pub struct CharacterInfoPet {
+    pub tmpl_id: ItemId,
+    pub name: String,
+    pub level: u8,
+    pub tameness: u16,
+    pub fullness: u8,
+    pub skill: u16,
+    pub equip_item_id: ItemId,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CharacterInfoTamingMob {
+    pub level: u32,
+    pub exp: u32,
+    pub fatigue: u32,
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct CharacterInfoMedal {
+    pub medal_id: ItemId,
+    pub quests: MapleList16<u16>,
+}
+
 #[derive(MooplePacket, Debug)]
 pub struct CharacterInfoResp {
     pub char_id: CharacterId,
     pub level: u8,
     pub job: JobId,
+    pub fame: i16,
+    pub married: bool,
+    pub guild_name: String,
+    pub alliance_name: String,
+    pub medal_info: u8,
+    /// Pets by their index, starting from 1
+    pub pets: MapleIndexListZ8<CharacterInfoPet>,
+    pub taming_mob: MapleOption8<CharacterInfoTamingMob>,
+    pub wish_list: MapleList8<u32>,
+    pub medal: CharacterInfoMedal,
+    pub chairs: MapleList32<ItemId>,
 }
 packet_opcode!(CharacterInfoResp, SendOpcodes::CharacterInfo);
 
@@ -107,6 +150,8 @@ pub struct ServerMessage {
 maple_packet_enum!(
     BroadcastMessageResp,
     u8,
+    Notice(String) => 0,
+    Popup(String) => 1,
     ServerMessage(ServerMessage) => 4,
     PinkMessage(String) => 5,
 );
@@ -1,7 +1,11 @@
 use moople_derive::MooplePacket;
-use moople_packet::{packet_opcode, proto::MapleList8};
+use moople_packet::{
+    packet_opcode,
+    proto::{primitive::OptionTail, MapleList8},
+};
 
 use crate::{
+    recv_opcodes::RecvOpcodes,
     send_opcodes::SendOpcodes,
     shared::{char::AvatarData, movement::MovePath, FootholdId, Range2, Vec2},
 };
@@ -94,7 +98,8 @@ packet_opcode!(NpcSetScriptResp, SendOpcodes::NpcSetScript);
 pub struct NpcMove {
     pub action: u8,
     pub chat: u8, //TODO correct?
-    pub move_path: MovePath,
+    /// Only present for npcs, which can move
+    pub move_path: OptionTail<MovePath>,
 }
 pub type NpcMoveResp = NpcPoolPacket<NpcMove>;
 packet_opcode!(NpcMoveResp, SendOpcodes::NpcMove);
@@ -111,4 +116,25 @@ pub struct NpcSetSpecialAction {
     pub action: String
 }
 pub type NpcSetSpecialActionResp = NpcPoolPacket<NpcSetSpecialAction>;
-packet_opcode!(NpcSetSpecialActionResp, SendOpcodes::NpcSpecialAction);
\ No newline at end of file
+packet_opcode!(NpcSetSpecialActionResp, SendOpcodes::NpcSpecialAction);
+
+#[derive(MooplePacket, Debug)]
+pub struct NpcMoveReq {
+    pub id: ObjectId,
+    pub data: NpcMove,
+}
+packet_opcode!(NpcMoveReq, RecvOpcodes::NpcMove);
+
+#[derive(MooplePacket, Debug)]
+pub struct NpcSpecialActionReq {
+    pub id: ObjectId,
+    pub action: String,
+}
+packet_opcode!(NpcSpecialActionReq, RecvOpcodes::NpcSpecialAction);
+
+#[derive(MooplePacket, Debug)]
+pub struct UserSelectNpcReq {
+    pub id: ObjectId,
+    pub pos: Vec2,
+}
+packet_opcode!(UserSelectNpcReq, RecvOpcodes::UserSelectNpc);
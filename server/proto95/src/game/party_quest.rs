@@ -0,0 +1,24 @@
+use moople_derive::MooplePacket;
+use moople_packet::{packet_opcode, proto::MapleList8};
+
+use crate::{id::ItemId, recv_opcodes::RecvOpcodes, send_opcodes::SendOpcodes};
+
+/// Requests the rewards once the reward window is opened, the request has no payload
+#[derive(MooplePacket, Debug)]
+pub struct UserRequestPQRewardReq;
+packet_opcode!(UserRequestPQRewardReq, RecvOpcodes::UserRequestPQReward);
+
+/// Reward the character picked, only the leading index is read
+#[derive(MooplePacket, Debug)]
+pub struct UserSelectPQRewardReq {
+    /// Index into the items of the shown rewards
+    pub ix: u8,
+}
+packet_opcode!(UserSelectPQRewardReq, RecvOpcodes::UserSelectPQReward);
+
+/// Shows the rewards the character can pick from
+#[derive(MooplePacket, Debug)]
+pub struct UserShowPQRewardResp {
+    pub items: MapleList8<ItemId>,
+}
+packet_opcode!(UserShowPQRewardResp, SendOpcodes::UserShowPQReward);
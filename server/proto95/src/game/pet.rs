@@ -1,7 +1,15 @@
 use moople_derive::MooplePacket;
-use moople_packet::{packet_opcode, proto::time::Ticks};
+use moople_packet::{
+    maple_enum_code, packet_opcode,
+    proto::{conditional::CondEither, time::Ticks},
+};
 
-use crate::{recv_opcodes::RecvOpcodes, shared::Vec2};
+use crate::{
+    id::ItemId,
+    recv_opcodes::RecvOpcodes,
+    send_opcodes::SendOpcodes,
+    shared::{char::CharacterId, movement::MovePath, FootholdId, Vec2},
+};
 
 use super::ObjectId;
 
@@ -9,10 +17,13 @@ pub type PetLockerId = u64;
 pub type PetId = u32;
 pub type PetIx = u8;
 
+/// Amount of pets a character can have summoned at the same time
+pub const MAX_PETS: usize = 3;
+
 #[derive(MooplePacket, Debug)]
 pub struct PetDropPickUpReq {
     pub locker_id: PetLockerId,
-    pub u1: u8,// Pet id?
+    pub u1: u8, // Pet id?
     pub ticks: Ticks,
     pub point: Vec2,
     pub drop_id: ObjectId,
@@ -23,9 +34,115 @@ pub struct PetDropPickUpReq {
     // TOdo: drop_id / 0xd * 0xd == drop_id, figure this out
     pub drop_pos: Vec2,
     pub pos_crc: u32,
-    pub rect_crc: u32
+    pub rect_crc: u32,
+}
+packet_opcode!(PetDropPickUpReq, RecvOpcodes::PetDropPickUpRequest);
+
+#[derive(MooplePacket, Debug)]
+pub struct PetActivateReq {
+    pub ticks: Ticks,
+    pub slot: u16,
+    pub boss_pet: bool,
+}
+packet_opcode!(PetActivateReq, RecvOpcodes::UserActivatePetRequest);
+
+#[derive(MooplePacket, Debug)]
+pub struct PetMoveReq {
+    pub locker_id: PetLockerId,
+    pub move_path: MovePath,
+}
+packet_opcode!(PetMoveReq, RecvOpcodes::PetMove);
+
+#[derive(MooplePacket, Debug)]
+pub struct PetActionReq {
+    pub locker_id: PetLockerId,
+    pub ticks: Ticks,
+    pub ty: u8,
+    pub action: u8,
+    pub msg: String,
+}
+packet_opcode!(PetActionReq, RecvOpcodes::PetAction);
+
+#[derive(MooplePacket, Debug)]
+pub struct PetInteractionReq {
+    pub locker_id: PetLockerId,
+    pub u1: u8,
+    pub command: u8,
+}
+packet_opcode!(PetInteractionReq, RecvOpcodes::PetInteractionRequest);
+
+#[derive(MooplePacket, Debug)]
+pub struct PetFoodItemUseReq {
+    pub ticks: Ticks,
+    pub slot: u16,
+    pub item_id: ItemId,
+}
+packet_opcode!(PetFoodItemUseReq, RecvOpcodes::UserPetFoodItemUseRequest);
 
+#[derive(MooplePacket, Debug, Clone)]
+pub struct PetInitInfo {
+    pub tmpl_id: ItemId,
+    pub name: String,
+    pub locker_id: PetLockerId,
+    pub pos: Vec2,
+    pub move_action: u8,
+    pub fh: FootholdId,
+    pub name_tag: bool,
+    pub chat_balloon: bool,
+}
 
-    
+#[derive(MooplePacket, Debug)]
+pub struct PetActivatedInfo {
+    /// Set when the pet is summoned, unset when it's loaded with the user
+    pub initial: bool,
+    pub pet: PetInitInfo,
+}
+
+maple_enum_code!(PetLeaveReason, u8, UserRequest = 0, Expired = 1, Hungry = 2);
+
+fn is_true(v: &bool) -> bool {
+    *v
+}
+
+#[derive(MooplePacket, Debug)]
+pub struct PetActivatedResp {
+    pub char_id: CharacterId,
+    pub pet_ix: PetIx,
+    pub activated: bool,
+    #[pkt(either(field = "activated", cond = "is_true"))]
+    pub data: CondEither<PetActivatedInfo, PetLeaveReason>,
+}
+packet_opcode!(PetActivatedResp, SendOpcodes::PetActivated);
+
+#[derive(MooplePacket, Debug)]
+pub struct PetMoveResp {
+    pub char_id: CharacterId,
+    pub pet_ix: PetIx,
+    pub move_path: MovePath,
+}
+packet_opcode!(PetMoveResp, SendOpcodes::PetMove);
+
+#[derive(MooplePacket, Debug)]
+pub struct PetActionResp {
+    pub char_id: CharacterId,
+    pub pet_ix: PetIx,
+    pub ty: u8,
+    pub action: u8,
+    pub msg: String,
+    pub chat_balloon: bool,
+}
+packet_opcode!(PetActionResp, SendOpcodes::PetAction);
+
+maple_enum_code!(PetCommandType, u8, Interaction = 0, Food = 1);
+
+#[derive(MooplePacket, Debug)]
+pub struct PetActionCommandResp {
+    pub char_id: CharacterId,
+    pub pet_ix: PetIx,
+    pub ty: PetCommandType,
+    /// Command index, unused for food
+    pub action: u8,
+    pub success: bool,
+    pub chat_balloon: bool,
 }
-packet_opcode!(PetDropPickUpReq, RecvOpcodes::PetDropPickUpRequest);
\ No newline at end of file
+packet_opcode!(PetActionCommandResp, SendOpcodes::PetActionCommand);
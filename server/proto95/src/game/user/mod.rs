@@ -16,7 +16,7 @@ use crate::{
     id::{ItemId, MapId, SkillId},
     recv_opcodes::RecvOpcodes,
     send_opcodes::SendOpcodes,
-    shared::{movement::MovePath, TagPoint, Vec2},
+    shared::{char::CharacterId, movement::MovePath, TagPoint, Vec2},
 };
 
 use super::{mob::MobId, ObjectId};
@@ -98,12 +98,19 @@ pub struct UserHitKnockback {
     pub user_pos: Vec2,
 }
 
+/// Attack index of a hit caused by a map obstacle
+pub const OBSTACLE_ATTACK: u8 = 0xFE;
+
+fn is_mob_hit(atk_idx: &u8) -> bool {
+    *atk_idx != OBSTACLE_ATTACK
+}
+
+fn is_obstacle_hit(atk_idx: &u8) -> bool {
+    *atk_idx == OBSTACLE_ATTACK
+}
+
 #[derive(MooplePacket, Debug)]
-pub struct UserHitReq {
-    pub damaged_ticks: Ticks,
-    pub mob_atk_idx: u8, //todo: 0xfe is no atk => 1,4,2
-    pub magic_elem_attr: u8,
-    pub dmg_internal: u32,
+pub struct UserHitMob {
     pub mob_tmpl_id: MobId,
     pub mob_id: ObjectId,
     pub left: bool,
@@ -111,9 +118,28 @@ pub struct UserHitReq {
     pub guard: bool,
     pub knockback: u8,
     //TODO: If knockback | reflect  > 0 => UserHitKnockback
-    //non 0xfe end here
     pub unknown: u8,
 }
+
+#[derive(MooplePacket, Debug)]
+pub struct UserHitReq {
+    pub damaged_ticks: Ticks,
+    /// Index of the mob attack or one of the touch and obstacle indices
+    pub mob_atk_idx: u8,
+    pub magic_elem_attr: u8,
+    pub dmg_internal: u32,
+    #[pkt(if(field = "mob_atk_idx", cond = "is_mob_hit"))]
+    pub mob: CondOption<UserHitMob>,
+    #[pkt(if(field = "mob_atk_idx", cond = "is_obstacle_hit"))]
+    pub obstacle: CondOption<u16>,
+}
+
+impl UserHitReq {
+    /// Index into the attacks of the mob, None for touch and obstacle hits
+    pub fn mob_attack_ix(&self) -> Option<usize> {
+        (self.mob_atk_idx < OBSTACLE_ATTACK).then_some(self.mob_atk_idx as usize)
+    }
+}
 packet_opcode!(UserHitReq, RecvOpcodes::UserHit);
 
 bitflags! {
@@ -550,12 +576,35 @@ pub struct ChangeSkillRecordResp {
 packet_opcode!(ChangeSkillRecordResp, SendOpcodes::ChangeSkillRecordResult);
 
 #[derive(MooplePacket, Debug)]
-pub struct CharGivePopularityResult {
-    pub code: u8,     // Code 0-5, code 0 and 5 are success
-    pub name: String, // Code 0,5
-    pub up: bool,     // Code 0,5
-    pub pop: u32,     // Only used when code == 0
+pub struct UserGivePopularityReq {
+    pub target: CharacterId,
+    pub up: bool,
+}
+packet_opcode!(UserGivePopularityReq, RecvOpcodes::UserGivePopularityRequest);
+
+#[derive(MooplePacket, Debug)]
+pub struct GivePopularityDone {
+    pub target_name: String,
+    pub up: bool,
+    pub fame: u32,
 }
+
+#[derive(MooplePacket, Debug)]
+pub struct GivePopularityReceived {
+    pub giver_name: String,
+    pub up: bool,
+}
+
+maple_packet_enum!(
+    CharGivePopularityResult,
+    u8,
+    Done(GivePopularityDone) => 0,
+    InvalidTarget(()) => 1,
+    LevelTooLow(()) => 2,
+    AlreadyGivenToday(()) => 3,
+    AlreadyGivenToTarget(()) => 4,
+    Received(GivePopularityReceived) => 5,
+);
 packet_opcode!(CharGivePopularityResult, SendOpcodes::GivePopularityResult);
 
 maple_packet_enum!(
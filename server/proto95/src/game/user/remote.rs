@@ -8,6 +8,7 @@ use moople_packet::{
 };
 
 use crate::{
+    game::pet::PetInitInfo,
     id::{job_id::JobId, ItemId, SkillId},
     send_opcodes::SendOpcodes,
     shared::{
@@ -29,16 +30,6 @@ pub struct GuildMarkData {
     mark_color: u8,
 }
 
-#[derive(MooplePacket, Debug)]
-pub struct PetInitInfo {
-    tmpl_id: u32,
-    name: String,
-    pet_locker_sn: u64,
-    pos_prev: Vec2,
-    move_action: u8,
-    fh: FootholdId,
-}
-
 #[derive(MooplePacket, Debug, Default)]
 pub struct TamingMobData {
     level: u32,
@@ -260,6 +251,7 @@ packet_opcode!(
 
 #[derive(MooplePacket, Debug)]
 pub struct UserReceiveHPResp {
+    pub char_id: CharacterId,
     pub hp: u32,
     pub max_hp: u32,
 }
@@ -39,6 +39,14 @@ impl ItemId {
         (2022359..=2022421).contains(&self.0)
     }
 
+    pub fn is_personal_shop_permit(&self) -> bool {
+        self.0 / 10000 == 514
+    }
+
+    pub fn is_entrusted_shop_permit(&self) -> bool {
+        self.0 / 10000 == 503
+    }
+
     pub fn is_chair(&self) -> bool {
         Self::CHAIR_RANGE.contains(self)
     }
@@ -68,6 +76,10 @@ impl ItemId {
         self.0 / 1000 == 5000
     }
 
+    pub fn is_pet_food(&self) -> bool {
+        self.0 / 10000 == 212
+    }
+
     pub fn is_nx_card(&self) -> bool {
         matches!(*self, Self::NX_CARD_100 | Self::NX_CARD_250)
     }
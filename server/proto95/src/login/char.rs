@@ -40,7 +40,8 @@ maple_packet_enum!(
     SelectCharResult,
     u8,
     Success(MigrateStageInfo) => 0,
-    //TODO add the rest
+    // The client only shows the error code of the response
+    Error(()) => 1,
 );
 
 #[derive(MooplePacket, Debug)]
@@ -55,7 +56,7 @@ packet_opcode!(SelectCharResp, SendOpcodes::SelectCharacterResult);
 //TODO how does this work? must use prestored world i guess
 #[derive(MooplePacket, Debug)]
 pub struct ViewAllCharReq {
-    start_mode: StartModeInfo,
+    pub start_mode: StartModeInfo,
 }
 packet_opcode!(ViewAllCharReq, RecvOpcodes::ViewAllChar);
 
@@ -276,9 +277,9 @@ packet_opcode!(SelectCharCheckPicVac, RecvOpcodes::CheckSPWRequestByVAC);
 // Login Opt 2/3
 #[derive(MooplePacket, Debug)]
 pub struct SelectCharReqVac {
-    char_id: CharacterId,
-    world_id: WorldId,
-    hw_info: HardwareInfo,
+    pub char_id: CharacterId,
+    pub world_id: WorldId,
+    pub hw_info: HardwareInfo,
 }
 packet_opcode!(SelectCharReqVac, RecvOpcodes::SelectCharacterByVAC);
 
@@ -40,13 +40,13 @@ packet_opcode!(WorldCheckUserLimitResp, SendOpcodes::CheckUserLimitResult);
 
 #[derive(Debug, MooplePacket)]
 pub struct RecommendWorldMessage {
-    world_id: WorldId,
-    message: String,
+    pub world_id: WorldId,
+    pub message: String,
 }
 
 #[derive(Debug, MooplePacket)]
 pub struct RecommendWorldMessageResp {
-    messages: MapleList8<RecommendWorldMessage>,
+    pub messages: MapleList8<RecommendWorldMessage>,
 }
 packet_opcode!(
     RecommendWorldMessageResp,
@@ -77,7 +77,7 @@ pub struct WorldBalloon {
 #[derive(Debug, MooplePacket)]
 pub struct WorldItem {
     pub name: String,
-    pub state: u8, // 0 = normal, 1 = hot?, 2 = new
+    pub state: u8, // 0 = normal, 1 = event, 2 = new, 3 = hot
     pub event_desc: String,
     pub event_exp: u16,
     pub event_drop_rate: u16,
@@ -151,12 +151,13 @@ pub struct QuestCompleteInfo {
     time: MapleTime,
 }
 
-#[derive(Debug, MooplePacket)]
+#[derive(Debug, MooplePacket, Clone, Copy, PartialEq, Eq)]
 pub struct MiniGameInfo {
-    game_id: u32,
-    win: u32,
-    draw: u32,
-    score: u32,
+    pub game_id: u32,
+    pub win: u32,
+    pub draw: u32,
+    pub lose: u32,
+    pub score: u32,
 }
 
 pub type CharId = u32;
@@ -312,8 +313,9 @@ partial_data!(
     Ap(u16) => 1 << 14,
     // TODO handle extended SP
     Sp(u16) => 1 << 15,
-    Exp(u32) => 1 << 17
-
+    Exp(u32) => 1 << 16,
+    Fame(u16) => 1 << 17,
+    Money(Money) => 1 << 18
 );
 
 #[derive(Debug, MooplePacket)]
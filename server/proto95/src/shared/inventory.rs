@@ -3,6 +3,7 @@ use moople_packet::{
     maple_enum_code, maple_packet_enum, packet_opcode,
     proto::{
         list::{MapleIndexListZ16, MapleIndexListZ8},
+        option::MapleOption8,
         time::{MapleTime, Ticks},
         MapleList8,
     },
@@ -18,7 +19,7 @@ maple_enum_code!(
     u8,
     Equip = 1,
     Consume = 2,
-    Install = 30,
+    Install = 3,
     Etc = 4,
     Cash = 5,
     Equipped = 6,
@@ -305,3 +306,19 @@ impl UserSitReq {
     }
 }
 packet_opcode!(UserSitReq, RecvOpcodes::UserSitRequest);
+
+#[derive(Debug, MooplePacket)]
+pub struct UserPortableChairSitReq {
+    pub chair_id: ItemId,
+}
+packet_opcode!(
+    UserPortableChairSitReq,
+    RecvOpcodes::UserPortableChairSitRequest
+);
+
+#[derive(Debug, MooplePacket)]
+pub struct UserSitResultResp {
+    /// None If the user stands up
+    pub seat_id: MapleOption8<u16>,
+}
+packet_opcode!(UserSitResultResp, SendOpcodes::UserSitResult);